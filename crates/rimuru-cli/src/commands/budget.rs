@@ -0,0 +1,172 @@
+use anyhow::Result;
+use iii_sdk::{III, TriggerRequest};
+use serde_json::{Value, json};
+
+use crate::output::{self, OutputFormat};
+
+pub async fn status(iii: &III, project: Option<&str>, format: &OutputFormat) -> Result<()> {
+    let payload = match project {
+        Some(p) => json!({"project_path": p}),
+        None => json!({}),
+    };
+    let result = iii
+        .trigger(TriggerRequest {
+            function_id: "rimuru.budget.status".to_string(),
+            payload,
+            action: None,
+            timeout_ms: None,
+        })
+        .await?;
+    let result = crate::output::unwrap_body(result);
+    println!("{}", output::format_budget_status(&result, format));
+    Ok(())
+}
+
+pub async fn forecast(
+    iii: &III,
+    lookback_days: Option<u64>,
+    timezone: Option<&str>,
+    format: &OutputFormat,
+) -> Result<()> {
+    let mut payload = json!({});
+    if let Some(days) = lookback_days {
+        payload["lookback_days"] = json!(days);
+    }
+    if let Some(tz) = timezone {
+        payload["timezone"] = json!(tz);
+    }
+    let result = iii
+        .trigger(TriggerRequest {
+            function_id: "rimuru.budget.forecast".to_string(),
+            payload,
+            action: None,
+            timeout_ms: None,
+        })
+        .await?;
+    let result = crate::output::unwrap_body(result);
+    println!("{}", output::format_budget_forecast(&result, format));
+    Ok(())
+}
+
+/// Granting and revoking overrides needs an admin or owner token, which the
+/// CLI takes from `RIMURU_TOKEN`.
+fn auth_headers() -> Value {
+    match std::env::var("RIMURU_TOKEN") {
+        Ok(token) if !token.trim().is_empty() => {
+            json!({"authorization": format!("Bearer {}", token.trim())})
+        }
+        _ => Value::Null,
+    }
+}
+
+pub async fn grant_override(
+    iii: &III,
+    amount: f64,
+    duration: &str,
+    reason: &str,
+    format: &OutputFormat,
+) -> Result<()> {
+    let result = iii
+        .trigger(TriggerRequest {
+            function_id: "rimuru.budget.override.create".to_string(),
+            payload: json!({
+                "headers": auth_headers(),
+                "amount": amount,
+                "duration": duration,
+                "reason": reason,
+            }),
+            action: None,
+            timeout_ms: None,
+        })
+        .await?;
+    let result = crate::output::unwrap_body(result);
+    let entry = result.get("override").cloned().unwrap_or(Value::Null);
+    let currency = result
+        .get("currency")
+        .and_then(|v| v.as_str())
+        .unwrap_or("USD");
+    println!(
+        "{}",
+        output::format_budget_overrides(&[entry], currency, format)
+    );
+    Ok(())
+}
+
+pub async fn list_overrides(iii: &III, all: bool, format: &OutputFormat) -> Result<()> {
+    let result = iii
+        .trigger(TriggerRequest {
+            function_id: "rimuru.budget.override.list".to_string(),
+            payload: json!({"all": all}),
+            action: None,
+            timeout_ms: None,
+        })
+        .await?;
+    let result = crate::output::unwrap_body(result);
+    let entries = result
+        .get("overrides")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let currency = result
+        .get("currency")
+        .and_then(|v| v.as_str())
+        .unwrap_or("USD");
+    println!(
+        "{}",
+        output::format_budget_overrides(&entries, currency, format)
+    );
+    Ok(())
+}
+
+pub async fn revoke_override(iii: &III, id: &str, format: &OutputFormat) -> Result<()> {
+    let result = iii
+        .trigger(TriggerRequest {
+            function_id: "rimuru.budget.override.revoke".to_string(),
+            payload: json!({"headers": auth_headers(), "id": id}),
+            action: None,
+            timeout_ms: None,
+        })
+        .await?;
+    let result = crate::output::unwrap_body(result);
+    let entry = result.get("override").cloned().unwrap_or(Value::Null);
+    let currency = result
+        .get("currency")
+        .and_then(|v| v.as_str())
+        .unwrap_or("USD");
+    println!(
+        "{}",
+        output::format_budget_overrides(&[entry], currency, format)
+    );
+    Ok(())
+}
+
+pub async fn set_project(
+    iii: &III,
+    pattern: &str,
+    daily: Option<f64>,
+    monthly: Option<f64>,
+    format: &OutputFormat,
+) -> Result<()> {
+    if daily.is_none() && monthly.is_none() {
+        anyhow::bail!("specify at least one of --daily or --monthly");
+    }
+    let mut payload = json!({"project": pattern});
+    if let Some(d) = daily {
+        payload["project_daily_limit"] = json!(d);
+    }
+    if let Some(m) = monthly {
+        payload["project_monthly_limit"] = json!(m);
+    }
+
+    let result = iii
+        .trigger(TriggerRequest {
+            function_id: "rimuru.budget.set".to_string(),
+            payload,
+            action: None,
+            timeout_ms: None,
+        })
+        .await?;
+    let result: Value = crate::output::unwrap_body(result);
+    output::print_value(&result, format);
+    Ok(())
+}
@@ -37,8 +37,9 @@ pub async fn sync_export(iii: &III) -> Result<()> {
 /// Read a canonical JSON file (or `-` for stdin) and either print the
 /// per-agent diff (default, dry-run) or apply it to every installed
 /// agent's native config (`--apply`). Backups are taken before each
-/// write.
-pub async fn sync_import(iii: &III, path: &str, apply: bool) -> Result<()> {
+/// write. `--downgrade` swaps each agent's default model for the cheaper
+/// substitute the budget's `downgrade` action currently recommends.
+pub async fn sync_import(iii: &III, path: &str, apply: bool, downgrade: bool) -> Result<()> {
     let raw = if path == "-" {
         use std::io::Read;
         let mut buf = String::new();
@@ -56,6 +57,7 @@ pub async fn sync_import(iii: &III, path: &str, apply: bool) -> Result<()> {
             payload: json!({
                 "canonical": canonical,
                 "apply": apply,
+                "downgrade": downgrade,
             }),
             action: None,
             timeout_ms: Some(30_000),
@@ -79,6 +81,13 @@ pub async fn sync_import(iii: &III, path: &str, apply: bool) -> Result<()> {
             let error = entry.get("error").and_then(|v| v.as_str());
 
             println!("== {} ==", agent);
+            if let Some(sub) = entry.get("downgrade") {
+                println!(
+                    "  model: {} -> {}",
+                    sub.get("from").and_then(|v| v.as_str()).unwrap_or("-"),
+                    sub.get("to").and_then(|v| v.as_str()).unwrap_or("-")
+                );
+            }
             if applied {
                 println!("  applied");
                 if !backup.is_empty() {
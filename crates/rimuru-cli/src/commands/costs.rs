@@ -4,26 +4,50 @@ use serde_json::{Value, json};
 
 use crate::output::{self, OutputFormat};
 
-pub async fn summary(iii: &III, format: &OutputFormat) -> Result<()> {
+fn currency_of(result: &Value) -> &str {
+    result
+        .get("currency")
+        .and_then(|v| v.as_str())
+        .unwrap_or("USD")
+}
+
+pub async fn summary(iii: &III, currency: Option<&str>, format: &OutputFormat) -> Result<()> {
+    let mut payload = json!({});
+    if let Some(c) = currency {
+        payload["currency"] = json!(c);
+    }
     let result = iii
         .trigger(TriggerRequest {
             function_id: "rimuru.costs.summary".to_string(),
-            payload: json!({}),
+            payload,
             action: None,
             timeout_ms: None,
         })
         .await?;
     let result = crate::output::unwrap_body(result);
-    let summary = result.get("summary").unwrap_or(&result);
-    println!("{}", output::format_costs_summary(summary, format));
+    let mut summary = result.get("summary").cloned().unwrap_or(result.clone());
+    summary["currency"] = json!(currency_of(&result));
+    println!("{}", output::format_costs_summary(&summary, format));
     Ok(())
 }
 
-pub async fn daily(iii: &III, format: &OutputFormat) -> Result<()> {
+pub async fn daily(
+    iii: &III,
+    timezone: Option<&str>,
+    currency: Option<&str>,
+    format: &OutputFormat,
+) -> Result<()> {
+    let mut payload = json!({});
+    if let Some(tz) = timezone {
+        payload["timezone"] = json!(tz);
+    }
+    if let Some(c) = currency {
+        payload["currency"] = json!(c);
+    }
     let result = iii
         .trigger(TriggerRequest {
             function_id: "rimuru.costs.daily".to_string(),
-            payload: json!({}),
+            payload,
             action: None,
             timeout_ms: None,
         })
@@ -34,16 +58,27 @@ pub async fn daily(iii: &III, format: &OutputFormat) -> Result<()> {
         .and_then(|v| v.as_array())
         .cloned()
         .unwrap_or_default();
-    println!("{}", output::format_daily_costs(&entries, format));
+    println!(
+        "{}",
+        output::format_daily_costs(&entries, currency_of(&result), format)
+    );
     Ok(())
 }
 
-pub async fn agent(iii: &III, agent_id: Option<&str>, format: &OutputFormat) -> Result<()> {
-    let input = if let Some(id) = agent_id {
+pub async fn agent(
+    iii: &III,
+    agent_id: Option<&str>,
+    currency: Option<&str>,
+    format: &OutputFormat,
+) -> Result<()> {
+    let mut input = if let Some(id) = agent_id {
         json!({"agent_id": id})
     } else {
         json!({})
     };
+    if let Some(c) = currency {
+        input["currency"] = json!(c);
+    }
     let result = iii
         .trigger(TriggerRequest {
             function_id: "rimuru.costs.by_agent".to_string(),
@@ -53,11 +88,12 @@ pub async fn agent(iii: &III, agent_id: Option<&str>, format: &OutputFormat) ->
         })
         .await?;
     let result = crate::output::unwrap_body(result);
+    let code = currency_of(&result).to_string();
     let agents = match result {
         Value::Array(arr) => arr,
         other => vec![other],
     };
-    println!("{}", output::format_daily_costs(&agents, format));
+    println!("{}", output::format_daily_costs(&agents, &code, format));
     Ok(())
 }
 
@@ -67,12 +103,16 @@ pub async fn export(
     period: &str,
     from: Option<&str>,
     to: Option<&str>,
+    currency: Option<&str>,
     output: Option<&std::path::Path>,
 ) -> Result<()> {
     let mut payload = json!({
         "format": format,
         "period": period,
     });
+    if let Some(c) = currency {
+        payload["currency"] = json!(c);
+    }
     if let Some(f) = from {
         payload["from"] = Value::String(f.to_string());
     }
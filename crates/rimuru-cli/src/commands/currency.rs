@@ -0,0 +1,84 @@
+use anyhow::Result;
+use iii_sdk::{III, TriggerRequest};
+use serde_json::json;
+
+use crate::output::{self, OutputFormat};
+
+pub async fn rates(iii: &III, currency: Option<&str>, format: &OutputFormat) -> Result<()> {
+    let mut payload = json!({});
+    if let Some(c) = currency {
+        payload["currency"] = json!(c);
+    }
+    let result = iii
+        .trigger(TriggerRequest {
+            function_id: "rimuru.currency.rates".to_string(),
+            payload,
+            action: None,
+            timeout_ms: None,
+        })
+        .await?;
+    let result = crate::output::unwrap_body(result);
+    println!("{}", output::format_exchange_rates(&result, format));
+    Ok(())
+}
+
+pub async fn set(
+    iii: &III,
+    code: &str,
+    rate: f64,
+    effective_from: Option<&str>,
+    format: &OutputFormat,
+) -> Result<()> {
+    let mut payload = json!({"currency": code, "rate": rate});
+    if let Some(date) = effective_from {
+        payload["effective_from"] = json!(date);
+    }
+    let result = iii
+        .trigger(TriggerRequest {
+            function_id: "rimuru.currency.set_rate".to_string(),
+            payload,
+            action: None,
+            timeout_ms: None,
+        })
+        .await?;
+    let result = crate::output::unwrap_body(result);
+    output::print_value(&result, format);
+    Ok(())
+}
+
+pub async fn convert(
+    iii: &III,
+    amount: f64,
+    from: &str,
+    to: Option<&str>,
+    date: Option<&str>,
+    format: &OutputFormat,
+) -> Result<()> {
+    let mut payload = json!({"amount": amount, "from": from});
+    if let Some(t) = to {
+        payload["to"] = json!(t);
+    }
+    if let Some(d) = date {
+        payload["date"] = json!(d);
+    }
+    let result = iii
+        .trigger(TriggerRequest {
+            function_id: "rimuru.currency.convert".to_string(),
+            payload,
+            action: None,
+            timeout_ms: None,
+        })
+        .await?;
+    let result = crate::output::unwrap_body(result);
+    match format {
+        OutputFormat::Table => println!(
+            "{} {} = {} ({})",
+            result["amount"],
+            result["from"].as_str().unwrap_or(from),
+            result["formatted"].as_str().unwrap_or("-"),
+            result["date"].as_str().unwrap_or("-"),
+        ),
+        _ => output::print_value(&result, format),
+    }
+    Ok(())
+}
@@ -3,14 +3,17 @@ use std::time::Duration;
 use anyhow::Result;
 use comfy_table::{Cell, Color, ContentArrangement, Table};
 use iii_sdk::{III, TriggerRequest};
-use serde_json::json;
+use serde_json::{Value, json};
 
 use crate::output::{self, OutputFormat};
 
+mod snapshot;
+
 #[derive(Debug, Clone, Copy, clap::ValueEnum)]
 pub enum GuardActionMode {
     Kill,
     Warn,
+    Pause,
 }
 
 impl GuardActionMode {
@@ -18,10 +21,127 @@ impl GuardActionMode {
         match self {
             GuardActionMode::Kill => "kill",
             GuardActionMode::Warn => "warn",
+            GuardActionMode::Pause => "pause",
         }
     }
 }
 
+/// Limits a guard enforces; any combination, at least one.
+#[derive(Debug, Default, clap::Args)]
+#[group(required = true, multiple = true)]
+pub struct GuardLimits {
+    #[arg(long, value_parser = validate_limit, help = "Dollar limit")]
+    pub limit: Option<f64>,
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..), help = "Token limit")]
+    pub max_tokens: Option<u64>,
+    #[arg(long, value_parser = validate_duration, help = "Wall-clock limit, e.g. 30m or 2h")]
+    pub max_duration: Option<u64>,
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..), help = "Turn limit")]
+    pub max_turns: Option<u64>,
+}
+
+impl GuardLimits {
+    fn payload(&self) -> Value {
+        json!({
+            "limit": self.limit,
+            "max_tokens": self.max_tokens,
+            "max_duration": self.max_duration,
+            "max_turns": self.max_turns,
+        })
+    }
+}
+
+fn format_tokens(n: u64) -> String {
+    if n >= 1_000_000 {
+        format!("{:.1}M", n as f64 / 1_000_000.0)
+    } else if n >= 1_000 {
+        format!("{:.0}k", n as f64 / 1_000.0)
+    } else {
+        n.to_string()
+    }
+}
+
+fn format_secs(secs: u64) -> String {
+    match secs {
+        s if s >= 3600 => format!("{}h{:02}m", s / 3600, s % 3600 / 60),
+        s if s >= 60 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+/// One line for whichever limits a guard record (or usage reply) carries.
+fn describe_limits(v: &Value) -> String {
+    let mut parts = Vec::new();
+    if let Some(n) = v.get("limit").and_then(|v| v.as_f64()) {
+        parts.push(format!("${:.2}", n));
+    }
+    if let Some(n) = v.get("max_tokens").and_then(|v| v.as_u64()) {
+        parts.push(format!("{} tokens", format_tokens(n)));
+    }
+    let duration = v
+        .get("max_duration_secs")
+        .or_else(|| v.get("max_duration"))
+        .and_then(|v| v.as_u64());
+    if let Some(n) = duration {
+        parts.push(format_secs(n));
+    }
+    if let Some(n) = v.get("max_turns").and_then(|v| v.as_u64()) {
+        parts.push(format!("{} turns", n));
+    }
+    parts.join(", ")
+}
+
+/// Usage so far, in the same units as `describe_limits`.
+fn describe_usage(v: &Value) -> String {
+    let cost = v
+        .get("current_cost")
+        .or_else(|| v.get("cost"))
+        .and_then(|v| v.as_f64())
+        .unwrap_or(0.0);
+    let tokens = v.get("tokens").and_then(|v| v.as_u64()).unwrap_or(0);
+    let turns = v.get("turns").and_then(|v| v.as_u64()).unwrap_or(0);
+    let elapsed = v.get("elapsed_secs").and_then(|v| v.as_u64()).unwrap_or(0);
+    format!(
+        "${:.2}, {} tokens, {} turns, {}",
+        cost,
+        format_tokens(tokens),
+        turns,
+        format_secs(elapsed)
+    )
+}
+
+#[derive(Debug, clap::Args)]
+pub struct StartArgs {
+    #[command(flatten)]
+    pub limits: GuardLimits,
+    #[arg(long, value_enum, default_value_t = GuardActionMode::Warn)]
+    pub action: GuardActionMode,
+    #[arg(
+        long,
+        default_value_t = 10,
+        help = "Seconds to wait after SIGINT, and again after SIGTERM, before escalating"
+    )]
+    pub grace: u64,
+    #[arg(
+        long,
+        help = "Agent the command runs (e.g. claude_code); inferred from the command when omitted"
+    )]
+    pub agent: Option<String>,
+    #[arg(
+        long,
+        help = "Snapshot the git working tree at start and when the guard steps in"
+    )]
+    pub snapshot: bool,
+    #[arg(trailing_var_arg = true, required = true)]
+    pub command: Vec<String>,
+}
+
+pub fn validate_duration(s: &str) -> Result<u64, String> {
+    rimuru_core::functions::period::parse_duration(s)
+        .map(|d| d.num_seconds() as u64)
+        .map_err(|_| format!("invalid duration: {} (try 30m, 2h, 1d)", s))
+}
+
 pub fn validate_limit(s: &str) -> Result<f64, String> {
     let n: f64 = s.parse().map_err(|_| format!("invalid number: {}", s))?;
     if n.is_nan() || !n.is_finite() {
@@ -33,37 +153,184 @@ pub fn validate_limit(s: &str) -> Result<f64, String> {
     Ok(n)
 }
 
-pub async fn start(
-    iii: &III,
-    limit: f64,
-    action: GuardActionMode,
-    command: &[String],
-    _format: &OutputFormat,
-) -> Result<()> {
+#[cfg(unix)]
+mod process {
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+    use std::time::Duration;
+
+    use tokio::process::{Child, Command};
+
+    fn stdin_is_tty() -> bool {
+        unsafe { libc::isatty(libc::STDIN_FILENO) == 1 }
+    }
+
+    /// Start the child as the leader of its own process group so signals
+    /// reach every process it spawns. On a terminal the group also becomes
+    /// the foreground group, so interactive agents can still read input.
+    pub fn isolate(cmd: &mut Command) {
+        cmd.process_group(0);
+        if !stdin_is_tty() {
+            return;
+        }
+        // Handing the terminal over, and taking it back, from outside the
+        // foreground group would otherwise stop us with SIGTTOU.
+        unsafe {
+            libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+            cmd.pre_exec(|| {
+                libc::tcsetpgrp(libc::STDIN_FILENO, libc::getpid());
+                libc::signal(libc::SIGTTOU, libc::SIG_DFL);
+                Ok(())
+            });
+        }
+    }
+
+    pub fn restore_terminal() {
+        if stdin_is_tty() {
+            unsafe {
+                libc::tcsetpgrp(libc::STDIN_FILENO, libc::getpgrp());
+            }
+        }
+    }
+
+    fn signal_group(pgid: i32, signal: i32) {
+        unsafe {
+            libc::kill(-pgid, signal);
+        }
+    }
+
+    pub fn pause(pgid: i32) {
+        signal_group(pgid, libc::SIGSTOP);
+    }
+
+    pub fn resume(pgid: i32) {
+        signal_group(pgid, libc::SIGCONT);
+    }
+
+    /// Stop the whole group: SIGINT so the agent can flush its session,
+    /// then SIGTERM, then SIGKILL, waiting `grace` after each. Returns the
+    /// signal that ended the run.
+    pub async fn terminate(child: &mut Child, pgid: i32, grace: Duration) -> &'static str {
+        for (signal, name) in [(libc::SIGINT, "SIGINT"), (libc::SIGTERM, "SIGTERM")] {
+            signal_group(pgid, signal);
+            // A paused group only sees the signal once it runs again.
+            resume(pgid);
+            if tokio::time::timeout(grace, child.wait()).await.is_ok() {
+                // Helpers that ignored the signal should not outlive the agent.
+                signal_group(pgid, libc::SIGTERM);
+                return name;
+            }
+        }
+        signal_group(pgid, libc::SIGKILL);
+        let _ = child.wait().await;
+        "SIGKILL"
+    }
+
+    /// The signal that killed the child, if one did.
+    pub fn exit_signal(status: &ExitStatus) -> Option<String> {
+        let name = match status.signal()? {
+            libc::SIGINT => "SIGINT".to_string(),
+            libc::SIGTERM => "SIGTERM".to_string(),
+            libc::SIGKILL => "SIGKILL".to_string(),
+            libc::SIGHUP => "SIGHUP".to_string(),
+            n => format!("signal {}", n),
+        };
+        Some(name)
+    }
+}
+
+/// Elsewhere the child can only be killed outright, and never paused.
+#[cfg(not(unix))]
+mod process {
+    use std::process::ExitStatus;
+    use std::time::Duration;
+
+    use tokio::process::{Child, Command};
+
+    pub fn isolate(_cmd: &mut Command) {}
+
+    pub fn restore_terminal() {}
+
+    pub fn pause(_pgid: i32) {}
+
+    pub fn resume(_pgid: i32) {}
+
+    pub async fn terminate(child: &mut Child, _pgid: i32, _grace: Duration) -> &'static str {
+        let _ = child.kill().await;
+        "kill"
+    }
+
+    pub fn exit_signal(_status: &ExitStatus) -> Option<String> {
+        None
+    }
+}
+
+/// Snapshot the tree when the guard steps in. A failure here should not
+/// get in the way of stopping the process, so it only warns.
+async fn intervention_snapshot(dir: &std::path::Path, guard_id: &str) -> Option<Value> {
+    match snapshot::take(dir, guard_id, "stop").await {
+        Ok(snap) => Some(snap),
+        Err(e) => {
+            eprintln!("[GUARD] Warning: snapshot failed: {}", e);
+            None
+        }
+    }
+}
+
+pub async fn start(iii: &III, args: &StartArgs, _format: &OutputFormat) -> Result<()> {
+    let StartArgs {
+        limits,
+        action,
+        grace,
+        agent,
+        snapshot: take_snapshots,
+        command,
+    } = args;
+    let (action, grace, agent) = (*action, *grace, agent.as_deref());
     if command.is_empty() {
         anyhow::bail!("No command specified");
     }
+    if matches!(action, GuardActionMode::Pause) && cfg!(not(unix)) {
+        anyhow::bail!("--action pause needs job control and is only supported on Unix");
+    }
 
     let guard_id = uuid::Uuid::new_v4().to_string();
     let command_str = command.join(" ");
     let started_at = chrono::Utc::now().to_rfc3339();
-
-    let mut child = tokio::process::Command::new(&command[0])
-        .args(&command[1..])
-        .spawn()?;
+    let dir = std::env::current_dir()?;
+    let cwd = dir.to_string_lossy().into_owned();
+    let start_snapshot = if *take_snapshots {
+        let snap = snapshot::take(&dir, &guard_id, "start")
+            .await
+            .map_err(|e| anyhow::anyhow!("--snapshot needs a git work tree: {}", e))?;
+        Some(snap)
+    } else {
+        None
+    };
+    let mut stop_snapshot: Option<Value> = None;
+
+    let mut cmd = tokio::process::Command::new(&command[0]);
+    cmd.args(&command[1..]);
+    process::isolate(&mut cmd);
+    let mut child = cmd.spawn()?;
     let pid = child.id().unwrap_or(0) as i64;
+    let pgid = pid as i32;
+
+    let mut payload = limits.payload();
+    payload["id"] = json!(guard_id);
+    payload["command"] = json!(command_str);
+    payload["action"] = json!(action.as_str());
+    payload["started_at"] = json!(started_at);
+    payload["pid"] = json!(pid);
+    payload["cwd"] = json!(cwd);
+    payload["agent"] = json!(agent);
+    payload["process_group"] = json!(cfg!(unix));
+    payload["snapshot"] = json!(start_snapshot);
 
     let register_result = iii
         .trigger(TriggerRequest {
             function_id: "rimuru.guard.register".to_string(),
-            payload: json!({
-                "id": guard_id,
-                "command": command_str,
-                "limit": limit,
-                "action": action.as_str(),
-                "started_at": started_at,
-                "pid": pid
-            }),
+            payload,
             action: None,
             timeout_ms: None,
         })
@@ -71,18 +338,34 @@ pub async fn start(
 
     if let Err(e) = register_result {
         let _ = child.kill().await;
+        process::restore_terminal();
         anyhow::bail!("failed to register guard: {}", e);
     }
 
     eprintln!("Guard started: {}", &guard_id[..8]);
     eprintln!("  PID: {}", pid);
-    eprintln!("  Limit: ${:.2} | Action: {}", limit, action.as_str());
+    let mut limit_line = describe_limits(&limits.payload());
+    eprintln!("  Limit: {} | Action: {}", limit_line, action.as_str());
     eprintln!("  Running: {}", command_str);
     eprintln!();
 
+    let grace = Duration::from_secs(grace);
     let mut action_taken = "none".to_string();
     let mut warned = false;
+    let mut paused = false;
+    // Paused on request rather than by a limit, so raising limits alone
+    // does not resume it.
+    let mut held = false;
+    // The limit that was over when a resume was requested; it does not
+    // pause the process again until usage drops back under it.
+    let mut waived: Option<String> = None;
+    // Queued commands as (request id, command).
+    let mut commands: Vec<(String, String)> = Vec::new();
+    let mut signal: Option<String> = None;
     let mut current_cost = 0.0_f64;
+    let mut usage_line = describe_usage(&json!({}));
+    let mut reached: Option<String> = None;
+    let mut session_ids: Vec<String> = Vec::new();
 
     loop {
         tokio::select! {
@@ -90,6 +373,7 @@ pub async fn start(
                 match exit_status {
                     Ok(status) => {
                         eprintln!("\nProcess exited with status: {}", status);
+                        signal = process::exit_signal(&status);
                     }
                     Err(e) => {
                         eprintln!("\nProcess error: {}", e);
@@ -98,43 +382,138 @@ pub async fn start(
                 break;
             }
             _ = tokio::time::sleep(Duration::from_secs(5)) => {
-                let cost_result = iii.trigger(TriggerRequest {
-                    function_id: "rimuru.costs.summary".to_string(),
-                    payload: json!({"since": started_at}),
+                let usage_result = iii.trigger(TriggerRequest {
+                    function_id: "rimuru.guard.poll".to_string(),
+                    payload: json!({"id": guard_id}),
                     action: None,
                     timeout_ms: Some(10_000),
                 }).await;
 
-                if let Ok(result) = cost_result {
+                if let Ok(result) = usage_result {
                     let body = output::unwrap_body(result);
                     current_cost = body
-                        .get("summary")
-                        .and_then(|s| s.get("total_cost"))
+                        .get("cost")
                         .and_then(|v| v.as_f64())
                         .unwrap_or(current_cost);
+                    usage_line = describe_usage(&body);
+                    if let Some(l) = body.get("limits") {
+                        limit_line = describe_limits(l);
+                    }
+                    reached = body.get("reached").and_then(|v| v.as_str()).map(String::from);
+                    commands = body
+                        .get("commands")
+                        .and_then(|v| v.as_array())
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|v| {
+                            let field = |key| v.get(key).and_then(|v| v.as_str()).map(String::from);
+                            Some((field("id")?, field("command")?))
+                        })
+                        .collect();
+                    for id in body
+                        .get("new_sessions")
+                        .and_then(|v| v.as_array())
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|v| v.as_str())
+                    {
+                        eprintln!("[GUARD] Tracking session {}", &id[..8.min(id.len())]);
+                        session_ids.push(id.to_string());
+                    }
+                }
+
+                // A kill ends the run; `complete` drops whatever is still queued.
+                if commands.iter().any(|(_, c)| c == "kill") {
+                    eprintln!("\n[GUARD] Kill requested — stopping process");
+                    signal = Some(process::terminate(&mut child, pgid, grace).await.to_string());
+                    action_taken = "killed:remote".to_string();
+                    break;
+                }
+                let mut handled = Vec::new();
+                for (request_id, command) in commands.drain(..) {
+                    handled.push(request_id);
+                    match command.as_str() {
+                        "pause" if !paused => {
+                            eprintln!("\n[GUARD] Pause requested — pausing process");
+                            process::pause(pgid);
+                            action_taken = "paused:remote".to_string();
+                            paused = true;
+                            held = true;
+                            if *take_snapshots {
+                                stop_snapshot = intervention_snapshot(&dir, &guard_id).await.or(stop_snapshot);
+                            }
+                        }
+                        "resume" if paused => {
+                            eprintln!("\n[GUARD] Resume requested — resuming process");
+                            process::resume(pgid);
+                            paused = false;
+                            held = false;
+                            waived = reached.clone();
+                        }
+                        _ => {}
+                    }
+                }
+                // Acknowledged only once acted on; if this fails the next
+                // poll hands the same commands over again.
+                if !handled.is_empty() {
+                    let _ = iii.trigger(TriggerRequest {
+                        function_id: "rimuru.guard.ack".to_string(),
+                        payload: json!({"id": guard_id, "request_ids": handled}),
+                        action: None,
+                        timeout_ms: Some(10_000),
+                    }).await;
+                }
+                if reached.is_none() {
+                    waived = None;
                 }
 
-                if current_cost >= limit {
-                    match action {
+                match (reached.as_ref().filter(|&r| waived.as_ref() != Some(r)), paused) {
+                    (None, true) if held => {}
+                    (None, true) => {
+                        eprintln!("\n[GUARD] Limit raised to {} — resuming process", limit_line);
+                        process::resume(pgid);
+                        paused = false;
+                    }
+                    (None, false) => {}
+                    (Some(fired), _) => match action {
                         GuardActionMode::Kill => {
-                            eprintln!("\n[GUARD] Cost ${:.2} exceeded limit ${:.2} — killing process", current_cost, limit);
-                            let _ = child.kill().await;
-                            action_taken = "killed".to_string();
+                            eprintln!("\n[GUARD] {} reached ({}) — stopping process", fired, usage_line);
+                            signal = Some(process::terminate(&mut child, pgid, grace).await.to_string());
+                            action_taken = format!("killed:{}", fired);
                             break;
                         }
+                        GuardActionMode::Pause => {
+                            if !paused {
+                                eprintln!("\n[GUARD] {} reached ({}) — pausing process", fired, usage_line);
+                                eprintln!("[GUARD] Resume or extend it with `rimuru guard resume|extend {}`", &guard_id[..8]);
+                                process::pause(pgid);
+                                action_taken = format!("paused:{}", fired);
+                                paused = true;
+                                if *take_snapshots {
+                                    stop_snapshot = intervention_snapshot(&dir, &guard_id).await.or(stop_snapshot);
+                                }
+                            }
+                        }
                         GuardActionMode::Warn => {
                             if !warned {
-                                eprintln!("\n[GUARD] Warning: cost ${:.2} exceeded limit ${:.2}", current_cost, limit);
-                                action_taken = "warned".to_string();
+                                eprintln!("\n[GUARD] Warning: {} reached ({})", fired, usage_line);
+                                action_taken = format!("warned:{}", fired);
                                 warned = true;
                             }
                         }
-                    }
+                    },
                 }
             }
         }
     }
 
+    process::restore_terminal();
+    // Whoever sent it, a signal means the run was cut short.
+    if *take_snapshots && signal.is_some() {
+        stop_snapshot = intervention_snapshot(&dir, &guard_id)
+            .await
+            .or(stop_snapshot);
+    }
     let ended_at = chrono::Utc::now().to_rfc3339();
 
     if let Err(e) = iii
@@ -144,7 +523,9 @@ pub async fn start(
                 "id": guard_id,
                 "final_cost": current_cost,
                 "action_taken": action_taken,
-                "ended_at": ended_at
+                "ended_at": ended_at,
+                "signal": signal,
+                "stop_snapshot": stop_snapshot
             }),
             action: None,
             timeout_ms: None,
@@ -161,13 +542,132 @@ pub async fn start(
     eprintln!();
     eprintln!("Guard summary:");
     eprintln!("  ID: {}", &guard_id[..8]);
-    eprintln!("  Final cost: ${:.2}", current_cost);
-    eprintln!("  Limit: ${:.2}", limit);
+    eprintln!("  Final usage: {}", usage_line);
+    eprintln!("  Limit: {}", limit_line);
     eprintln!("  Action taken: {}", action_taken);
+    if let Some(signal) = &signal {
+        eprintln!("  Ended by: {}", signal);
+    }
+    if start_snapshot.is_some() {
+        eprintln!(
+            "  Snapshot: rimuru guard restore {} [--apply]",
+            &guard_id[..8]
+        );
+    }
+    eprintln!("  Sessions: {}", session_ids.len());
 
     Ok(())
 }
 
+/// Full id of the active guard whose id starts with `prefix`.
+async fn resolve_guard(iii: &III, prefix: &str) -> Result<String> {
+    let result = iii
+        .trigger(TriggerRequest {
+            function_id: "rimuru.guard.list".to_string(),
+            payload: json!({}),
+            action: None,
+            timeout_ms: None,
+        })
+        .await?;
+    let body = output::unwrap_body(result);
+    let ids: Vec<String> = body
+        .get("guards")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|g| g.get("id").and_then(|v| v.as_str()))
+        .filter(|id| id.starts_with(prefix))
+        .map(String::from)
+        .collect();
+    match ids.as_slice() {
+        [id] => Ok(id.clone()),
+        [] => anyhow::bail!("no active guard matches {}", prefix),
+        _ => anyhow::bail!(
+            "{} matches {} guards; use more of the id",
+            prefix,
+            ids.len()
+        ),
+    }
+}
+
+/// Send `kill`, `pause`, `resume` or `extend` to a guard running in
+/// another terminal. It acts on the request at its next poll.
+pub async fn request(
+    iii: &III,
+    id: &str,
+    command: &str,
+    limits: Option<&GuardLimits>,
+    format: &OutputFormat,
+) -> Result<()> {
+    let id = resolve_guard(iii, id).await?;
+    let mut payload = limits.map_or_else(|| json!({}), GuardLimits::payload);
+    payload["id"] = json!(id);
+
+    let result = iii
+        .trigger(TriggerRequest {
+            function_id: format!("rimuru.guard.{}", command),
+            payload,
+            action: None,
+            timeout_ms: None,
+        })
+        .await?;
+    let body = output::unwrap_body(result);
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&body)?),
+        OutputFormat::Yaml => println!("{}", serde_yaml::to_string(&body)?),
+        OutputFormat::Table => {
+            println!("Sent {} to guard {} (applies within 5s)", command, &id[..8]);
+        }
+    }
+    Ok(())
+}
+
+/// Diff against, or roll back to, the working tree a `--snapshot` guard
+/// started from.
+pub async fn restore(iii: &III, id: &str, apply: bool) -> Result<()> {
+    let result = iii
+        .trigger(TriggerRequest {
+            function_id: "rimuru.guard.history".to_string(),
+            payload: json!({}),
+            action: None,
+            timeout_ms: None,
+        })
+        .await?;
+    let body = output::unwrap_body(result);
+    let matches: Vec<Value> = body
+        .get("history")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|r| {
+            r.get("id")
+                .and_then(|v| v.as_str())
+                .is_some_and(|rid| rid.starts_with(id))
+        })
+        .cloned()
+        .collect();
+    let record = match matches.as_slice() {
+        [record] => record,
+        [] => anyhow::bail!("no finished guard matches {}", id),
+        _ => anyhow::bail!(
+            "{} matches {} guards; use more of the id",
+            id,
+            matches.len()
+        ),
+    };
+    let guard_id = record.get("id").and_then(|v| v.as_str()).unwrap_or(id);
+    let Some(start) = record.get("start_snapshot").filter(|v| !v.is_null()) else {
+        anyhow::bail!("guard {} was not started with --snapshot", id);
+    };
+    let dir = match record.get("cwd").and_then(|v| v.as_str()) {
+        Some(cwd) => std::path::PathBuf::from(cwd),
+        None => std::env::current_dir()?,
+    };
+
+    snapshot::restore(&dir, guard_id, start, apply).await
+}
+
 pub async fn status(iii: &III, format: &OutputFormat) -> Result<()> {
     let result = iii
         .trigger(TriggerRequest {
@@ -205,7 +705,8 @@ pub async fn status(iii: &III, format: &OutputFormat) -> Result<()> {
                 Cell::new("PID").fg(Color::Cyan),
                 Cell::new("Command").fg(Color::Cyan),
                 Cell::new("Limit").fg(Color::Cyan),
-                Cell::new("Current").fg(Color::Cyan),
+                Cell::new("Usage").fg(Color::Cyan),
+                Cell::new("Sessions").fg(Color::Cyan),
                 Cell::new("Action").fg(Color::Cyan),
                 Cell::new("Started").fg(Color::Cyan),
             ]);
@@ -217,17 +718,14 @@ pub async fn status(iii: &III, format: &OutputFormat) -> Result<()> {
                     Cell::new(short_id),
                     Cell::new(guard.get("pid").and_then(|v| v.as_i64()).unwrap_or(0)),
                     Cell::new(guard.get("command").and_then(|v| v.as_str()).unwrap_or("")),
-                    Cell::new(format!(
-                        "${:.2}",
-                        guard.get("limit").and_then(|v| v.as_f64()).unwrap_or(0.0)
-                    )),
-                    Cell::new(format!(
-                        "${:.2}",
+                    Cell::new(describe_limits(guard)),
+                    Cell::new(describe_usage(guard)),
+                    Cell::new(
                         guard
-                            .get("current_cost")
-                            .and_then(|v| v.as_f64())
-                            .unwrap_or(0.0)
-                    )),
+                            .get("session_ids")
+                            .and_then(|v| v.as_array())
+                            .map_or(0, |a| a.len()),
+                    ),
                     Cell::new(
                         guard
                             .get("action")
@@ -288,6 +786,7 @@ pub async fn history(iii: &III, format: &OutputFormat) -> Result<()> {
                 Cell::new("Limit").fg(Color::Cyan),
                 Cell::new("Final Cost").fg(Color::Cyan),
                 Cell::new("Action Taken").fg(Color::Cyan),
+                Cell::new("Signal").fg(Color::Cyan),
                 Cell::new("Started").fg(Color::Cyan),
                 Cell::new("Ended").fg(Color::Cyan),
             ]);
@@ -298,10 +797,7 @@ pub async fn history(iii: &III, format: &OutputFormat) -> Result<()> {
                 table.add_row(vec![
                     Cell::new(short_id),
                     Cell::new(record.get("command").and_then(|v| v.as_str()).unwrap_or("")),
-                    Cell::new(format!(
-                        "${:.2}",
-                        record.get("limit").and_then(|v| v.as_f64()).unwrap_or(0.0)
-                    )),
+                    Cell::new(describe_limits(record)),
                     Cell::new(format!(
                         "${:.2}",
                         record
@@ -315,6 +811,7 @@ pub async fn history(iii: &III, format: &OutputFormat) -> Result<()> {
                             .and_then(|v| v.as_str())
                             .unwrap_or("none"),
                     ),
+                    Cell::new(record.get("signal").and_then(|v| v.as_str()).unwrap_or("-")),
                     Cell::new(
                         record
                             .get("started_at")
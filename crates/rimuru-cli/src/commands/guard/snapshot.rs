@@ -0,0 +1,165 @@
+//! Git snapshots of a guarded command's working tree, for `--snapshot` and
+//! `rimuru guard restore`.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use tokio::process::Command;
+
+async fn git(dir: &Path, args: &[&str], env: &[(&str, &str)]) -> Result<String> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .envs(env.iter().copied())
+        .output()
+        .await
+        .context("failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The checked-out branch as a full ref, or `None` on a detached HEAD.
+async fn branch(dir: &Path) -> Option<String> {
+    git(dir, &["symbolic-ref", "-q", "HEAD"], &[]).await.ok()
+}
+
+/// Tree of the whole working tree, untracked files included. The tree is
+/// staged into a scratch index, so the real index and the files are left
+/// alone; nothing points at it until a commit and ref are made for it.
+async fn write_tree(dir: &Path, guard_id: &str, label: &str) -> Result<String> {
+    let index = git(dir, &["rev-parse", "--git-path", "index"], &[]).await?;
+    let scratch = std::env::temp_dir().join(format!("rimuru-guard-{}-{}.index", guard_id, label));
+    // Starting from the real index means `add` only rehashes what changed.
+    let _ = tokio::fs::copy(dir.join(&index), &scratch).await;
+    let scratch_env = [("GIT_INDEX_FILE", scratch.to_str().unwrap_or_default())];
+
+    let tree = async {
+        git(dir, &["add", "-A"], &scratch_env).await?;
+        git(dir, &["write-tree"], &scratch_env).await
+    }
+    .await;
+    let _ = tokio::fs::remove_file(&scratch).await;
+    tree
+}
+
+/// Commit the whole working tree on top of HEAD and pin it at
+/// `refs/rimuru/guards/<id>/<label>`. The branch, the index and the files
+/// are left alone.
+pub async fn take(dir: &Path, guard_id: &str, label: &str) -> Result<Value> {
+    let head = git(dir, &["rev-parse", "HEAD"], &[]).await?;
+    let tree = write_tree(dir, guard_id, label).await?;
+
+    let message = format!("rimuru guard {} {}", guard_id, label);
+    let identity = [
+        ("GIT_AUTHOR_NAME", "rimuru guard"),
+        ("GIT_AUTHOR_EMAIL", "guard@rimuru.local"),
+        ("GIT_COMMITTER_NAME", "rimuru guard"),
+        ("GIT_COMMITTER_EMAIL", "guard@rimuru.local"),
+    ];
+    let commit = git(
+        dir,
+        &["commit-tree", &tree, "-p", &head, "-m", &message],
+        &identity,
+    )
+    .await?;
+    let git_ref = format!("refs/rimuru/guards/{}/{}", guard_id, label);
+    git(dir, &["update-ref", &git_ref, &commit], &[]).await?;
+
+    Ok(json!({
+        "head": head,
+        "branch": branch(dir).await,
+        "commit": commit,
+        "ref": git_ref,
+        "taken_at": chrono::Utc::now().to_rfc3339(),
+    }))
+}
+
+/// Show what changed since `start`, or with `apply` put the working tree and
+/// branch back the way they were. Showing writes no refs; applying first
+/// pins the current state at a timestamped `before-restore-*` ref, so every
+/// rollback can itself be undone.
+pub async fn restore(dir: &Path, guard_id: &str, start: &Value, apply: bool) -> Result<()> {
+    let field = |key: &str| {
+        start
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(String::from)
+            .with_context(|| format!("snapshot has no {}", key))
+    };
+    let (start_head, start_commit) = (field("head")?, field("commit")?);
+
+    if !apply {
+        let current_tree = write_tree(dir, guard_id, "diff").await?;
+        let stat = git(dir, &["diff", "--stat", &start_commit, &current_tree], &[]).await?;
+        if stat.is_empty() {
+            println!("No changes since the guard started");
+        } else {
+            println!("{}", stat);
+            println!();
+            println!("Full patch: git diff {} {}", start_commit, current_tree);
+            println!("Roll back:  rimuru guard restore {} --apply", guard_id);
+        }
+        return Ok(());
+    }
+
+    // `reset --hard` moves whatever branch is checked out, so it has to be
+    // the one the guard started on.
+    let start_branch = start.get("branch").and_then(|v| v.as_str());
+    let current_branch = branch(dir).await;
+    if start_branch != current_branch.as_deref() {
+        let name = |b: Option<&str>| {
+            b.map(|b| b.trim_start_matches("refs/heads/").to_string())
+                .unwrap_or_else(|| "a detached HEAD".to_string())
+        };
+        anyhow::bail!(
+            "the guard started on {} but {} is checked out; check it out again before --apply",
+            name(start_branch),
+            name(current_branch.as_deref())
+        );
+    }
+
+    let label = format!(
+        "before-restore-{}",
+        chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ")
+    );
+    let current = take(dir, guard_id, &label).await?;
+    let current_commit = current["commit"].as_str().unwrap_or_default();
+    let current_ref = current["ref"].as_str().unwrap_or_default();
+
+    let top = git(dir, &["rev-parse", "--show-toplevel"], &[]).await?;
+    let top = Path::new(&top);
+    // Only files the run added go; `git clean` would also take untracked
+    // files whose ignore rule the run happened to change.
+    let added = git(
+        dir,
+        &[
+            "diff",
+            "-z",
+            "--name-only",
+            "--no-renames",
+            "--diff-filter=A",
+            &start_commit,
+            current_commit,
+        ],
+        &[],
+    )
+    .await?;
+    git(top, &["reset", "--hard", "-q", &start_head], &[]).await?;
+    for path in added.split('\0').filter(|p| !p.is_empty()) {
+        let _ = tokio::fs::remove_file(top.join(path)).await;
+    }
+    git(top, &["checkout", &start_commit, "--", "."], &[]).await?;
+    // Files that were uncommitted before the run go back to being so.
+    git(top, &["reset", "-q"], &[]).await?;
+
+    println!("Restored the working tree to its state before the guard started");
+    println!("The state before this restore is saved at {}", current_ref);
+    Ok(())
+}
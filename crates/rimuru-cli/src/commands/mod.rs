@@ -1,8 +1,10 @@
 pub mod agents;
+pub mod budget;
 pub mod config;
 pub mod context;
 pub mod costs;
 pub mod ctx;
+pub mod currency;
 pub mod guard;
 pub mod health;
 pub mod hooks;
@@ -10,5 +12,6 @@ pub mod mcp;
 pub mod metrics;
 pub mod models;
 pub mod plugins;
+pub mod runaway;
 pub mod sessions;
 pub mod slim;
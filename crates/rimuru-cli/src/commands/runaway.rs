@@ -0,0 +1,98 @@
+use anyhow::Result;
+use iii_sdk::{III, TriggerRequest};
+use serde_json::{Value, json};
+
+use crate::output::{self, OutputFormat};
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum LabelKind {
+    TruePositive,
+    FalsePositive,
+    Clear,
+}
+
+/// Detector settings to try for one backtest run without saving them.
+#[derive(Debug, Default, clap::Args)]
+pub struct BacktestOverrides {
+    #[arg(long, help = "Turns the structural detectors look at")]
+    pub window: Option<u64>,
+    #[arg(long)]
+    pub repeat_threshold: Option<u64>,
+    #[arg(long)]
+    pub token_explosion_ratio: Option<f64>,
+    #[arg(long, help = "Spend rate in $/min that flags on its own (0 disables)")]
+    pub velocity_limit: Option<f64>,
+    #[arg(long, help = "max or weighted_sum")]
+    pub scoring: Option<String>,
+    #[arg(long, help = "Combined severity that counts as runaway")]
+    pub severity_cutoff: Option<f64>,
+}
+
+impl BacktestOverrides {
+    fn payload(&self) -> Value {
+        let mut payload = json!({});
+        if let Some(n) = self.window {
+            payload["window"] = json!(n);
+        }
+        if let Some(n) = self.repeat_threshold {
+            payload["repeat_threshold"] = json!(n);
+        }
+        if let Some(n) = self.token_explosion_ratio {
+            payload["token_explosion_ratio"] = json!(n);
+        }
+        if let Some(n) = self.velocity_limit {
+            payload["velocity_limit"] = json!(n);
+        }
+        if let Some(s) = &self.scoring {
+            payload["scoring"] = json!(s);
+        }
+        if let Some(n) = self.severity_cutoff {
+            payload["severity_cutoff"] = json!(n);
+        }
+        payload
+    }
+}
+
+pub async fn backtest(
+    iii: &III,
+    overrides: &BacktestOverrides,
+    all: bool,
+    format: &OutputFormat,
+) -> Result<()> {
+    let result = iii
+        .trigger(TriggerRequest {
+            function_id: "rimuru.runaway.backtest".to_string(),
+            payload: overrides.payload(),
+            action: None,
+            timeout_ms: Some(120_000),
+        })
+        .await?;
+    let result = crate::output::unwrap_body(result);
+    println!("{}", output::format_runaway_backtest(&result, all, format));
+    Ok(())
+}
+
+pub async fn label(
+    iii: &III,
+    session_id: &str,
+    kind: LabelKind,
+    note: Option<&str>,
+    format: &OutputFormat,
+) -> Result<()> {
+    let label = match kind {
+        LabelKind::TruePositive => json!("true_positive"),
+        LabelKind::FalsePositive => json!("false_positive"),
+        LabelKind::Clear => Value::Null,
+    };
+    let result = iii
+        .trigger(TriggerRequest {
+            function_id: "rimuru.runaway.label".to_string(),
+            payload: json!({"session_id": session_id, "label": label, "note": note}),
+            action: None,
+            timeout_ms: None,
+        })
+        .await?;
+    let result = crate::output::unwrap_body(result);
+    output::print_value(&result, format);
+    Ok(())
+}
@@ -2,6 +2,7 @@ use std::io::{Read, Write};
 
 use anyhow::Result;
 use rimuru_core::mcp::compress::{self, CompressionStrategy};
+use rimuru_core::tokenizer::Encoding;
 use serde_json::Value;
 
 #[derive(Debug, Clone, Copy, clap::ValueEnum)]
@@ -12,6 +13,7 @@ pub enum SlimStrategy {
     Json,
     Errors,
     Tree,
+    Dedup,
 }
 
 impl From<SlimStrategy> for CompressionStrategy {
@@ -23,17 +25,24 @@ impl From<SlimStrategy> for CompressionStrategy {
             SlimStrategy::Json => CompressionStrategy::JsonPaths,
             SlimStrategy::Errors => CompressionStrategy::ErrorsOnly,
             SlimStrategy::Tree => CompressionStrategy::TreeView,
+            SlimStrategy::Dedup => CompressionStrategy::Dedup,
         }
     }
 }
 
-pub fn run(strategy: SlimStrategy, max_tokens: u64, stats: bool) -> Result<()> {
+pub fn run(
+    strategy: SlimStrategy,
+    max_tokens: u64,
+    stats: bool,
+    model: Option<&str>,
+) -> Result<()> {
     let mut buf = String::new();
     std::io::stdin().read_to_string(&mut buf)?;
 
     let input_value: Value = serde_json::from_str(&buf).unwrap_or(Value::String(buf.clone()));
 
-    let result = compress::compress(&input_value, strategy.into(), max_tokens);
+    let encoding = model.map(Encoding::for_model).unwrap_or_default();
+    let result = compress::compress_with(&input_value, strategy.into(), max_tokens, encoding);
 
     let output = match &result.compressed {
         Value::String(s) => s.clone(),
@@ -53,8 +62,10 @@ pub fn run(strategy: SlimStrategy, max_tokens: u64, stats: bool) -> Result<()> {
         let mut err = stderr.lock();
         writeln!(
             err,
-            "[slim] strategy={} original={} compressed={} savings={:.1}%",
+            "[slim] strategy={} tokenizer={}{} original={} compressed={} savings={:.1}%",
             result.strategy_used,
+            encoding,
+            if encoding.is_exact() { "" } else { "-approx" },
             result.original_tokens,
             result.compressed_tokens,
             result.savings_percent
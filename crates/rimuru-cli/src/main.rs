@@ -49,6 +49,18 @@ enum Commands {
         action: CostsAction,
     },
 
+    #[command(about = "Budget caps and spend status")]
+    Budget {
+        #[command(subcommand)]
+        action: BudgetAction,
+    },
+
+    #[command(about = "Exchange rates for the display and budget currencies")]
+    Currency {
+        #[command(subcommand)]
+        action: CurrencyAction,
+    },
+
     #[command(about = "Model pricing data")]
     Models {
         #[command(subcommand)]
@@ -97,6 +109,12 @@ enum Commands {
         action: GuardAction,
     },
 
+    #[command(about = "Runaway detection tuning")]
+    Runaway {
+        #[command(subcommand)]
+        action: RunawayAction,
+    },
+
     #[command(about = "Live context budget tracker (htop for tokens)")]
     Ctx {
         #[arg(long, help = "Target a specific session id (full or prefix)")]
@@ -113,6 +131,11 @@ enum Commands {
         max_tokens: u64,
         #[arg(long, help = "Print compression stats to stderr")]
         stats: bool,
+        #[arg(
+            long,
+            help = "Count tokens with this model's tokenizer (e.g. claude-sonnet-4-6, gpt-4o)"
+        )]
+        model: Option<String>,
     },
 
     #[command(about = "Health check")]
@@ -154,13 +177,23 @@ enum SessionsAction {
 #[derive(Subcommand)]
 enum CostsAction {
     #[command(about = "Cost summary")]
-    Summary,
+    Summary {
+        #[arg(long, help = "ISO currency code to report in (defaults to config)")]
+        currency: Option<String>,
+    },
     #[command(about = "Daily cost breakdown")]
-    Daily,
+    Daily {
+        #[arg(long, help = "IANA timezone for day boundaries (defaults to config)")]
+        timezone: Option<String>,
+        #[arg(long, help = "ISO currency code to report in (defaults to config)")]
+        currency: Option<String>,
+    },
     #[command(about = "Cost breakdown by agent")]
     Agent {
         #[arg(long)]
         agent_id: Option<String>,
+        #[arg(long, help = "ISO currency code to report in (defaults to config)")]
+        currency: Option<String>,
     },
     #[command(about = "Export cost data as CSV or JSON")]
     Export {
@@ -172,6 +205,8 @@ enum CostsAction {
         from: Option<String>,
         #[arg(long)]
         to: Option<String>,
+        #[arg(long, help = "ISO currency code to report in (defaults to config)")]
+        currency: Option<String>,
         #[arg(long)]
         output: Option<PathBuf>,
     },
@@ -211,6 +246,78 @@ impl ExportPeriod {
     }
 }
 
+#[derive(Subcommand)]
+enum BudgetAction {
+    #[command(about = "Show spend against configured caps")]
+    Status {
+        #[arg(long, help = "Only show project caps matching this path")]
+        project: Option<String>,
+    },
+    #[command(about = "Project week-end and month-end spend and when each cap will be hit")]
+    Forecast {
+        #[arg(long, help = "Days of history to fit on (7-180, default 28)")]
+        lookback_days: Option<u64>,
+        #[arg(long, help = "IANA timezone for day boundaries (defaults to config)")]
+        timezone: Option<String>,
+    },
+    #[command(about = "Temporarily raise every cap by an amount, with a reason")]
+    Override {
+        #[arg(
+            long,
+            help = "Extra amount allowed on each cap, in the budget currency"
+        )]
+        amount: f64,
+        #[arg(long = "for", help = "How long the override lasts, e.g. 30m, 2h, 1d")]
+        duration: String,
+        #[arg(long)]
+        reason: String,
+    },
+    #[command(about = "List budget overrides")]
+    Overrides {
+        #[arg(long, help = "Include expired and revoked overrides")]
+        all: bool,
+    },
+    #[command(about = "Revoke an active budget override")]
+    Revoke { id: String },
+    #[command(about = "Set daily/monthly caps for a project path or glob (0 removes)")]
+    Project {
+        pattern: String,
+        #[arg(long)]
+        daily: Option<f64>,
+        #[arg(long)]
+        monthly: Option<f64>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CurrencyAction {
+    #[command(about = "List exchange rates, newest first")]
+    Rates {
+        #[arg(long, help = "Only show rates for this currency")]
+        currency: Option<String>,
+    },
+    #[command(about = "Record a rate (units per USD) effective from a date")]
+    Set {
+        code: String,
+        rate: f64,
+        #[arg(
+            long,
+            help = "First day the rate applies, YYYY-MM-DD (defaults to today)"
+        )]
+        effective_from: Option<String>,
+    },
+    #[command(about = "Convert an amount between currencies")]
+    Convert {
+        amount: f64,
+        #[arg(long, help = "Target currency (defaults to config)")]
+        to: Option<String>,
+        #[arg(long, default_value = "USD")]
+        from: String,
+        #[arg(long, help = "Use the rate effective on this date, YYYY-MM-DD")]
+        date: Option<String>,
+    },
+}
+
 #[derive(Subcommand)]
 enum ModelsAction {
     #[command(about = "List models and pricing")]
@@ -323,6 +430,11 @@ enum ConfigSyncAction {
         path: String,
         #[arg(long, help = "Actually write changes (default is dry-run)")]
         apply: bool,
+        #[arg(
+            long,
+            help = "Swap each agent's default model for the budget downgrade substitute"
+        )]
+        downgrade: bool,
     },
     #[command(about = "Show drift between installed agents")]
     Diff {
@@ -334,18 +446,50 @@ enum ConfigSyncAction {
 #[derive(Subcommand)]
 enum GuardAction {
     #[command(about = "Start a guarded process")]
-    Start {
-        #[arg(long, value_parser = commands::guard::validate_limit)]
-        limit: f64,
-        #[arg(long, value_enum, default_value_t = commands::guard::GuardActionMode::Warn)]
-        action: commands::guard::GuardActionMode,
-        #[arg(trailing_var_arg = true, required = true)]
-        command: Vec<String>,
-    },
+    Start(commands::guard::StartArgs),
     #[command(about = "Show active guarded processes")]
     Status,
+    #[command(about = "Stop a guarded process from another terminal")]
+    Kill { id: String },
+    #[command(about = "Pause a guarded process until it is resumed")]
+    Pause { id: String },
+    #[command(about = "Resume a paused guarded process")]
+    Resume { id: String },
+    #[command(about = "Raise a running guard's limits")]
+    Extend {
+        id: String,
+        #[command(flatten)]
+        limits: commands::guard::GuardLimits,
+    },
     #[command(about = "Show guard history")]
     History,
+    #[command(about = "Show what a --snapshot guard's run changed, or roll it back")]
+    Restore {
+        id: String,
+        #[arg(long, help = "Roll the working tree back to its state before the run")]
+        apply: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum RunawayAction {
+    #[command(
+        about = "Replay stored sessions through the detectors and report what they would have caught"
+    )]
+    Backtest {
+        #[command(flatten)]
+        overrides: commands::runaway::BacktestOverrides,
+        #[arg(long, help = "Show sessions that were neither flagged nor labeled")]
+        all: bool,
+    },
+    #[command(about = "Mark a session as a true or false positive for future backtests")]
+    Label {
+        session_id: String,
+        #[arg(value_enum)]
+        label: commands::runaway::LabelKind,
+        #[arg(long)]
+        note: Option<String>,
+    },
 }
 
 #[tokio::main]
@@ -361,9 +505,10 @@ async fn main() -> Result<()> {
         strategy,
         max_tokens,
         stats,
+        model,
     } = cli.command
     {
-        return commands::slim::run(strategy, max_tokens, stats);
+        return commands::slim::run(strategy, max_tokens, stats, model.as_deref());
     }
 
     let iii = register_worker(&cli.engine_url, InitOptions::default());
@@ -403,16 +548,21 @@ async fn main() -> Result<()> {
         },
 
         Commands::Costs { action } => match action {
-            CostsAction::Summary => commands::costs::summary(&iii, format).await,
-            CostsAction::Daily => commands::costs::daily(&iii, format).await,
-            CostsAction::Agent { agent_id } => {
-                commands::costs::agent(&iii, agent_id.as_deref(), format).await
+            CostsAction::Summary { currency } => {
+                commands::costs::summary(&iii, currency.as_deref(), format).await
+            }
+            CostsAction::Daily { timezone, currency } => {
+                commands::costs::daily(&iii, timezone.as_deref(), currency.as_deref(), format).await
+            }
+            CostsAction::Agent { agent_id, currency } => {
+                commands::costs::agent(&iii, agent_id.as_deref(), currency.as_deref(), format).await
             }
             CostsAction::Export {
                 format: export_format,
                 period,
                 from,
                 to,
+                currency,
                 output,
             } => {
                 if matches!(period, ExportPeriod::Custom) && (from.is_none() || to.is_none()) {
@@ -424,12 +574,68 @@ async fn main() -> Result<()> {
                     period.as_str(),
                     from.as_deref(),
                     to.as_deref(),
+                    currency.as_deref(),
                     output.as_deref(),
                 )
                 .await
             }
         },
 
+        Commands::Currency { action } => match action {
+            CurrencyAction::Rates { currency } => {
+                commands::currency::rates(&iii, currency.as_deref(), format).await
+            }
+            CurrencyAction::Set {
+                code,
+                rate,
+                effective_from,
+            } => {
+                commands::currency::set(&iii, &code, rate, effective_from.as_deref(), format).await
+            }
+            CurrencyAction::Convert {
+                amount,
+                to,
+                from,
+                date,
+            } => {
+                commands::currency::convert(
+                    &iii,
+                    amount,
+                    &from,
+                    to.as_deref(),
+                    date.as_deref(),
+                    format,
+                )
+                .await
+            }
+        },
+
+        Commands::Budget { action } => match action {
+            BudgetAction::Status { project } => {
+                commands::budget::status(&iii, project.as_deref(), format).await
+            }
+            BudgetAction::Forecast {
+                lookback_days,
+                timezone,
+            } => commands::budget::forecast(&iii, lookback_days, timezone.as_deref(), format).await,
+            BudgetAction::Override {
+                amount,
+                duration,
+                reason,
+            } => commands::budget::grant_override(&iii, amount, &duration, &reason, format).await,
+            BudgetAction::Overrides { all } => {
+                commands::budget::list_overrides(&iii, all, format).await
+            }
+            BudgetAction::Revoke { id } => {
+                commands::budget::revoke_override(&iii, &id, format).await
+            }
+            BudgetAction::Project {
+                pattern,
+                daily,
+                monthly,
+            } => commands::budget::set_project(&iii, &pattern, daily, monthly, format).await,
+        },
+
         Commands::Models { action } => match action {
             ModelsAction::List => commands::models::list(&iii, format).await,
             ModelsAction::Sync => commands::models::sync(&iii, format).await,
@@ -500,9 +706,11 @@ async fn main() -> Result<()> {
             }
             ConfigAction::Sync { action } => match action {
                 ConfigSyncAction::Export => commands::config::sync_export(&iii).await,
-                ConfigSyncAction::Import { path, apply } => {
-                    commands::config::sync_import(&iii, &path, apply).await
-                }
+                ConfigSyncAction::Import {
+                    path,
+                    apply,
+                    downgrade,
+                } => commands::config::sync_import(&iii, &path, apply, downgrade).await,
                 ConfigSyncAction::Diff { agent } => {
                     commands::config::sync_diff(&iii, agent.as_deref()).await
                 }
@@ -510,13 +718,33 @@ async fn main() -> Result<()> {
         },
 
         Commands::Guard { action } => match action {
-            GuardAction::Start {
-                limit,
-                action,
-                command,
-            } => commands::guard::start(&iii, limit, action, &command, format).await,
+            GuardAction::Start(args) => commands::guard::start(&iii, &args, format).await,
             GuardAction::Status => commands::guard::status(&iii, format).await,
+            GuardAction::Kill { id } => {
+                commands::guard::request(&iii, &id, "kill", None, format).await
+            }
+            GuardAction::Pause { id } => {
+                commands::guard::request(&iii, &id, "pause", None, format).await
+            }
+            GuardAction::Resume { id } => {
+                commands::guard::request(&iii, &id, "resume", None, format).await
+            }
+            GuardAction::Extend { id, limits } => {
+                commands::guard::request(&iii, &id, "extend", Some(&limits), format).await
+            }
             GuardAction::History => commands::guard::history(&iii, format).await,
+            GuardAction::Restore { id, apply } => commands::guard::restore(&iii, &id, apply).await,
+        },
+
+        Commands::Runaway { action } => match action {
+            RunawayAction::Backtest { overrides, all } => {
+                commands::runaway::backtest(&iii, &overrides, all, format).await
+            }
+            RunawayAction::Label {
+                session_id,
+                label,
+                note,
+            } => commands::runaway::label(&iii, &session_id, label, note.as_deref(), format).await,
         },
 
         Commands::Ctx { session, once } => commands::ctx::run(&iii, session, !once).await,
@@ -1,4 +1,5 @@
 use comfy_table::{Cell, CellAlignment, Color, ContentArrangement, Table};
+use rimuru_core::functions::currency;
 use serde::Serialize;
 use serde_json::Value;
 
@@ -42,6 +43,23 @@ fn f64_field(v: &Value, key: &str) -> f64 {
     v.get(key).and_then(|v| v.as_f64()).unwrap_or(0.0)
 }
 
+/// Currency a response reports its amounts in; USD when it doesn't say.
+fn currency_of(v: &Value) -> &str {
+    v.get("currency")
+        .and_then(|v| v.as_str())
+        .unwrap_or(currency::BASE_CURRENCY)
+}
+
+/// `amount` with the currency's symbol (or code) and `precision` decimals,
+/// dropping them for currencies without minor units.
+fn money(amount: f64, code: &str, precision: usize) -> String {
+    let precision = precision.min(currency::decimals(code));
+    match currency::symbol(code) {
+        Some(sym) => format!("{sym}{amount:.precision$}"),
+        None => format!("{amount:.precision$} {code}"),
+    }
+}
+
 fn bool_field(v: &Value, key: &str) -> bool {
     v.get(key).and_then(|v| v.as_bool()).unwrap_or(false)
 }
@@ -235,11 +253,12 @@ pub fn format_costs_summary(costs: &Value, format: &OutputFormat) -> String {
         OutputFormat::Yaml => serde_yaml_to_string(costs),
         OutputFormat::Table => {
             let mut out = String::new();
+            let code = currency_of(costs);
 
             let mut summary = new_table(&["Metric", "Value"]);
             summary.add_row(vec![
                 Cell::new("Est. Cost (API rates)"),
-                Cell::new(format!("${:.2}", f64_field(costs, "total_cost"))),
+                Cell::new(money(f64_field(costs, "total_cost"), code, 2)),
             ]);
             summary.add_row(vec![
                 Cell::new("Input Tokens"),
@@ -264,7 +283,7 @@ pub fn format_costs_summary(costs: &Value, format: &OutputFormat) -> String {
                     agent_table.add_row(vec![
                         Cell::new(str_field(a, "agent_name")),
                         Cell::new(str_field(a, "agent_type")),
-                        Cell::new(format!("${:.4}", f64_field(a, "total_cost"))),
+                        Cell::new(money(f64_field(a, "total_cost"), code, 4)),
                         Cell::new(u64_field(a, "record_count")).set_alignment(CellAlignment::Right),
                     ]);
                 }
@@ -280,7 +299,7 @@ pub fn format_costs_summary(costs: &Value, format: &OutputFormat) -> String {
                     model_table.add_row(vec![
                         Cell::new(str_field(m, "model")),
                         Cell::new(str_field(m, "provider")),
-                        Cell::new(format!("${:.4}", f64_field(m, "total_cost"))),
+                        Cell::new(money(f64_field(m, "total_cost"), code, 4)),
                         Cell::new(format_tokens(u64_field(m, "total_tokens"))),
                     ]);
                 }
@@ -292,7 +311,7 @@ pub fn format_costs_summary(costs: &Value, format: &OutputFormat) -> String {
     }
 }
 
-pub fn format_daily_costs(days: &[Value], format: &OutputFormat) -> String {
+pub fn format_daily_costs(days: &[Value], currency: &str, format: &OutputFormat) -> String {
     match format {
         OutputFormat::Json => serde_json::to_string_pretty(days).unwrap_or_default(),
         OutputFormat::Yaml => serde_yaml_to_string(days),
@@ -302,7 +321,7 @@ pub fn format_daily_costs(days: &[Value], format: &OutputFormat) -> String {
             for day in days {
                 table.add_row(vec![
                     Cell::new(str_field(day, "date")),
-                    Cell::new(format!("${:.4}", f64_field(day, "total_cost"))),
+                    Cell::new(money(f64_field(day, "total_cost"), currency, 4)),
                     Cell::new(format_tokens(u64_field(day, "total_input_tokens"))),
                     Cell::new(format_tokens(u64_field(day, "total_output_tokens"))),
                     Cell::new(u64_field(day, "record_count")).set_alignment(CellAlignment::Right),
@@ -613,6 +632,357 @@ pub fn format_mcp_list(servers: &[Value], format: &OutputFormat) -> String {
     }
 }
 
+fn limit_cell(limit: f64, currency: &str) -> Cell {
+    if limit > 0.0 {
+        Cell::new(money(limit, currency, 2))
+    } else {
+        Cell::new("-")
+    }
+}
+
+fn remaining_cell(limit: f64, spent: f64, currency: &str) -> Cell {
+    if limit > 0.0 {
+        Cell::new(money((limit - spent).max(0.0), currency, 2))
+    } else {
+        Cell::new("-")
+    }
+}
+
+fn cap_status(spent: f64, limit: f64, threshold: f64) -> &'static str {
+    if limit <= 0.0 {
+        "-"
+    } else if spent >= limit {
+        "exceeded"
+    } else if spent >= limit * threshold {
+        "warning"
+    } else {
+        "ok"
+    }
+}
+
+pub fn format_budget_status(status: &Value, format: &OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(status).unwrap_or_default(),
+        OutputFormat::Yaml => serde_yaml_to_string(status),
+        OutputFormat::Table => {
+            let budget_colors = [
+                ("ok", Color::Green),
+                ("warning", Color::Yellow),
+                ("exceeded", Color::Red),
+            ];
+            let threshold = status
+                .get("alert_threshold")
+                .and_then(|v| v.as_f64())
+                .unwrap_or(0.8);
+            let code = currency_of(status);
+
+            let mut table = new_table(&["Scope", "Limit", "Spent", "Remaining", "Status"]);
+            for (label, limit_key, spent_key) in [
+                ("Monthly", "monthly_limit", "monthly_spent"),
+                ("Daily", "daily_limit", "daily_spent"),
+            ] {
+                let limit = f64_field(status, limit_key);
+                let spent = f64_field(status, spent_key);
+                table.add_row(vec![
+                    Cell::new(label),
+                    limit_cell(limit, code),
+                    Cell::new(money(spent, code, 2)),
+                    remaining_cell(limit, spent, code),
+                    status_cell(cap_status(spent, limit, threshold), &budget_colors),
+                ]);
+            }
+
+            if let Some(rolling) = status.get("rolling").and_then(|v| v.as_array()) {
+                for window in rolling {
+                    let limit = f64_field(window, "limit");
+                    let spent = f64_field(window, "spent");
+                    table.add_row(vec![
+                        Cell::new(str_field(window, "label")),
+                        limit_cell(limit, code),
+                        Cell::new(money(spent, code, 2)),
+                        remaining_cell(limit, spent, code),
+                        status_cell(cap_status(spent, limit, threshold), &budget_colors),
+                    ]);
+                }
+            }
+
+            if let Some(projects) = status.get("projects").and_then(|v| v.as_array()) {
+                for project in projects {
+                    let pattern = str_field(project, "pattern");
+                    for (period, limit_key, spent_key) in [
+                        ("daily", "daily_limit", "daily_spent"),
+                        ("monthly", "monthly_limit", "monthly_spent"),
+                    ] {
+                        let limit = f64_field(project, limit_key);
+                        if limit <= 0.0 {
+                            continue;
+                        }
+                        let spent = f64_field(project, spent_key);
+                        table.add_row(vec![
+                            Cell::new(format!("{pattern} ({period})")),
+                            limit_cell(limit, code),
+                            Cell::new(money(spent, code, 2)),
+                            remaining_cell(limit, spent, code),
+                            status_cell(cap_status(spent, limit, threshold), &budget_colors),
+                        ]);
+                    }
+                }
+            }
+
+            format!(
+                "{table}\nBurn rate: {}/day  Projected monthly: {}  Action on exceed: {}",
+                money(f64_field(status, "burn_rate_daily"), code, 2),
+                money(f64_field(status, "projected_monthly"), code, 2),
+                str_field(status, "action_on_exceed"),
+            )
+        }
+    }
+}
+
+pub fn format_budget_overrides(
+    overrides: &[Value],
+    currency: &str,
+    format: &OutputFormat,
+) -> String {
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(overrides).unwrap_or_default(),
+        OutputFormat::Yaml => serde_yaml_to_string(overrides),
+        OutputFormat::Table => {
+            if overrides.is_empty() {
+                return "No budget overrides.".to_string();
+            }
+            let colors = [
+                ("active", Color::Green),
+                ("expired", Color::DarkGrey),
+                ("revoked", Color::Yellow),
+            ];
+            let mut table =
+                new_table(&["ID", "Amount", "Status", "Expires", "Granted by", "Reason"]);
+            for o in overrides {
+                table.add_row(vec![
+                    Cell::new(str_field(o, "id")),
+                    Cell::new(format!("+{}", money(f64_field(o, "amount"), currency, 2))),
+                    status_cell(str_field(o, "status"), &colors),
+                    Cell::new(str_field(o, "expires_at")),
+                    Cell::new(str_field(o, "granted_by")),
+                    Cell::new(str_field(o, "reason")),
+                ]);
+            }
+            table.to_string()
+        }
+    }
+}
+
+pub fn format_exchange_rates(rates: &Value, format: &OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(rates).unwrap_or_default(),
+        OutputFormat::Yaml => serde_yaml_to_string(rates),
+        OutputFormat::Table => {
+            let entries = rates
+                .get("rates")
+                .and_then(|v| v.as_array())
+                .cloned()
+                .unwrap_or_default();
+            let footer = format!(
+                "Display currency: {}  Budget currency: {}",
+                str_field(rates, "currency"),
+                str_field(rates, "budget_currency"),
+            );
+            if entries.is_empty() {
+                return format!("No exchange rates.\n{footer}");
+            }
+            let mut table = new_table(&["Currency", "Per USD", "Effective from", "Set by"]);
+            for r in &entries {
+                table.add_row(vec![
+                    Cell::new(str_field(r, "currency")),
+                    Cell::new(format!("{}", f64_field(r, "rate")))
+                        .set_alignment(CellAlignment::Right),
+                    Cell::new(str_field(r, "effective_from")),
+                    Cell::new(str_field(r, "set_by")),
+                ]);
+            }
+            format!("{table}\n{footer}")
+        }
+    }
+}
+
+fn band_cell(band: &Value, currency: &str) -> Cell {
+    Cell::new(format!(
+        "{} ({} - {})",
+        money(f64_field(band, "expected"), currency, 2),
+        money(f64_field(band, "low"), currency, 2),
+        money(f64_field(band, "high"), currency, 2),
+    ))
+}
+
+pub fn format_budget_forecast(forecast: &Value, format: &OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(forecast).unwrap_or_default(),
+        OutputFormat::Yaml => serde_yaml_to_string(forecast),
+        OutputFormat::Table => {
+            let confidence = f64_field(forecast, "confidence") * 100.0;
+            let code = currency_of(forecast);
+            let mut periods = new_table(&[
+                "Period",
+                "Ends",
+                "Spent",
+                &format!("Projected ({confidence:.0}% band)"),
+            ]);
+            for (label, key) in [("Week", "week_end"), ("Month", "month_end")] {
+                let Some(period) = forecast.get(key) else {
+                    continue;
+                };
+                periods.add_row(vec![
+                    Cell::new(label),
+                    Cell::new(str_field(period, "date")),
+                    Cell::new(money(f64_field(period, "spent"), code, 2)),
+                    band_cell(period.get("projected").unwrap_or(&Value::Null), code),
+                ]);
+            }
+            let mut out = periods.to_string();
+
+            let caps = forecast
+                .get("caps")
+                .and_then(|v| v.as_array())
+                .cloned()
+                .unwrap_or_default();
+            if !caps.is_empty() {
+                let mut table =
+                    new_table(&["Cap", "Limit", "Spent", "Expected hit", "Earliest hit"]);
+                for cap in &caps {
+                    table.add_row(vec![
+                        Cell::new(str_field(cap, "label")),
+                        limit_cell(f64_field(cap, "limit"), code),
+                        Cell::new(money(f64_field(cap, "spent"), code, 2)),
+                        Cell::new(str_field(cap, "expected_hit_date")),
+                        Cell::new(str_field(cap, "earliest_hit_date")),
+                    ]);
+                }
+                out.push_str(&format!("\n{table}"));
+            }
+
+            for (title, key, label_key) in [
+                ("Agent", "by_agent", "agent_name"),
+                ("Model", "by_model", "model"),
+            ] {
+                let rows = forecast
+                    .get(key)
+                    .and_then(|v| v.as_array())
+                    .cloned()
+                    .unwrap_or_default();
+                if rows.is_empty() {
+                    continue;
+                }
+                let mut table = new_table(&[title, "Month spent", "Rate/day", "Month end"]);
+                for row in &rows {
+                    table.add_row(vec![
+                        Cell::new(str_field(row, label_key)),
+                        Cell::new(money(f64_field(row, "month_spent"), code, 2)),
+                        Cell::new(money(f64_field(row, "daily_rate"), code, 2)),
+                        band_cell(row.get("month_end").unwrap_or(&Value::Null), code),
+                    ]);
+                }
+                out.push_str(&format!("\n{table}"));
+            }
+
+            let model = forecast.get("model").unwrap_or(&Value::Null);
+            format!(
+                "{out}\nStatus: {}  Daily level: {} +/- {}  History: {} days  Timezone: {}",
+                str_field(forecast, "status"),
+                money(f64_field(model, "daily_level"), code, 2),
+                money(f64_field(model, "daily_sigma"), code, 2),
+                u64_field(model, "history_days"),
+                str_field(forecast, "timezone"),
+            )
+        }
+    }
+}
+
+pub fn format_runaway_backtest(result: &Value, all: bool, format: &OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(result).unwrap_or_default(),
+        OutputFormat::Yaml => serde_yaml_to_string(result),
+        OutputFormat::Table => {
+            let sessions = result
+                .get("sessions")
+                .and_then(|v| v.as_array())
+                .cloned()
+                .unwrap_or_default();
+            let shown: Vec<&Value> = sessions
+                .iter()
+                .filter(|s| all || !s["flagged_at_turn"].is_null() || !s["label"].is_null())
+                .collect();
+            let colors = [
+                ("true_positive", Color::Green),
+                ("false_positive", Color::Red),
+            ];
+            let mut table = new_table(&[
+                "Session",
+                "Turns",
+                "Flagged at",
+                "Severity",
+                "Patterns",
+                "Tokens saved",
+                "Saved",
+                "Label",
+            ]);
+            for s in &shown {
+                let flagged = match s["flagged_at_turn"].as_u64() {
+                    Some(turn) => match s["flagged_at"].as_str() {
+                        Some(at) => format!("turn {turn} ({at})"),
+                        None => format!("turn {turn}"),
+                    },
+                    None => "-".to_string(),
+                };
+                let patterns: Vec<&str> = s["patterns"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|p| p.as_str())
+                    .collect();
+                table.add_row(vec![
+                    Cell::new(str_field(s, "session_id")),
+                    Cell::new(u64_field(s, "turns")).set_alignment(CellAlignment::Right),
+                    Cell::new(flagged),
+                    Cell::new(format!("{:.2}", f64_field(s, "severity")))
+                        .set_alignment(CellAlignment::Right),
+                    Cell::new(patterns.join(", ")),
+                    Cell::new(format_tokens(u64_field(s, "tokens_saved")))
+                        .set_alignment(CellAlignment::Right),
+                    Cell::new(money(f64_field(s, "dollars_saved"), "USD", 2))
+                        .set_alignment(CellAlignment::Right),
+                    status_cell(str_field(s, "label"), &colors),
+                ]);
+            }
+
+            let summary = result.get("summary").unwrap_or(&Value::Null);
+            let pct = |key: &str| match summary[key].as_f64() {
+                Some(v) => format!("{:.0}%", v * 100.0),
+                None => "-".to_string(),
+            };
+            let footer = format!(
+                "{} of {} sessions flagged  Saved: {} tokens, {}  Precision: {}  Recall: {}\n\
+                 Labeled: {}  False positives: {} ({} tokens)  Missed: {}  Unlabeled flags: {}",
+                u64_field(summary, "flagged"),
+                u64_field(summary, "sessions"),
+                format_tokens(u64_field(summary, "tokens_saved")),
+                money(f64_field(summary, "dollars_saved"), "USD", 2),
+                pct("precision"),
+                pct("recall"),
+                u64_field(summary, "labeled"),
+                u64_field(summary, "false_positives"),
+                format_tokens(u64_field(summary, "false_positive_tokens")),
+                u64_field(summary, "missed"),
+                u64_field(summary, "unlabeled_flags"),
+            );
+            if shown.is_empty() {
+                return format!("No sessions flagged.\n{footer}");
+            }
+            format!("{table}\n{footer}")
+        }
+    }
+}
+
 fn format_uptime(secs: u64) -> String {
     let days = secs / 86400;
     let hours = (secs % 86400) / 3600;
@@ -0,0 +1,196 @@
+//! `rimuru.budget.check` latency against a growing `cost_records` scope.
+//!
+//! The check reads spend counters instead of scanning records, so the time
+//! per call should stay flat from 1k to 100k records. The worker talks to a
+//! minimal in-process engine: `state::*` is served from memory and every
+//! other invocation is echoed back to the worker that sent it, which is all
+//! a single worker calling its own functions needs.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+use chrono_tz::Tz;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use futures_util::{SinkExt, StreamExt};
+use iii_sdk::{III, IIIConnectionState, InitOptions, TriggerRequest, register_worker};
+use rimuru_core::StateKV;
+use rimuru_core::functions::{budget, counters};
+use rimuru_core::models::{AgentType, CostRecord};
+use serde_json::{Value, json};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+use uuid::Uuid;
+
+type Scopes = Arc<Mutex<HashMap<String, BTreeMap<String, Value>>>>;
+
+fn handle_state(scopes: &Scopes, function_id: &str, data: &Value) -> Value {
+    let scope = data["scope"].as_str().unwrap_or_default().to_string();
+    let key = data["key"].as_str().unwrap_or_default().to_string();
+    let mut scopes = scopes.lock().unwrap();
+    let entries = scopes.entry(scope).or_default();
+    match function_id {
+        "state::get" => entries.get(&key).cloned().unwrap_or(Value::Null),
+        "state::set" => {
+            entries.insert(key, data["value"].clone());
+            Value::Null
+        }
+        "state::delete" => {
+            entries.remove(&key);
+            Value::Null
+        }
+        "state::list" => Value::Array(entries.values().cloned().collect()),
+        "state::update" => {
+            let entry = entries.entry(key).or_insert_with(|| json!({}));
+            for op in data["ops"].as_array().into_iter().flatten() {
+                let path = op["path"].as_str().unwrap_or_default();
+                match op["type"].as_str() {
+                    Some("increment") => {
+                        let by = op["by"].as_i64().unwrap_or(0);
+                        entry[path] = json!(entry[path].as_i64().unwrap_or(0) + by);
+                    }
+                    Some("set") => entry[path] = op["value"].clone(),
+                    _ => {}
+                }
+            }
+            json!({ "new_value": entry.clone() })
+        }
+        _ => Value::Null,
+    }
+}
+
+async fn serve(listener: TcpListener, scopes: Scopes) {
+    while let Ok((stream, _)) = listener.accept().await {
+        let scopes = scopes.clone();
+        tokio::spawn(async move {
+            let Ok(ws) = tokio_tungstenite::accept_async(stream).await else {
+                return;
+            };
+            let (mut tx, mut rx) = ws.split();
+            while let Some(Ok(Message::Text(text))) = rx.next().await {
+                let Ok(msg) = serde_json::from_str::<Value>(&text) else {
+                    continue;
+                };
+                let reply = match msg["type"].as_str() {
+                    Some("invokefunction") => {
+                        let function_id = msg["function_id"].as_str().unwrap_or_default();
+                        if function_id.starts_with("state::") {
+                            let result = handle_state(&scopes, function_id, &msg["data"]);
+                            msg["invocation_id"].as_str().map(|id| {
+                                json!({
+                                    "type": "invocationresult",
+                                    "invocation_id": id,
+                                    "function_id": function_id,
+                                    "result": result,
+                                })
+                            })
+                        } else {
+                            Some(msg)
+                        }
+                    }
+                    Some("invocationresult") => Some(msg),
+                    _ => None,
+                };
+                if let Some(reply) = reply
+                    && tx
+                        .send(Message::Text(reply.to_string().into()))
+                        .await
+                        .is_err()
+                {
+                    return;
+                }
+            }
+        });
+    }
+}
+
+/// `n` records spread over the last 30 days and 50 sessions, stored together
+/// with the counters `costs.record` would have maintained for them.
+fn seed(scopes: &Scopes, n: usize, agent_id: Uuid, sessions: &[Uuid]) {
+    let now = Utc::now();
+    let records: Vec<CostRecord> = (0..n)
+        .map(|i| {
+            let mut r = CostRecord::new(
+                agent_id,
+                AgentType::ClaudeCode,
+                "claude-sonnet-4-6".into(),
+                "anthropic".into(),
+                2_000,
+                400,
+                0.006,
+                0.006,
+            );
+            r.session_id = Some(sessions[i % sessions.len()]);
+            r.recorded_at = now - chrono::Duration::minutes((i % (30 * 24 * 60)) as i64);
+            r
+        })
+        .collect();
+    let totals = counters::rebuild(&records, Tz::UTC, &HashMap::new(), &[]);
+
+    let mut scopes = scopes.lock().unwrap();
+    scopes.clear();
+    scopes.insert(
+        "cost_records".into(),
+        records
+            .iter()
+            .map(|r| (r.id.to_string(), serde_json::to_value(r).unwrap()))
+            .collect(),
+    );
+    scopes.insert(
+        counters::SPEND_SCOPE.into(),
+        totals
+            .into_iter()
+            .map(|(k, micros)| (k, json!({ "cost_micros": micros })))
+            .collect(),
+    );
+    let config = scopes.entry("config".into()).or_default();
+    for key in ["budget_monthly", "budget_daily", "budget_session"] {
+        config.insert(key.into(), json!(1_000_000.0));
+    }
+}
+
+fn bench_budget_check(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let scopes: Scopes = Arc::default();
+    let iii: III = rt.block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, scopes.clone()));
+        let iii = register_worker(&url, InitOptions::default());
+        budget::register(&iii, &StateKV::new(iii.clone()));
+        while iii.get_connection_state() != IIIConnectionState::Connected {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        iii
+    });
+
+    let agent_id = Uuid::new_v4();
+    let sessions: Vec<Uuid> = (0..50).map(|_| Uuid::new_v4()).collect();
+    let payload = json!({
+        "agent_id": agent_id.to_string(),
+        "session_id": sessions[0].to_string(),
+        "pending_cost": 0.01,
+    });
+
+    let mut group = c.benchmark_group("budget_check");
+    for n in [1_000, 10_000, 100_000] {
+        seed(&scopes, n, agent_id, &sessions);
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, _| {
+            b.to_async(&rt).iter(|| async {
+                iii.trigger(TriggerRequest {
+                    function_id: "rimuru.budget.check".to_string(),
+                    payload: payload.clone(),
+                    action: None,
+                    timeout_ms: Some(10_000),
+                })
+                .await
+                .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_budget_check);
+criterion_main!(benches);
@@ -1,7 +1,93 @@
-use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use criterion::{Criterion, Throughput, black_box, criterion_group, criterion_main};
 use rimuru_core::mcp::McpClient;
+use rimuru_core::tokenizer::{self, Encoding};
 use serde_json::json;
 
+fn samples() -> Vec<(&'static str, String)> {
+    let prose = "Budgets are enforced per session. When a guarded agent exceeds its \
+                 limit, rimuru stops the process group and records why it intervened. "
+        .repeat(100);
+    let json = serde_json::to_string(&json!({
+        "tools": (0..50)
+            .map(|i| json!({
+                "name": format!("tool_{i}"),
+                "description": "a tool that does a thing",
+                "input_schema": {"type": "object", "properties": {"x": {"type": "string"}}},
+            }))
+            .collect::<Vec<_>>()
+    }))
+    .unwrap();
+    let code = include_str!("../src/mcp/compress.rs").to_string();
+    let cjk = "東京は日本の首都であり、世界最大の都市圏の一つです。".repeat(100);
+    let logs = (0..500)
+        .map(|i| {
+            format!(
+                "2026-01-01T00:00:{:02}Z INFO worker[{i}] handled req 0x{:08x} in {}ms",
+                i % 60,
+                i * 7919,
+                i % 97
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    vec![
+        ("prose", prose),
+        ("json", json),
+        ("code", code),
+        ("cjk", cjk),
+        ("logs", logs),
+    ]
+}
+
+/// Prints how far the tokenizer and `bytes / 4` are from tiktoken's counts
+/// on `tokenizer::REFERENCES`. The embedded vocabularies are tiktoken's own,
+/// so the table column should show no error.
+fn report_accuracy() {
+    println!("\nencoding  tiktoken  table (error)  bytes/4 (error)");
+    for encoding in Encoding::ALL {
+        let (mut want, mut table, mut guess) = (0, 0, 0);
+        for r in tokenizer::REFERENCES {
+            let Some(n) = r.count(encoding) else {
+                continue;
+            };
+            want += n;
+            table += encoding.count(r.text);
+            guess += tokenizer::heuristic_tokens(r.text);
+        }
+        if want == 0 {
+            println!("{encoding:<8}  no reference counts");
+            continue;
+        }
+        let err = |n: u64| (n as f64 - want as f64) / want as f64 * 100.0;
+        let exact = if encoding.is_exact() { "" } else { "~" };
+        println!(
+            "{:<8}  {want:>8}  {table:>5} ({:+.0}%)  {guess:>7} ({:+.0}%)",
+            format!("{encoding}{exact}"),
+            err(table),
+            err(guess)
+        );
+    }
+    println!();
+}
+
+fn bench_tokenizer(c: &mut Criterion) {
+    report_accuracy();
+
+    let mut group = c.benchmark_group("count_tokens");
+    for (name, text) in samples() {
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_function(format!("heuristic/{name}"), |b| {
+            b.iter(|| tokenizer::heuristic_tokens(black_box(&text)))
+        });
+        for encoding in Encoding::ALL {
+            group.bench_function(format!("{encoding}/{name}"), |b| {
+                b.iter(|| encoding.count(black_box(&text)))
+            });
+        }
+    }
+    group.finish();
+}
+
 fn bench_estimate_tokens(c: &mut Criterion) {
     let small = json!({"x": 1, "y": "hello"});
     let medium = json!({
@@ -47,5 +133,10 @@ fn bench_compression(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bench_estimate_tokens, bench_compression);
+criterion_group!(
+    benches,
+    bench_tokenizer,
+    bench_estimate_tokens,
+    bench_compression
+);
 criterion_main!(benches);
@@ -1,17 +1,18 @@
 use async_trait::async_trait;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use chrono::Utc;
 use serde_json::Value;
 use tracing::{debug, warn};
 use uuid::Uuid;
 
-use super::{AdapterCore, AgentAdapter};
+use super::{AdapterCore, AgentAdapter, TailParser};
 use crate::error::RimuruError;
 use crate::models::{
-    Agent, AgentStatus, AgentType, ContextBreakdown, Session, SessionStatus, ToolCallRecord,
-    TurnRecord,
+    Agent, AgentStatus, AgentType, ContextBreakdown, FileEdit, Session, SessionStatus,
+    ToolCallRecord, TurnRecord,
 };
+use crate::tokenizer::Encoding;
 
 type Result<T> = std::result::Result<T, RimuruError>;
 
@@ -40,7 +41,7 @@ impl ClaudeCodeAdapter {
         self.config_path = path;
     }
 
-    fn projects_dir(&self) -> PathBuf {
+    pub(crate) fn projects_dir(&self) -> PathBuf {
         self.config_path.join("projects")
     }
 
@@ -115,6 +116,16 @@ impl ClaudeCodeAdapter {
         Ok(())
     }
 
+    /// Project directory name a session file sits under.
+    pub(crate) fn project_dir(jsonl_path: &Path) -> String {
+        jsonl_path
+            .parent()
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str())
+            .unwrap_or("")
+            .to_string()
+    }
+
     fn parse_session_jsonl(&self, jsonl_path: &PathBuf) -> Result<Session> {
         let (session, _breakdown) = self.parse_session_jsonl_full(jsonl_path)?;
         Ok(session)
@@ -127,235 +138,27 @@ impl ClaudeCodeAdapter {
         let content = std::fs::read_to_string(jsonl_path)?;
 
         let mut session = Session::new(self.agent_id, AgentType::ClaudeCode);
-        let mut breakdown = ContextBreakdown::new(session.id);
-
-        let project_dir = jsonl_path
-            .parent()
-            .map(|p| p.file_name().and_then(|n| n.to_str()).unwrap_or(""))
-            .unwrap_or("");
-        session.project_path = Some(project_dir.to_string());
-
-        let mut msg_count: u64 = 0;
-        let mut total_input: u64 = 0;
-        let mut total_output: u64 = 0;
-        let mut total_web_search_requests: u64 = 0;
-        let mut has_fast_mode = false;
-        let mut total_cache_read: u64 = 0;
-        let mut total_cache_write: u64 = 0;
-        let mut last_model: Option<String> = None;
-        let mut session_id_found: Option<String> = None;
-        let mut first_timestamp: Option<String> = None;
-        let mut last_timestamp: Option<String> = None;
-        let mut turn_index: u32 = 0;
+        session.project_path = Some(Self::project_dir(jsonl_path));
 
+        let mut parser = JsonlParser::new(session.id);
         for line in content.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-            let entry: Value = match serde_json::from_str(line) {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
-
-            let timestamp = entry
-                .get("timestamp")
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string());
-
-            if let Some(ref ts) = timestamp {
-                if first_timestamp.is_none() {
-                    first_timestamp = Some(ts.clone());
-                }
-                last_timestamp = Some(ts.clone());
-            }
-
-            if session_id_found.is_none()
-                && let Some(sid) = entry.get("sessionId").and_then(|v| v.as_str())
-            {
-                session_id_found = Some(sid.to_string());
-            }
-
-            if let Some(msg) = entry.get("message") {
-                let role = msg
-                    .get("role")
-                    .and_then(|r| r.as_str())
-                    .unwrap_or("unknown");
-                let model = msg.get("model").and_then(|m| m.as_str()).map(String::from);
-
-                if role == "assistant" {
-                    msg_count += 1;
-                }
-                if let Some(ref m) = model {
-                    last_model = Some(m.clone());
-                }
-
-                let mut turn_input: u64 = 0;
-                let mut turn_output: u64 = 0;
-                let mut turn_cache_read: u64 = 0;
-                let mut turn_cache_write: u64 = 0;
-
-                if let Some(usage) = msg.get("usage") {
-                    turn_input = usage
-                        .get("input_tokens")
-                        .and_then(|v| v.as_u64())
-                        .unwrap_or(0);
-                    turn_output = usage
-                        .get("output_tokens")
-                        .and_then(|v| v.as_u64())
-                        .unwrap_or(0);
-                    turn_cache_read = usage
-                        .get("cache_read_input_tokens")
-                        .and_then(|v| v.as_u64())
-                        .unwrap_or(0);
-                    turn_cache_write = usage
-                        .get("cache_creation_input_tokens")
-                        .and_then(|v| v.as_u64())
-                        .unwrap_or(0);
-
-                    total_input += turn_input;
-                    total_output += turn_output;
-                    total_cache_read += turn_cache_read;
-                    total_cache_write += turn_cache_write;
-
-                    if usage.get("speed").and_then(|v| v.as_str()) == Some("fast") {
-                        has_fast_mode = true;
-                    }
-
-                    if let Some(stu) = usage.get("server_tool_use") {
-                        total_web_search_requests += stu
-                            .get("web_search_requests")
-                            .and_then(|v| v.as_u64())
-                            .unwrap_or(0);
-                    }
-                }
-
-                let mut tool_calls = Vec::new();
-                let mut content_type = "text".to_string();
-                let mut content_estimated: u64 = 0;
-
-                if let Some(content_arr) = msg.get("content").and_then(|c| c.as_array()) {
-                    for block in content_arr {
-                        let block_type =
-                            block.get("type").and_then(|t| t.as_str()).unwrap_or("text");
-
-                        match block_type {
-                            "tool_use" => {
-                                content_type = "tool_use".to_string();
-                                let tool_name = block
-                                    .get("name")
-                                    .and_then(|n| n.as_str())
-                                    .unwrap_or("unknown")
-                                    .to_string();
-                                let tool_id =
-                                    block.get("id").and_then(|i| i.as_str()).map(String::from);
-                                let input_est = block
-                                    .get("input")
-                                    .map(|v| v.to_string().len() as u64 / 4)
-                                    .unwrap_or(0);
-
-                                content_estimated += input_est;
-                                Self::classify_tool_tokens(
-                                    &tool_name,
-                                    input_est,
-                                    0,
-                                    &mut breakdown,
-                                );
-
-                                tool_calls.push(ToolCallRecord {
-                                    tool_name,
-                                    tool_id,
-                                    input_tokens_estimate: input_est,
-                                    output_tokens_estimate: 0,
-                                });
-                            }
-                            "tool_result" => {
-                                content_type = "tool_result".to_string();
-                                let output_est = block
-                                    .get("content")
-                                    .map(|v| v.to_string().len() as u64 / 4)
-                                    .unwrap_or(0);
-
-                                content_estimated += output_est;
-
-                                let tool_use_id = block
-                                    .get("tool_use_id")
-                                    .or_else(|| block.get("id"))
-                                    .and_then(|v| v.as_str());
-
-                                let matched = tool_use_id.and_then(|tid| {
-                                    tool_calls
-                                        .iter_mut()
-                                        .find(|tc| tc.tool_id.as_deref() == Some(tid))
-                                });
-
-                                if let Some(tc) = matched {
-                                    tc.output_tokens_estimate = output_est;
-                                    Self::classify_tool_tokens(
-                                        &tc.tool_name,
-                                        0,
-                                        output_est,
-                                        &mut breakdown,
-                                    );
-                                } else if let Some(last_tool) = tool_calls.last_mut() {
-                                    last_tool.output_tokens_estimate = output_est;
-                                    Self::classify_tool_tokens(
-                                        &last_tool.tool_name,
-                                        0,
-                                        output_est,
-                                        &mut breakdown,
-                                    );
-                                } else {
-                                    breakdown.tool_result_tokens += output_est;
-                                }
-                            }
-                            "text" => {
-                                let text_est = block
-                                    .get("text")
-                                    .and_then(|v| v.as_str())
-                                    .map(|s| s.len() as u64 / 4)
-                                    .unwrap_or(0);
-
-                                content_estimated += text_est;
-                                match role {
-                                    "user" | "human" => breakdown.user_tokens += text_est,
-                                    "assistant" => breakdown.assistant_tokens += text_est,
-                                    "system" => breakdown.system_prompt_tokens += text_est,
-                                    _ => breakdown.conversation_tokens += text_est,
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-
-                if turn_input > 0 || turn_output > 0 {
-                    let actual_total = turn_input + turn_output;
-                    if actual_total > content_estimated {
-                        breakdown.conversation_tokens += actual_total - content_estimated;
-                    }
-
-                    breakdown.turns.push(TurnRecord {
-                        turn_index,
-                        role: role.to_string(),
-                        model: model.clone(),
-                        input_tokens: turn_input,
-                        output_tokens: turn_output,
-                        cache_read: turn_cache_read,
-                        cache_write: turn_cache_write,
-                        tool_calls,
-                        timestamp: timestamp.clone(),
-                        content_type,
-                    });
-                    turn_index += 1;
-                }
-            }
-
-            let entry_type = entry.get("type").and_then(|t| t.as_str()).unwrap_or("");
-            if entry_type == "human" || entry_type == "user" {
-                msg_count += 1;
-            }
+            parser.push_line(line);
         }
+        let JsonlParser {
+            mut breakdown,
+            msg_count,
+            total_input,
+            total_output,
+            total_web_search_requests,
+            has_fast_mode,
+            total_cache_read,
+            total_cache_write,
+            last_model,
+            session_id_found,
+            first_timestamp,
+            last_timestamp,
+            ..
+        } = parser;
 
         if let Some(ref sid) = session_id_found {
             if let Ok(parsed) = uuid::Uuid::parse_str(sid) {
@@ -381,10 +184,6 @@ impl ClaudeCodeAdapter {
         session.total_tokens = total_input + total_output + total_cache_read + total_cache_write;
         session.model = last_model.clone();
 
-        breakdown.total_tokens = session.total_tokens;
-        breakdown.cache_read_tokens = total_cache_read;
-        breakdown.cache_write_tokens = total_cache_write;
-
         let turns_json = serde_json::to_value(&breakdown.turns).unwrap_or_default();
         session.metadata = serde_json::json!({"turns": turns_json});
 
@@ -526,6 +325,294 @@ impl ClaudeCodeAdapter {
     }
 }
 
+/// Parse state carried from one session JSONL line to the next.
+/// `parse_session_jsonl_full` feeds it a whole file; the live tail feeds it
+/// lines as they are appended and picks up the new turns.
+pub struct JsonlParser {
+    breakdown: ContextBreakdown,
+    msg_count: u64,
+    total_input: u64,
+    total_output: u64,
+    total_web_search_requests: u64,
+    has_fast_mode: bool,
+    total_cache_read: u64,
+    total_cache_write: u64,
+    last_model: Option<String>,
+    session_id_found: Option<String>,
+    first_timestamp: Option<String>,
+    last_timestamp: Option<String>,
+    turn_index: u32,
+    project_path: Option<String>,
+}
+
+impl JsonlParser {
+    pub fn new(session_id: Uuid) -> Self {
+        Self {
+            breakdown: ContextBreakdown::new(session_id),
+            msg_count: 0,
+            total_input: 0,
+            total_output: 0,
+            total_web_search_requests: 0,
+            has_fast_mode: false,
+            total_cache_read: 0,
+            total_cache_write: 0,
+            last_model: None,
+            session_id_found: None,
+            first_timestamp: None,
+            last_timestamp: None,
+            turn_index: 0,
+            project_path: None,
+        }
+    }
+
+    /// Parser for a session file read from the start, keyed on the id in
+    /// its file name until a line names one.
+    pub fn for_file(path: &Path) -> Self {
+        let session_id = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| Uuid::parse_str(s).ok())
+            .unwrap_or_else(Uuid::nil);
+        let mut parser = Self::new(session_id);
+        parser.project_path = Some(ClaudeCodeAdapter::project_dir(path));
+        parser
+    }
+
+    pub fn push_line(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        let entry: Value = match serde_json::from_str(line) {
+            Ok(v) => v,
+            Err(_) => return,
+        };
+
+        let timestamp = entry
+            .get("timestamp")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+
+        if let Some(ref ts) = timestamp {
+            if self.first_timestamp.is_none() {
+                self.first_timestamp = Some(ts.clone());
+            }
+            self.last_timestamp = Some(ts.clone());
+        }
+
+        if self.session_id_found.is_none()
+            && let Some(sid) = entry.get("sessionId").and_then(|v| v.as_str())
+        {
+            self.session_id_found = Some(sid.to_string());
+            if let Ok(parsed) = Uuid::parse_str(sid) {
+                self.breakdown.session_id = parsed;
+            }
+        }
+
+        if let Some(msg) = entry.get("message") {
+            let role = msg
+                .get("role")
+                .and_then(|r| r.as_str())
+                .unwrap_or("unknown");
+            let model = msg.get("model").and_then(|m| m.as_str()).map(String::from);
+
+            if role == "assistant" {
+                self.msg_count += 1;
+            }
+            if let Some(ref m) = model {
+                self.last_model = Some(m.clone());
+            }
+
+            let mut turn_input: u64 = 0;
+            let mut turn_output: u64 = 0;
+            let mut turn_cache_read: u64 = 0;
+            let mut turn_cache_write: u64 = 0;
+
+            if let Some(usage) = msg.get("usage") {
+                turn_input = usage
+                    .get("input_tokens")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(0);
+                turn_output = usage
+                    .get("output_tokens")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(0);
+                turn_cache_read = usage
+                    .get("cache_read_input_tokens")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(0);
+                turn_cache_write = usage
+                    .get("cache_creation_input_tokens")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(0);
+
+                self.total_input += turn_input;
+                self.total_output += turn_output;
+                self.total_cache_read += turn_cache_read;
+                self.total_cache_write += turn_cache_write;
+                self.breakdown.total_tokens +=
+                    turn_input + turn_output + turn_cache_read + turn_cache_write;
+                self.breakdown.cache_read_tokens += turn_cache_read;
+                self.breakdown.cache_write_tokens += turn_cache_write;
+
+                if usage.get("speed").and_then(|v| v.as_str()) == Some("fast") {
+                    self.has_fast_mode = true;
+                }
+
+                if let Some(stu) = usage.get("server_tool_use") {
+                    self.total_web_search_requests += stu
+                        .get("web_search_requests")
+                        .and_then(|v| v.as_u64())
+                        .unwrap_or(0);
+                }
+            }
+
+            let mut tool_calls = Vec::new();
+            let mut content_type = "text".to_string();
+            let mut content_estimated: u64 = 0;
+
+            if let Some(content_arr) = msg.get("content").and_then(|c| c.as_array()) {
+                for block in content_arr {
+                    let block_type = block.get("type").and_then(|t| t.as_str()).unwrap_or("text");
+
+                    match block_type {
+                        "tool_use" => {
+                            content_type = "tool_use".to_string();
+                            let tool_name = block
+                                .get("name")
+                                .and_then(|n| n.as_str())
+                                .unwrap_or("unknown")
+                                .to_string();
+                            let tool_id =
+                                block.get("id").and_then(|i| i.as_str()).map(String::from);
+                            let input = block.get("input");
+                            let input_est =
+                                input.map(|v| Encoding::Claude.count_value(v)).unwrap_or(0);
+
+                            content_estimated += input_est;
+                            ClaudeCodeAdapter::classify_tool_tokens(
+                                &tool_name,
+                                input_est,
+                                0,
+                                &mut self.breakdown,
+                            );
+
+                            let edits = input
+                                .map(|i| FileEdit::from_tool_call(&tool_name, i))
+                                .unwrap_or_default();
+                            tool_calls.push(ToolCallRecord {
+                                tool_name,
+                                tool_id,
+                                input_tokens_estimate: input_est,
+                                output_tokens_estimate: 0,
+                                arg_fingerprint: input.map(ToolCallRecord::fingerprint_args),
+                                target_path: input.and_then(ToolCallRecord::target_path),
+                                edits,
+                            });
+                        }
+                        "tool_result" => {
+                            content_type = "tool_result".to_string();
+                            let output_est = block
+                                .get("content")
+                                .map(|v| Encoding::Claude.count_value(v))
+                                .unwrap_or(0);
+
+                            content_estimated += output_est;
+
+                            let tool_use_id = block
+                                .get("tool_use_id")
+                                .or_else(|| block.get("id"))
+                                .and_then(|v| v.as_str());
+
+                            let matched = tool_use_id.and_then(|tid| {
+                                tool_calls
+                                    .iter_mut()
+                                    .find(|tc| tc.tool_id.as_deref() == Some(tid))
+                            });
+
+                            if let Some(tc) = matched {
+                                tc.output_tokens_estimate = output_est;
+                                ClaudeCodeAdapter::classify_tool_tokens(
+                                    &tc.tool_name,
+                                    0,
+                                    output_est,
+                                    &mut self.breakdown,
+                                );
+                            } else if let Some(last_tool) = tool_calls.last_mut() {
+                                last_tool.output_tokens_estimate = output_est;
+                                ClaudeCodeAdapter::classify_tool_tokens(
+                                    &last_tool.tool_name,
+                                    0,
+                                    output_est,
+                                    &mut self.breakdown,
+                                );
+                            } else {
+                                self.breakdown.tool_result_tokens += output_est;
+                            }
+                        }
+                        "text" => {
+                            let text_est = block
+                                .get("text")
+                                .and_then(|v| v.as_str())
+                                .map(|s| Encoding::Claude.count(s))
+                                .unwrap_or(0);
+
+                            content_estimated += text_est;
+                            match role {
+                                "user" | "human" => self.breakdown.user_tokens += text_est,
+                                "assistant" => self.breakdown.assistant_tokens += text_est,
+                                "system" => self.breakdown.system_prompt_tokens += text_est,
+                                _ => self.breakdown.conversation_tokens += text_est,
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            if turn_input > 0 || turn_output > 0 {
+                let actual_total = turn_input + turn_output;
+                if actual_total > content_estimated {
+                    self.breakdown.conversation_tokens += actual_total - content_estimated;
+                }
+
+                self.breakdown.turns.push(TurnRecord {
+                    turn_index: self.turn_index,
+                    role: role.to_string(),
+                    model: model.clone(),
+                    input_tokens: turn_input,
+                    output_tokens: turn_output,
+                    cache_read: turn_cache_read,
+                    cache_write: turn_cache_write,
+                    tool_calls,
+                    timestamp: timestamp.clone(),
+                    content_type,
+                });
+                self.turn_index += 1;
+            }
+        }
+
+        let entry_type = entry.get("type").and_then(|t| t.as_str()).unwrap_or("");
+        if entry_type == "human" || entry_type == "user" {
+            self.msg_count += 1;
+        }
+    }
+}
+
+impl TailParser for JsonlParser {
+    fn push_line(&mut self, line: &str) {
+        JsonlParser::push_line(self, line);
+    }
+
+    fn breakdown(&self) -> &ContextBreakdown {
+        &self.breakdown
+    }
+
+    fn project_path(&self) -> Option<&str> {
+        self.project_path.as_deref()
+    }
+}
+
 impl Default for ClaudeCodeAdapter {
     fn default() -> Self {
         Self::new()
@@ -1,14 +1,18 @@
 use async_trait::async_trait;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use chrono::Utc;
 use serde_json::Value;
 use tracing::{debug, warn};
 use uuid::Uuid;
 
-use super::{AdapterCore, AgentAdapter};
+use super::{AdapterCore, AgentAdapter, TailParser};
 use crate::error::RimuruError;
-use crate::models::{Agent, AgentStatus, AgentType, Session, SessionStatus};
+use crate::models::{
+    Agent, AgentStatus, AgentType, ContextBreakdown, FileEdit, Session, SessionStatus,
+    ToolCallRecord, TurnRecord,
+};
+use crate::tokenizer::Encoding;
 
 type Result<T> = std::result::Result<T, RimuruError>;
 
@@ -38,7 +42,7 @@ impl CodexAdapter {
         self.config_path.join("config.json")
     }
 
-    fn sessions_dir(&self) -> PathBuf {
+    pub(crate) fn sessions_dir(&self) -> PathBuf {
         self.config_path.join("sessions")
     }
 
@@ -191,6 +195,47 @@ impl CodexAdapter {
         Ok(session)
     }
 
+    /// Per-turn breakdown of a rollout file. Each `token_count` event closes
+    /// a turn holding the tool calls made since the previous one. Patches
+    /// are recorded as `apply_patch` whether Codex ran them as a custom tool
+    /// or through `shell`.
+    pub fn parse_session_breakdown(&self, path: &Path) -> Result<ContextBreakdown> {
+        let content = std::fs::read_to_string(path)?;
+        let mut parser = RolloutParser::new(Uuid::new_v4());
+        for line in content.lines() {
+            parser.push_line(line);
+        }
+        Ok(parser.breakdown)
+    }
+
+    /// Tool name and input of a rollout `response_item`, if it is a call.
+    fn tool_call(payload: &Value) -> Option<(String, Value)> {
+        let name = payload.get("name").and_then(|n| n.as_str());
+        match payload.get("type").and_then(|t| t.as_str())? {
+            "custom_tool_call" => Some((
+                name?.to_string(),
+                payload.get("input").cloned().unwrap_or(Value::Null),
+            )),
+            "function_call" => {
+                let args = payload.get("arguments").and_then(|a| a.as_str())?;
+                let args: Value =
+                    serde_json::from_str(args).unwrap_or_else(|_| Value::String(args.into()));
+                let patch = args
+                    .get("command")
+                    .and_then(|c| c.as_array())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|part| part.as_str())
+                    .find_map(|part| part.find("*** Begin Patch").map(|at| &part[at..]));
+                match patch {
+                    Some(patch) => Some(("apply_patch".to_string(), Value::String(patch.into()))),
+                    None => Some((name?.to_string(), args)),
+                }
+            }
+            _ => None,
+        }
+    }
+
     fn parse_history(&self) -> Result<Vec<Session>> {
         let history = self.history_file();
         if !history.exists() {
@@ -248,6 +293,141 @@ impl CodexAdapter {
     }
 }
 
+/// Line-by-line state of a rollout parse, shared by
+/// `parse_session_breakdown` and the live tail.
+pub struct RolloutParser {
+    breakdown: ContextBreakdown,
+    model: Option<String>,
+    pending: Vec<ToolCallRecord>,
+    cwd: Option<String>,
+}
+
+impl RolloutParser {
+    pub fn new(session_id: Uuid) -> Self {
+        Self {
+            breakdown: ContextBreakdown::new(session_id),
+            model: None,
+            pending: Vec::new(),
+            cwd: None,
+        }
+    }
+
+    pub fn push_line(&mut self, line: &str) {
+        let Ok(entry) = serde_json::from_str::<Value>(line.trim()) else {
+            return;
+        };
+        let payload = entry.get("payload").unwrap_or(&Value::Null);
+        let kind = payload.get("type").and_then(|t| t.as_str()).unwrap_or("");
+
+        match entry.get("type").and_then(|t| t.as_str()).unwrap_or("") {
+            "session_meta" => {
+                if let Some(id) = payload
+                    .get("id")
+                    .and_then(|v| v.as_str())
+                    .and_then(|id| Uuid::parse_str(id).ok())
+                {
+                    self.breakdown.session_id = id;
+                }
+                if let Some(cwd) = payload.get("cwd").and_then(|v| v.as_str()) {
+                    self.cwd = Some(cwd.to_string());
+                }
+            }
+            "turn_context" => {
+                if let Some(m) = payload.get("model").and_then(|m| m.as_str()) {
+                    self.model = Some(m.to_string());
+                }
+            }
+            "response_item" => {
+                let encoding = self
+                    .model
+                    .as_deref()
+                    .map_or(Encoding::O200k, Encoding::for_model);
+                if let Some((name, input)) = CodexAdapter::tool_call(payload) {
+                    let input_est = encoding.count_value(&input);
+                    let edits = FileEdit::from_tool_call(&name, &input);
+                    let target_path = ToolCallRecord::target_path(&input)
+                        .or_else(|| edits.first().map(|e| e.path.clone()));
+                    self.pending.push(ToolCallRecord {
+                        arg_fingerprint: Some(ToolCallRecord::fingerprint_args(&input)),
+                        target_path,
+                        edits,
+                        tool_name: name,
+                        tool_id: payload
+                            .get("call_id")
+                            .and_then(|v| v.as_str())
+                            .map(String::from),
+                        input_tokens_estimate: input_est,
+                        output_tokens_estimate: 0,
+                    });
+                } else if kind.ends_with("_call_output") {
+                    let output_est = payload
+                        .get("output")
+                        .map(|v| encoding.count_value(v))
+                        .unwrap_or(0);
+                    self.breakdown.tool_result_tokens += output_est;
+                    let call_id = payload.get("call_id").and_then(|v| v.as_str());
+                    if let Some(tc) = self
+                        .pending
+                        .iter_mut()
+                        .find(|tc| tc.tool_id.as_deref() == call_id)
+                    {
+                        tc.output_tokens_estimate = output_est;
+                    }
+                }
+            }
+            "event_msg" if kind == "token_count" => {
+                let Some(usage) = payload.get("info").and_then(|i| i.get("last_token_usage"))
+                else {
+                    return;
+                };
+                let field = |k: &str| usage.get(k).and_then(|v| v.as_u64()).unwrap_or(0);
+                let cached = field("cached_input_tokens");
+                let input = field("input_tokens").saturating_sub(cached);
+                let output = field("output_tokens");
+
+                self.breakdown.total_tokens += input + cached + output;
+                self.breakdown.cache_read_tokens += cached;
+                self.breakdown.assistant_tokens += output;
+                let tool_calls = std::mem::take(&mut self.pending);
+                self.breakdown.turns.push(TurnRecord {
+                    turn_index: self.breakdown.turns.len() as u32,
+                    role: "assistant".to_string(),
+                    model: self.model.clone(),
+                    input_tokens: input,
+                    output_tokens: output,
+                    cache_read: cached,
+                    cache_write: 0,
+                    content_type: if tool_calls.is_empty() {
+                        "text".to_string()
+                    } else {
+                        "tool_use".to_string()
+                    },
+                    tool_calls,
+                    timestamp: entry
+                        .get("timestamp")
+                        .and_then(|v| v.as_str())
+                        .map(String::from),
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+impl TailParser for RolloutParser {
+    fn push_line(&mut self, line: &str) {
+        RolloutParser::push_line(self, line);
+    }
+
+    fn breakdown(&self) -> &ContextBreakdown {
+        &self.breakdown
+    }
+
+    fn project_path(&self) -> Option<&str> {
+        self.cwd.as_deref()
+    }
+}
+
 impl Default for CodexAdapter {
     fn default() -> Self {
         Self::new()
@@ -1,14 +1,16 @@
 use async_trait::async_trait;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use chrono::Utc;
 use serde_json::Value;
 use tracing::{debug, warn};
 use uuid::Uuid;
 
-use super::{AdapterCore, AgentAdapter, binary_on_path};
+use super::{AdapterCore, AgentAdapter, TailParser, binary_on_path};
 use crate::error::RimuruError;
-use crate::models::{Agent, AgentStatus, AgentType, Session, SessionStatus};
+use crate::models::{
+    Agent, AgentStatus, AgentType, ContextBreakdown, Session, SessionStatus, TurnRecord,
+};
 
 type Result<T> = std::result::Result<T, RimuruError>;
 
@@ -38,7 +40,7 @@ impl GeminiCliAdapter {
         self.config_path.join("settings.json")
     }
 
-    fn sessions_dir(&self) -> PathBuf {
+    pub(crate) fn sessions_dir(&self) -> PathBuf {
         self.config_path.join("sessions")
     }
 
@@ -154,76 +156,21 @@ impl GeminiCliAdapter {
                 }
             }
         } else {
-            let mut msg_count: u64 = 0;
-            let mut total_input: u64 = 0;
-            let mut total_output: u64 = 0;
-            let mut last_model: Option<String> = None;
-            let mut session_id_found: Option<String> = None;
-            let mut first_ts: Option<String> = None;
-
+            let mut parser = JsonlParser::new(Uuid::nil());
             for line in content.lines() {
-                let line = line.trim();
-                if line.is_empty() {
-                    continue;
-                }
-                let entry: Value = match serde_json::from_str(line) {
-                    Ok(v) => v,
-                    Err(_) => continue,
-                };
-
-                msg_count += 1;
-
-                if session_id_found.is_none()
-                    && let Some(sid) = entry
-                        .get("sessionId")
-                        .or_else(|| entry.get("session_id"))
-                        .and_then(|v| v.as_str())
-                {
-                    session_id_found = Some(sid.to_string());
-                }
-
-                if first_ts.is_none()
-                    && let Some(ts) = entry
-                        .get("timestamp")
-                        .or_else(|| entry.get("createdAt"))
-                        .and_then(|v| v.as_str())
-                {
-                    first_ts = Some(ts.to_string());
-                }
-
-                let usage = entry.get("usage").or_else(|| entry.get("usageMetadata"));
-                if let Some(u) = usage {
-                    let inp = u
-                        .get("promptTokenCount")
-                        .or_else(|| u.get("input_tokens"))
-                        .and_then(|v| v.as_u64())
-                        .unwrap_or(0);
-                    let out = u
-                        .get("candidatesTokenCount")
-                        .or_else(|| u.get("output_tokens"))
-                        .and_then(|v| v.as_u64())
-                        .unwrap_or(0);
-                    total_input += inp;
-                    total_output += out;
-                }
-
-                if let Some(model) = entry.get("model").and_then(|m| m.as_str()) {
-                    last_model = Some(model.to_string());
-                }
+                parser.push_line(line);
             }
 
-            session.messages = msg_count;
-            session.input_tokens = total_input;
-            session.output_tokens = total_output;
-            session.model = last_model;
+            session.messages = parser.msg_count;
+            session.input_tokens = parser.total_input;
+            session.output_tokens = parser.total_output;
+            session.model = parser.last_model;
 
-            if let Some(sid) = session_id_found
-                && let Ok(parsed) = uuid::Uuid::parse_str(&sid)
-            {
-                session.id = parsed;
+            if !parser.breakdown.session_id.is_nil() {
+                session.id = parser.breakdown.session_id;
             }
 
-            if let Some(ts) = first_ts
+            if let Some(ts) = parser.first_ts
                 && let Ok(dt) = chrono::DateTime::parse_from_rfc3339(&ts)
             {
                 session.started_at = dt.with_timezone(&Utc);
@@ -367,6 +314,125 @@ impl GeminiCliAdapter {
     }
 }
 
+/// Line-by-line state of a JSONL session parse, shared by
+/// `parse_session_file` and the live tail. Every line carrying usage is one
+/// turn; Gemini CLI logs do not record tool calls.
+pub struct JsonlParser {
+    breakdown: ContextBreakdown,
+    msg_count: u64,
+    total_input: u64,
+    total_output: u64,
+    last_model: Option<String>,
+    first_ts: Option<String>,
+}
+
+impl JsonlParser {
+    pub fn new(session_id: Uuid) -> Self {
+        Self {
+            breakdown: ContextBreakdown::new(session_id),
+            msg_count: 0,
+            total_input: 0,
+            total_output: 0,
+            last_model: None,
+            first_ts: None,
+        }
+    }
+
+    /// Parser for a session file read from the start, keyed on the id in
+    /// its file name until a line names one.
+    pub fn for_file(path: &Path) -> Self {
+        Self::new(
+            path.file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| Uuid::parse_str(s).ok())
+                .unwrap_or_else(Uuid::nil),
+        )
+    }
+
+    pub fn push_line(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        let entry: Value = match serde_json::from_str(line) {
+            Ok(v) => v,
+            Err(_) => return,
+        };
+
+        self.msg_count += 1;
+
+        if let Some(sid) = entry
+            .get("sessionId")
+            .or_else(|| entry.get("session_id"))
+            .and_then(|v| v.as_str())
+            && let Ok(parsed) = Uuid::parse_str(sid)
+            && self.breakdown.session_id.is_nil()
+        {
+            self.breakdown.session_id = parsed;
+        }
+
+        let timestamp = entry
+            .get("timestamp")
+            .or_else(|| entry.get("createdAt"))
+            .and_then(|v| v.as_str())
+            .map(String::from);
+        if self.first_ts.is_none() {
+            self.first_ts = timestamp.clone();
+        }
+
+        if let Some(model) = entry.get("model").and_then(|m| m.as_str()) {
+            self.last_model = Some(model.to_string());
+        }
+
+        let usage = entry.get("usage").or_else(|| entry.get("usageMetadata"));
+        if let Some(u) = usage {
+            let inp = u
+                .get("promptTokenCount")
+                .or_else(|| u.get("input_tokens"))
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0);
+            let out = u
+                .get("candidatesTokenCount")
+                .or_else(|| u.get("output_tokens"))
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0);
+            self.total_input += inp;
+            self.total_output += out;
+
+            if inp > 0 || out > 0 {
+                self.breakdown.total_tokens += inp + out;
+                self.breakdown.assistant_tokens += out;
+                self.breakdown.turns.push(TurnRecord {
+                    turn_index: self.breakdown.turns.len() as u32,
+                    role: entry
+                        .get("role")
+                        .and_then(|r| r.as_str())
+                        .unwrap_or("assistant")
+                        .to_string(),
+                    model: self.last_model.clone(),
+                    input_tokens: inp,
+                    output_tokens: out,
+                    cache_read: 0,
+                    cache_write: 0,
+                    tool_calls: Vec::new(),
+                    timestamp,
+                    content_type: "text".to_string(),
+                });
+            }
+        }
+    }
+}
+
+impl TailParser for JsonlParser {
+    fn push_line(&mut self, line: &str) {
+        JsonlParser::push_line(self, line);
+    }
+
+    fn breakdown(&self) -> &ContextBreakdown {
+        &self.breakdown
+    }
+}
+
 impl Default for GeminiCliAdapter {
     fn default() -> Self {
         Self::new()
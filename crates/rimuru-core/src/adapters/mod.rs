@@ -17,7 +17,7 @@ pub mod windsurf;
 use serde_json::Value;
 
 use crate::error::RimuruError;
-use crate::models::{Agent, AgentType, Session};
+use crate::models::{Agent, AgentType, ContextBreakdown, Session};
 
 /// Return true if any of the given executable names can be found on
 /// the user's PATH. Handles the Windows `.exe` suffix transparently:
@@ -52,6 +52,21 @@ pub trait AgentAdapter: Send + Sync {
     async fn health_check(&self) -> Result<bool>;
 }
 
+/// Incremental parser for an append-only session log. Lines are fed in the
+/// order they were written, and each one may add turns to the breakdown.
+pub trait TailParser: Send + Sync {
+    fn push_line(&mut self, line: &str);
+
+    /// Everything parsed so far. `session_id` stays nil until the log names
+    /// its session, unless the parser was created with one.
+    fn breakdown(&self) -> &ContextBreakdown;
+
+    /// Project the session works in, once the log has said.
+    fn project_path(&self) -> Option<&str> {
+        None
+    }
+}
+
 #[async_trait]
 pub trait CostTracker: Send + Sync {
     async fn get_usage(&self) -> Result<Value>;
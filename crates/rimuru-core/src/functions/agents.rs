@@ -543,7 +543,23 @@ async fn sync_agent_sessions(
             result.has_active = true;
         }
 
+        // Sessions the live tail has seen get a cost record per turn from
+        // it; a session-level record here would count them twice. Only the
+        // turns from before the tail picked the session up are left here.
+        let marker = kv
+            .get::<Value>(crate::tail::TAIL_SCOPE, &session_id)
+            .await
+            .ok()
+            .flatten();
+        let tailed = marker.is_some();
+        if let Some(marker) = marker.and_then(|m| serde_json::from_value(m).ok())
+            && crate::tail::backfill_before_tail(kv, agent, &session, &marker).await
+        {
+            result.costs_stored += 1;
+        }
+
         if session.total_cost > 0.0
+            && !tailed
             && let Some(ref model) = session.model
         {
             let model_lower = model.to_lowercase();
@@ -597,7 +613,7 @@ async fn sync_agent_sessions(
             );
             cost_record.recorded_at = session.started_at;
             let record_id = cost_record.id.to_string();
-            if let Err(e) = kv.set("cost_records", &record_id, &cost_record).await {
+            if let Err(e) = super::costs::store_record(kv, &cost_record).await {
                 warn!("Failed to store cost record {}: {}", record_id, e);
             }
             result.costs_stored += 1;
@@ -697,6 +713,14 @@ fn register_sync(iii: &III, kv: &StateKV) {
                     synced_sessions, synced_costs, synced_agents
                 );
 
+                // Synced records are written directly, not through
+                // costs.record, so the budget counters need catching up.
+                if synced_costs > 0
+                    && let Err(e) = super::counters::reconcile(&kv).await
+                {
+                    warn!("Failed to reconcile spend counters after sync: {}", e);
+                }
+
                 Ok(api_response(json!({
                     "synced_agents": synced_agents,
                     "synced_sessions": synced_sessions,
@@ -0,0 +1,397 @@
+//! Runaway detector backtesting (`rimuru.runaway.backtest`) and the labels
+//! that score it (`rimuru.runaway.label`).
+//!
+//! A backtest replays every stored `ContextBreakdown` one turn at a time
+//! through the built-in detectors, as `rimuru.runaway.scan` would have seen
+//! it live, and reports the first turn each session would have been flagged
+//! at and what stopping there would have saved. Any `configure` key in the
+//! input is tried out on top of the stored config without being saved.
+//!
+//! Labels mark sessions as true or false positives. They outlive any one
+//! backtest, so every later run scores itself against them. Plugin detectors
+//! are not replayed: calling out once per turn per session is too slow for
+//! a full history.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use iii_sdk::{III, IIIError, RegisterFunctionMessage};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use super::runaway::{self, RunawayConfig, analyze_turns, turn_cost};
+use super::sysutil::{api_response, extract_input, kv_err, require_str};
+use crate::models::{ContextBreakdown, TurnRecord};
+use crate::state::StateKV;
+
+pub(crate) const LABEL_SCOPE: &str = "runaway_labels";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Label {
+    TruePositive,
+    FalsePositive,
+}
+
+impl Label {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "true_positive" => Some(Self::TruePositive),
+            "false_positive" => Some(Self::FalsePositive),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunawayLabel {
+    pub session_id: Uuid,
+    pub label: Label,
+    #[serde(default)]
+    pub note: Option<String>,
+    pub labeled_at: DateTime<Utc>,
+}
+
+/// One session's replay.
+#[derive(Debug, Clone, Serialize)]
+pub struct Replay {
+    pub session_id: Uuid,
+    pub turns: usize,
+    /// `turn_index` of the turn the session would have been flagged after.
+    pub flagged_at_turn: Option<u32>,
+    /// That turn's timestamp, when the adapter recorded one.
+    pub flagged_at: Option<String>,
+    pub severity: f64,
+    pub patterns: Vec<String>,
+    pub tokens_total: u64,
+    pub cost_total: f64,
+    /// Tokens and dollars spent on the turns after the flag.
+    pub tokens_saved: u64,
+    pub dollars_saved: f64,
+    pub label: Option<Label>,
+}
+
+fn turn_tokens(t: &TurnRecord) -> u64 {
+    t.input_tokens + t.output_tokens + t.cache_read + t.cache_write
+}
+
+/// Replay `turns` as if they arrived live and stop at the first flag.
+pub fn replay(session_id: Uuid, turns: &[TurnRecord], cfg: &RunawayConfig) -> Replay {
+    let costs: Vec<f64> = turns.iter().map(|t| turn_cost(t, &cfg.catalog)).collect();
+    let mut result = Replay {
+        session_id,
+        turns: turns.len(),
+        flagged_at_turn: None,
+        flagged_at: None,
+        severity: 0.0,
+        patterns: Vec::new(),
+        tokens_total: turns.iter().map(turn_tokens).sum(),
+        cost_total: costs.iter().sum(),
+        tokens_saved: 0,
+        dollars_saved: 0.0,
+        label: None,
+    };
+
+    for end in 1..=turns.len() {
+        let analysis = analyze_turns(session_id, &turns[..end], cfg);
+        if !analysis.is_runaway {
+            continue;
+        }
+        let flagged = &turns[end - 1];
+        result.flagged_at_turn = Some(flagged.turn_index);
+        result.flagged_at = flagged.timestamp.clone();
+        result.severity = analysis.severity;
+        result.patterns = analysis
+            .patterns
+            .into_iter()
+            .map(|p| p.pattern_type)
+            .collect();
+        result.tokens_saved = turns[end..].iter().map(turn_tokens).sum();
+        result.dollars_saved = costs[end..].iter().sum();
+        break;
+    }
+    result
+}
+
+/// How a replay run compares with the stored labels.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BacktestSummary {
+    pub sessions: usize,
+    pub flagged: usize,
+    pub labeled: usize,
+    pub true_positives: usize,
+    pub false_positives: usize,
+    /// Labeled true positives the config no longer flags.
+    pub missed: usize,
+    /// Flagged sessions nobody has labeled yet.
+    pub unlabeled_flags: usize,
+    pub precision: Option<f64>,
+    pub recall: Option<f64>,
+    /// Savings from flags not labeled false positive.
+    pub tokens_saved: u64,
+    pub dollars_saved: f64,
+    /// What stopping the false positives would have cut short.
+    pub false_positive_tokens: u64,
+    pub false_positive_dollars: f64,
+}
+
+pub fn summarize(replays: &[Replay]) -> BacktestSummary {
+    let mut s = BacktestSummary {
+        sessions: replays.len(),
+        ..BacktestSummary::default()
+    };
+    for r in replays {
+        let flagged = r.flagged_at_turn.is_some();
+        if flagged {
+            s.flagged += 1;
+        }
+        if r.label.is_some() {
+            s.labeled += 1;
+        }
+        match (flagged, r.label) {
+            (true, Some(Label::FalsePositive)) => {
+                s.false_positives += 1;
+                s.false_positive_tokens += r.tokens_saved;
+                s.false_positive_dollars += r.dollars_saved;
+                continue;
+            }
+            (true, Some(Label::TruePositive)) => s.true_positives += 1,
+            (true, None) => s.unlabeled_flags += 1,
+            (false, Some(Label::TruePositive)) => s.missed += 1,
+            (false, _) => {}
+        }
+        s.tokens_saved += r.tokens_saved;
+        s.dollars_saved += r.dollars_saved;
+    }
+    let judged = s.true_positives + s.false_positives;
+    if judged > 0 {
+        s.precision = Some(s.true_positives as f64 / judged as f64);
+    }
+    let positives = s.true_positives + s.missed;
+    if positives > 0 {
+        s.recall = Some(s.true_positives as f64 / positives as f64);
+    }
+    s
+}
+
+pub fn register(iii: &III, kv: &StateKV) {
+    register_backtest(iii, kv);
+    register_label(iii, kv);
+}
+
+fn register_backtest(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.runaway.backtest".to_string()),
+        move |input: Value| {
+            let kv = kv.clone();
+            async move {
+                let input = extract_input(input);
+
+                let mut cfg = runaway::load_runaway_config(&kv).await;
+                let mut overrides = serde_json::Map::new();
+                for (input_key, config_key) in runaway::CONFIG_KEYS {
+                    let Some(val) = input.get(*input_key) else {
+                        continue;
+                    };
+                    let val = runaway::validate_setting(&kv, input_key, config_key, val).await?;
+                    runaway::apply_setting(&mut cfg, config_key, &val);
+                    overrides.insert(config_key.to_string(), val);
+                }
+
+                let labels: HashMap<Uuid, Label> = kv
+                    .list::<RunawayLabel>(LABEL_SCOPE)
+                    .await
+                    .map_err(kv_err)?
+                    .into_iter()
+                    .map(|l| (l.session_id, l.label))
+                    .collect();
+                let breakdowns: Vec<ContextBreakdown> =
+                    kv.list("context_breakdowns").await.map_err(kv_err)?;
+
+                // Replaying every prefix of every session is CPU-bound; keep
+                // it off the runtime threads that serve other calls.
+                let replays = tokio::task::spawn_blocking(move || {
+                    let mut replays: Vec<Replay> = breakdowns
+                        .iter()
+                        .map(|b| {
+                            let mut r = replay(b.session_id, &b.turns, &cfg);
+                            r.label = labels.get(&b.session_id).copied();
+                            r
+                        })
+                        .collect();
+                    replays.sort_by(|a, b| b.dollars_saved.total_cmp(&a.dollars_saved));
+                    replays
+                })
+                .await
+                .map_err(|e| IIIError::Handler(format!("backtest failed: {}", e)))?;
+                let summary = summarize(&replays);
+
+                Ok(api_response(json!({
+                    "summary": summary,
+                    "sessions": replays,
+                    "overrides": overrides,
+                })))
+            }
+        },
+    );
+}
+
+fn register_label(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.runaway.label".to_string()),
+        move |input: Value| {
+            let kv = kv.clone();
+            async move {
+                let input = extract_input(input);
+                let session_id = require_str(&input, "session_id")?;
+                let session_id = session_id
+                    .parse::<Uuid>()
+                    .map_err(|e| IIIError::Handler(format!("invalid session_id: {}", e)))?;
+                let key = session_id.to_string();
+
+                let label = match input.get("label") {
+                    Some(Value::Null) | None => {
+                        kv.delete(LABEL_SCOPE, &key).await.map_err(kv_err)?;
+                        return Ok(api_response(json!({ "cleared": key })));
+                    }
+                    Some(v) => v.as_str().and_then(Label::parse).ok_or_else(|| {
+                        IIIError::Handler(
+                            "label must be true_positive, false_positive or null".into(),
+                        )
+                    })?,
+                };
+                let entry = RunawayLabel {
+                    session_id,
+                    label,
+                    note: input
+                        .get("note")
+                        .and_then(|v| v.as_str())
+                        .filter(|s| !s.trim().is_empty())
+                        .map(String::from),
+                    labeled_at: Utc::now(),
+                };
+                kv.set(LABEL_SCOPE, &key, &entry).await.map_err(kv_err)?;
+
+                Ok(api_response(json!({ "label": entry })))
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{FileEdit, ToolCallRecord};
+
+    fn bash(turn_index: u32, command: &str) -> TurnRecord {
+        let input = json!({ "command": command });
+        TurnRecord {
+            turn_index,
+            role: "assistant".into(),
+            model: None,
+            input_tokens: 10_000,
+            output_tokens: 0,
+            cache_read: 0,
+            cache_write: 0,
+            tool_calls: vec![ToolCallRecord {
+                tool_name: "Bash".into(),
+                tool_id: None,
+                input_tokens_estimate: 10,
+                output_tokens_estimate: 0,
+                arg_fingerprint: Some(ToolCallRecord::fingerprint_args(&input)),
+                target_path: None,
+                edits: Vec::new(),
+            }],
+            timestamp: None,
+            content_type: "tool_use".into(),
+        }
+    }
+
+    fn edit(turn_index: u32, n: u32) -> TurnRecord {
+        let input = json!({
+            "file_path": "/src/lib.rs",
+            "old_string": format!("v{}", n),
+            "new_string": format!("v{}", n + 1),
+        });
+        let mut turn = bash(turn_index, "");
+        turn.tool_calls = vec![ToolCallRecord {
+            tool_name: "Edit".into(),
+            tool_id: None,
+            input_tokens_estimate: 10,
+            output_tokens_estimate: 0,
+            arg_fingerprint: Some(ToolCallRecord::fingerprint_args(&input)),
+            target_path: ToolCallRecord::target_path(&input),
+            edits: FileEdit::from_tool_call("Edit", &input),
+        }];
+        turn
+    }
+
+    #[test]
+    fn replay_stops_at_the_first_flag() {
+        // Two distinct commands, four rounds of two edits then a test run,
+        // then the same test run over and over with nothing changed.
+        let mut turns = vec![bash(0, "ls"), bash(1, "cargo build")];
+        for round in 0..4 {
+            let at = 2 + round * 3;
+            turns.push(edit(at, round * 2));
+            turns.push(edit(at + 1, round * 2 + 1));
+            turns.push(bash(at + 2, "cargo test"));
+        }
+        turns.extend((14..20).map(|i| bash(i, "cargo test")));
+        let r = replay(Uuid::nil(), &turns, &RunawayConfig::default());
+
+        // Testing after each round of edits is progress; four runs since
+        // the last edit trip identical_calls (threshold 3).
+        assert_eq!(r.flagged_at_turn, Some(16), "{:?}", r.patterns);
+        assert_eq!(r.tokens_saved, 3 * 10_000);
+        assert!((r.dollars_saved - 3.0 * 0.09).abs() < 1e-9);
+        assert_eq!(r.tokens_total, 20 * 10_000);
+
+        let mut strict = RunawayConfig::default();
+        strict.repeat_threshold = 20;
+        let r = replay(Uuid::nil(), &turns, &strict);
+        assert_eq!(r.flagged_at_turn, None);
+        assert_eq!(r.tokens_saved, 0);
+    }
+
+    #[test]
+    fn summary_scores_against_labels() {
+        let flagged = |label, saved| Replay {
+            session_id: Uuid::new_v4(),
+            turns: 10,
+            flagged_at_turn: Some(4),
+            flagged_at: None,
+            severity: 0.7,
+            patterns: vec!["identical_calls".into()],
+            tokens_total: 100,
+            cost_total: 1.0,
+            tokens_saved: saved,
+            dollars_saved: saved as f64 / 100.0,
+            label,
+        };
+        let missed = Replay {
+            flagged_at_turn: None,
+            tokens_saved: 0,
+            dollars_saved: 0.0,
+            ..flagged(Some(Label::TruePositive), 0)
+        };
+        let replays = vec![
+            flagged(Some(Label::TruePositive), 50),
+            flagged(Some(Label::FalsePositive), 30),
+            flagged(None, 20),
+            missed,
+        ];
+        let s = summarize(&replays);
+        assert_eq!(s.flagged, 3);
+        assert_eq!(s.labeled, 3);
+        assert_eq!((s.true_positives, s.false_positives, s.missed), (1, 1, 1));
+        assert_eq!(s.unlabeled_flags, 1);
+        assert_eq!(s.precision, Some(0.5));
+        assert_eq!(s.recall, Some(0.5));
+        assert_eq!(s.tokens_saved, 70);
+        assert_eq!(s.false_positive_tokens, 30);
+    }
+}
@@ -1,15 +1,45 @@
-use chrono::{Datelike, Utc};
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use chrono_tz::Tz;
 use iii_sdk::{III, IIIError, RegisterFunctionMessage, TriggerRequest};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use uuid::Uuid;
 
+use super::counters;
+use super::currency::{self, BUDGET_CURRENCY_KEY, RateTable};
+use super::downgrade::{self, DEFAULT_DOWNGRADE_THRESHOLD, DOWNGRADE_THRESHOLD_KEY, Level};
+use super::forecast::{self, BAND_CONFIDENCE, CapHit, DailyModel, DayForecast};
+use super::jwt::{authorize, extract_bearer};
+use super::overrides;
+use super::period::{self, days_in_month, local_date};
+use super::reservation;
 use super::sysutil::{api_response, extract_input, kv_err};
+use super::team::{TEAM_SCOPE, Team};
 use super::webhook::{load_webhook_url, post_webhook};
-use crate::models::CostRecord;
+use crate::models::{Agent, CostRecord, Session};
 use crate::state::StateKV;
 
-const VALID_ACTIONS: &[&str] = &["alert", "block", "warn"];
+const VALID_ACTIONS: &[&str] = &["alert", "block", "downgrade", "warn"];
+
+/// Rolling-window caps: `(config key, window in hours, label)`. Spend is summed
+/// from `cost_hourly` buckets, so a window never resets at a calendar edge.
+const ROLLING_WINDOWS: &[(&str, i64, &str)] = &[
+    ("budget_rolling_5h", 5, "Rolling 5h"),
+    ("budget_rolling_7d", 24 * 7, "Rolling 7d"),
+];
+
+/// Config key holding per-project caps, keyed by normalized path or glob.
+const PROJECT_BUDGETS_KEY: &str = "budget_projects";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ProjectBudget {
+    #[serde(default)]
+    daily_limit: f64,
+    #[serde(default)]
+    monthly_limit: f64,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct BudgetAlert {
@@ -19,6 +49,34 @@ struct BudgetAlert {
     monthly_spent: f64,
     daily_spent: f64,
     limit_hit: String,
+    #[serde(default)]
+    team_id: Option<String>,
+    /// Currency of the spend figures; alerts from before currencies existed
+    /// are USD.
+    #[serde(default)]
+    currency: Option<String>,
+}
+
+/// One cap `budget.check` enforces. Global and project caps are in
+/// `budget_currency`; a team cap may be in the team's own currency.
+struct Cap {
+    label: String,
+    limit: f64,
+    spent: Option<f64>,
+    currency: String,
+    /// Override headroom converted into `currency`.
+    headroom: f64,
+}
+
+impl Cap {
+    fn money(&self, amount: f64) -> String {
+        currency::format_amount(amount, &self.currency)
+    }
+}
+
+/// Units of `code` per dollar today, for comparing USD counters with limits.
+fn today_rate(table: &RateTable, code: &str, tz: Tz) -> Result<f64, IIIError> {
+    table.rate_on(code, local_date(Utc::now(), tz))
 }
 
 pub fn register(iii: &III, kv: &StateKV) {
@@ -26,6 +84,7 @@ pub fn register(iii: &III, kv: &StateKV) {
     register_status(iii, kv);
     register_set(iii, kv);
     register_alerts(iii, kv);
+    register_forecast(iii, kv);
 }
 
 async fn get_config_f64(kv: &StateKV, key: &str, default: f64) -> Result<f64, IIIError> {
@@ -47,63 +106,226 @@ async fn read_daily_cents(kv: &StateKV, date_key: &str) -> Result<i64, IIIError>
         .unwrap_or(0))
 }
 
-async fn read_agent_daily_cents(
-    kv: &StateKV,
-    agent_id: Uuid,
-    date_key: &str,
-) -> Result<i64, IIIError> {
-    let key = format!("{}::{}", agent_id, date_key);
-    let value: Option<Value> = kv.get("cost_agent", &key).await.map_err(kv_err)?;
-    Ok(value
-        .and_then(|v| v.get("total_cost_cents").and_then(|c| c.as_i64()))
-        .unwrap_or(0))
+async fn compute_monthly_spent(kv: &StateKV, tz: Tz) -> Result<f64, IIIError> {
+    counters::read(kv, &counters::month_key(Utc::now(), tz)).await
 }
 
-async fn compute_monthly_spent(kv: &StateKV) -> Result<f64, IIIError> {
-    let now = Utc::now();
-    let year = now.year();
-    let month = now.month();
-    let last_day = days_in_month(year, month);
+async fn compute_weekly_spent(kv: &StateKV, tz: Tz) -> Result<f64, IIIError> {
+    counters::read(kv, &counters::week_key(Utc::now(), tz)).await
+}
 
-    let mut total_cents: i64 = 0;
-    for day in 1..=last_day {
-        let date_key = format!("{:04}-{:02}-{:02}", year, month, day);
-        total_cents += read_daily_cents(kv, &date_key).await?;
-    }
-    Ok(total_cents as f64 / 100.0)
+async fn compute_daily_spent(kv: &StateKV, tz: Tz) -> Result<f64, IIIError> {
+    counters::read(kv, &counters::day_key(Utc::now(), tz)).await
 }
 
-async fn compute_daily_spent(kv: &StateKV) -> Result<f64, IIIError> {
-    let date_key = Utc::now().format("%Y-%m-%d").to_string();
-    let cents = read_daily_cents(kv, &date_key).await?;
-    Ok(cents as f64 / 100.0)
+/// Key for the `cost_hourly` rollup bucket containing `at`.
+pub fn hour_key(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m-%dT%H").to_string()
+}
+
+/// Hourly bucket keys covering the last `hours` hours up to and including the
+/// current bucket. The oldest bucket is included whole, so the window errs on
+/// the side of counting slightly more spend rather than less.
+fn rolling_hour_keys(now: DateTime<Utc>, hours: i64) -> Vec<String> {
+    (0..=hours.max(0))
+        .map(|h| hour_key(now - chrono::Duration::hours(h)))
+        .collect()
+}
+
+async fn compute_rolling_spent(kv: &StateKV, hours: i64) -> Result<f64, IIIError> {
+    let mut total = 0.0;
+    for bucket in rolling_hour_keys(Utc::now(), hours) {
+        total += counters::read(kv, &format!("hour::{}", bucket)).await?;
+    }
+    Ok(total)
 }
 
 async fn compute_session_spent(kv: &StateKV, session_id: Uuid) -> Result<f64, IIIError> {
-    let records: Vec<CostRecord> = kv.list("cost_records").await.map_err(kv_err)?;
-    Ok(records
-        .iter()
-        .filter(|r| r.session_id == Some(session_id))
-        .map(|r| r.total_cost)
-        .sum())
+    counters::read(kv, &counters::session_key(session_id)).await
+}
+
+async fn compute_agent_daily_spent(kv: &StateKV, agent_id: Uuid, tz: Tz) -> Result<f64, IIIError> {
+    counters::read(kv, &counters::agent_day_key(agent_id, Utc::now(), tz)).await
+}
+
+/// Canonical form for project paths so `cost_project` keys and budget
+/// patterns agree: forward slashes, no duplicate or trailing separators.
+pub fn normalize_project_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for c in path.trim().chars() {
+        let c = if c == '\\' { '/' } else { c };
+        if c == '/' && out.ends_with('/') {
+            continue;
+        }
+        out.push(c);
+    }
+    while out.len() > 1 && out.ends_with('/') {
+        out.pop();
+    }
+    out
 }
 
-async fn compute_agent_daily_spent(kv: &StateKV, agent_id: Uuid) -> Result<f64, IIIError> {
-    let date_key = Utc::now().format("%Y-%m-%d").to_string();
-    let cents = read_agent_daily_cents(kv, agent_id, &date_key).await?;
-    Ok(cents as f64 / 100.0)
+/// A pattern without glob characters matches the path itself and anything
+/// beneath it. Otherwise `*` and `?` stay within one path segment and `**`
+/// spans any number of segments.
+pub fn project_matches(pattern: &str, project: &str) -> bool {
+    let pattern = normalize_project_path(pattern);
+    let project = normalize_project_path(project);
+    if !pattern.contains(['*', '?']) {
+        return project == pattern
+            || project
+                .strip_prefix(&pattern)
+                .is_some_and(|rest| rest.starts_with('/') || pattern.ends_with('/'));
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let project: Vec<char> = project.chars().collect();
+    glob_match(&pattern, &project)
+}
+
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', rest @ ..] => {
+            if let ['/', after @ ..] = rest
+                && glob_match(after, text)
+            {
+                return true;
+            }
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        ['*', rest @ ..] => {
+            for i in 0..=text.len() {
+                if glob_match(rest, &text[i..]) {
+                    return true;
+                }
+                if text.get(i) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        ['?', rest @ ..] => {
+            matches!(text.first(), Some(c) if *c != '/') && glob_match(rest, &text[1..])
+        }
+        [c, rest @ ..] => text.first() == Some(c) && glob_match(rest, &text[1..]),
+    }
 }
 
-fn days_in_month(year: i32, month: u32) -> u32 {
-    let (next_year, next_month) = if month == 12 {
-        (year + 1, 1)
+/// Resolve the project a cost belongs to: an explicit `project_path` wins,
+/// otherwise the project recorded on the session.
+pub(crate) async fn resolve_project_path(
+    kv: &StateKV,
+    input: &Value,
+    session_id: Option<Uuid>,
+) -> Result<Option<String>, IIIError> {
+    if let Some(path) = input
+        .get("project_path")
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.trim().is_empty())
+    {
+        return Ok(Some(normalize_project_path(path)));
+    }
+    let Some(sid) = session_id else {
+        return Ok(None);
+    };
+    let session: Option<Session> = kv.get("sessions", &sid.to_string()).await.map_err(kv_err)?;
+    Ok(session
+        .and_then(|s| s.project_path)
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| normalize_project_path(&p)))
+}
+
+async fn load_project_budgets(kv: &StateKV) -> Result<BTreeMap<String, ProjectBudget>, IIIError> {
+    let value: Option<Value> = kv
+        .get("config", PROJECT_BUDGETS_KEY)
+        .await
+        .map_err(kv_err)?;
+    Ok(value
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+/// The configured project budget patterns.
+pub(crate) async fn project_patterns(kv: &StateKV) -> Result<Vec<String>, IIIError> {
+    Ok(load_project_budgets(kv).await?.into_keys().collect())
+}
+
+/// Today's and this month's spend across every project matching `pattern`,
+/// from the pattern's spend counters.
+async fn compute_project_spent(
+    kv: &StateKV,
+    pattern: &str,
+    tz: Tz,
+) -> Result<(f64, f64), IIIError> {
+    let today = local_date(Utc::now(), tz);
+    let daily = counters::read(kv, &counters::project_day_key(pattern, today)).await?;
+    let monthly = counters::read(kv, &counters::project_month_key(pattern, today)).await?;
+    Ok((daily, monthly))
+}
+
+/// Status of one cap the way `budget.check` judges it: spend past the cap
+/// but inside override `headroom` is a warning, not a breach.
+fn cap_status(spent: f64, limit: f64, headroom: f64, alert_threshold: f64) -> &'static str {
+    if limit <= 0.0 {
+        "ok"
+    } else if spent >= limit + headroom {
+        "exceeded"
+    } else if spent >= limit * alert_threshold {
+        "warning"
     } else {
-        (year, month + 1)
+        "ok"
+    }
+}
+
+/// Team caps apply to the current calendar month in the team's timezone (or
+/// the global one), read from the team's month counter. Returns the team
+/// with its USD spend so far, or `None` when the team no longer exists: a
+/// stale `team_id` means no team cap, not a failed check that would skip
+/// every other cap too.
+async fn compute_team_spent(
+    kv: &StateKV,
+    team_id: &str,
+    default_tz: Tz,
+) -> Result<Option<(Team, Tz, f64)>, IIIError> {
+    let Some(team) = kv.get::<Team>(TEAM_SCOPE, team_id).await.map_err(kv_err)? else {
+        tracing::warn!("team {} not found, checking without a team cap", team_id);
+        return Ok(None);
     };
-    chrono::NaiveDate::from_ymd_opt(next_year, next_month, 1)
-        .and_then(|d| d.pred_opt())
-        .map(|d| d.day())
-        .unwrap_or(31)
+    let tz = counters::team_timezone(&team, default_tz);
+    let spent = counters::read(kv, &counters::team_month_key(team_id, Utc::now(), tz)).await?;
+    Ok(Some((team, tz, spent)))
+}
+
+/// Resolve the team to check against. A verified JWT pins the team to its
+/// claims and rejects a mismatching `team_id` in the body; internal callers
+/// without a bearer token pass `team_id` directly.
+pub(crate) fn resolve_team_id(
+    raw_input: &Value,
+    input: &Value,
+) -> Result<Option<String>, IIIError> {
+    let client_team = input
+        .get("team_id")
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty());
+
+    let headers = raw_input.get("headers").cloned().unwrap_or(Value::Null);
+    if extract_bearer(&headers).is_none() {
+        return Ok(client_team.map(String::from));
+    }
+
+    match authorize(raw_input)? {
+        Some(claims) => {
+            if let (Some(ct), Some(bt)) = (claims.team_id.as_deref(), client_team)
+                && ct != bt
+            {
+                return Err(IIIError::Handler(
+                    "unauthorized: team_id does not match token claims".into(),
+                ));
+            }
+            Ok(claims.team_id.or_else(|| client_team.map(String::from)))
+        }
+        None => Ok(client_team.map(String::from)),
+    }
 }
 
 fn register_check(iii: &III, kv: &StateKV) {
@@ -113,7 +335,9 @@ fn register_check(iii: &III, kv: &StateKV) {
         move |input: Value| {
             let kv = kv.clone();
             async move {
-                let input = extract_input(input);
+                let raw_input = input;
+                let input = extract_input(raw_input.clone());
+                let team_id = resolve_team_id(&raw_input, &input)?;
 
                 let monthly_limit = get_config_f64(&kv, "budget_monthly", 0.0).await?;
                 let daily_limit = get_config_f64(&kv, "budget_daily", 0.0).await?;
@@ -127,59 +351,222 @@ fn register_check(iii: &III, kv: &StateKV) {
                     .and_then(|v| v.as_f64())
                     .unwrap_or(0.0);
 
-                let monthly_spent = compute_monthly_spent(&kv).await? + pending_cost;
-                let daily_spent = compute_daily_spent(&kv).await? + pending_cost;
+                let session_id: Option<Uuid> = input
+                    .get("session_id")
+                    .and_then(|v| v.as_str())
+                    .map(|sid| {
+                        sid.parse::<Uuid>()
+                            .map_err(|e| IIIError::Handler(format!("invalid session_id: {}", e)))
+                    })
+                    .transpose()?;
+                let agent_id: Option<Uuid> = input
+                    .get("agent_id")
+                    .and_then(|v| v.as_str())
+                    .map(|aid| {
+                        aid.parse::<Uuid>()
+                            .map_err(|e| IIIError::Handler(format!("invalid agent_id: {}", e)))
+                    })
+                    .transpose()?;
 
-                let session_spent: Option<f64> =
-                    if let Some(sid) = input.get("session_id").and_then(|v| v.as_str()) {
-                        let parsed = sid
-                            .parse::<Uuid>()
-                            .map_err(|e| IIIError::Handler(format!("invalid session_id: {}", e)))?;
-                        Some(compute_session_spent(&kv, parsed).await? + pending_cost)
-                    } else {
-                        None
-                    };
+                // Outstanding reservations count as spend. A commit passes its
+                // own reservation_id so the hold isn't counted on top of the
+                // record that replaces it.
+                reservation::sweep(&kv).await?;
+                let committing = match input.get("reservation_id").and_then(|v| v.as_str()) {
+                    Some(id) => reservation::load(&kv, id).await?,
+                    None => None,
+                };
+                let session_key = session_id.map(|id| id.to_string());
+                let agent_key = agent_id.map(|id| id.to_string());
+                let reserved = reservation::outstanding(
+                    &kv,
+                    agent_key.as_deref(),
+                    session_key.as_deref(),
+                    team_id.as_deref(),
+                    committing.as_ref(),
+                )
+                .await?;
 
-                let agent_daily_spent: Option<f64> =
-                    if let Some(aid) = input.get("agent_id").and_then(|v| v.as_str()) {
-                        let parsed = aid
-                            .parse::<Uuid>()
-                            .map_err(|e| IIIError::Handler(format!("invalid agent_id: {}", e)))?;
-                        Some(compute_agent_daily_spent(&kv, parsed).await? + pending_cost)
-                    } else {
-                        None
-                    };
+                // Counters, reservations and `pending_cost` are USD; limits
+                // are in the budget currency at today's rate.
+                let tz = period::load_timezone(&kv).await?;
+                let budget_currency = currency::load_code(&kv, BUDGET_CURRENCY_KEY).await?;
+                let rates = currency::load_table(&kv).await?;
+                let rate = today_rate(&rates, &budget_currency, tz)?;
+                let held = pending_cost + reserved.global;
+                let monthly_spent = (compute_monthly_spent(&kv, tz).await? + held) * rate;
+                let daily_spent = (compute_daily_spent(&kv, tz).await? + held) * rate;
+
+                let session_spent: Option<f64> = match session_id {
+                    Some(sid) => Some(
+                        (compute_session_spent(&kv, sid).await? + pending_cost + reserved.session)
+                            * rate,
+                    ),
+                    None => None,
+                };
+
+                let agent_daily_spent: Option<f64> = match agent_id {
+                    Some(aid) => Some(
+                        (compute_agent_daily_spent(&kv, aid, tz).await?
+                            + pending_cost
+                            + reserved.agent)
+                            * rate,
+                    ),
+                    None => None,
+                };
+
+                let team = match team_id.as_deref() {
+                    Some(tid) => compute_team_spent(&kv, tid, tz).await?,
+                    None => None,
+                };
+                let (team_limit, team_spent, team_currency, team_rate) = match team {
+                    Some((team, team_tz, spent)) => {
+                        let code = team.currency.unwrap_or_else(|| budget_currency.clone());
+                        let team_rate = today_rate(&rates, &code, team_tz)?;
+                        (
+                            team.budget_limit.unwrap_or(0.0),
+                            Some((spent + pending_cost + reserved.team) * team_rate),
+                            code,
+                            team_rate,
+                        )
+                    }
+                    None => (0.0, None, budget_currency.clone(), rate),
+                };
+
+                let project_path = resolve_project_path(&kv, &input, session_id).await?;
+                let mut project_spent: Vec<Value> = Vec::new();
 
                 let mut status = "ok".to_string();
                 let mut warnings: Vec<String> = Vec::new();
                 let mut exceeded = false;
                 let mut warning = false;
-                let mut breached: Option<(&'static str, f64, f64)> = None;
+                let mut breached: Option<(String, f64, f64, String)> = None;
 
-                let checks: [(&'static str, f64, Option<f64>); 4] = [
-                    ("Monthly", monthly_limit, Some(monthly_spent)),
-                    ("Daily", daily_limit, Some(daily_spent)),
-                    ("Session", session_limit, session_spent),
-                    ("Agent daily", agent_daily_limit, agent_daily_spent),
+                let mut checks: Vec<(String, f64, Option<f64>)> = vec![
+                    ("Monthly".to_string(), monthly_limit, Some(monthly_spent)),
+                    ("Daily".to_string(), daily_limit, Some(daily_spent)),
+                    ("Session".to_string(), session_limit, session_spent),
+                    (
+                        "Agent daily".to_string(),
+                        agent_daily_limit,
+                        agent_daily_spent,
+                    ),
                 ];
 
-                for (label, limit, spent_opt) in checks {
+                let mut rolling_spent = serde_json::Map::new();
+                for (config_key, hours, label) in ROLLING_WINDOWS {
+                    let limit = get_config_f64(&kv, config_key, 0.0).await?;
+                    if limit <= 0.0 {
+                        continue;
+                    }
+                    let spent = (compute_rolling_spent(&kv, *hours).await? + held) * rate;
+                    rolling_spent.insert(config_key.to_string(), json!(spent));
+                    checks.push((label.to_string(), limit, Some(spent)));
+                }
+
+                if let Some(project) = project_path.as_deref() {
+                    for (pattern, budget) in load_project_budgets(&kv).await? {
+                        if !project_matches(&pattern, project) {
+                            continue;
+                        }
+                        let (daily, monthly) = compute_project_spent(&kv, &pattern, tz).await?;
+                        let project_held = pending_cost + reserved.project(&pattern);
+                        let (daily, monthly) = (
+                            (daily + project_held) * rate,
+                            (monthly + project_held) * rate,
+                        );
+                        checks.push((
+                            format!("Project daily [{}]", pattern),
+                            budget.daily_limit,
+                            Some(daily),
+                        ));
+                        checks.push((
+                            format!("Project monthly [{}]", pattern),
+                            budget.monthly_limit,
+                            Some(monthly),
+                        ));
+                        project_spent.push(json!({
+                            "pattern": pattern,
+                            "daily_limit": budget.daily_limit,
+                            "daily_spent": daily,
+                            "monthly_limit": budget.monthly_limit,
+                            "monthly_spent": monthly,
+                        }));
+                    }
+                }
+
+                // Active overrides add headroom to every cap. Spend past a cap
+                // but inside the headroom is reported as a warning, not a
+                // breach, so `block` lets it through. Override amounts are in
+                // the budget currency.
+                let active_overrides = overrides::load_active(&kv).await?;
+                let headroom = active_overrides.headroom;
+                let mut caps: Vec<Cap> = checks
+                    .into_iter()
+                    .map(|(label, limit, spent)| Cap {
+                        label,
+                        limit,
+                        spent,
+                        currency: budget_currency.clone(),
+                        headroom,
+                    })
+                    .collect();
+                caps.push(Cap {
+                    label: "Team monthly".to_string(),
+                    limit: team_limit,
+                    spent: team_spent,
+                    currency: team_currency.clone(),
+                    headroom: headroom / rate * team_rate,
+                });
+                let mut peak_ratio: f64 = 0.0;
+
+                for cap in caps {
+                    let (limit, headroom) = (cap.limit, cap.headroom);
                     if limit <= 0.0 {
                         continue;
                     }
-                    let Some(spent) = spent_opt else {
+                    let Some(spent) = cap.spent else {
                         continue;
                     };
-                    if spent >= limit {
+                    peak_ratio = peak_ratio.max(spent / (limit + headroom));
+                    if spent >= limit + headroom {
                         status = "exceeded".to_string();
                         exceeded = true;
+                        if headroom > 0.0 {
+                            warnings.push(format!(
+                                "{} budget exceeded: {} / {} (+{} override)",
+                                cap.label,
+                                cap.money(spent),
+                                cap.money(limit),
+                                cap.money(headroom)
+                            ));
+                        } else {
+                            warnings.push(format!(
+                                "{} budget exceeded: {} / {}",
+                                cap.label,
+                                cap.money(spent),
+                                cap.money(limit)
+                            ));
+                        }
+                        let current_is_exceeded =
+                            matches!(breached, Some((_, s, l, _)) if s >= l);
+                        if !current_is_exceeded {
+                            breached = Some((cap.label, spent, limit, cap.currency));
+                        }
+                    } else if spent >= limit {
+                        if status != "exceeded" {
+                            status = "warning".to_string();
+                        }
+                        warning = true;
                         warnings.push(format!(
-                            "{} budget exceeded: ${:.2} / ${:.2}",
-                            label, spent, limit
+                            "{} budget over cap, covered by override: {} / {} (+{})",
+                            cap.label,
+                            cap.money(spent),
+                            cap.money(limit),
+                            cap.money(headroom)
                         ));
-                        let current_is_exceeded = matches!(breached, Some((_, s, l)) if s >= l);
-                        if !current_is_exceeded {
-                            breached = Some((label, spent, limit));
+                        if breached.is_none() {
+                            breached = Some((cap.label, spent, limit, cap.currency));
                         }
                     } else if spent >= limit * alert_threshold {
                         if status != "exceeded" {
@@ -187,18 +574,48 @@ fn register_check(iii: &III, kv: &StateKV) {
                         }
                         warning = true;
                         warnings.push(format!(
-                            "{} budget warning: ${:.2} / ${:.2} ({:.0}%)",
-                            label,
-                            spent,
-                            limit,
+                            "{} budget warning: {} / {} ({:.0}%)",
+                            cap.label,
+                            cap.money(spent),
+                            cap.money(limit),
                             spent / limit * 100.0
                         ));
                         if breached.is_none() {
-                            breached = Some((label, spent, limit));
+                            breached = Some((cap.label, spent, limit, cap.currency));
                         }
                     }
                 }
 
+                // Under `downgrade` the caller gets a cheaper model instead of
+                // a refusal. Without a `model` in the request only the level
+                // is reported, so `rimuru sync` can apply it per agent.
+                let downgrade = if action == "downgrade" {
+                    let threshold =
+                        get_config_f64(&kv, DOWNGRADE_THRESHOLD_KEY, DEFAULT_DOWNGRADE_THRESHOLD)
+                            .await?;
+                    let level = Level::for_ratio(peak_ratio, threshold);
+                    let model = input.get("model").and_then(|v| v.as_str());
+                    let substitute = match model {
+                        Some(m) if level != Level::None => {
+                            let catalog = downgrade::load_catalog(&kv).await?;
+                            downgrade::substitute(&catalog, m, level).map(|s| s.id.clone())
+                        }
+                        _ => None,
+                    };
+                    if let (Some(from), Some(to)) = (model, substitute.as_deref()) {
+                        warnings.push(format!("Downgrading {} to {}", from, to));
+                    }
+                    json!({
+                        "level": level.as_str(),
+                        "threshold": threshold,
+                        "ratio": peak_ratio,
+                        "model": model,
+                        "recommended_model": substitute,
+                    })
+                } else {
+                    Value::Null
+                };
+
                 if warning || exceeded {
                     let alert = BudgetAlert {
                         timestamp: Utc::now().to_rfc3339(),
@@ -211,6 +628,8 @@ fn register_check(iii: &III, kv: &StateKV) {
                         } else {
                             "threshold".to_string()
                         },
+                        team_id: team_id.clone(),
+                        currency: Some(budget_currency.clone()),
                     };
 
                     let alert_key = format!(
@@ -238,6 +657,10 @@ fn register_check(iii: &III, kv: &StateKV) {
                                     "monthly_spent": monthly_spent,
                                     "daily_spent": daily_spent,
                                     "session_spent": session_spent,
+                                    "team_id": team_id,
+                                    "team_spent": team_spent,
+                                    "project_path": project_path,
+                                    "currency": budget_currency,
                                     "warnings": warnings
                                 }
                             }),
@@ -250,8 +673,13 @@ fn register_check(iii: &III, kv: &StateKV) {
                     }
 
                     if let Some(url) = load_webhook_url(&kv, "webhooks.budget_url").await {
-                        let (scope_label, breached_spent, breached_limit) =
-                            breached.unwrap_or(("Monthly", monthly_spent, monthly_limit));
+                        let (scope_label, breached_spent, breached_limit, breached_currency) =
+                            breached.unwrap_or((
+                                "Monthly".to_string(),
+                                monthly_spent,
+                                monthly_limit,
+                                budget_currency.clone(),
+                            ));
                         let percent = if breached_limit > 0.0 {
                             breached_spent / breached_limit * 100.0
                         } else {
@@ -268,8 +696,11 @@ fn register_check(iii: &III, kv: &StateKV) {
                             "scope": scope_label,
                             "current_spend": breached_spent,
                             "limit": breached_limit,
+                            "currency": breached_currency,
                             "percent": percent,
                             "agent": agent,
+                            "team": team_id,
+                            "project": project_path,
                             "timestamp": Utc::now().to_rfc3339(),
                         });
                         post_webhook(&url, &webhook_payload).await;
@@ -281,10 +712,26 @@ fn register_check(iii: &III, kv: &StateKV) {
                     "exceeded": exceeded,
                     "warning": warning,
                     "action": action,
+                    "currency": budget_currency,
                     "monthly_spent": monthly_spent,
                     "daily_spent": daily_spent,
                     "session_spent": session_spent,
                     "agent_daily_spent": agent_daily_spent,
+                    "team_id": team_id,
+                    "team_limit": team_limit,
+                    "team_spent": team_spent,
+                    "team_currency": team_id.as_ref().map(|_| &team_currency),
+                    "project_path": project_path,
+                    "project_spent": project_spent,
+                    "rolling_spent": rolling_spent,
+                    "reserved": reserved.global * rate,
+                    "recommended_model": downgrade.get("recommended_model").cloned().unwrap_or(Value::Null),
+                    "downgrade": downgrade,
+                    "override": {
+                        "headroom": headroom,
+                        "ids": active_overrides.ids,
+                        "until": active_overrides.until.map(|t| t.to_rfc3339()),
+                    },
                     "warnings": warnings
                 })))
             }
@@ -296,9 +743,16 @@ fn register_status(iii: &III, kv: &StateKV) {
     let kv = kv.clone();
     iii.register_function_with(
         RegisterFunctionMessage::with_id("rimuru.budget.status".to_string()),
-        move |_input: Value| {
+        move |input: Value| {
             let kv = kv.clone();
             async move {
+                let input = extract_input(input);
+                let project_filter = input
+                    .get("project_path")
+                    .and_then(|v| v.as_str())
+                    .filter(|s| !s.trim().is_empty())
+                    .map(normalize_project_path);
+
                 let monthly_limit = get_config_f64(&kv, "budget_monthly", 0.0).await?;
                 let daily_limit = get_config_f64(&kv, "budget_daily", 0.0).await?;
                 let session_limit = get_config_f64(&kv, "budget_session", 0.0).await?;
@@ -306,12 +760,23 @@ fn register_status(iii: &III, kv: &StateKV) {
                 let alert_threshold = get_config_f64(&kv, "budget_alert_threshold", 0.8).await?;
                 let action = get_config_str(&kv, "budget_action", "alert").await?;
 
-                let monthly_spent = compute_monthly_spent(&kv).await?;
-                let daily_spent = compute_daily_spent(&kv).await?;
+                let tz = period::resolve_timezone(&kv, &input).await?;
+                let budget_currency = currency::load_code(&kv, BUDGET_CURRENCY_KEY).await?;
+                let rate = today_rate(&currency::load_table(&kv).await?, &budget_currency, tz)?;
+                let monthly_spent = compute_monthly_spent(&kv, tz).await? * rate;
+                let weekly_spent = compute_weekly_spent(&kv, tz).await? * rate;
+                let daily_spent = compute_daily_spent(&kv, tz).await? * rate;
+                let reserved = reservation::outstanding(&kv, None, None, None, None)
+                    .await?
+                    .global
+                    * rate;
+                // Override amounts are in the budget currency, like the caps.
+                let active_overrides = overrides::load_active(&kv).await?;
+                let headroom = active_overrides.headroom;
 
-                let now = Utc::now();
-                let day_of_month = now.day() as f64;
-                let days_this_month = days_in_month(now.year(), now.month()) as f64;
+                let today = local_date(Utc::now(), tz);
+                let day_of_month = today.day() as f64;
+                let days_this_month = days_in_month(today.year(), today.month()) as f64;
                 let burn_rate_daily = if day_of_month > 0.0 {
                     monthly_spent / day_of_month
                 } else {
@@ -320,34 +785,86 @@ fn register_status(iii: &III, kv: &StateKV) {
                 let projected_monthly = burn_rate_daily * days_this_month;
 
                 let monthly_remaining = if monthly_limit > 0.0 {
-                    (monthly_limit - monthly_spent).max(0.0)
+                    (monthly_limit + headroom - monthly_spent).max(0.0)
                 } else {
                     -1.0
                 };
 
                 let daily_remaining = if daily_limit > 0.0 {
-                    (daily_limit - daily_spent).max(0.0)
+                    (daily_limit + headroom - daily_spent).max(0.0)
                 } else {
                     -1.0
                 };
 
-                let status = if (monthly_limit > 0.0 && monthly_spent >= monthly_limit)
-                    || (daily_limit > 0.0 && daily_spent >= daily_limit)
-                {
+                let mut rolling: Vec<Value> = Vec::new();
+                let mut cap_statuses = vec![
+                    cap_status(monthly_spent, monthly_limit, headroom, alert_threshold),
+                    cap_status(daily_spent, daily_limit, headroom, alert_threshold),
+                ];
+                for (config_key, hours, label) in ROLLING_WINDOWS {
+                    let limit = get_config_f64(&kv, config_key, 0.0).await?;
+                    if limit <= 0.0 {
+                        continue;
+                    }
+                    let spent = compute_rolling_spent(&kv, *hours).await? * rate;
+                    let window_status = cap_status(spent, limit, headroom, alert_threshold);
+                    cap_statuses.push(window_status);
+                    rolling.push(json!({
+                        "key": config_key,
+                        "label": label,
+                        "window_hours": hours,
+                        "limit": limit,
+                        "spent": spent,
+                        "remaining": (limit + headroom - spent).max(0.0),
+                        "status": window_status,
+                    }));
+                }
+
+                let status = if cap_statuses.contains(&"exceeded") {
                     "exceeded"
-                } else if (monthly_limit > 0.0 && monthly_spent >= monthly_limit * alert_threshold)
-                    || (daily_limit > 0.0 && daily_spent >= daily_limit * alert_threshold)
-                {
+                } else if cap_statuses.contains(&"warning") {
                     "warning"
                 } else {
                     "ok"
                 };
 
+                let mut projects: Vec<Value> = Vec::new();
+                for (pattern, budget) in load_project_budgets(&kv).await? {
+                    if let Some(filter) = project_filter.as_deref()
+                        && !project_matches(&pattern, filter)
+                    {
+                        continue;
+                    }
+                    let (daily, monthly) = compute_project_spent(&kv, &pattern, tz).await?;
+                    let (daily, monthly) = (daily * rate, monthly * rate);
+                    let daily_status =
+                        cap_status(daily, budget.daily_limit, headroom, alert_threshold);
+                    let monthly_status =
+                        cap_status(monthly, budget.monthly_limit, headroom, alert_threshold);
+                    let project_status =
+                        if daily_status == "exceeded" || monthly_status == "exceeded" {
+                            "exceeded"
+                        } else if daily_status == "warning" || monthly_status == "warning" {
+                            "warning"
+                        } else {
+                            "ok"
+                        };
+                    projects.push(json!({
+                        "pattern": pattern,
+                        "daily_limit": budget.daily_limit,
+                        "daily_spent": daily,
+                        "monthly_limit": budget.monthly_limit,
+                        "monthly_spent": monthly,
+                        "status": project_status,
+                    }));
+                }
+
                 Ok(api_response(json!({
                     "monthly_limit": monthly_limit,
                     "monthly_spent": monthly_spent,
                     "monthly_remaining": monthly_remaining,
                     "daily_limit": daily_limit,
+                    "weekly_spent": weekly_spent,
                     "daily_spent": daily_spent,
                     "daily_remaining": daily_remaining,
                     "session_limit": session_limit,
@@ -358,7 +875,17 @@ fn register_status(iii: &III, kv: &StateKV) {
                     "status_scope": "global",
                     "burn_rate_daily": burn_rate_daily,
                     "projected_monthly": projected_monthly,
-                    "days_in_month": days_this_month
+                    "days_in_month": days_this_month,
+                    "reserved": reserved,
+                    "override": {
+                        "headroom": headroom,
+                        "ids": active_overrides.ids,
+                        "until": active_overrides.until.map(|t| t.to_rfc3339()),
+                    },
+                    "currency": budget_currency,
+                    "timezone": tz.name(),
+                    "rolling": rolling,
+                    "projects": projects
                 })))
             }
         },
@@ -378,14 +905,15 @@ fn validate_limit(value: &Value, key: &str) -> Result<f64, IIIError> {
     Ok(n)
 }
 
-fn validate_threshold(value: &Value) -> Result<f64, IIIError> {
+fn validate_threshold(value: &Value, field: &str) -> Result<f64, IIIError> {
     let n = value
         .as_f64()
-        .ok_or_else(|| IIIError::Handler("alert_threshold must be a number".into()))?;
+        .ok_or_else(|| IIIError::Handler(format!("{} must be a number", field)))?;
     if !(0.0..=1.0).contains(&n) {
-        return Err(IIIError::Handler(
-            "alert_threshold must be between 0.0 and 1.0".into(),
-        ));
+        return Err(IIIError::Handler(format!(
+            "{} must be between 0.0 and 1.0",
+            field
+        )));
     }
     Ok(n)
 }
@@ -418,6 +946,8 @@ fn register_set(iii: &III, kv: &StateKV) {
                     ("daily_limit", "budget_daily"),
                     ("session_limit", "budget_session"),
                     ("daily_agent_limit", "budget_daily_agent"),
+                    ("rolling_5h_limit", "budget_rolling_5h"),
+                    ("rolling_7d_limit", "budget_rolling_7d"),
                 ];
 
                 for (input_key, config_key) in &limit_keys {
@@ -430,14 +960,54 @@ fn register_set(iii: &III, kv: &StateKV) {
                     }
                 }
 
+                if let Some(raw) = input.get("project").and_then(|v| v.as_str()) {
+                    let pattern = normalize_project_path(raw);
+                    if pattern.is_empty() {
+                        return Err(IIIError::Handler("project must not be empty".into()));
+                    }
+                    let mut budgets = load_project_budgets(&kv).await?;
+                    let added = !budgets.contains_key(&pattern);
+                    let entry = budgets.entry(pattern.clone()).or_default();
+                    if let Some(val) = input.get("project_daily_limit") {
+                        entry.daily_limit = validate_limit(val, "project_daily_limit")?;
+                    }
+                    if let Some(val) = input.get("project_monthly_limit") {
+                        entry.monthly_limit = validate_limit(val, "project_monthly_limit")?;
+                    }
+                    if entry.daily_limit <= 0.0 && entry.monthly_limit <= 0.0 {
+                        budgets.remove(&pattern);
+                    }
+                    kv.set("config", PROJECT_BUDGETS_KEY, &budgets)
+                        .await
+                        .map_err(kv_err)?;
+                    updated.push(format!("{}[{}]", PROJECT_BUDGETS_KEY, pattern));
+
+                    // A new pattern's counters only see records from now on
+                    // until a reconcile counts the ones before it.
+                    if added
+                        && budgets.contains_key(&pattern)
+                        && let Err(e) = counters::reconcile(&kv).await
+                    {
+                        tracing::warn!("failed to seed spend counters for {}: {}", pattern, e);
+                    }
+                }
+
                 if let Some(val) = input.get("alert_threshold") {
-                    let n = validate_threshold(val)?;
+                    let n = validate_threshold(val, "alert_threshold")?;
                     kv.set("config", "budget_alert_threshold", &json!(n))
                         .await
                         .map_err(kv_err)?;
                     updated.push("budget_alert_threshold".to_string());
                 }
 
+                if let Some(val) = input.get("downgrade_threshold") {
+                    let n = validate_threshold(val, "downgrade_threshold")?;
+                    kv.set("config", DOWNGRADE_THRESHOLD_KEY, &json!(n))
+                        .await
+                        .map_err(kv_err)?;
+                    updated.push(DOWNGRADE_THRESHOLD_KEY.to_string());
+                }
+
                 if let Some(val) = input.get("action") {
                     let s = validate_action(val)?;
                     kv.set("config", "budget_action", &json!(s))
@@ -470,13 +1040,428 @@ fn register_alerts(iii: &III, kv: &StateKV) {
                 alerts.sort_by_key(|b| std::cmp::Reverse(b.timestamp.clone()));
                 let total = alerts.len();
                 alerts.truncate(limit);
+                let override_history = overrides::history(&kv, limit).await?;
 
                 Ok(api_response(json!({
                     "alerts": alerts,
                     "count": alerts.len(),
-                    "total": total
+                    "total": total,
+                    "overrides": override_history
+                })))
+            }
+        },
+    );
+}
+
+/// Recorded spend per local date from `from` through `to`, for every
+/// project matching `pattern`.
+async fn load_project_history(
+    kv: &StateKV,
+    pattern: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<BTreeMap<NaiveDate, f64>, IIIError> {
+    let mut history = BTreeMap::new();
+    for date in from.iter_days().take_while(|d| *d <= to) {
+        let spent = counters::read(kv, &counters::project_day_key(pattern, date)).await?;
+        if spent != 0.0 {
+            history.insert(date, spent);
+        }
+    }
+    Ok(history)
+}
+
+fn hit_json(hit: CapHit) -> Value {
+    json!({
+        "expected_hit_date": hit.expected.map(|d| d.to_string()),
+        "earliest_hit_date": hit.earliest.map(|d| d.to_string()),
+    })
+}
+
+fn cap_forecast(key: &str, label: &str, limit: f64, spent: f64, hit: CapHit) -> Value {
+    let mut out = json!({
+        "key": key,
+        "label": label,
+        "limit": limit,
+        "spent": spent,
+    });
+    if let (Value::Object(map), Value::Object(dates)) = (&mut out, hit_json(hit)) {
+        map.extend(dates);
+    }
+    out
+}
+
+/// Everything needed to forecast one spend series over the current month.
+struct SeriesForecast {
+    month_spent: f64,
+    today_spent: f64,
+    model: DailyModel,
+    path: Vec<DayForecast>,
+}
+
+struct ForecastWindow {
+    today: NaiveDate,
+    today_remaining: f64,
+    history_start: NaiveDate,
+    month_start: NaiveDate,
+    month_end: NaiveDate,
+    horizon_end: NaiveDate,
+}
+
+impl ForecastWindow {
+    fn series(&self, spend: &BTreeMap<NaiveDate, f64>) -> SeriesForecast {
+        let yesterday = self.today - chrono::Duration::days(1);
+        let history = forecast::dense_series(spend, self.history_start, yesterday);
+        let model = DailyModel::fit(&history, forecast::DEFAULT_ALPHA);
+        let path = model.path(self.today, self.today_remaining, self.horizon_end);
+        SeriesForecast {
+            month_spent: spend
+                .range(self.month_start..=self.today)
+                .map(|(_, v)| v)
+                .sum(),
+            today_spent: spend.get(&self.today).copied().unwrap_or(0.0),
+            model,
+            path,
+        }
+    }
+
+    fn month_path<'a>(&self, path: &'a [DayForecast]) -> &'a [DayForecast] {
+        let n = path.iter().take_while(|d| d.date <= self.month_end).count();
+        &path[..n]
+    }
+}
+
+fn breakdown_json(
+    label_key: &str,
+    label: &str,
+    window: &ForecastWindow,
+    s: &SeriesForecast,
+) -> Value {
+    let month_end = forecast::project_total(s.month_spent, &s.path, window.month_end);
+    json!({
+        label_key: label,
+        "month_spent": s.month_spent,
+        "daily_rate": s.model.level,
+        "month_end": month_end,
+    })
+}
+
+fn register_forecast(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.budget.forecast".to_string()),
+        move |input: Value| {
+            let kv = kv.clone();
+            async move {
+                let input = extract_input(input);
+                // Query params arrive as strings, so accept both forms.
+                let lookback_days = input
+                    .get("lookback_days")
+                    .and_then(|v| v.as_u64().or_else(|| v.as_str()?.parse().ok()))
+                    .unwrap_or(28)
+                    .clamp(7, 180) as i64;
+                let tz = period::resolve_timezone(&kv, &input).await?;
+
+                let now = Utc::now();
+                let today = local_date(now, tz);
+                let today_start = period::day_start(today, tz);
+                let tomorrow_start = period::day_start(today + chrono::Duration::days(1), tz);
+                let day_secs = (tomorrow_start - today_start).num_seconds().max(1) as f64;
+                let month_start = period::first_of_month(today);
+                let month_end = month_start
+                    + chrono::Duration::days(days_in_month(today.year(), today.month()) as i64 - 1);
+                let week_start = local_date(period::week_start(now, tz), tz);
+                let week_end = week_start + chrono::Duration::days(6);
+                let window = ForecastWindow {
+                    today,
+                    today_remaining: (tomorrow_start - now).num_seconds() as f64 / day_secs,
+                    history_start: today - chrono::Duration::days(lookback_days),
+                    month_start,
+                    month_end,
+                    horizon_end: month_end.max(week_end),
+                };
+                let read_from = window.history_start.min(month_start);
+                // Caps are compared at today's rate, as in `budget.check`, so
+                // history is scaled by it too rather than by each day's rate.
+                let budget_currency = currency::load_code(&kv, BUDGET_CURRENCY_KEY).await?;
+                let rate = today_rate(&currency::load_table(&kv).await?, &budget_currency, tz)?;
+
+                let mut daily: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+                let mut date = read_from;
+                while date <= today {
+                    let cents = read_daily_cents(&kv, &date.format("%Y-%m-%d").to_string()).await?;
+                    daily.insert(date, cents as f64 / 100.0 * rate);
+                    date += chrono::Duration::days(1);
+                }
+                let total = window.series(&daily);
+                let week_spent: f64 = daily.range(week_start..=today).map(|(_, v)| v).sum();
+                let month_path = window.month_path(&total.path);
+
+                let alert_threshold = get_config_f64(&kv, "budget_alert_threshold", 0.8).await?;
+                let mut caps: Vec<Value> = Vec::new();
+                let monthly_limit = get_config_f64(&kv, "budget_monthly", 0.0).await?;
+                if monthly_limit > 0.0 {
+                    let hit = forecast::cumulative_hit(
+                        total.month_spent,
+                        monthly_limit,
+                        month_path,
+                        today,
+                    );
+                    caps.push(cap_forecast(
+                        "budget_monthly",
+                        "Monthly",
+                        monthly_limit,
+                        total.month_spent,
+                        hit,
+                    ));
+                }
+                let daily_limit = get_config_f64(&kv, "budget_daily", 0.0).await?;
+                if daily_limit > 0.0 {
+                    let hit = forecast::window_hit(
+                        daily_limit,
+                        1,
+                        1.0,
+                        &daily,
+                        &total.path,
+                        total.today_spent,
+                        today,
+                    );
+                    caps.push(cap_forecast(
+                        "budget_daily",
+                        "Daily",
+                        daily_limit,
+                        total.today_spent,
+                        hit,
+                    ));
+                }
+                for (config_key, hours, label) in ROLLING_WINDOWS {
+                    let limit = get_config_f64(&kv, config_key, 0.0).await?;
+                    if limit <= 0.0 {
+                        continue;
+                    }
+                    let spent = compute_rolling_spent(&kv, *hours).await? * rate;
+                    // Whole days covered by the window, scaled down for
+                    // windows shorter than a day (5h = 5/24 of a day's spend).
+                    let days = ((*hours as f64) / 24.0).ceil().max(1.0);
+                    let scale = *hours as f64 / (days * 24.0);
+                    let hit = forecast::window_hit(
+                        limit,
+                        days as usize,
+                        scale,
+                        &daily,
+                        &total.path,
+                        spent,
+                        today,
+                    );
+                    caps.push(cap_forecast(config_key, label, limit, spent, hit));
+                }
+                for (pattern, budget) in load_project_budgets(&kv).await? {
+                    if budget.daily_limit <= 0.0 && budget.monthly_limit <= 0.0 {
+                        continue;
+                    }
+                    let mut history = load_project_history(&kv, &pattern, read_from, today).await?;
+                    history.values_mut().for_each(|v| *v *= rate);
+                    let project = window.series(&history);
+                    if budget.monthly_limit > 0.0 {
+                        let hit = forecast::cumulative_hit(
+                            project.month_spent,
+                            budget.monthly_limit,
+                            window.month_path(&project.path),
+                            today,
+                        );
+                        let mut cap = cap_forecast(
+                            PROJECT_BUDGETS_KEY,
+                            &format!("Project monthly [{}]", pattern),
+                            budget.monthly_limit,
+                            project.month_spent,
+                            hit,
+                        );
+                        cap["pattern"] = json!(pattern);
+                        caps.push(cap);
+                    }
+                    if budget.daily_limit > 0.0 {
+                        let hit = forecast::window_hit(
+                            budget.daily_limit,
+                            1,
+                            1.0,
+                            &history,
+                            &project.path,
+                            project.today_spent,
+                            today,
+                        );
+                        let mut cap = cap_forecast(
+                            PROJECT_BUDGETS_KEY,
+                            &format!("Project daily [{}]", pattern),
+                            budget.daily_limit,
+                            project.today_spent,
+                            hit,
+                        );
+                        cap["pattern"] = json!(pattern);
+                        caps.push(cap);
+                    }
+                }
+
+                let records: Vec<CostRecord> = kv.list("cost_records").await.map_err(kv_err)?;
+                let mut by_agent: BTreeMap<Uuid, (String, BTreeMap<NaiveDate, f64>)> =
+                    BTreeMap::new();
+                let mut by_model: BTreeMap<String, BTreeMap<NaiveDate, f64>> = BTreeMap::new();
+                for r in records {
+                    let date = local_date(r.recorded_at, tz);
+                    if date < read_from || date > today {
+                        continue;
+                    }
+                    let agent = by_agent
+                        .entry(r.agent_id)
+                        .or_insert_with(|| (r.agent_type.to_string(), BTreeMap::new()));
+                    let cost = r.total_cost * rate;
+                    *agent.1.entry(date).or_insert(0.0) += cost;
+                    *by_model
+                        .entry(r.model)
+                        .or_default()
+                        .entry(date)
+                        .or_insert(0.0) += cost;
+                }
+                let agents: Vec<Agent> = kv.list("agents").await.map_err(kv_err)?;
+                let mut agent_rows: Vec<Value> = by_agent
+                    .iter()
+                    .map(|(id, (agent_type, spend))| {
+                        let name = agents
+                            .iter()
+                            .find(|a| a.id == *id)
+                            .map(|a| a.name.clone())
+                            .unwrap_or_else(|| id.to_string());
+                        let mut row =
+                            breakdown_json("agent_name", &name, &window, &window.series(spend));
+                        row["agent_id"] = json!(id);
+                        row["agent_type"] = json!(agent_type);
+                        row
+                    })
+                    .collect();
+                let mut model_rows: Vec<Value> = by_model
+                    .iter()
+                    .map(|(model, spend)| {
+                        breakdown_json("model", model, &window, &window.series(spend))
+                    })
+                    .collect();
+                let by_projected = |a: &Value, b: &Value| {
+                    let key = |v: &Value| v["month_end"]["expected"].as_f64().unwrap_or(0.0);
+                    key(b).total_cmp(&key(a))
+                };
+                agent_rows.sort_by(by_projected);
+                model_rows.sort_by(by_projected);
+
+                let month_projection =
+                    forecast::project_total(total.month_spent, &total.path, month_end);
+                let week_projection = forecast::project_total(week_spent, &total.path, week_end);
+                // "projected_exceed" when the central forecast crosses a cap
+                // inside the horizon, "at_risk" when only the upper band does
+                // or spend is already past the alert threshold.
+                let status = caps
+                    .iter()
+                    .map(|c| {
+                        let spent = c["spent"].as_f64().unwrap_or(0.0);
+                        let limit = c["limit"].as_f64().unwrap_or(0.0);
+                        if c["expected_hit_date"].is_string() {
+                            2
+                        } else if c["earliest_hit_date"].is_string()
+                            || cap_status(spent, limit, 0.0, alert_threshold) != "ok"
+                        {
+                            1
+                        } else {
+                            0
+                        }
+                    })
+                    .max()
+                    .map_or("ok", |rank| ["ok", "at_risk", "projected_exceed"][rank]);
+
+                Ok(api_response(json!({
+                    "generated_at": now.to_rfc3339(),
+                    "timezone": tz.name(),
+                    "currency": budget_currency,
+                    "lookback_days": lookback_days,
+                    "confidence": BAND_CONFIDENCE,
+                    "model": {
+                        "method": "ewma_weekday",
+                        "alpha": forecast::DEFAULT_ALPHA,
+                        "daily_level": total.model.level,
+                        "daily_sigma": total.model.sigma,
+                        "weekday_factors": total.model.weekday_factors,
+                        "history_days": total.model.history_days,
+                    },
+                    "today": {
+                        "date": today.to_string(),
+                        "spent": total.today_spent,
+                        "remaining_expected": total.path.first().map(|d| d.expected).unwrap_or(0.0),
+                    },
+                    "week_end": {
+                        "date": week_end.to_string(),
+                        "spent": week_spent,
+                        "projected": week_projection,
+                    },
+                    "month_end": {
+                        "date": month_end.to_string(),
+                        "spent": total.month_spent,
+                        "projected": month_projection,
+                    },
+                    "status": status,
+                    "caps": caps,
+                    "by_agent": agent_rows,
+                    "by_model": model_rows,
                 })))
             }
         },
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_project_path_canonicalizes_separators() {
+        assert_eq!(normalize_project_path("/home/u/repo/"), "/home/u/repo");
+        assert_eq!(normalize_project_path("/home//u///repo"), "/home/u/repo");
+        assert_eq!(normalize_project_path("C:\\src\\repo\\"), "C:/src/repo");
+        assert_eq!(normalize_project_path("  /  "), "/");
+    }
+
+    #[test]
+    fn plain_pattern_matches_path_and_subpaths() {
+        assert!(project_matches("/src/mono", "/src/mono"));
+        assert!(project_matches("/src/mono/", "/src/mono/services/api"));
+        assert!(!project_matches("/src/mono", "/src/monorepo"));
+        assert!(!project_matches("/src/mono/api", "/src/mono"));
+    }
+
+    #[test]
+    fn glob_pattern_respects_segments() {
+        assert!(project_matches("/src/*/api", "/src/mono/api"));
+        assert!(!project_matches("/src/*/api", "/src/mono/services/api"));
+        assert!(project_matches("/src/**/api", "/src/mono/services/api"));
+        assert!(project_matches("/src/**/api", "/src/api"));
+        assert!(project_matches("/src/mono/**", "/src/mono/web"));
+        assert!(project_matches("/src/app-?", "/src/app-1"));
+        assert!(!project_matches("/src/app-?", "/src/app-12"));
+    }
+
+    #[test]
+    fn rolling_hour_keys_cover_window_inclusive() {
+        use chrono::TimeZone;
+        let now = Utc.with_ymd_and_hms(2026, 3, 1, 2, 30, 0).unwrap();
+        let keys = rolling_hour_keys(now, 5);
+        assert_eq!(keys.len(), 6);
+        assert_eq!(keys.first().unwrap(), "2026-03-01T02");
+        assert_eq!(keys.last().unwrap(), "2026-02-28T21");
+    }
+
+    #[test]
+    fn cap_status_thresholds() {
+        assert_eq!(cap_status(5.0, 0.0, 0.0, 0.8), "ok");
+        assert_eq!(cap_status(7.0, 10.0, 0.0, 0.8), "ok");
+        assert_eq!(cap_status(8.0, 10.0, 0.0, 0.8), "warning");
+        assert_eq!(cap_status(10.0, 10.0, 0.0, 0.8), "exceeded");
+        // Inside override headroom, as budget.check reports it.
+        assert_eq!(cap_status(12.0, 10.0, 5.0, 0.8), "warning");
+        assert_eq!(cap_status(15.0, 10.0, 5.0, 0.8), "exceeded");
+    }
+}
@@ -1,6 +1,8 @@
 use iii_sdk::{III, RegisterFunctionMessage};
 use serde_json::{Value, json};
 
+use super::currency::{BUDGET_CURRENCY_KEY, DISPLAY_CURRENCY_KEY, require_rate};
+use super::period::{TIMEZONE_CONFIG_KEY, parse_timezone};
 use super::sysutil::{api_response, extract_input, kv_err, require_str};
 use crate::state::StateKV;
 
@@ -39,12 +41,18 @@ fn default_config() -> Value {
         "log_level": "info",
         "theme": "dark",
         "currency": "USD",
+        "budget_currency": "USD",
+        "timezone": "UTC",
         "budget_monthly": 0.0,
         "budget_daily": 0.0,
         "budget_session": 0.0,
         "budget_daily_agent": 0.0,
+        "budget_rolling_5h": 0.0,
+        "budget_rolling_7d": 0.0,
         "budget_alert_threshold": 0.8,
+        "budget_reservation_ttl_secs": 300,
         "budget_action": "alert",
+        "budget_downgrade_threshold": 0.9,
         "auto_detect_agents": true,
         "auto_sync_models": true,
         "model_sync_interval_hours": 24,
@@ -193,6 +201,19 @@ fn register_set(iii: &III, kv: &StateKV) {
                     }
                 }
 
+                if key == TIMEZONE_CONFIG_KEY
+                    && let Some(name) = value.as_str()
+                {
+                    parse_timezone(name)?;
+                }
+
+                let value = match (key.as_str(), value.as_str()) {
+                    (DISPLAY_CURRENCY_KEY | BUDGET_CURRENCY_KEY, Some(code)) => {
+                        json!(require_rate(&kv, code).await?)
+                    }
+                    _ => value,
+                };
+
                 let old_value: Option<Value> = kv.get("config", &key).await.map_err(kv_err)?;
 
                 kv.set("config", &key, &value).await.map_err(kv_err)?;
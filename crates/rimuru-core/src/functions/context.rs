@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 
 use super::sysutil::{api_response, extract_input, kv_err, require_str};
-use crate::adapters::ClaudeCodeAdapter;
+use crate::adapters::{ClaudeCodeAdapter, CodexAdapter};
 use crate::models::{AgentType, ContextBreakdown, ContextUtilization, Session};
 use crate::state::StateKV;
 
@@ -68,6 +68,27 @@ fn register_breakdown(iii: &III, kv: &StateKV) {
                     }
                 }
 
+                if session.agent_type == AgentType::Codex
+                    && let Some(path) = session
+                        .metadata
+                        .get("source_file")
+                        .and_then(|v| v.as_str())
+                        .filter(|p| p.ends_with(".jsonl"))
+                {
+                    let mut breakdown = CodexAdapter::new()
+                        .parse_session_breakdown(std::path::Path::new(path))
+                        .map_err(|e| iii_sdk::IIIError::Handler(format!("Parse error: {}", e)))?;
+                    breakdown.session_id = session.id;
+
+                    if let Err(e) = kv.set("context_breakdowns", &session_id, &breakdown).await {
+                        tracing::warn!("Failed to cache breakdown: {}", e);
+                    }
+
+                    return Ok(api_response(
+                        serde_json::to_value(breakdown).unwrap_or_default(),
+                    ));
+                }
+
                 Ok(json!({"status_code": 404, "body": {"error": "No breakdown available for this session type"}}))
             }
         },
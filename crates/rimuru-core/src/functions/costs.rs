@@ -1,7 +1,8 @@
 use std::collections::HashMap;
 
 use chrono::{NaiveDate, Utc};
-use iii_sdk::{III, RegisterFunctionMessage, TriggerRequest};
+use chrono_tz::Tz;
+use iii_sdk::{III, IIIError, RegisterFunctionMessage, TriggerRequest};
 use serde_json::{Value, json};
 use uuid::Uuid;
 
@@ -10,8 +11,73 @@ use crate::models::{
 };
 use crate::state::StateKV;
 
+use super::currency;
+use super::period::{self, day_key, local_date, resolve_timezone};
 use super::sysutil::{api_response, extract_input, kv_err, require_str};
 
+/// Claim counters for records written under a caller-chosen `record_id`.
+pub(crate) const RECORD_CLAIM_SCOPE: &str = "cost_record_claims";
+
+/// Cost records again, one scope per UTC date they were recorded on, so the
+/// daily rollup reads the day it finalizes instead of every record.
+const RECORDS_BY_DAY_PREFIX: &str = "cost_records_by_day";
+/// `config` flag set once records written before the day scopes existed have
+/// been filed into them.
+const RECORDS_BY_DAY_READY_KEY: &str = "cost_records_by_day_ready";
+
+fn records_by_day_scope(date: NaiveDate) -> String {
+    format!("{}::{}", RECORDS_BY_DAY_PREFIX, date)
+}
+
+/// Stores `record` and files it under its UTC date.
+pub(crate) async fn store_record(kv: &StateKV, record: &CostRecord) -> Result<(), IIIError> {
+    let id = record.id.to_string();
+    kv.set("cost_records", &id, record).await.map_err(kv_err)?;
+    kv.set(
+        &records_by_day_scope(record.recorded_at.date_naive()),
+        &id,
+        record,
+    )
+    .await
+    .map_err(kv_err)
+}
+
+/// Records of the local day `date`, read from the UTC date scopes it
+/// overlaps. The first call after an upgrade files existing records first.
+async fn records_for_day(
+    kv: &StateKV,
+    date: NaiveDate,
+    tz: Tz,
+) -> Result<Vec<CostRecord>, IIIError> {
+    let ready: Option<Value> = kv
+        .get("config", RECORDS_BY_DAY_READY_KEY)
+        .await
+        .map_err(kv_err)?;
+    if ready.is_none() {
+        let records: Vec<CostRecord> = kv.list("cost_records").await.map_err(kv_err)?;
+        for record in &records {
+            store_record(kv, record).await?;
+        }
+        kv.set("config", RECORDS_BY_DAY_READY_KEY, &json!(true))
+            .await
+            .map_err(kv_err)?;
+    }
+
+    let mut records = Vec::new();
+    for utc_date in period::utc_dates(date, tz) {
+        let bucket: Vec<CostRecord> = kv
+            .list(&records_by_day_scope(utc_date))
+            .await
+            .map_err(kv_err)?;
+        records.extend(
+            bucket
+                .into_iter()
+                .filter(|r| local_date(r.recorded_at, tz) == date),
+        );
+    }
+    Ok(records)
+}
+
 struct AgentAccum {
     agent_type: AgentType,
     cost: f64,
@@ -115,6 +181,9 @@ fn register_record(iii: &III, kv: &StateKV) {
                     record.session_id = Uuid::parse_str(session_id).ok();
                 }
 
+                record.project_path =
+                    super::budget::resolve_project_path(&kv, &input, record.session_id).await?;
+
                 if let Some(cache_read) = input.get("cache_read_tokens").and_then(|v| v.as_u64()) {
                     record.cache_read_tokens = cache_read;
                 }
@@ -162,6 +231,41 @@ fn register_record(iii: &III, kv: &StateKV) {
                     }
                 }
 
+                // A caller that retries with the same `record_id` (a
+                // reservation commit) must not be charged twice: only the
+                // first claim records, later ones get that record back.
+                let claim_key = match input.get("record_id").and_then(|v| v.as_str()) {
+                    Some(id) => {
+                        record.id = Uuid::parse_str(id).map_err(|e| {
+                            iii_sdk::IIIError::Handler(format!("invalid record_id: {}", e))
+                        })?;
+                        Some(record.id.to_string())
+                    }
+                    None => None,
+                };
+                if let Some(key) = &claim_key {
+                    let claims = kv
+                        .increment(RECORD_CLAIM_SCOPE, key, "claims", 1)
+                        .await
+                        .map_err(kv_err)?;
+                    if claims > 1 {
+                        let existing: Option<CostRecord> =
+                            kv.get("cost_records", key).await.map_err(kv_err)?;
+                        let existing = existing.ok_or_else(|| {
+                            iii_sdk::IIIError::Handler(format!(
+                                "cost record {} is already being recorded",
+                                key
+                            ))
+                        })?;
+                        return Ok(api_response(json!({
+                            "record": existing,
+                            "recorded": false,
+                            "duplicate": true,
+                            "budget_warning": false
+                        })));
+                    }
+                }
+
                 let check_result = kv
                     .iii()
                     .trigger(TriggerRequest {
@@ -169,6 +273,9 @@ fn register_record(iii: &III, kv: &StateKV) {
                         payload: json!({
                             "session_id": record.session_id.map(|id| id.to_string()),
                             "agent_id": agent_id.to_string(),
+                            "team_id": record.team_id,
+                            "project_path": record.project_path,
+                            "reservation_id": input.get("reservation_id"),
                             "pending_cost": record.total_cost
                         }),
                         action: None,
@@ -201,17 +308,19 @@ fn register_record(iii: &III, kv: &StateKV) {
                 };
 
                 if exceeded && action == "block" {
+                    // Nothing was written, so a later retry may record.
+                    if let Some(key) = &claim_key {
+                        kv.delete(RECORD_CLAIM_SCOPE, key).await.map_err(kv_err)?;
+                    }
                     return Err(iii_sdk::IIIError::Handler(
                         "Budget exceeded. Cost recording blocked.".into(),
                     ));
                 }
 
-                let record_id = record.id.to_string();
-                kv.set("cost_records", &record_id, &record)
-                    .await
-                    .map_err(kv_err)?;
+                store_record(&kv, &record).await?;
 
-                let today = Utc::now().format("%Y-%m-%d").to_string();
+                let tz = period::load_timezone(&kv).await?;
+                let today = day_key(record.recorded_at, tz);
                 kv.increment(
                     "cost_daily",
                     &today,
@@ -224,6 +333,21 @@ fn register_record(iii: &III, kv: &StateKV) {
                     .await
                     .map_err(kv_err)?;
 
+                super::counters::apply(&kv, &record, tz).await?;
+
+                let hour = super::budget::hour_key(record.recorded_at);
+                kv.increment(
+                    "cost_hourly",
+                    &hour,
+                    "total_cost_cents",
+                    (record.total_cost * 100.0) as i64,
+                )
+                .await
+                .map_err(kv_err)?;
+                kv.increment("cost_hourly", &hour, "record_count", 1)
+                    .await
+                    .map_err(kv_err)?;
+
                 let agent_cost_key = format!("{}::{}", agent_id, today);
                 kv.increment(
                     "cost_agent",
@@ -250,6 +374,21 @@ fn register_record(iii: &III, kv: &StateKV) {
                 .await
                 .map_err(kv_err)?;
 
+                if let Some(project) = &record.project_path {
+                    let project_cost_key = format!("{}::{}", project, today);
+                    kv.increment(
+                        "cost_project",
+                        &project_cost_key,
+                        "total_cost_cents",
+                        (record.total_cost * 100.0) as i64,
+                    )
+                    .await
+                    .map_err(kv_err)?;
+                    kv.increment("cost_project", &project_cost_key, "record_count", 1)
+                        .await
+                        .map_err(kv_err)?;
+                }
+
                 if let Some(session_id) = record.session_id {
                     let session_key = session_id.to_string();
                     let prev_total: f64 = kv
@@ -317,7 +456,10 @@ fn register_summary(iii: &III, kv: &StateKV) {
             let kv = kv.clone();
             async move {
                 let input = extract_input(input);
-                let records: Vec<CostRecord> = kv.list("cost_records").await.map_err(kv_err)?;
+                let tz = resolve_timezone(&kv, &input).await?;
+                let converter = currency::display_converter(&kv, &input, tz).await?;
+                let mut records: Vec<CostRecord> = kv.list("cost_records").await.map_err(kv_err)?;
+                converter.apply(&mut records)?;
 
                 let since = input
                     .get("since")
@@ -415,7 +557,10 @@ fn register_summary(iii: &III, kv: &StateKV) {
                     period_end: until,
                 };
 
-                Ok(api_response(json!({"summary": summary})))
+                Ok(api_response(json!({
+                    "summary": summary,
+                    "currency": converter.code,
+                })))
             }
         },
     );
@@ -430,8 +575,11 @@ fn register_daily(iii: &III, kv: &StateKV) {
             async move {
                 let input = extract_input(input);
                 let days = input.get("days").and_then(|v| v.as_u64()).unwrap_or(30);
+                let tz = resolve_timezone(&kv, &input).await?;
+                let converter = currency::display_converter(&kv, &input, tz).await?;
 
-                let records: Vec<CostRecord> = kv.list("cost_records").await.map_err(kv_err)?;
+                let mut records: Vec<CostRecord> = kv.list("cost_records").await.map_err(kv_err)?;
+                converter.apply(&mut records)?;
 
                 let cutoff = Utc::now() - chrono::Duration::days(days as i64);
 
@@ -441,7 +589,7 @@ fn register_daily(iii: &III, kv: &StateKV) {
                 let mut daily_map: HashMap<NaiveDate, DailyAccum> = HashMap::new();
 
                 for r in &filtered {
-                    let date = r.recorded_at.date_naive();
+                    let date = local_date(r.recorded_at, tz);
                     let entry = daily_map.entry(date).or_insert(DailyAccum {
                         cost: 0.0,
                         input_tokens: 0,
@@ -479,6 +627,8 @@ fn register_daily(iii: &III, kv: &StateKV) {
                     "daily": daily,
                     "total_cost": total_cost,
                     "days": days,
+                    "timezone": tz.name(),
+                    "currency": converter.code,
                     "total_days_with_usage": daily.len()
                 })))
             }
@@ -499,7 +649,10 @@ fn register_by_agent(iii: &III, kv: &StateKV) {
                 let agent_id = Uuid::parse_str(&agent_id_str)
                     .map_err(|e| iii_sdk::IIIError::Handler(format!("invalid agent_id: {}", e)))?;
 
-                let records: Vec<CostRecord> = kv.list("cost_records").await.map_err(kv_err)?;
+                let tz = resolve_timezone(&kv, &input).await?;
+                let converter = currency::display_converter(&kv, &input, tz).await?;
+                let mut records: Vec<CostRecord> = kv.list("cost_records").await.map_err(kv_err)?;
+                converter.apply(&mut records)?;
 
                 let days = input.get("days").and_then(|v| v.as_u64()).unwrap_or(30);
 
@@ -546,7 +699,8 @@ fn register_by_agent(iii: &III, kv: &StateKV) {
                     "total_output_tokens": total_output,
                     "total_records": filtered.len(),
                     "by_model": models,
-                    "days": days
+                    "days": days,
+                    "currency": converter.code
                 })))
             }
         },
@@ -562,20 +716,35 @@ fn register_daily_rollup(iii: &III, kv: &StateKV) {
             async move {
                 let input = extract_input(input);
                 let date_str = input.get("date").and_then(|v| v.as_str()).unwrap_or("");
+                // The rollup overwrites `cost_daily`, whose keys and counters
+                // costs.record and budget checks share under the configured
+                // timezone, so it cannot be recomputed for another one.
+                let tz = period::load_timezone(&kv).await?;
+                if let Some(name) = input
+                    .get("timezone")
+                    .and_then(|v| v.as_str())
+                    .filter(|n| !n.trim().is_empty())
+                    && period::parse_timezone(name)? != tz
+                {
+                    return Err(iii_sdk::IIIError::Handler(format!(
+                        "daily rollups are persisted in the configured timezone ({}); \
+                         use rimuru.costs.daily for other timezones",
+                        tz.name()
+                    )));
+                }
 
+                // Without an explicit date, finalize the local day that most
+                // recently ended; the hourly cron lands shortly after it.
                 let target_date = if date_str.is_empty() {
-                    Utc::now().date_naive()
+                    local_date(Utc::now(), tz)
+                        .pred_opt()
+                        .unwrap_or_else(|| local_date(Utc::now(), tz))
                 } else {
                     NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
                         .map_err(|e| iii_sdk::IIIError::Handler(format!("invalid date: {}", e)))?
                 };
 
-                let records: Vec<CostRecord> = kv.list("cost_records").await.map_err(kv_err)?;
-
-                let day_records: Vec<&CostRecord> = records
-                    .iter()
-                    .filter(|r| r.recorded_at.date_naive() == target_date)
-                    .collect();
+                let day_records = records_for_day(&kv, target_date, tz).await?;
 
                 let total_cost: f64 = day_records.iter().map(|r| r.total_cost).sum();
                 let total_input: u64 = day_records.iter().map(|r| r.input_tokens).sum();
@@ -628,12 +797,18 @@ fn register_daily_rollup(iii: &III, kv: &StateKV) {
                     by_agent,
                 };
 
-                kv.set("cost_daily", &target_date.to_string(), &rollup)
+                // Budget checks read the counter maintained by costs.record, so
+                // carry it alongside the recomputed summary instead of
+                // dropping it when the key is overwritten.
+                let mut persisted = serde_json::to_value(&rollup).map_err(kv_err)?;
+                persisted["total_cost_cents"] = json!((total_cost * 100.0).round() as i64);
+                kv.set("cost_daily", &target_date.to_string(), &persisted)
                     .await
                     .map_err(kv_err)?;
 
                 Ok(api_response(json!({
                     "rollup": rollup,
+                    "timezone": tz.name(),
                     "persisted": true
                 })))
             }
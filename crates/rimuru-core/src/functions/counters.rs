@@ -0,0 +1,413 @@
+//! Spend counters read by `rimuru.budget.check`.
+//!
+//! `costs.record` bumps one counter per period the record falls in (hour,
+//! local day, week and month, session, agent-day, team-month, and project
+//! day and month for each budgeted pattern the project matches) with
+//! `StateKV::increment`, so a check costs a fixed number of reads however
+//! much history has piled up. Amounts are kept in micro-dollars: sub-cent
+//! calls are common and would vanish if truncated to cents one by one.
+//!
+//! Records that bypass `costs.record` (`rimuru.agents.sync` backfills) and
+//! retention cleanup make the counters drift, so `rimuru.budget.reconcile`
+//! rebuilds the expected totals from `cost_records` and applies the
+//! difference. It runs hourly, at worker start, after each agent sync and
+//! when a project budget is added, which is how a new pattern's counters
+//! pick up the spend recorded before it.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use iii_sdk::{III, IIIError, RegisterFunctionMessage};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use super::budget::project_matches;
+use super::period::{self, local_date};
+use super::sysutil::{api_response, kv_err};
+use super::team::{TEAM_SCOPE, Team};
+use crate::models::CostRecord;
+use crate::state::StateKV;
+
+pub const SPEND_SCOPE: &str = "spend_counters";
+const FIELD: &str = "cost_micros";
+
+pub(crate) fn to_micros(dollars: f64) -> i64 {
+    (dollars * 1_000_000.0).round() as i64
+}
+
+pub(crate) fn from_micros(micros: i64) -> f64 {
+    micros as f64 / 1_000_000.0
+}
+
+/// Same UTC buckets as `cost_hourly`, so rolling windows line up with it.
+pub fn hour_key(at: DateTime<Utc>) -> String {
+    format!("hour::{}", super::budget::hour_key(at))
+}
+
+pub fn day_key(at: DateTime<Utc>, tz: Tz) -> String {
+    format!("day::{}", local_date(at, tz).format("%Y-%m-%d"))
+}
+
+/// Keyed by the local Monday the week starts on.
+pub fn week_key(at: DateTime<Utc>, tz: Tz) -> String {
+    let today = local_date(at, tz);
+    let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    format!("week::{}", monday.format("%Y-%m-%d"))
+}
+
+pub fn month_key(at: DateTime<Utc>, tz: Tz) -> String {
+    format!("month::{}", local_date(at, tz).format("%Y-%m"))
+}
+
+pub fn session_key(session_id: Uuid) -> String {
+    format!("session::{}", session_id)
+}
+
+pub fn agent_day_key(agent_id: Uuid, at: DateTime<Utc>, tz: Tz) -> String {
+    format!(
+        "agent::{}::{}",
+        agent_id,
+        local_date(at, tz).format("%Y-%m-%d")
+    )
+}
+
+/// Team months follow the team's own timezone, as `budget.check` does.
+pub fn team_month_key(team_id: &str, at: DateTime<Utc>, team_tz: Tz) -> String {
+    format!(
+        "team::{}::{}",
+        team_id,
+        local_date(at, team_tz).format("%Y-%m")
+    )
+}
+
+/// Project budgets are keyed by pattern and a project can match several, so
+/// project spend is counted per pattern, on the local date.
+pub fn project_day_key(pattern: &str, date: NaiveDate) -> String {
+    format!("project::{}::day::{}", pattern, date.format("%Y-%m-%d"))
+}
+
+pub fn project_month_key(pattern: &str, date: NaiveDate) -> String {
+    format!("project::{}::month::{}", pattern, date.format("%Y-%m"))
+}
+
+/// Every counter `record` contributes to. Team spend only counts records
+/// that carry a user, matching `team::aggregate`, so `team_tz` is `None`
+/// when the record has no team, no user, or the team does not exist.
+/// `patterns` are the configured project budget patterns.
+pub(crate) fn record_keys(
+    record: &CostRecord,
+    tz: Tz,
+    team_tz: Option<Tz>,
+    patterns: &[String],
+) -> Vec<String> {
+    let at = record.recorded_at;
+    let mut keys = vec![
+        hour_key(at),
+        day_key(at, tz),
+        week_key(at, tz),
+        month_key(at, tz),
+        agent_day_key(record.agent_id, at, tz),
+    ];
+    if let Some(sid) = record.session_id {
+        keys.push(session_key(sid));
+    }
+    if let (Some(team_id), Some(team_tz)) = (record.team_id.as_deref(), team_tz) {
+        keys.push(team_month_key(team_id, at, team_tz));
+    }
+    if let Some(project) = record.project_path.as_deref() {
+        let date = local_date(at, tz);
+        for pattern in patterns.iter().filter(|p| project_matches(p, project)) {
+            keys.push(project_day_key(pattern, date));
+            keys.push(project_month_key(pattern, date));
+        }
+    }
+    keys
+}
+
+/// Timezone of the team `record` bills to, when team counters apply.
+async fn record_team_tz(
+    kv: &StateKV,
+    record: &CostRecord,
+    default_tz: Tz,
+) -> Result<Option<Tz>, IIIError> {
+    let (Some(team_id), Some(_)) = (record.team_id.as_deref(), record.user_id.as_deref()) else {
+        return Ok(None);
+    };
+    let team: Option<Team> = kv.get(TEAM_SCOPE, team_id).await.map_err(kv_err)?;
+    Ok(team.map(|t| team_timezone(&t, default_tz)))
+}
+
+pub(crate) fn team_timezone(team: &Team, default_tz: Tz) -> Tz {
+    team.timezone
+        .as_deref()
+        .and_then(|name| period::parse_timezone(name).ok())
+        .unwrap_or(default_tz)
+}
+
+/// Add a freshly stored record to its counters.
+pub(crate) async fn apply(kv: &StateKV, record: &CostRecord, tz: Tz) -> Result<(), IIIError> {
+    let team_tz = record_team_tz(kv, record, tz).await?;
+    let patterns = super::budget::project_patterns(kv).await?;
+    let micros = to_micros(record.total_cost);
+    for key in record_keys(record, tz, team_tz, &patterns) {
+        kv.increment(SPEND_SCOPE, &key, FIELD, micros)
+            .await
+            .map_err(kv_err)?;
+    }
+    Ok(())
+}
+
+/// Current value of one counter in dollars; missing counters are zero.
+pub(crate) async fn read(kv: &StateKV, key: &str) -> Result<f64, IIIError> {
+    let value: Option<Value> = kv.get(SPEND_SCOPE, key).await.map_err(kv_err)?;
+    Ok(from_micros(
+        value
+            .and_then(|v| v.get(FIELD).and_then(|m| m.as_i64()))
+            .unwrap_or(0),
+    ))
+}
+
+/// Expected counter values for `records`, in micro-dollars.
+pub fn rebuild(
+    records: &[CostRecord],
+    tz: Tz,
+    team_tzs: &HashMap<String, Tz>,
+    patterns: &[String],
+) -> BTreeMap<String, i64> {
+    let mut totals = BTreeMap::new();
+    for r in records {
+        let team_tz = match (r.team_id.as_deref(), r.user_id.as_deref()) {
+            (Some(team_id), Some(_)) => team_tzs.get(team_id).copied(),
+            _ => None,
+        };
+        let micros = to_micros(r.total_cost);
+        for key in record_keys(r, tz, team_tz, patterns) {
+            *totals.entry(key).or_insert(0) += micros;
+        }
+    }
+    totals
+}
+
+/// Records stamped this close to the counter snapshot may have been stored
+/// but not yet added to their counters, so the keys they touch sit out one
+/// reconcile rather than being "corrected" into a double count.
+const UNSETTLED_SECS: i64 = 300;
+
+#[derive(Debug, Default)]
+pub(crate) struct Reconciled {
+    pub counters: usize,
+    pub corrected: usize,
+    pub skipped: usize,
+    pub drift_micros: i64,
+}
+
+/// Increments that take `have` to `expected`. Counters no record maps to
+/// any more (retention cleanup) go back to zero; `unsettled` keys are left
+/// alone.
+pub fn corrections(
+    expected: &BTreeMap<String, i64>,
+    have: &BTreeMap<String, i64>,
+    unsettled: &BTreeSet<String>,
+) -> Vec<(String, i64)> {
+    let keys: BTreeSet<&String> = expected.keys().chain(have.keys()).collect();
+    keys.into_iter()
+        .filter(|key| !unsettled.contains(*key))
+        .filter_map(|key| {
+            let want = expected.get(key).copied().unwrap_or(0);
+            let delta = want - have.get(key).copied().unwrap_or(0);
+            (delta != 0).then(|| (key.clone(), delta))
+        })
+        .collect()
+}
+
+/// Bring every counter derived from `cost_records` back in line. Counters
+/// are read before records are listed: anything already counted is then
+/// also listed, since `costs.record` stores before it increments. Records
+/// stamped after the snapshot (less a grace period) may sit on one side
+/// only, so their keys are skipped until the next run. Each fix is an
+/// atomic increment by the difference rather than an overwrite, so
+/// increments that land mid-run survive.
+pub(crate) async fn reconcile(kv: &StateKV) -> Result<Reconciled, IIIError> {
+    let tz = period::load_timezone(kv).await?;
+    let teams: Vec<Team> = kv.list(TEAM_SCOPE).await.map_err(kv_err)?;
+    let team_tzs: HashMap<String, Tz> = teams
+        .iter()
+        .map(|t| (t.id.clone(), team_timezone(t, tz)))
+        .collect();
+    let patterns = super::budget::project_patterns(kv).await?;
+
+    let snapshot_at = Utc::now();
+    let mut have = BTreeMap::new();
+    for key in kv.list_keys(SPEND_SCOPE).await.map_err(kv_err)? {
+        let value: Option<Value> = kv.get(SPEND_SCOPE, &key).await.map_err(kv_err)?;
+        let micros = value
+            .and_then(|v| v.get(FIELD).and_then(|m| m.as_i64()))
+            .unwrap_or(0);
+        have.insert(key, micros);
+    }
+
+    let records: Vec<CostRecord> = kv.list("cost_records").await.map_err(kv_err)?;
+    let expected = rebuild(&records, tz, &team_tzs, &patterns);
+    let settle_from = snapshot_at - Duration::seconds(UNSETTLED_SECS);
+    let recent: Vec<CostRecord> = records
+        .into_iter()
+        .filter(|r| r.recorded_at >= settle_from)
+        .collect();
+    let unsettled: BTreeSet<String> = rebuild(&recent, tz, &team_tzs, &patterns)
+        .into_keys()
+        .collect();
+
+    let mut out = Reconciled {
+        counters: expected
+            .keys()
+            .chain(have.keys())
+            .collect::<BTreeSet<_>>()
+            .len(),
+        skipped: unsettled.len(),
+        ..Default::default()
+    };
+    for (key, delta) in corrections(&expected, &have, &unsettled) {
+        kv.increment(SPEND_SCOPE, &key, FIELD, delta)
+            .await
+            .map_err(kv_err)?;
+        out.corrected += 1;
+        out.drift_micros += delta.abs();
+    }
+    Ok(out)
+}
+
+pub fn register(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.budget.reconcile".to_string()),
+        move |_input: Value| {
+            let kv = kv.clone();
+            async move {
+                let result = reconcile(&kv).await?;
+                if result.corrected > 0 {
+                    tracing::info!(
+                        "reconciled {} of {} spend counters (drift ${:.6})",
+                        result.corrected,
+                        result.counters,
+                        from_micros(result.drift_micros)
+                    );
+                }
+                Ok(api_response(json!({
+                    "counters": result.counters,
+                    "corrected": result.corrected,
+                    "skipped": result.skipped,
+                    "drift": from_micros(result.drift_micros),
+                    "reconciled_at": Utc::now().to_rfc3339(),
+                })))
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AgentType;
+    use chrono::TimeZone;
+
+    fn record(cost: f64, at: DateTime<Utc>) -> CostRecord {
+        let mut r = CostRecord::new(
+            Uuid::new_v4(),
+            AgentType::ClaudeCode,
+            "claude-sonnet-4-6".into(),
+            "anthropic".into(),
+            1000,
+            100,
+            cost,
+            0.0,
+        );
+        r.recorded_at = at;
+        r
+    }
+
+    #[test]
+    fn keys_cover_every_period() {
+        let at = Utc.with_ymd_and_hms(2026, 3, 15, 20, 0, 0).unwrap();
+        let tz = period::parse_timezone("Asia/Kolkata").unwrap();
+        let mut r = record(0.01, at);
+        r.session_id = Some(Uuid::new_v4());
+        r.team_id = Some("core".into());
+        let keys = record_keys(&r, tz, Some(Tz::UTC), &[]);
+        assert!(keys.contains(&"hour::2026-03-15T20".to_string()));
+        // 01:30 Monday in Kolkata.
+        assert!(keys.contains(&"day::2026-03-16".to_string()));
+        assert!(keys.contains(&"week::2026-03-16".to_string()));
+        assert!(keys.contains(&"month::2026-03".to_string()));
+        assert!(keys.contains(&"team::core::2026-03".to_string()));
+        assert_eq!(keys.len(), 7);
+        assert_eq!(record_keys(&r, tz, None, &[]).len(), 6);
+    }
+
+    #[test]
+    fn rebuild_keeps_sub_cent_costs() {
+        let at = Utc.with_ymd_and_hms(2026, 5, 2, 9, 0, 0).unwrap();
+        let records: Vec<CostRecord> = (0..1000).map(|_| record(0.004, at)).collect();
+        let totals = rebuild(&records, Tz::UTC, &HashMap::new(), &[]);
+        assert_eq!(from_micros(totals["month::2026-05"]), 4.0);
+        assert_eq!(from_micros(totals["day::2026-05-02"]), 4.0);
+    }
+
+    #[test]
+    fn team_counters_need_a_user_and_known_team() {
+        let at = Utc.with_ymd_and_hms(2026, 5, 31, 23, 30, 0).unwrap();
+        let mut with_user = record(1.0, at);
+        with_user.team_id = Some("core".into());
+        with_user.user_id = Some("alice".into());
+        let mut no_user = record(2.0, at);
+        no_user.team_id = Some("core".into());
+        let mut unknown_team = record(4.0, at);
+        unknown_team.team_id = Some("ghost".into());
+        unknown_team.user_id = Some("bob".into());
+
+        let tokyo = period::parse_timezone("Asia/Tokyo").unwrap();
+        let team_tzs = HashMap::from([("core".to_string(), tokyo)]);
+        let totals = rebuild(&[with_user, no_user, unknown_team], Tz::UTC, &team_tzs, &[]);
+        // 23:30 UTC on May 31 is already June in Tokyo.
+        assert_eq!(from_micros(totals["team::core::2026-06"]), 1.0);
+        assert!(!totals.keys().any(|k| k.starts_with("team::ghost")));
+        assert_eq!(from_micros(totals["month::2026-05"]), 7.0);
+    }
+
+    #[test]
+    fn project_counters_follow_every_matching_pattern() {
+        let at = Utc.with_ymd_and_hms(2026, 5, 31, 23, 30, 0).unwrap();
+        let mut api = record(1.0, at);
+        api.project_path = Some("/work/api".into());
+        let mut web = record(2.0, at);
+        web.project_path = Some("/work/web".into());
+        let untracked = record(4.0, at);
+
+        let patterns = vec!["/work".to_string(), "/work/api".to_string()];
+        let tz = period::parse_timezone("Asia/Tokyo").unwrap();
+        let totals = rebuild(&[api, web, untracked], tz, &HashMap::new(), &patterns);
+        // Local dates: 23:30 UTC on May 31 is June 1 in Tokyo.
+        assert_eq!(from_micros(totals["project::/work::day::2026-06-01"]), 3.0);
+        assert_eq!(from_micros(totals["project::/work::month::2026-06"]), 3.0);
+        assert_eq!(
+            from_micros(totals["project::/work/api::month::2026-06"]),
+            1.0
+        );
+        assert!(!totals.contains_key("project::/work::day::2026-05-31"));
+    }
+
+    #[test]
+    fn corrections_zero_orphans_and_skip_unsettled_keys() {
+        let expected = BTreeMap::from([("day::a".to_string(), 5), ("day::b".to_string(), 7)]);
+        let have = BTreeMap::from([
+            ("day::a".to_string(), 3),
+            ("day::b".to_string(), 9),
+            ("session::gone".to_string(), 4),
+        ]);
+        let unsettled = BTreeSet::from(["day::b".to_string()]);
+        assert_eq!(
+            corrections(&expected, &have, &unsettled),
+            vec![("day::a".to_string(), 2), ("session::gone".to_string(), -4)]
+        );
+    }
+}
@@ -0,0 +1,464 @@
+//! Display and budget currencies (`rimuru.currency.*`).
+//!
+//! Cost records stay in USD. Reports convert each record at the rate in
+//! effect on its own local date, so a month closed at 0.92 EUR/USD still
+//! reads the same after the operator adds a newer rate. Budget limits are
+//! denominated in `budget_currency` (or a team's own `currency`) and compare
+//! against spend converted at today's rate.
+//!
+//! Rates live in the `currency_rates` scope, one entry per currency and
+//! effective date, and are maintained by hand with `rimuru currency set`.
+//! Setting a rate for an existing date replaces it; nothing is deleted, so
+//! past reports can always be reproduced.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use iii_sdk::{III, IIIError, RegisterFunctionMessage};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use super::period::local_date;
+use super::sysutil::{api_response, extract_input, kv_err, require_str};
+use crate::models::CostRecord;
+use crate::state::StateKV;
+
+pub const RATES_SCOPE: &str = "currency_rates";
+/// Config key for the currency reports are rendered in.
+pub const DISPLAY_CURRENCY_KEY: &str = "currency";
+/// Config key for the currency global and project limits are set in.
+pub const BUDGET_CURRENCY_KEY: &str = "budget_currency";
+pub const BASE_CURRENCY: &str = "USD";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExchangeRate {
+    pub currency: String,
+    /// Units of `currency` per US dollar.
+    pub rate: f64,
+    pub effective_from: NaiveDate,
+    #[serde(default)]
+    pub set_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ExchangeRate {
+    pub fn key(&self) -> String {
+        rate_key(&self.currency, self.effective_from)
+    }
+}
+
+pub fn rate_key(currency: &str, effective_from: NaiveDate) -> String {
+    format!("{}::{}", currency, effective_from.format("%Y-%m-%d"))
+}
+
+/// Normalize an ISO 4217 code (`eur` → `EUR`).
+pub fn parse_code(code: &str) -> Result<String, IIIError> {
+    let code = code.trim();
+    if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(IIIError::Handler(format!(
+            "invalid currency code: {:?} (expected ISO 4217, e.g. EUR)",
+            code
+        )));
+    }
+    Ok(code.to_ascii_uppercase())
+}
+
+/// Symbol and number of minor-unit digits for rendering amounts.
+pub fn symbol(code: &str) -> Option<&'static str> {
+    match code {
+        "USD" => Some("$"),
+        "EUR" => Some("€"),
+        "GBP" => Some("£"),
+        "INR" => Some("₹"),
+        "JPY" => Some("¥"),
+        _ => None,
+    }
+}
+
+pub fn decimals(code: &str) -> usize {
+    match code {
+        "JPY" | "KRW" => 0,
+        _ => 2,
+    }
+}
+
+/// `$1.23`, `€1.23`, `¥123`, or `1.23 CHF` for codes without a symbol.
+pub fn format_amount(amount: f64, code: &str) -> String {
+    let digits = decimals(code);
+    match symbol(code) {
+        Some(sym) => format!("{}{:.*}", sym, digits, amount),
+        None => format!("{:.*} {}", digits, amount, code),
+    }
+}
+
+/// Every stored rate, grouped by currency and sorted by effective date.
+#[derive(Debug, Clone, Default)]
+pub struct RateTable {
+    rates: HashMap<String, Vec<(NaiveDate, f64)>>,
+}
+
+impl RateTable {
+    pub fn new(rates: &[ExchangeRate]) -> Self {
+        let mut table = RateTable::default();
+        for r in rates {
+            table
+                .rates
+                .entry(r.currency.clone())
+                .or_default()
+                .push((r.effective_from, r.rate));
+        }
+        for entries in table.rates.values_mut() {
+            entries.sort_by_key(|(date, _)| *date);
+        }
+        table
+    }
+
+    pub fn has(&self, code: &str) -> bool {
+        code == BASE_CURRENCY || self.rates.contains_key(code)
+    }
+
+    /// Units of `code` per dollar on `date`: the latest rate effective on or
+    /// before it. Dates before the first rate use the first rate, so old
+    /// records still render once a currency is introduced.
+    pub fn rate_on(&self, code: &str, date: NaiveDate) -> Result<f64, IIIError> {
+        if code == BASE_CURRENCY {
+            return Ok(1.0);
+        }
+        let entries = self.rates.get(code).ok_or_else(|| missing_rate(code))?;
+        let idx = entries.partition_point(|(from, _)| *from <= date);
+        Ok(entries[idx.saturating_sub(1)].1)
+    }
+}
+
+fn missing_rate(code: &str) -> IIIError {
+    IIIError::Handler(format!(
+        "no exchange rate for {}; add one with `rimuru currency set {} <rate>`",
+        code, code
+    ))
+}
+
+pub(crate) async fn load_table(kv: &StateKV) -> Result<RateTable, IIIError> {
+    let rates: Vec<ExchangeRate> = kv.list(RATES_SCOPE).await.map_err(kv_err)?;
+    Ok(RateTable::new(&rates))
+}
+
+/// A configured currency code, falling back to USD when unset or invalid.
+pub(crate) async fn load_code(kv: &StateKV, config_key: &str) -> Result<String, IIIError> {
+    let value: Option<Value> = kv.get("config", config_key).await.map_err(kv_err)?;
+    Ok(match value.as_ref().and_then(|v| v.as_str()) {
+        Some(code) => parse_code(code).unwrap_or_else(|e| {
+            tracing::warn!("ignoring configured {}: {}", config_key, e);
+            BASE_CURRENCY.to_string()
+        }),
+        None => BASE_CURRENCY.to_string(),
+    })
+}
+
+/// Converts USD amounts into one currency at the rate for each amount's
+/// local date.
+#[derive(Debug, Clone)]
+pub struct Converter {
+    pub code: String,
+    table: RateTable,
+    tz: Tz,
+}
+
+impl Converter {
+    pub fn new(code: &str, table: RateTable, tz: Tz) -> Result<Self, IIIError> {
+        if !table.has(code) {
+            return Err(missing_rate(code));
+        }
+        Ok(Converter {
+            code: code.to_string(),
+            table,
+            tz,
+        })
+    }
+
+    pub fn rate_at(&self, at: DateTime<Utc>) -> Result<f64, IIIError> {
+        self.table.rate_on(&self.code, local_date(at, self.tz))
+    }
+
+    /// Rewrite the money fields of `records` in this currency, so existing
+    /// aggregation code sums converted amounts unchanged.
+    pub fn apply(&self, records: &mut [CostRecord]) -> Result<(), IIIError> {
+        if self.code == BASE_CURRENCY {
+            return Ok(());
+        }
+        for r in records {
+            let rate = self.rate_at(r.recorded_at)?;
+            r.input_cost *= rate;
+            r.output_cost *= rate;
+            r.total_cost *= rate;
+        }
+        Ok(())
+    }
+}
+
+/// Converter for a report: a `currency` field on the request wins over the
+/// configured display currency. A configured currency without a rate (e.g.
+/// `currency: "EUR"` set before rates existed) renders in USD rather than
+/// failing the report; responses carry `converter.code`, so callers see
+/// which currency was used.
+pub(crate) async fn display_converter(
+    kv: &StateKV,
+    input: &Value,
+    tz: Tz,
+) -> Result<Converter, IIIError> {
+    let table = load_table(kv).await?;
+    if let Some(code) = input
+        .get("currency")
+        .and_then(|v| v.as_str())
+        .filter(|c| !c.trim().is_empty())
+    {
+        return Converter::new(&parse_code(code)?, table, tz);
+    }
+    let code = load_code(kv, DISPLAY_CURRENCY_KEY).await?;
+    if !table.has(&code) {
+        tracing::warn!(
+            "no exchange rate for display currency {}; reporting in {}",
+            code,
+            BASE_CURRENCY
+        );
+        return Converter::new(BASE_CURRENCY, table, tz);
+    }
+    Converter::new(&code, table, tz)
+}
+
+/// Reject a currency that conversions would fail on.
+pub(crate) async fn require_rate(kv: &StateKV, code: &str) -> Result<String, IIIError> {
+    let code = parse_code(code)?;
+    if !load_table(kv).await?.has(&code) {
+        return Err(missing_rate(&code));
+    }
+    Ok(code)
+}
+
+fn parse_date(input: &Value, key: &str) -> Result<Option<NaiveDate>, IIIError> {
+    match input.get(key).and_then(|v| v.as_str()) {
+        Some(s) if !s.trim().is_empty() => NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d")
+            .map(Some)
+            .map_err(|e| {
+                IIIError::Handler(format!("invalid {} (expected YYYY-MM-DD): {}", key, e))
+            }),
+        _ => Ok(None),
+    }
+}
+
+pub fn register(iii: &III, kv: &StateKV) {
+    register_rates(iii, kv);
+    register_set_rate(iii, kv);
+    register_convert(iii, kv);
+}
+
+fn register_rates(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.currency.rates".to_string()),
+        move |input: Value| {
+            let kv = kv.clone();
+            async move {
+                let input = extract_input(input);
+                let filter = match input.get("currency").and_then(|v| v.as_str()) {
+                    Some(code) if !code.trim().is_empty() => Some(parse_code(code)?),
+                    _ => None,
+                };
+
+                let mut rates: Vec<ExchangeRate> = kv.list(RATES_SCOPE).await.map_err(kv_err)?;
+                rates.retain(|r| filter.as_deref().is_none_or(|c| r.currency == c));
+                rates.sort_by(|a, b| {
+                    a.currency
+                        .cmp(&b.currency)
+                        .then(b.effective_from.cmp(&a.effective_from))
+                });
+
+                Ok(api_response(json!({
+                    "base": BASE_CURRENCY,
+                    "currency": load_code(&kv, DISPLAY_CURRENCY_KEY).await?,
+                    "budget_currency": load_code(&kv, BUDGET_CURRENCY_KEY).await?,
+                    "rates": rates,
+                    "count": rates.len(),
+                })))
+            }
+        },
+    );
+}
+
+fn register_set_rate(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.currency.set_rate".to_string()),
+        move |input: Value| {
+            let kv = kv.clone();
+            async move {
+                let input = extract_input(input);
+                let currency = parse_code(&require_str(&input, "currency")?)?;
+                if currency == BASE_CURRENCY {
+                    return Err(IIIError::Handler(format!(
+                        "{} is the base currency; its rate is always 1",
+                        BASE_CURRENCY
+                    )));
+                }
+                let rate = input
+                    .get("rate")
+                    .and_then(|v| v.as_f64().or_else(|| v.as_str()?.parse().ok()))
+                    .ok_or_else(|| IIIError::Handler("rate must be a number".into()))?;
+                if !rate.is_finite() || rate <= 0.0 {
+                    return Err(IIIError::Handler("rate must be > 0".into()));
+                }
+                let effective_from = parse_date(&input, "effective_from")?
+                    .unwrap_or_else(|| Utc::now().date_naive());
+
+                let entry = ExchangeRate {
+                    currency,
+                    rate,
+                    effective_from,
+                    set_by: input
+                        .get("set_by")
+                        .and_then(|v| v.as_str())
+                        .filter(|s| !s.is_empty())
+                        .map(String::from),
+                    created_at: Utc::now(),
+                };
+                let key = entry.key();
+                let previous: Option<ExchangeRate> =
+                    kv.get(RATES_SCOPE, &key).await.map_err(kv_err)?;
+                kv.set(RATES_SCOPE, &key, &entry).await.map_err(kv_err)?;
+                tracing::info!(
+                    "exchange rate {} = {} per USD from {}",
+                    entry.currency,
+                    entry.rate,
+                    entry.effective_from
+                );
+
+                Ok(api_response(json!({
+                    "rate": entry,
+                    "replaced": previous.map(|p| p.rate),
+                })))
+            }
+        },
+    );
+}
+
+fn register_convert(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.currency.convert".to_string()),
+        move |input: Value| {
+            let kv = kv.clone();
+            async move {
+                let input = extract_input(input);
+                let amount = input
+                    .get("amount")
+                    .and_then(|v| v.as_f64().or_else(|| v.as_str()?.parse().ok()))
+                    .ok_or_else(|| IIIError::Handler("amount must be a number".into()))?;
+                let code = |key: &str| match input.get(key).and_then(|v| v.as_str()) {
+                    Some(c) if !c.trim().is_empty() => parse_code(c).map(Some),
+                    _ => Ok(None),
+                };
+                let from = code("from")?.unwrap_or_else(|| BASE_CURRENCY.to_string());
+                let to = match code("to")? {
+                    Some(c) => c,
+                    None => load_code(&kv, DISPLAY_CURRENCY_KEY).await?,
+                };
+                let date = parse_date(&input, "date")?.unwrap_or_else(|| Utc::now().date_naive());
+
+                let table = load_table(&kv).await?;
+                let from_rate = table.rate_on(&from, date)?;
+                let to_rate = table.rate_on(&to, date)?;
+                let converted = amount / from_rate * to_rate;
+
+                Ok(api_response(json!({
+                    "amount": amount,
+                    "from": from,
+                    "to": to,
+                    "date": date.to_string(),
+                    "rate": to_rate / from_rate,
+                    "converted": converted,
+                    "formatted": format_amount(converted, &to),
+                })))
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AgentType;
+    use chrono::TimeZone;
+    use uuid::Uuid;
+
+    fn rate(code: &str, rate: f64, from: &str) -> ExchangeRate {
+        ExchangeRate {
+            currency: code.into(),
+            rate,
+            effective_from: NaiveDate::parse_from_str(from, "%Y-%m-%d").unwrap(),
+            set_by: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn rate_follows_effective_dates() {
+        let table = RateTable::new(&[
+            rate("EUR", 0.95, "2026-06-01"),
+            rate("EUR", 0.90, "2026-01-01"),
+            rate("INR", 83.0, "2026-01-01"),
+        ]);
+        assert_eq!(table.rate_on("EUR", date("2026-03-15")).unwrap(), 0.90);
+        assert_eq!(table.rate_on("EUR", date("2026-06-01")).unwrap(), 0.95);
+        assert_eq!(table.rate_on("EUR", date("2027-01-01")).unwrap(), 0.95);
+        // Before the first rate, the first rate applies.
+        assert_eq!(table.rate_on("EUR", date("2025-12-31")).unwrap(), 0.90);
+        assert_eq!(table.rate_on("USD", date("2026-03-15")).unwrap(), 1.0);
+        assert!(table.rate_on("GBP", date("2026-03-15")).is_err());
+    }
+
+    #[test]
+    fn converter_uses_each_records_local_date() {
+        let table = RateTable::new(&[
+            rate("EUR", 0.90, "2026-01-01"),
+            rate("EUR", 1.00, "2026-02-01"),
+        ]);
+        let berlin = super::super::period::parse_timezone("Europe/Berlin").unwrap();
+        let conv = Converter::new("EUR", table, berlin).unwrap();
+        let mut r = CostRecord::new(
+            Uuid::nil(),
+            AgentType::ClaudeCode,
+            "claude-sonnet-4-6".into(),
+            "anthropic".into(),
+            1000,
+            100,
+            4.0,
+            6.0,
+        );
+        // 23:30 UTC on Jan 31 is already February in Berlin.
+        r.recorded_at = Utc.with_ymd_and_hms(2026, 1, 31, 23, 30, 0).unwrap();
+        let mut early = r.clone();
+        early.recorded_at = Utc.with_ymd_and_hms(2026, 1, 31, 12, 0, 0).unwrap();
+        let mut records = vec![r, early];
+        conv.apply(&mut records).unwrap();
+        assert_eq!(records[0].total_cost, 10.0);
+        assert!((records[1].total_cost - 9.0).abs() < 1e-9);
+        assert!((records[1].input_cost - 3.6).abs() < 1e-9);
+        assert!(Converter::new("GBP", RateTable::default(), Tz::UTC).is_err());
+    }
+
+    #[test]
+    fn codes_and_formatting() {
+        assert_eq!(parse_code(" eur ").unwrap(), "EUR");
+        for bad in ["", "EU", "EURO", "E1R"] {
+            assert!(parse_code(bad).is_err(), "{bad:?} should not parse");
+        }
+        assert_eq!(format_amount(12.345, "USD"), "$12.35");
+        assert_eq!(format_amount(12.345, "EUR"), "€12.35");
+        assert_eq!(format_amount(1234.5, "JPY"), "¥1234");
+        assert_eq!(format_amount(3.0, "CHF"), "3.00 CHF");
+    }
+}
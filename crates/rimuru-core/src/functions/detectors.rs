@@ -0,0 +1,695 @@
+//! Runaway detectors and the registry `rimuru.runaway.*` scores them with.
+//!
+//! A detector looks at a session's turns and reports zero or more
+//! `RunawayPattern`s. The built-ins live in `runaway`. Plugins contribute
+//! their own through `rimuru.runaway.detector.register`, naming an iii
+//! function that receives the turn window and returns patterns in the same
+//! shape, for workflow-specific loops the built-ins cannot know about.
+//!
+//! `runaway_detectors` enables, weights and sets a minimum severity per
+//! detector id, and `runaway_scoring` picks how the surviving patterns
+//! combine into the session's severity.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use iii_sdk::{III, IIIError, RegisterFunctionMessage, TriggerRequest};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use super::runaway::{self, RunawayConfig, RunawayPattern};
+use super::sysutil::{api_response, extract_input, kv_err, require_str};
+use crate::models::TurnRecord;
+use crate::state::StateKV;
+
+pub(crate) const DETECTOR_SCOPE: &str = "runaway_detectors";
+
+/// How far back baselines look. Bounding it keeps a scan's cost flat on
+/// long sessions, and a backtest replay of every prefix linear rather than
+/// quadratic in session length.
+pub const HISTORY_TURNS: usize = 500;
+
+/// What a detector gets to look at.
+pub struct DetectorInput<'a> {
+    pub session_id: Uuid,
+    /// The last `RunawayConfig::window` turns.
+    pub window: &'a [TurnRecord],
+    /// The last `HISTORY_TURNS` turns (or the window, if longer), for
+    /// detectors that need a baseline.
+    pub history: &'a [TurnRecord],
+}
+
+impl<'a> DetectorInput<'a> {
+    pub fn new(session_id: Uuid, history: &'a [TurnRecord], window: usize) -> Self {
+        let history = &history[history.len().saturating_sub(HISTORY_TURNS.max(window))..];
+        Self {
+            session_id,
+            window: &history[history.len().saturating_sub(window)..],
+            history,
+        }
+    }
+}
+
+pub trait RunawayDetector: Send + Sync {
+    /// Key for this detector in `runaway_detectors`.
+    fn id(&self) -> &str;
+
+    fn detect(&self, input: &DetectorInput<'_>, cfg: &RunawayConfig) -> Vec<RunawayPattern>;
+}
+
+type DetectFn = fn(&DetectorInput<'_>, &RunawayConfig, &mut Vec<RunawayPattern>);
+
+struct Builtin {
+    id: &'static str,
+    run: DetectFn,
+}
+
+impl RunawayDetector for Builtin {
+    fn id(&self) -> &str {
+        self.id
+    }
+
+    fn detect(&self, input: &DetectorInput<'_>, cfg: &RunawayConfig) -> Vec<RunawayPattern> {
+        let mut patterns = Vec::new();
+        (self.run)(input, cfg, &mut patterns);
+        patterns
+    }
+}
+
+const BUILTINS: &[Builtin] = &[
+    Builtin {
+        id: "repeated_calls",
+        run: |i, cfg, p| runaway::detect_repeated_calls(i.window, cfg.repeat_threshold, p),
+    },
+    Builtin {
+        id: "identical_calls",
+        run: |i, cfg, p| runaway::detect_identical_calls(i.window, cfg.repeat_threshold, p),
+    },
+    Builtin {
+        id: "repeated_errors",
+        run: |i, cfg, p| runaway::detect_repeated_errors(i.window, cfg.repeat_threshold, p),
+    },
+    Builtin {
+        id: "token_explosion",
+        run: |i, cfg, p| runaway::detect_token_explosion(i.window, cfg.token_explosion_ratio, p),
+    },
+    Builtin {
+        id: "oscillation",
+        run: |i, _, p| runaway::detect_oscillation(i.window, p),
+    },
+    Builtin {
+        id: "edit_ping_pong",
+        run: |i, _, p| runaway::detect_edit_ping_pong(i.window, p),
+    },
+    Builtin {
+        id: "cost_velocity",
+        run: |i, cfg, p| runaway::detect_cost_velocity(i.history, cfg, p),
+    },
+];
+
+pub fn builtin_ids() -> impl Iterator<Item = &'static str> {
+    BUILTINS.iter().map(|b| b.id)
+}
+
+/// Per-detector overrides from `runaway_detectors`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DetectorSettings {
+    pub enabled: bool,
+    /// Multiplier on each pattern's severity before scoring.
+    pub weight: f64,
+    /// Patterns below this severity are dropped.
+    pub threshold: f64,
+}
+
+impl Default for DetectorSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            weight: 1.0,
+            threshold: 0.0,
+        }
+    }
+}
+
+impl DetectorSettings {
+    fn validate(&self, id: &str) -> Result<(), String> {
+        if !self.weight.is_finite() || self.weight < 0.0 {
+            return Err(format!("detectors.{}.weight must be >= 0", id));
+        }
+        if !(0.0..=1.0).contains(&self.threshold) {
+            return Err(format!(
+                "detectors.{}.threshold must be between 0 and 1",
+                id
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// How weighted pattern severities combine into the session's severity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scoring {
+    /// The single worst pattern decides.
+    #[default]
+    Max,
+    /// Patterns add up, so several weak signals can cross the cutoff.
+    WeightedSum,
+}
+
+impl Scoring {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "max" => Some(Self::Max),
+            "weighted_sum" => Some(Self::WeightedSum),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Max => "max",
+            Self::WeightedSum => "weighted_sum",
+        }
+    }
+
+    /// Combined severity, capped at 1.
+    pub fn combine(self, weighted: impl Iterator<Item = f64>) -> f64 {
+        let score = match self {
+            Self::Max => weighted.fold(0.0_f64, f64::max),
+            Self::WeightedSum => weighted.sum(),
+        };
+        score.min(1.0)
+    }
+}
+
+/// A pattern that survived its detector's settings, with the weight it
+/// scores at.
+pub struct Finding {
+    pub weight: f64,
+    pub pattern: RunawayPattern,
+}
+
+fn admit(id: &str, patterns: Vec<RunawayPattern>, cfg: &RunawayConfig, out: &mut Vec<Finding>) {
+    let settings = cfg.detector(id);
+    out.extend(
+        patterns
+            .into_iter()
+            .filter(|p| p.severity >= settings.threshold)
+            .map(|pattern| Finding {
+                weight: settings.weight,
+                pattern,
+            }),
+    );
+}
+
+/// In-process detectors, run in registration order.
+pub struct DetectorRegistry {
+    detectors: Vec<Box<dyn RunawayDetector>>,
+}
+
+impl Default for DetectorRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl DetectorRegistry {
+    pub fn empty() -> Self {
+        Self {
+            detectors: Vec::new(),
+        }
+    }
+
+    pub fn builtin() -> Self {
+        let mut registry = Self::empty();
+        for b in BUILTINS {
+            registry.detectors.push(Box::new(Builtin {
+                id: b.id,
+                run: b.run,
+            }));
+        }
+        registry
+    }
+
+    pub fn register(&mut self, detector: impl RunawayDetector + 'static) {
+        self.detectors.push(Box::new(detector));
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.detectors.iter().map(|d| d.id())
+    }
+
+    /// Run every enabled detector and keep the patterns its settings admit.
+    pub fn run(&self, input: &DetectorInput<'_>, cfg: &RunawayConfig) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        for detector in &self.detectors {
+            if cfg.detector(detector.id()).enabled {
+                admit(
+                    detector.id(),
+                    detector.detect(input, cfg),
+                    cfg,
+                    &mut findings,
+                );
+            }
+        }
+        findings
+    }
+}
+
+/// A detector served by an iii function, usually from a plugin.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginDetector {
+    pub id: String,
+    pub function_id: String,
+    #[serde(default)]
+    pub plugin_id: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub registered_at: DateTime<Utc>,
+}
+
+pub(crate) async fn load_plugin_detectors(kv: &StateKV) -> Vec<PluginDetector> {
+    match kv.list(DETECTOR_SCOPE).await {
+        Ok(detectors) => detectors,
+        Err(e) => {
+            tracing::warn!("failed to load runaway detectors: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Patterns from a detector function's response: `{"patterns": [...]}` or a
+/// bare array, optionally wrapped in an `api_response` body. A pattern
+/// without a `pattern_type` is reported under the detector's id.
+fn parse_patterns(id: &str, response: &Value) -> Vec<RunawayPattern> {
+    let body = response.get("body").unwrap_or(response);
+    let items = body
+        .get("patterns")
+        .unwrap_or(body)
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    items
+        .iter()
+        .filter_map(|p| {
+            let severity = p.get("severity")?.as_f64()?;
+            Some(RunawayPattern {
+                pattern_type: p
+                    .get("pattern_type")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or(id)
+                    .to_string(),
+                description: p
+                    .get("description")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                severity: severity.clamp(0.0, 1.0),
+                metadata: p.get("metadata").cloned().unwrap_or_else(|| json!({})),
+                start_turn: turn_field(p, "start_turn"),
+                end_turn: turn_field(p, "end_turn"),
+            })
+        })
+        .collect()
+}
+
+fn turn_field(pattern: &Value, key: &str) -> Option<u32> {
+    pattern
+        .get(key)?
+        .as_u64()
+        .and_then(|n| u32::try_from(n).ok())
+}
+
+/// Call each enabled plugin detector with the turn window. A detector that
+/// fails or times out is logged and skipped so it cannot stall a scan.
+pub(crate) async fn run_plugins(
+    kv: &StateKV,
+    plugins: &[PluginDetector],
+    input: &DetectorInput<'_>,
+    cfg: &RunawayConfig,
+    out: &mut Vec<Finding>,
+) {
+    for detector in plugins {
+        if !cfg.detector(&detector.id).enabled {
+            continue;
+        }
+        let result = kv
+            .iii()
+            .trigger(TriggerRequest {
+                function_id: detector.function_id.clone(),
+                payload: json!({
+                    "session_id": input.session_id.to_string(),
+                    "detector_id": detector.id,
+                    "turns": input.window,
+                    "window": cfg.window,
+                }),
+                action: None,
+                timeout_ms: Some(5000),
+            })
+            .await;
+        match result {
+            Ok(response) => admit(
+                &detector.id,
+                parse_patterns(&detector.id, &response),
+                cfg,
+                out,
+            ),
+            Err(e) => tracing::warn!(
+                "runaway detector {} ({}) failed: {}",
+                detector.id,
+                detector.function_id,
+                e
+            ),
+        }
+    }
+}
+
+/// Fold a `detectors` update into the stored map. Each entry may set any of
+/// `enabled`, `weight` and `threshold`; `null` resets a detector to defaults.
+pub(crate) fn merge_settings(
+    stored: Option<Value>,
+    update: &Value,
+    known: &[String],
+) -> Result<Value, String> {
+    let update = update
+        .as_object()
+        .ok_or("detectors must be an object keyed by detector id")?;
+    let mut merged: serde_json::Map<String, Value> = stored
+        .and_then(|v| v.as_object().cloned())
+        .unwrap_or_default();
+    for (id, patch) in update {
+        if !known.iter().any(|k| k == id) {
+            return Err(format!("unknown detector: {}", id));
+        }
+        if patch.is_null() {
+            merged.remove(id);
+            continue;
+        }
+        let mut entry = merged.get(id).cloned().unwrap_or_else(|| json!({}));
+        let (Some(fields), Some(patch)) = (entry.as_object_mut(), patch.as_object()) else {
+            return Err(format!("detectors.{} must be an object", id));
+        };
+        for (k, v) in patch {
+            fields.insert(k.clone(), v.clone());
+        }
+        let settings: DetectorSettings = serde_json::from_value(entry.clone())
+            .map_err(|e| format!("detectors.{}: {}", id, e))?;
+        settings.validate(id)?;
+        merged.insert(id.clone(), entry);
+    }
+    Ok(Value::Object(merged))
+}
+
+/// Ids `runaway_detectors` may configure: built-ins plus registered plugins.
+pub(crate) async fn known_ids(kv: &StateKV) -> Vec<String> {
+    builtin_ids()
+        .map(String::from)
+        .chain(load_plugin_detectors(kv).await.into_iter().map(|d| d.id))
+        .collect()
+}
+
+pub fn register(iii: &III, kv: &StateKV) {
+    register_register(iii, kv);
+    register_list(iii, kv);
+    register_unregister(iii, kv);
+}
+
+fn register_register(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.runaway.detector.register".to_string()),
+        move |input: Value| {
+            let kv = kv.clone();
+            async move {
+                let input = extract_input(input);
+                let id = require_str(&input, "id")?;
+                let function_id = require_str(&input, "function_id")?;
+                if id.trim().is_empty() || function_id.trim().is_empty() {
+                    return Err(IIIError::Handler(
+                        "id and function_id must not be empty".into(),
+                    ));
+                }
+                if builtin_ids().any(|b| b == id) {
+                    return Err(IIIError::Handler(format!("{} is a built-in detector", id)));
+                }
+                let plugin_id = input
+                    .get("plugin_id")
+                    .and_then(|v| v.as_str())
+                    .map(String::from);
+                if let Some(ref p) = plugin_id {
+                    let installed: Option<Value> = kv.get("plugins", p).await.map_err(kv_err)?;
+                    if installed.is_none() {
+                        return Err(IIIError::Handler(format!("plugin not found: {}", p)));
+                    }
+                }
+
+                let existing: Option<PluginDetector> =
+                    kv.get(DETECTOR_SCOPE, &id).await.map_err(kv_err)?;
+                let detector = PluginDetector {
+                    id: id.clone(),
+                    function_id,
+                    plugin_id,
+                    description: input
+                        .get("description")
+                        .and_then(|v| v.as_str())
+                        .map(String::from),
+                    registered_at: Utc::now(),
+                };
+                kv.set(DETECTOR_SCOPE, &id, &detector)
+                    .await
+                    .map_err(kv_err)?;
+
+                Ok(api_response(json!({
+                    "detector": detector,
+                    "updated": existing.is_some(),
+                    "registered": existing.is_none()
+                })))
+            }
+        },
+    );
+}
+
+fn register_list(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.runaway.detector.list".to_string()),
+        move |_input: Value| {
+            let kv = kv.clone();
+            async move {
+                let cfg = runaway::load_runaway_config(&kv).await;
+                let mut detectors: Vec<Value> = builtin_ids()
+                    .map(|id| {
+                        json!({
+                            "id": id,
+                            "source": "builtin",
+                            "settings": cfg.detector(id),
+                        })
+                    })
+                    .collect();
+                for d in load_plugin_detectors(&kv).await {
+                    detectors.push(json!({
+                        "id": d.id,
+                        "source": "plugin",
+                        "function_id": d.function_id,
+                        "plugin_id": d.plugin_id,
+                        "description": d.description,
+                        "settings": cfg.detector(&d.id),
+                    }));
+                }
+
+                Ok(api_response(json!({
+                    "detectors": detectors,
+                    "count": detectors.len(),
+                    "scoring": cfg.scoring.as_str(),
+                    "severity_cutoff": cfg.severity_cutoff,
+                })))
+            }
+        },
+    );
+}
+
+fn register_unregister(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.runaway.detector.unregister".to_string()),
+        move |input: Value| {
+            let kv = kv.clone();
+            async move {
+                let input = extract_input(input);
+                let id = require_str(&input, "id")?;
+                let existing: Option<PluginDetector> =
+                    kv.get(DETECTOR_SCOPE, &id).await.map_err(kv_err)?;
+                if existing.is_none() {
+                    return Err(IIIError::Handler(format!("detector not found: {}", id)));
+                }
+                kv.delete(DETECTOR_SCOPE, &id).await.map_err(kv_err)?;
+                forget_settings(&kv, std::slice::from_ref(&id)).await?;
+
+                Ok(api_response(json!({ "unregistered": id })))
+            }
+        },
+    );
+}
+
+/// Drop `runaway_detectors` entries for detectors that no longer exist.
+pub(crate) async fn forget_settings(kv: &StateKV, ids: &[String]) -> Result<(), IIIError> {
+    let stored: Option<Value> = kv
+        .get("config", "runaway_detectors")
+        .await
+        .map_err(kv_err)?;
+    if let Some(Value::Object(mut map)) = stored {
+        let before = map.len();
+        map.retain(|k, _| !ids.contains(k));
+        if map.len() != before {
+            kv.set("config", "runaway_detectors", &Value::Object(map))
+                .await
+                .map_err(kv_err)?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove every detector a plugin registered, on uninstall.
+pub(crate) async fn remove_for_plugin(kv: &StateKV, plugin_id: &str) -> Result<(), IIIError> {
+    let ids: Vec<String> = load_plugin_detectors(kv)
+        .await
+        .into_iter()
+        .filter(|d| d.plugin_id.as_deref() == Some(plugin_id))
+        .map(|d| d.id)
+        .collect();
+    for id in &ids {
+        kv.delete(DETECTOR_SCOPE, id).await.map_err(kv_err)?;
+    }
+    forget_settings(kv, &ids).await
+}
+
+/// Parse stored `runaway_detectors`, skipping entries that no longer
+/// deserialize rather than failing every analysis.
+pub(crate) fn parse_settings(stored: &Value) -> HashMap<String, DetectorSettings> {
+    stored
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(id, v)| Some((id.clone(), serde_json::from_value(v.clone()).ok()?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(kind: &str, severity: f64) -> RunawayPattern {
+        RunawayPattern {
+            pattern_type: kind.into(),
+            description: String::new(),
+            severity,
+            metadata: json!({}),
+            start_turn: None,
+            end_turn: None,
+        }
+    }
+
+    struct Fixed(&'static str, f64);
+
+    impl RunawayDetector for Fixed {
+        fn id(&self) -> &str {
+            self.0
+        }
+
+        fn detect(&self, _: &DetectorInput<'_>, _: &RunawayConfig) -> Vec<RunawayPattern> {
+            vec![pattern(self.0, self.1)]
+        }
+    }
+
+    #[test]
+    fn settings_disable_weight_and_threshold_detectors() {
+        let mut registry = DetectorRegistry::empty();
+        registry.register(Fixed("flaky_reruns", 0.4));
+        registry.register(Fixed("noisy", 0.2));
+        registry.register(Fixed("off", 0.9));
+
+        let mut cfg = RunawayConfig::default();
+        cfg.detectors = parse_settings(&json!({
+            "flaky_reruns": { "weight": 1.5 },
+            "noisy": { "threshold": 0.3 },
+            "off": { "enabled": false },
+        }));
+        let input = DetectorInput::new(Uuid::nil(), &[], 10);
+        let findings = registry.run(&input, &cfg);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].pattern.pattern_type, "flaky_reruns");
+        assert_eq!(findings[0].weight, 1.5);
+    }
+
+    #[test]
+    fn weighted_sum_lets_weak_signals_add_up() {
+        let weighted = [0.3, 0.25, 0.2];
+        assert_eq!(Scoring::Max.combine(weighted.into_iter()), 0.3);
+        assert!((Scoring::WeightedSum.combine(weighted.into_iter()) - 0.75).abs() < 1e-9);
+        assert_eq!(Scoring::WeightedSum.combine([0.8, 0.9].into_iter()), 1.0);
+    }
+
+    #[test]
+    fn merge_validates_and_resets_entries() {
+        let known = vec!["oscillation".to_string(), "flaky_reruns".to_string()];
+        let merged = merge_settings(
+            Some(json!({ "oscillation": { "weight": 0.5 } })),
+            &json!({ "oscillation": { "threshold": 0.6 }, "flaky_reruns": { "enabled": false } }),
+            &known,
+        )
+        .unwrap();
+        assert_eq!(
+            merged,
+            json!({
+                "oscillation": { "weight": 0.5, "threshold": 0.6 },
+                "flaky_reruns": { "enabled": false },
+            })
+        );
+
+        let reset = merge_settings(Some(merged), &json!({ "oscillation": null }), &known).unwrap();
+        assert_eq!(reset, json!({ "flaky_reruns": { "enabled": false } }));
+
+        assert!(merge_settings(None, &json!({ "typo": {} }), &known).is_err());
+        assert!(merge_settings(None, &json!({ "oscillation": { "weight": -1 } }), &known).is_err());
+        assert!(merge_settings(None, &json!({ "oscillation": { "wieght": 1 } }), &known).is_err());
+    }
+
+    #[test]
+    fn plugin_responses_parse_in_either_shape() {
+        let wrapped = json!({ "status_code": 200, "body": { "patterns": [
+            { "severity": 0.7, "description": "pytest rerun 4x" },
+        ]}});
+        let parsed = parse_patterns("flaky_reruns", &wrapped);
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].pattern_type, "flaky_reruns");
+
+        let bare = json!([{ "pattern_type": "x", "severity": 3.0 }, { "no": "severity" }]);
+        let parsed = parse_patterns("flaky_reruns", &bare);
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].severity, 1.0);
+    }
+
+    #[test]
+    fn input_bounds_the_history_detectors_see() {
+        let turn = |i: u32| TurnRecord {
+            turn_index: i,
+            role: "assistant".into(),
+            model: None,
+            input_tokens: 0,
+            output_tokens: 0,
+            cache_read: 0,
+            cache_write: 0,
+            tool_calls: Vec::new(),
+            timestamp: None,
+            content_type: "text".into(),
+        };
+        let turns: Vec<TurnRecord> = (0..HISTORY_TURNS as u32 + 100).map(turn).collect();
+        let input = DetectorInput::new(Uuid::nil(), &turns, 10);
+        assert_eq!(input.history.len(), HISTORY_TURNS);
+        assert_eq!(input.history[0].turn_index, 100);
+        assert_eq!(input.window.len(), 10);
+        assert_eq!(input.window[0].turn_index, HISTORY_TURNS as u32 + 90);
+    }
+}
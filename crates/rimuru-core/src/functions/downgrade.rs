@@ -0,0 +1,168 @@
+//! Model substitution for `budget_action = downgrade`.
+//!
+//! Past `budget_downgrade_threshold` of any cap, `rimuru.budget.check`
+//! recommends a cheaper model from the same provider instead of blocking:
+//! one step down the price ladder (opus → sonnet), or the cheapest model
+//! with the same capabilities once a cap is exceeded (opus → haiku). The
+//! ladder comes from the `model_info` catalog, so a `rimuru models sync`
+//! picks up new tiers without code changes.
+
+use iii_sdk::IIIError;
+
+use super::models::hardcoded_models;
+use super::sysutil::kv_err;
+use crate::models::ModelInfo;
+use crate::state::StateKV;
+
+/// Fraction of a cap past which `downgrade` starts substituting models.
+pub const DOWNGRADE_THRESHOLD_KEY: &str = "budget_downgrade_threshold";
+pub const DEFAULT_DOWNGRADE_THRESHOLD: f64 = 0.9;
+
+/// A model only counts as a step down when it is at least this much
+/// cheaper, so dated snapshots of the same tier are not "downgrades".
+const MIN_SAVING: f64 = 0.9;
+
+/// How far down the ladder to go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    None,
+    /// Past the downgrade threshold: the next cheaper tier.
+    Step,
+    /// A cap is exceeded: the cheapest tier.
+    Floor,
+}
+
+impl Level {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::None => "none",
+            Level::Step => "step",
+            Level::Floor => "floor",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Level> {
+        match s {
+            "none" => Some(Level::None),
+            "step" => Some(Level::Step),
+            "floor" => Some(Level::Floor),
+            _ => None,
+        }
+    }
+
+    /// Level for the cap closest to (or furthest past) its limit, where
+    /// `ratio` is spend over limit including any override headroom.
+    pub fn for_ratio(ratio: f64, threshold: f64) -> Level {
+        if ratio >= 1.0 {
+            Level::Floor
+        } else if ratio >= threshold {
+            Level::Step
+        } else {
+            Level::None
+        }
+    }
+}
+
+/// Stored catalog, falling back to the built-in one when nothing has
+/// been synced yet (same rule as `rimuru.models.list`).
+pub(crate) async fn load_catalog(kv: &StateKV) -> Result<Vec<ModelInfo>, IIIError> {
+    let stored: Vec<ModelInfo> = kv.list("model_info").await.map_err(kv_err)?;
+    Ok(if stored.is_empty() {
+        hardcoded_models()
+    } else {
+        stored
+    })
+}
+
+fn blended_price(m: &ModelInfo) -> f64 {
+    m.input_price_per_million + m.output_price_per_million
+}
+
+/// Find the catalog entry for a model name as agents report it. Accepts an
+/// exact id, a `provider/` prefix (`anthropic/claude-opus-4-6`) and dated
+/// snapshots (`claude-opus-4-6-20260101`), preferring the longest match.
+pub fn resolve<'a>(catalog: &'a [ModelInfo], model: &str) -> Option<&'a ModelInfo> {
+    let model = model.trim();
+    let bare = model.rsplit('/').next().unwrap_or(model);
+    catalog
+        .iter()
+        .filter(|m| m.id == model || m.id == bare || bare.starts_with(&format!("{}-", m.id)))
+        .max_by_key(|m| (m.id == model || m.id == bare, m.id.len()))
+}
+
+/// Cheaper substitute for `model` at `level`, or `None` when the model is
+/// unknown, already the cheapest, or no substitution is due. Candidates
+/// share the provider and keep tool support when the original has it.
+pub fn substitute<'a>(
+    catalog: &'a [ModelInfo],
+    model: &str,
+    level: Level,
+) -> Option<&'a ModelInfo> {
+    if level == Level::None {
+        return None;
+    }
+    let current = resolve(catalog, model)?;
+    let ceiling = blended_price(current) * MIN_SAVING;
+    let mut cheaper: Vec<&ModelInfo> = catalog
+        .iter()
+        .filter(|m| m.provider == current.provider)
+        .filter(|m| m.supports_tools || !current.supports_tools)
+        .filter(|m| blended_price(m) < ceiling)
+        .collect();
+    cheaper.sort_by(|a, b| {
+        blended_price(a)
+            .total_cmp(&blended_price(b))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    match level {
+        Level::Step => cheaper.last().copied(),
+        Level::Floor => cheaper.first().copied(),
+        Level::None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_down_the_anthropic_ladder() {
+        let catalog = hardcoded_models();
+        let step = |m: &str| substitute(&catalog, m, Level::Step).map(|s| s.id.as_str());
+        assert_eq!(step("claude-opus-4-6"), Some("claude-sonnet-4-6"));
+        assert_eq!(step("claude-sonnet-4-6"), Some("claude-haiku-3-5"));
+        assert_eq!(step("claude-haiku-3-5"), None);
+    }
+
+    #[test]
+    fn floor_jumps_to_cheapest_in_provider() {
+        let catalog = hardcoded_models();
+        let floor = substitute(&catalog, "claude-opus-4-6", Level::Floor).unwrap();
+        assert_eq!(floor.id, "claude-haiku-3-5");
+        let gemini = substitute(&catalog, "gemini-2.5-pro", Level::Floor).unwrap();
+        assert_eq!(gemini.id, "gemini-2.5-flash");
+    }
+
+    #[test]
+    fn resolves_prefixed_and_dated_names() {
+        let catalog = hardcoded_models();
+        for name in [
+            "claude-opus-4-6",
+            "anthropic/claude-opus-4-6",
+            "claude-opus-4-6-20260101",
+        ] {
+            assert_eq!(resolve(&catalog, name).unwrap().id, "claude-opus-4-6");
+        }
+        // gpt-4o-mini must not resolve to gpt-4o just because it shares a prefix.
+        assert_eq!(resolve(&catalog, "gpt-4o-mini").unwrap().id, "gpt-4o-mini");
+        assert!(resolve(&catalog, "some-local-model").is_none());
+    }
+
+    #[test]
+    fn level_follows_ratio() {
+        assert_eq!(Level::for_ratio(0.5, 0.9), Level::None);
+        assert_eq!(Level::for_ratio(0.95, 0.9), Level::Step);
+        assert_eq!(Level::for_ratio(1.2, 0.9), Level::Floor);
+        assert!(substitute(&hardcoded_models(), "claude-opus-4-6", Level::None).is_none());
+    }
+}
@@ -7,6 +7,8 @@ use lettre::transport::smtp::authentication::Credentials;
 use lettre::{Message, SmtpTransport, Transport};
 use serde_json::{Value, json};
 
+use super::currency;
+use super::period;
 use super::sysutil::{api_response, kv_err};
 use crate::models::CostRecord;
 use crate::state::StateKV;
@@ -43,7 +45,10 @@ pub fn register(iii: &III, kv: &StateKV) {
                     ));
                 }
 
-                let records: Vec<CostRecord> = kv.list("cost_records").await.map_err(kv_err)?;
+                let tz = period::load_timezone(&kv).await?;
+                let converter = currency::display_converter(&kv, &Value::Null, tz).await?;
+                let mut records: Vec<CostRecord> = kv.list("cost_records").await.map_err(kv_err)?;
+                converter.apply(&mut records)?;
                 let cutoff = Utc::now() - Duration::days(7);
                 let weekly: Vec<&CostRecord> =
                     records.iter().filter(|r| r.recorded_at >= cutoff).collect();
@@ -56,11 +61,11 @@ pub fn register(iii: &III, kv: &StateKV) {
                     "Rimuru weekly cost digest\n\n\
                      Window: last 7 days\n\
                      Records: {}\n\
-                     Total cost: ${:.4}\n\
+                     Total cost: {}\n\
                      Input tokens: {}\n\
                      Output tokens: {}\n",
                     weekly.len(),
-                    total_cost,
+                    currency::format_amount(total_cost, &converter.code),
                     total_input,
                     total_output
                 );
@@ -109,7 +114,8 @@ pub fn register(iii: &III, kv: &StateKV) {
                     "sent": true,
                     "recipients": to_list,
                     "record_count": weekly.len(),
-                    "total_cost": total_cost
+                    "total_cost": total_cost,
+                    "currency": converter.code
                 })))
             }
         },
@@ -1,7 +1,10 @@
-use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use iii_sdk::{III, IIIError, RegisterFunctionMessage};
 use serde_json::{Value, json};
 
+use super::currency;
+use super::period::{self, resolve_timezone};
 use super::sysutil::{api_response, extract_input, kv_err};
 use crate::models::CostRecord;
 use crate::state::StateKV;
@@ -27,7 +30,8 @@ pub fn register(iii: &III, kv: &StateKV) {
                     .unwrap_or("monthly")
                     .to_lowercase();
 
-                let (default_from, default_to) = period_window(&period)?;
+                let tz = resolve_timezone(&kv, &input).await?;
+                let (default_from, default_to) = period_window(&period, Utc::now(), tz)?;
 
                 let from = input
                     .get("from")
@@ -43,7 +47,9 @@ pub fn register(iii: &III, kv: &StateKV) {
                     .transpose()?
                     .or(default_to);
 
-                let records: Vec<CostRecord> = kv.list("cost_records").await.map_err(kv_err)?;
+                let converter = currency::display_converter(&kv, &input, tz).await?;
+                let mut records: Vec<CostRecord> = kv.list("cost_records").await.map_err(kv_err)?;
+                converter.apply(&mut records)?;
 
                 let filtered: Vec<CostRecord> = records
                     .into_iter()
@@ -52,7 +58,10 @@ pub fn register(iii: &III, kv: &StateKV) {
                     .collect();
 
                 let (body, content_type) = match format.as_str() {
-                    "csv" => (render_csv(&filtered)?, "text/csv".to_string()),
+                    "csv" => (
+                        render_csv(&filtered, &converter.code)?,
+                        "text/csv".to_string(),
+                    ),
                     "json" => (render_json(&filtered)?, "application/json".to_string()),
                     other => {
                         return Err(IIIError::Handler(format!("unsupported format: {}", other)));
@@ -62,6 +71,8 @@ pub fn register(iii: &III, kv: &StateKV) {
                 Ok(api_response(json!({
                     "format": format,
                     "period": period,
+                    "timezone": tz.name(),
+                    "currency": converter.code,
                     "from": from.map(|d| d.to_rfc3339()),
                     "to": to.map(|d| d.to_rfc3339()),
                     "record_count": filtered.len(),
@@ -81,32 +92,24 @@ fn parse_iso(s: &str) -> Result<DateTime<Utc>, IIIError> {
 
 type TimeWindow = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
 
-fn period_window(period: &str) -> Result<TimeWindow, IIIError> {
-    let now = Utc::now();
+/// Window for a named period, with day/week/month boundaries taken from
+/// local midnight in `tz`. Weeks start on Monday.
+fn period_window(period: &str, now: DateTime<Utc>, tz: Tz) -> Result<TimeWindow, IIIError> {
     match period {
         "daily" => {
-            let start = now - Duration::days(1);
-            Ok((Some(start), Some(now)))
-        }
-        "weekly" => {
-            let start = now - Duration::days(7);
-            Ok((Some(start), Some(now)))
-        }
-        "monthly" => {
-            let first = NaiveDate::from_ymd_opt(now.year(), now.month(), 1)
-                .ok_or_else(|| IIIError::Handler("failed to compute month start".into()))?;
-            let start = first
-                .and_hms_opt(0, 0, 0)
-                .map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc))
-                .ok_or_else(|| IIIError::Handler("failed to compute month start".into()))?;
+            let start = period::day_start(period::local_date(now, tz), tz);
             Ok((Some(start), Some(now)))
         }
+        "weekly" => Ok((Some(period::week_start(now, tz)), Some(now))),
+        "monthly" => Ok((Some(period::month_start(now, tz)), Some(now))),
         "custom" => Ok((None, None)),
         other => Err(IIIError::Handler(format!("unsupported period: {}", other))),
     }
 }
 
-pub fn render_csv(records: &[CostRecord]) -> Result<String, IIIError> {
+/// CSV with one row per record. Money columns are in `currency`, which is
+/// repeated on every row so a file is self-describing once detached.
+pub fn render_csv(records: &[CostRecord], currency: &str) -> Result<String, IIIError> {
     let mut wtr = csv::Writer::from_writer(vec![]);
     wtr.write_record([
         "recorded_at",
@@ -118,6 +121,7 @@ pub fn render_csv(records: &[CostRecord]) -> Result<String, IIIError> {
         "cache_read_tokens",
         "cache_write_tokens",
         "total_cost",
+        "currency",
         "session_id",
         "user_id",
         "team_id",
@@ -141,6 +145,7 @@ pub fn render_csv(records: &[CostRecord]) -> Result<String, IIIError> {
             r.cache_read_tokens.to_string(),
             r.cache_write_tokens.to_string(),
             r.total_cost.to_string(),
+            currency.to_string(),
             session_id,
             String::new(),
             String::new(),
@@ -183,12 +188,12 @@ mod tests {
     #[test]
     fn csv_header_and_row() {
         let records = vec![sample_record()];
-        let csv = render_csv(&records).unwrap();
+        let csv = render_csv(&records, "EUR").unwrap();
         let mut lines = csv.lines();
         let header = lines.next().unwrap();
         assert_eq!(
             header,
-            "recorded_at,agent_type,model,provider,input_tokens,output_tokens,cache_read_tokens,cache_write_tokens,total_cost,session_id,user_id,team_id"
+            "recorded_at,agent_type,model,provider,input_tokens,output_tokens,cache_read_tokens,cache_write_tokens,total_cost,currency,session_id,user_id,team_id"
         );
         let row = lines.next().unwrap();
         assert!(row.contains("claude-sonnet-4"));
@@ -196,9 +201,21 @@ mod tests {
         assert!(row.contains("1000"));
         assert!(row.contains("500"));
         assert!(row.contains("42"));
+        assert!(row.contains(",EUR,"));
         assert!(row.contains(",,"));
     }
 
+    #[test]
+    fn period_window_uses_local_midnight() {
+        use chrono::TimeZone;
+        let tz = period::parse_timezone("Europe/Berlin").unwrap();
+        let now = Utc.with_ymd_and_hms(2026, 7, 1, 12, 0, 0).unwrap();
+        let (from, to) = period_window("monthly", now, tz).unwrap();
+        assert_eq!(from, Utc.with_ymd_and_hms(2026, 6, 30, 22, 0, 0).single());
+        assert_eq!(to, Some(now));
+        assert!(period_window("yearly", now, tz).is_err());
+    }
+
     #[test]
     fn json_roundtrip() {
         let records = vec![sample_record()];
@@ -0,0 +1,376 @@
+//! Spend forecasting behind `rimuru.budget.forecast`.
+//!
+//! Daily spend is modelled as an EWMA level scaled by a weekday factor, so a
+//! team that only works on weekdays doesn't see its weekend lull smeared
+//! across Monday. One-step-ahead residuals size the confidence band. Errors
+//! are treated as independent from day to day, so the band widens with the
+//! square root of the horizon rather than linearly.
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::Serialize;
+
+/// Smoothing factor for the level; 0.3 puts ~90% of the weight on the last
+/// week of history.
+pub const DEFAULT_ALPHA: f64 = 0.3;
+
+/// Two-sided band coverage reported as `low` / `high`.
+pub const BAND_CONFIDENCE: f64 = 0.8;
+const BAND_Z: f64 = 1.2816;
+
+/// Weekday factors need at least two of each weekday to mean anything; with
+/// less history the forecast is flat.
+const MIN_SEASONAL_DAYS: usize = 14;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DailyModel {
+    pub level: f64,
+    /// Multipliers indexed Monday = 0; they average to 1.0.
+    pub weekday_factors: [f64; 7],
+    /// Standard deviation of a single day's spend around the forecast.
+    pub sigma: f64,
+    pub history_days: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DayForecast {
+    pub date: NaiveDate,
+    pub expected: f64,
+    pub variance: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Band {
+    pub expected: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+/// When a cap is projected to be hit: `expected` on the central forecast,
+/// `earliest` on the upper edge of the band. `None` means not within the
+/// forecast horizon.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct CapHit {
+    pub expected: Option<NaiveDate>,
+    pub earliest: Option<NaiveDate>,
+}
+
+fn weekday_index(date: NaiveDate) -> usize {
+    date.weekday().num_days_from_monday() as usize
+}
+
+impl DailyModel {
+    /// Fit on a contiguous, oldest-first series of daily spend (see
+    /// [`dense_series`]).
+    pub fn fit(history: &[(NaiveDate, f64)], alpha: f64) -> Self {
+        let n = history.len();
+        let mean = if n == 0 {
+            0.0
+        } else {
+            history.iter().map(|(_, y)| y).sum::<f64>() / n as f64
+        };
+        if mean <= 0.0 {
+            return Self {
+                level: 0.0,
+                weekday_factors: [1.0; 7],
+                sigma: 0.0,
+                history_days: n,
+            };
+        }
+
+        let mut factors = [1.0; 7];
+        if n >= MIN_SEASONAL_DAYS {
+            let mut sums = [0.0; 7];
+            let mut counts = [0.0; 7];
+            for (date, y) in history {
+                let w = weekday_index(*date);
+                sums[w] += y;
+                counts[w] += 1.0;
+            }
+            for w in 0..7 {
+                factors[w] = sums[w] / counts[w] / mean;
+            }
+            let norm = factors.iter().sum::<f64>() / 7.0;
+            for f in &mut factors {
+                *f /= norm;
+            }
+        }
+
+        let mut level = mean;
+        let mut squared_error = 0.0;
+        for (date, y) in history {
+            let factor = factors[weekday_index(*date)];
+            let err = y - level * factor;
+            squared_error += err * err;
+            // A weekday that never sees spend carries no signal about the
+            // level; dividing by its zero factor would be meaningless.
+            if factor > f64::EPSILON {
+                level = alpha * (y / factor) + (1.0 - alpha) * level;
+            }
+        }
+        // A single day says nothing about spread; assume it could double.
+        let sigma = if n >= 2 {
+            (squared_error / n as f64).sqrt()
+        } else {
+            level
+        };
+
+        Self {
+            level,
+            weekday_factors: factors,
+            sigma,
+            history_days: n,
+        }
+    }
+
+    pub fn expected(&self, date: NaiveDate) -> f64 {
+        self.level * self.weekday_factors[weekday_index(date)]
+    }
+
+    /// Forecast for each day from `today` through `end`. Today's entry only
+    /// covers the unelapsed `today_remaining` fraction of the day.
+    pub fn path(&self, today: NaiveDate, today_remaining: f64, end: NaiveDate) -> Vec<DayForecast> {
+        let remaining = today_remaining.clamp(0.0, 1.0);
+        let variance = self.sigma * self.sigma;
+        let mut out = Vec::new();
+        let mut date = today;
+        while date <= end {
+            let share = if date == today { remaining } else { 1.0 };
+            out.push(DayForecast {
+                date,
+                expected: self.expected(date) * share,
+                variance: variance * share,
+            });
+            date += Duration::days(1);
+        }
+        out
+    }
+}
+
+/// Contiguous daily series over `from..=to`, with zero for days that have
+/// no spend recorded.
+pub fn dense_series(
+    spend: &BTreeMap<NaiveDate, f64>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<(NaiveDate, f64)> {
+    let mut out = Vec::new();
+    let mut date = from;
+    while date <= to {
+        out.push((date, spend.get(&date).copied().unwrap_or(0.0)));
+        date += Duration::days(1);
+    }
+    out
+}
+
+fn band(expected: f64, variance: f64, floor: f64) -> Band {
+    let spread = BAND_Z * variance.max(0.0).sqrt();
+    Band {
+        expected,
+        low: (expected - spread).max(floor),
+        high: expected + spread,
+    }
+}
+
+/// Period total at `end`: what has been spent plus the forecast for the rest
+/// of the period. The low edge never drops below what is already spent.
+pub fn project_total(spent: f64, path: &[DayForecast], end: NaiveDate) -> Band {
+    let (mean, variance) = path
+        .iter()
+        .take_while(|d| d.date <= end)
+        .fold((0.0, 0.0), |(m, v), d| (m + d.expected, v + d.variance));
+    band(spent + mean, variance, spent)
+}
+
+/// Caps that accumulate over a period (monthly): walk the path until running
+/// spend crosses `limit`. The path should stop where the period resets.
+pub fn cumulative_hit(spent: f64, limit: f64, path: &[DayForecast], today: NaiveDate) -> CapHit {
+    if spent >= limit {
+        return CapHit {
+            expected: Some(today),
+            earliest: Some(today),
+        };
+    }
+    let mut hit = CapHit::default();
+    let (mut mean, mut variance) = (spent, 0.0);
+    for day in path {
+        mean += day.expected;
+        variance += day.variance;
+        if hit.earliest.is_none() && band(mean, variance, spent).high >= limit {
+            hit.earliest = Some(day.date);
+        }
+        if mean >= limit {
+            hit.expected = Some(day.date);
+            break;
+        }
+    }
+    hit
+}
+
+/// Caps over a sliding window of `window_days` days (1 for the daily cap, 7
+/// for a 7-day rolling cap), with the window sum multiplied by `scale` for
+/// windows shorter than a day. `actual` holds recorded spend per date,
+/// including today's so far; `current` is the window's spend right now.
+pub fn window_hit(
+    limit: f64,
+    window_days: usize,
+    scale: f64,
+    actual: &BTreeMap<NaiveDate, f64>,
+    path: &[DayForecast],
+    current: f64,
+    today: NaiveDate,
+) -> CapHit {
+    if current >= limit {
+        return CapHit {
+            expected: Some(today),
+            earliest: Some(today),
+        };
+    }
+    let forecast: BTreeMap<NaiveDate, DayForecast> = path.iter().map(|d| (d.date, *d)).collect();
+    let mut hit = CapHit::default();
+    for day in path {
+        let (mut mean, mut variance) = (0.0, 0.0);
+        for back in 0..window_days.max(1) {
+            let date = day.date - Duration::days(back as i64);
+            mean += actual.get(&date).copied().unwrap_or(0.0);
+            if let Some(f) = forecast.get(&date) {
+                mean += f.expected;
+                variance += f.variance;
+            }
+        }
+        let window = band(mean * scale, variance * scale * scale, 0.0);
+        if hit.earliest.is_none() && window.high >= limit {
+            hit.earliest = Some(day.date);
+        }
+        if window.expected >= limit {
+            hit.expected = Some(day.date);
+            break;
+        }
+    }
+    hit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    /// Four weeks starting Monday 2026-03-02: $10 on weekdays, $0 weekends.
+    fn weekday_only_history() -> Vec<(NaiveDate, f64)> {
+        (0..28)
+            .map(|i| {
+                let d = date(2026, 3, 2) + Duration::days(i);
+                let spend = if weekday_index(d) < 5 { 10.0 } else { 0.0 };
+                (d, spend)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn flat_history_forecasts_flat() {
+        let history: Vec<_> = (0..14)
+            .map(|i| (date(2026, 3, 1) + Duration::days(i), 4.0))
+            .collect();
+        let model = DailyModel::fit(&history, DEFAULT_ALPHA);
+        assert!((model.level - 4.0).abs() < 1e-9);
+        assert!(model.sigma.abs() < 1e-9);
+        assert!((model.expected(date(2026, 3, 20)) - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn weekday_seasonality_lowers_weekend_forecast() {
+        let model = DailyModel::fit(&weekday_only_history(), DEFAULT_ALPHA);
+        let monday = model.expected(date(2026, 3, 30));
+        let sunday = model.expected(date(2026, 4, 5));
+        assert!((monday - 10.0).abs() < 1e-9, "monday forecast {monday}");
+        assert!(sunday.abs() < 1e-9, "sunday forecast {sunday}");
+        let mean_factor = model.weekday_factors.iter().sum::<f64>() / 7.0;
+        assert!((mean_factor - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn empty_history_forecasts_zero() {
+        let model = DailyModel::fit(&[], DEFAULT_ALPHA);
+        let path = model.path(date(2026, 3, 1), 1.0, date(2026, 3, 31));
+        let total = project_total(12.0, &path, date(2026, 3, 31));
+        assert_eq!(total.expected, 12.0);
+        assert_eq!(total.low, 12.0);
+        assert_eq!(total.high, 12.0);
+    }
+
+    #[test]
+    fn today_only_counts_remaining_fraction() {
+        let history = vec![(date(2026, 3, 1), 8.0), (date(2026, 3, 2), 8.0)];
+        let model = DailyModel::fit(&history, DEFAULT_ALPHA);
+        let path = model.path(date(2026, 3, 3), 0.25, date(2026, 3, 4));
+        assert_eq!(path.len(), 2);
+        assert!((path[0].expected - 2.0).abs() < 1e-9);
+        assert!((path[1].expected - 8.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn band_never_drops_below_spent() {
+        let noisy: Vec<_> = (0..21)
+            .map(|i| {
+                (
+                    date(2026, 3, 9) + Duration::days(i),
+                    [2.0, 30.0, 9.0][i as usize % 3],
+                )
+            })
+            .collect();
+        let model = DailyModel::fit(&noisy, DEFAULT_ALPHA);
+        assert!(model.sigma > 0.0);
+        let today = date(2026, 3, 30);
+        let path = model.path(today, 1.0, date(2026, 3, 31));
+        let total = project_total(50.0, &path, date(2026, 3, 31));
+        assert!(total.low >= 50.0);
+        assert!(total.high > total.expected);
+    }
+
+    #[test]
+    fn cumulative_hit_finds_crossing_day() {
+        let history: Vec<_> = (0..14)
+            .map(|i| (date(2026, 3, 1) + Duration::days(i), 10.0))
+            .collect();
+        let model = DailyModel::fit(&history, DEFAULT_ALPHA);
+        let today = date(2026, 3, 15);
+        let path = model.path(today, 1.0, date(2026, 3, 31));
+        // 140 spent, 10/day: crosses 175 on the fourth forecast day.
+        let hit = cumulative_hit(140.0, 175.0, &path, today);
+        assert_eq!(hit.expected, Some(date(2026, 3, 18)));
+        assert_eq!(hit.earliest, Some(date(2026, 3, 18)));
+
+        let never = cumulative_hit(140.0, 1_000.0, &path, today);
+        assert_eq!(never, CapHit::default());
+
+        let already = cumulative_hit(200.0, 175.0, &path, today);
+        assert_eq!(already.expected, Some(today));
+    }
+
+    #[test]
+    fn window_hit_uses_recorded_and_forecast_days() {
+        let history: Vec<_> = (0..14)
+            .map(|i| (date(2026, 3, 1) + Duration::days(i), 10.0))
+            .collect();
+        let model = DailyModel::fit(&history, DEFAULT_ALPHA);
+        let today = date(2026, 3, 15);
+        let actual: BTreeMap<_, _> = history.iter().copied().collect();
+        let path = model.path(today, 1.0, date(2026, 3, 31));
+
+        // A daily cap below the daily rate is hit today.
+        let daily = window_hit(8.0, 1, 1.0, &actual, &path, 0.0, today);
+        assert_eq!(daily.expected, Some(today));
+
+        // A 7-day window already holds 60 from history; 70 is reached today.
+        let weekly = window_hit(70.0, 7, 1.0, &actual, &path, 60.0, today);
+        assert_eq!(weekly.expected, Some(today));
+
+        // Sub-day windows scale the day's spend: 5h of a $10 day is ~$2.08.
+        let short = window_hit(5.0, 1, 5.0 / 24.0, &actual, &path, 0.0, today);
+        assert_eq!(short.expected, None);
+    }
+}
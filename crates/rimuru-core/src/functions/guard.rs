@@ -1,36 +1,552 @@
-use iii_sdk::{III, RegisterFunctionMessage};
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use iii_sdk::{III, IIIError, RegisterFunctionMessage};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 
+use super::period::parse_duration;
 use super::sysutil::{api_response, extract_input, kv_err, require_str};
+use crate::models::{AgentType, ContextBreakdown, Session};
 use crate::state::StateKV;
 
+/// What a guard enforces. Any combination can be set; the first one the
+/// guarded sessions reach fires.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct GuardLimits {
+    /// Dollars.
+    #[serde(default)]
+    limit: Option<f64>,
+    #[serde(default)]
+    max_tokens: Option<u64>,
+    #[serde(default)]
+    max_duration_secs: Option<u64>,
+    #[serde(default)]
+    max_turns: Option<u64>,
+}
+
+impl GuardLimits {
+    fn from_input(input: &Value) -> Result<Self, IIIError> {
+        let invalid = |name: &str| IIIError::Handler(format!("{} must be a positive number", name));
+        let limit = match input.get("limit").filter(|v| !v.is_null()) {
+            Some(v) => match v.as_f64() {
+                Some(n) if n.is_finite() && n > 0.0 => Some(n),
+                _ => return Err(invalid("limit")),
+            },
+            None => None,
+        };
+        let count = |name: &str| match input.get(name).filter(|v| !v.is_null()) {
+            Some(v) => v
+                .as_u64()
+                .filter(|n| *n > 0)
+                .map(Some)
+                .ok_or_else(|| invalid(name)),
+            None => Ok(None),
+        };
+        let max_duration_secs = match input.get("max_duration").filter(|v| !v.is_null()) {
+            Some(Value::String(text)) => Some(parse_duration(text)?.num_seconds() as u64),
+            Some(Value::Number(n)) => Some(parse_duration(&n.to_string())?.num_seconds() as u64),
+            Some(_) => return Err(invalid("max_duration")),
+            None => None,
+        };
+        let limits = Self {
+            limit,
+            max_tokens: count("max_tokens")?,
+            max_duration_secs,
+            max_turns: count("max_turns")?,
+        };
+        if limits.limit.is_none()
+            && limits.max_tokens.is_none()
+            && limits.max_duration_secs.is_none()
+            && limits.max_turns.is_none()
+        {
+            return Err(IIIError::Handler(
+                "guard needs at least one of limit, max_tokens, max_duration, max_turns"
+                    .to_string(),
+            ));
+        }
+        Ok(limits)
+    }
+
+    /// These limits with the ones `more` sets raised to its values. A limit
+    /// can only go up, or be added where there was none.
+    fn extended(&self, more: &GuardLimits) -> Result<GuardLimits, IIIError> {
+        fn raise<T: PartialOrd + Copy>(
+            name: &str,
+            current: Option<T>,
+            new: Option<T>,
+        ) -> Result<Option<T>, IIIError> {
+            match (current, new) {
+                (Some(c), Some(n)) if n <= c => {
+                    Err(IIIError::Handler(format!("extend can only raise {}", name)))
+                }
+                (_, Some(n)) => Ok(Some(n)),
+                (c, None) => Ok(c),
+            }
+        }
+        Ok(GuardLimits {
+            limit: raise("limit", self.limit, more.limit)?,
+            max_tokens: raise("max_tokens", self.max_tokens, more.max_tokens)?,
+            max_duration_secs: raise(
+                "max_duration",
+                self.max_duration_secs,
+                more.max_duration_secs,
+            )?,
+            max_turns: raise("max_turns", self.max_turns, more.max_turns)?,
+        })
+    }
+
+    /// The first limit `usage` has reached, by the name `action_taken`
+    /// reports it under.
+    fn reached(&self, usage: &GuardUsage) -> Option<&'static str> {
+        if self.limit.is_some_and(|l| usage.cost >= l) {
+            Some("limit")
+        } else if self.max_tokens.is_some_and(|l| usage.tokens >= l) {
+            Some("max_tokens")
+        } else if self.max_turns.is_some_and(|l| usage.turns >= l) {
+            Some("max_turns")
+        } else if self
+            .max_duration_secs
+            .is_some_and(|l| usage.elapsed_secs >= l)
+        {
+            Some("max_duration")
+        } else {
+            None
+        }
+    }
+}
+
+/// What the guarded sessions have used so far.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct GuardUsage {
+    #[serde(rename = "current_cost")]
+    cost: f64,
+    #[serde(default)]
+    tokens: u64,
+    #[serde(default)]
+    turns: u64,
+    #[serde(default)]
+    elapsed_secs: u64,
+}
+
+/// A git snapshot of the guarded command's working tree, taken with
+/// `--snapshot` and kept from garbage collection by `ref`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct GuardRecord {
-    id: String,
+struct TreeSnapshot {
+    /// Commit checked out when the snapshot was taken.
+    head: String,
+    /// Branch checked out then, as a full ref; `None` on a detached HEAD.
+    #[serde(default)]
+    branch: Option<String>,
+    /// Commit of the whole working tree, untracked files included, whose
+    /// parent is `head`.
+    commit: String,
+    #[serde(rename = "ref")]
+    git_ref: String,
+    taken_at: String,
+}
+
+fn snapshot_input(input: &Value, key: &str) -> Result<Option<TreeSnapshot>, IIIError> {
+    input
+        .get(key)
+        .filter(|v| !v.is_null())
+        .map(|v| {
+            serde_json::from_value(v.clone())
+                .map_err(|e| IIIError::Handler(format!("invalid {}: {}", key, e)))
+        })
+        .transpose()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct GuardRecord {
+    pub id: String,
     command: String,
-    limit: f64,
+    #[serde(flatten)]
+    limits: GuardLimits,
     action: String,
     started_at: String,
-    current_cost: f64,
+    #[serde(flatten)]
+    usage: GuardUsage,
+    #[serde(default)]
+    pub pid: i64,
+    /// What rimuru did to the process on its own, e.g. `runaway_killed`
+    /// when a scheduled runaway scan stopped it.
+    #[serde(default)]
+    pub intervention: Option<String>,
+    /// Directory the guarded command was started in.
+    #[serde(default)]
+    cwd: Option<String>,
+    /// Agent the command runs, from `--agent` or read off the command.
+    #[serde(default)]
+    agent_type: Option<AgentType>,
+    /// Sessions attributed to the guarded process so far.
+    #[serde(default)]
+    pub session_ids: Vec<String>,
+    /// The command leads its own process group, so signals go to `-pid`
+    /// and reach everything it spawned.
+    #[serde(default)]
+    process_group: bool,
+    /// Working tree before the command ran.
+    #[serde(default)]
+    start_snapshot: Option<TreeSnapshot>,
+}
+
+/// Ways rimuru can act on a guarded process outside the guard's own loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Containment {
+    Kill,
+    Pause,
+}
+
+impl Containment {
+    fn signal(self) -> &'static str {
+        match self {
+            Containment::Kill => "-TERM",
+            Containment::Pause => "-STOP",
+        }
+    }
+
+    pub fn intervention(self) -> &'static str {
+        match self {
+            Containment::Kill => "runaway_killed",
+            Containment::Pause => "runaway_paused",
+        }
+    }
+}
+
+/// Executable names each agent is launched as.
+fn agent_binaries(agent: AgentType) -> &'static [&'static str] {
+    match agent {
+        AgentType::ClaudeCode => &["claude"],
+        AgentType::Cursor => &["cursor", "cursor-agent"],
+        AgentType::Copilot => &["copilot"],
+        AgentType::Codex => &["codex"],
+        AgentType::Goose => &["goose"],
+        AgentType::OpenCode => &["opencode"],
+        AgentType::GeminiCli => &["gemini"],
+        AgentType::Windsurf => &["windsurf"],
+        AgentType::Cline => &["cline"],
+        AgentType::Roo => &["roo"],
+        AgentType::Amp => &["amp"],
+        AgentType::Kiro => &["kiro", "kiro-cli"],
+    }
+}
+
+/// Whether `command` launches `agent`, judged by the file name of each
+/// word so wrappers like `npx codex` or absolute paths still match.
+fn runs_agent(command: &str, agent: AgentType) -> bool {
+    let binaries = agent_binaries(agent);
+    command
+        .split_whitespace()
+        .filter_map(|word| word.rsplit('/').next())
+        .any(|name| binaries.contains(&name))
+}
+
+/// The agent `command` launches, if it names exactly one.
+fn agent_for_command(command: &str) -> Option<AgentType> {
+    let mut found = AgentType::all()
+        .iter()
+        .copied()
+        .filter(|a| runs_agent(command, *a));
+    let agent = found.next()?;
+    found.next().is_none().then_some(agent)
+}
+
+/// Claude Code names a project's session directory after its path, with
+/// every character that is not alphanumeric replaced by `-`.
+fn claude_project_dir(cwd: &str) -> String {
+    cwd.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+fn same_project(project_path: &str, cwd: &str) -> bool {
+    let cwd = cwd.trim_end_matches('/');
+    project_path.trim_end_matches('/') == cwd || project_path == claude_project_dir(cwd)
+}
+
+impl GuardRecord {
+    /// Whether `session` looks like one the guarded process created: the
+    /// guard's agent, started no earlier than the guard, and in the guard's
+    /// directory. A session that records no directory only matches a guard
+    /// that knows its agent.
+    fn created(&self, session: &Session) -> bool {
+        let Ok(started) = DateTime::parse_from_rfc3339(&self.started_at) else {
+            return false;
+        };
+        if session.started_at < started.with_timezone(&Utc)
+            || self.agent_type.is_some_and(|a| a != session.agent_type)
+        {
+            return false;
+        }
+        match (&session.project_path, &self.cwd) {
+            (Some(project), Some(cwd)) => same_project(project, cwd),
+            (None, Some(_)) => self.agent_type.is_some(),
+            (_, None) => true,
+        }
+    }
+
+    /// Attach sessions the guarded process created since the last call.
+    /// Sessions another guard has already claimed stay with it, so two
+    /// guarded runs in one repository do not share spend.
+    fn bind(&mut self, sessions: &[Session], claimed: &HashSet<String>) -> Vec<String> {
+        let mut bound = Vec::new();
+        for session in sessions {
+            let id = session.id.to_string();
+            if self.session_ids.contains(&id) || claimed.contains(&id) || !self.created(session) {
+                continue;
+            }
+            self.session_ids.push(id.clone());
+            bound.push(id);
+        }
+        bound
+    }
+}
+
+/// The guard wrapping `session`: the one it is bound to. Sessions no guard
+/// has claimed are left alone rather than matched to a guard by agent,
+/// which could signal an unrelated process.
+pub(crate) fn guard_for_session<'a>(
+    guards: &'a [GuardRecord],
+    session: &Session,
+) -> Option<&'a GuardRecord> {
+    let id = session.id.to_string();
+    guards
+        .iter()
+        .find(|g| g.pid > 0 && g.session_ids.contains(&id))
+}
+
+/// Parse `ps -o etime` (`[[dd-]hh:]mm:ss`) into seconds.
+fn parse_etime(etime: &str) -> Option<i64> {
+    let etime = etime.trim();
+    let (days, clock) = match etime.split_once('-') {
+        Some((d, rest)) => (d.parse::<i64>().ok()?, rest),
+        None => (0, etime),
+    };
+    let mut secs = 0;
+    for part in clock.split(':') {
+        secs = secs * 60 + part.parse::<i64>().ok()?;
+    }
+    Some(days * 86_400 + secs)
+}
+
+/// When `pid` started, or `None` if no such process is running.
+async fn process_started(pid: i64) -> Option<DateTime<Utc>> {
+    let output = tokio::process::Command::new("ps")
+        .args(["-o", "etime=", "-p", &pid.to_string()])
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let secs = parse_etime(&String::from_utf8_lossy(&output.stdout))?;
+    Some(Utc::now() - chrono::Duration::seconds(secs))
+}
+
+/// How long after `started_at` the guarded child may have been spawned. A
+/// process holding the pid that started later is a reuse, not the child.
+const SPAWN_SLACK_SECS: i64 = 60;
+
+/// Signal the guarded process and record the intervention on its guard, so
+/// `guard.complete` reports it even though the child merely exited. The
+/// guard must still be registered and its pid still the process it spawned.
+pub(crate) async fn contain(
+    kv: &StateKV,
+    guard: &GuardRecord,
+    how: Containment,
+) -> Result<(), IIIError> {
+    let current: Option<GuardRecord> = kv.get("guards", &guard.id).await.map_err(kv_err)?;
+    let Some(guard) = current.filter(|g| g.pid > 0 && g.pid == guard.pid) else {
+        return Err(IIIError::Handler(format!(
+            "guard {} is no longer running",
+            guard.id
+        )));
+    };
+    let started = DateTime::parse_from_rfc3339(&guard.started_at)
+        .map_err(|e| IIIError::Handler(format!("guard {}: bad started_at: {}", guard.id, e)))?
+        .with_timezone(&Utc);
+    match process_started(guard.pid).await {
+        Some(at) if at <= started + chrono::Duration::seconds(SPAWN_SLACK_SECS) => {}
+        Some(_) => {
+            return Err(IIIError::Handler(format!(
+                "pid {} was reused since guard {} started",
+                guard.pid, guard.id
+            )));
+        }
+        None => {
+            return Err(IIIError::Handler(format!(
+                "guard {} pid {} is not running",
+                guard.id, guard.pid
+            )));
+        }
+    }
+
+    let target = if guard.process_group {
+        format!("-{}", guard.pid)
+    } else {
+        guard.pid.to_string()
+    };
+    let output = tokio::process::Command::new("kill")
+        .arg(how.signal())
+        .arg("--")
+        .arg(&target)
+        .output()
+        .await
+        .map_err(|e| IIIError::Handler(format!("failed to signal {}: {}", guard.pid, e)))?;
+    if !output.status.success() {
+        return Err(IIIError::Handler(format!(
+            "kill {} {} failed: {}",
+            how.signal(),
+            target,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    kv.update_field("guards", &guard.id, "intervention", &how.intervention())
+        .await
+        .map_err(kv_err)?;
+    Ok(())
+}
+
+/// Write the fields `value` serializes to onto guard `id` one by one, so
+/// concurrent writers of other fields (`contain` setting `intervention`)
+/// are not overwritten with a stale copy.
+async fn update_guard_fields<T: Serialize>(
+    kv: &StateKV,
+    id: &str,
+    value: &T,
+) -> Result<(), IIIError> {
+    let Value::Object(fields) =
+        serde_json::to_value(value).map_err(|e| IIIError::Handler(e.to_string()))?
+    else {
+        return Err(IIIError::Handler("guard fields must be an object".into()));
+    };
+    for (field, value) in fields {
+        kv.update_field("guards", id, &field, &value)
+            .await
+            .map_err(kv_err)?;
+    }
+    Ok(())
+}
+
+/// What someone away from the guard's terminal asked it to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum GuardCommand {
+    Kill,
+    Pause,
+    Resume,
+    Extend,
+}
+
+impl GuardCommand {
+    fn as_str(self) -> &'static str {
+        match self {
+            GuardCommand::Kill => "kill",
+            GuardCommand::Pause => "pause",
+            GuardCommand::Resume => "resume",
+            GuardCommand::Extend => "extend",
+        }
+    }
+}
+
+/// A queued command, held in `guard_requests` until the guard loop has
+/// acted on it and acknowledged it with `rimuru.guard.ack`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GuardRequest {
+    id: String,
+    guard_id: String,
+    command: GuardCommand,
+    /// Limits to raise, for `extend`.
     #[serde(default)]
-    pid: i64,
+    limits: Option<GuardLimits>,
+    requested_at: String,
+}
+
+/// The requests queued for `guard_id`, oldest first. They stay queued.
+async fn queued_requests(kv: &StateKV, guard_id: &str) -> Result<Vec<GuardRequest>, IIIError> {
+    let mut requests: Vec<GuardRequest> = kv
+        .list::<GuardRequest>("guard_requests")
+        .await
+        .map_err(kv_err)?
+        .into_iter()
+        .filter(|r| r.guard_id == guard_id)
+        .collect();
+    requests.sort_by(|a, b| a.requested_at.cmp(&b.requested_at));
+    Ok(requests)
+}
+
+async fn delete_requests<'a>(
+    kv: &StateKV,
+    requests: impl IntoIterator<Item = &'a GuardRequest>,
+) -> Result<(), IIIError> {
+    for request in requests {
+        kv.delete("guard_requests", &request.id)
+            .await
+            .map_err(kv_err)?;
+    }
+    Ok(())
+}
+
+fn elapsed_secs(started_at: &str) -> u64 {
+    DateTime::parse_from_rfc3339(started_at)
+        .map(|t| (Utc::now() - t.with_timezone(&Utc)).num_seconds().max(0) as u64)
+        .unwrap_or(0)
+}
+
+/// What `usage` and `poll` both report about a guard.
+fn usage_body(guard: &GuardRecord) -> Value {
+    json!({
+        "id": guard.id,
+        "cost": guard.usage.cost,
+        "tokens": guard.usage.tokens,
+        "turns": guard.usage.turns,
+        "elapsed_secs": guard.usage.elapsed_secs,
+        "limits": guard.limits,
+        "reached": guard.limits.reached(&guard.usage),
+        "session_ids": guard.session_ids,
+    })
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct GuardHistoryRecord {
     id: String,
     command: String,
-    limit: f64,
+    #[serde(flatten)]
+    limits: GuardLimits,
     final_cost: f64,
     action_taken: String,
     started_at: String,
     ended_at: String,
+    #[serde(default)]
+    session_ids: Vec<String>,
+    /// Signal that ended the run, when one did.
+    #[serde(default)]
+    signal: Option<String>,
+    #[serde(default)]
+    cwd: Option<String>,
+    /// Working tree before the command ran, and when the guard stepped in.
+    #[serde(default)]
+    start_snapshot: Option<TreeSnapshot>,
+    #[serde(default)]
+    stop_snapshot: Option<TreeSnapshot>,
 }
 
 pub fn register(iii: &III, kv: &StateKV) {
     register_register(iii, kv);
     register_complete(iii, kv);
+    register_usage(iii, kv);
+    register_poll(iii, kv);
+    register_ack(iii, kv);
+    for command in [
+        GuardCommand::Kill,
+        GuardCommand::Pause,
+        GuardCommand::Resume,
+        GuardCommand::Extend,
+    ] {
+        register_request(iii, kv, command);
+    }
     register_list(iii, kv);
     register_history(iii, kv);
 }
@@ -45,7 +561,7 @@ fn register_register(iii: &III, kv: &StateKV) {
                 let input = extract_input(input);
                 let id = require_str(&input, "id")?;
                 let command = require_str(&input, "command")?;
-                let limit = input.get("limit").and_then(|v| v.as_f64()).unwrap_or(0.0);
+                let limits = GuardLimits::from_input(&input)?;
                 let action = input
                     .get("action")
                     .and_then(|v| v.as_str())
@@ -53,15 +569,34 @@ fn register_register(iii: &III, kv: &StateKV) {
                     .to_string();
                 let started_at = require_str(&input, "started_at")?;
                 let pid = input.get("pid").and_then(|v| v.as_i64()).unwrap_or(0);
+                let process_group = input
+                    .get("process_group")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                let cwd = input.get("cwd").and_then(|v| v.as_str()).map(String::from);
+                let start_snapshot = snapshot_input(&input, "snapshot")?;
+                let agent_type = match input.get("agent").filter(|v| !v.is_null()) {
+                    Some(v) => Some(
+                        serde_json::from_value::<AgentType>(v.clone())
+                            .map_err(|_| IIIError::Handler(format!("unknown agent: {}", v)))?,
+                    ),
+                    None => agent_for_command(&command),
+                };
 
                 let record = GuardRecord {
                     id: id.clone(),
                     command,
-                    limit,
+                    limits,
                     action,
                     started_at,
-                    current_cost: 0.0,
+                    usage: GuardUsage::default(),
                     pid,
+                    intervention: None,
+                    cwd,
+                    agent_type,
+                    session_ids: Vec::new(),
+                    process_group,
+                    start_snapshot,
                 };
 
                 kv.set("guards", &id, &record).await.map_err(kv_err)?;
@@ -94,27 +629,45 @@ fn register_complete(iii: &III, kv: &StateKV) {
                     .unwrap_or("none")
                     .to_string();
                 let ended_at = require_str(&input, "ended_at")?;
+                let signal = input
+                    .get("signal")
+                    .and_then(|v| v.as_str())
+                    .map(String::from);
+                let stop_snapshot = snapshot_input(&input, "stop_snapshot")?;
 
                 let guard: Option<GuardRecord> = kv.get("guards", &id).await.map_err(kv_err)?;
 
                 let guard = guard.ok_or_else(|| {
                     iii_sdk::IIIError::Handler(format!("guard not found: {}", id))
                 })?;
+                // The wrapper only sees its child exit; keep the reason when
+                // rimuru was the one that stopped it.
+                let action_taken = match guard.intervention {
+                    Some(ref intervention) if action_taken == "none" => intervention.clone(),
+                    _ => action_taken,
+                };
 
                 let history = GuardHistoryRecord {
                     id: id.clone(),
                     command: guard.command,
-                    limit: guard.limit,
+                    limits: guard.limits,
                     final_cost,
                     action_taken,
                     started_at: guard.started_at,
                     ended_at,
+                    session_ids: guard.session_ids,
+                    signal,
+                    cwd: guard.cwd,
+                    start_snapshot: guard.start_snapshot,
+                    stop_snapshot,
                 };
 
                 kv.set("guard_history", &id, &history)
                     .await
                     .map_err(kv_err)?;
                 kv.delete("guards", &id).await.map_err(kv_err)?;
+                // Nothing is left to act on requests that arrived too late.
+                delete_requests(&kv, &queued_requests(&kv, &id).await?).await?;
 
                 Ok(api_response(json!({
                     "history": history,
@@ -125,6 +678,197 @@ fn register_complete(iii: &III, kv: &StateKV) {
     );
 }
 
+/// What the guard's sessions have used as of the guard loop's last poll,
+/// and which limit, if any, they have reached. Read-only, so dashboards can
+/// poll it without binding sessions or consuming queued commands.
+fn register_usage(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.guard.usage".to_string()),
+        move |input: Value| {
+            let kv = kv.clone();
+            async move {
+                let input = extract_input(input);
+                let id = require_str(&input, "id")?;
+                let mut guard: GuardRecord = kv
+                    .get("guards", &id)
+                    .await
+                    .map_err(kv_err)?
+                    .ok_or_else(|| IIIError::Handler(format!("guard not found: {}", id)))?;
+                guard.usage.elapsed_secs = elapsed_secs(&guard.started_at);
+                let pending = queued_requests(&kv, &id).await?.len();
+
+                let mut body = usage_body(&guard);
+                body["pending_requests"] = json!(pending);
+                Ok(api_response(body))
+            }
+        },
+    );
+}
+
+/// The guard loop's poll: bind the guard to any new sessions its process
+/// created, refresh what those sessions have used, apply queued `extend`s
+/// and hand over the other queued commands. Those stay queued until the
+/// loop acknowledges them with `rimuru.guard.ack`, so a poll that times out
+/// loses nothing. The loop polls this instead of global spend, so other
+/// agents running at the same time are not charged to it.
+fn register_poll(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.guard.poll".to_string()),
+        move |input: Value| {
+            let kv = kv.clone();
+            async move {
+                let input = extract_input(input);
+                let id = require_str(&input, "id")?;
+
+                let guards: Vec<GuardRecord> = kv.list("guards").await.map_err(kv_err)?;
+                let mut guard = guards
+                    .iter()
+                    .find(|g| g.id == id)
+                    .cloned()
+                    .ok_or_else(|| IIIError::Handler(format!("guard not found: {}", id)))?;
+                let claimed: HashSet<String> = guards
+                    .iter()
+                    .filter(|g| g.id != id)
+                    .flat_map(|g| g.session_ids.iter().cloned())
+                    .collect();
+
+                let (extends, commands): (Vec<_>, Vec<_>) = queued_requests(&kv, &id)
+                    .await?
+                    .into_iter()
+                    .partition(|r| r.command == GuardCommand::Extend);
+                for more in extends.iter().filter_map(|r| r.limits.as_ref()) {
+                    match guard.limits.extended(more) {
+                        Ok(limits) => guard.limits = limits,
+                        Err(e) => tracing::warn!("guard {}: dropping extend: {}", id, e),
+                    }
+                }
+
+                let sessions: Vec<Session> = kv.list("sessions").await.map_err(kv_err)?;
+                let new_sessions = guard.bind(&sessions, &claimed);
+                let bound: Vec<&Session> = sessions
+                    .iter()
+                    .filter(|s| guard.session_ids.contains(&s.id.to_string()))
+                    .collect();
+                let mut turns = 0;
+                for session in &bound {
+                    let breakdown: Option<ContextBreakdown> = kv
+                        .get("context_breakdowns", &session.id.to_string())
+                        .await
+                        .map_err(kv_err)?;
+                    turns += breakdown.map_or(0, |b| b.turns.len() as u64);
+                }
+                guard.usage = GuardUsage {
+                    cost: bound.iter().map(|s| s.total_cost).sum(),
+                    tokens: bound.iter().map(|s| s.total_tokens).sum(),
+                    turns,
+                    elapsed_secs: elapsed_secs(&guard.started_at),
+                };
+
+                // `usage` and `limits` are flattened into the record.
+                update_guard_fields(&kv, &id, &guard.usage).await?;
+                update_guard_fields(&kv, &id, &guard.limits).await?;
+                kv.update_field("guards", &id, "session_ids", &guard.session_ids)
+                    .await
+                    .map_err(kv_err)?;
+                // An extend is done once the raised limits are stored.
+                delete_requests(&kv, &extends).await?;
+
+                let mut body = usage_body(&guard);
+                body["commands"] = json!(
+                    commands
+                        .iter()
+                        .map(|r| json!({"id": r.id, "command": r.command}))
+                        .collect::<Vec<_>>()
+                );
+                body["new_sessions"] = json!(new_sessions);
+                Ok(api_response(body))
+            }
+        },
+    );
+}
+
+/// Drop the queued commands the guard loop has carried out, by request id.
+fn register_ack(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.guard.ack".to_string()),
+        move |input: Value| {
+            let kv = kv.clone();
+            async move {
+                let input = extract_input(input);
+                let id = require_str(&input, "id")?;
+                let acked: HashSet<&str> = input
+                    .get("request_ids")
+                    .and_then(|v| v.as_array())
+                    .ok_or_else(|| IIIError::Handler("request_ids is required".into()))?
+                    .iter()
+                    .filter_map(|v| v.as_str())
+                    .collect();
+                let done: Vec<GuardRequest> = queued_requests(&kv, &id)
+                    .await?
+                    .into_iter()
+                    .filter(|r| acked.contains(r.id.as_str()))
+                    .collect();
+                delete_requests(&kv, &done).await?;
+
+                Ok(api_response(json!({
+                    "id": id,
+                    "acknowledged": done.len()
+                })))
+            }
+        },
+    );
+}
+
+/// `rimuru.guard.kill`, `pause`, `resume` and `extend`. The command is
+/// queued rather than carried out here: only the guard loop knows how to
+/// stop its process group gracefully, and it picks the command up on its
+/// next poll. `extend` takes the same limit fields as `register`.
+fn register_request(iii: &III, kv: &StateKV, command: GuardCommand) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id(format!("rimuru.guard.{}", command.as_str())),
+        move |input: Value| {
+            let kv = kv.clone();
+            async move {
+                let input = extract_input(input);
+                let id = require_str(&input, "id")?;
+                let guard: GuardRecord = kv
+                    .get("guards", &id)
+                    .await
+                    .map_err(kv_err)?
+                    .ok_or_else(|| IIIError::Handler(format!("guard not found: {}", id)))?;
+
+                let limits = match command {
+                    GuardCommand::Extend => {
+                        let more = GuardLimits::from_input(&input)?;
+                        guard.limits.extended(&more)?;
+                        Some(more)
+                    }
+                    _ => None,
+                };
+                let request = GuardRequest {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    guard_id: id,
+                    command,
+                    limits,
+                    requested_at: Utc::now().to_rfc3339(),
+                };
+                kv.set("guard_requests", &request.id, &request)
+                    .await
+                    .map_err(kv_err)?;
+
+                Ok(api_response(json!({
+                    "request": request,
+                    "queued": true
+                })))
+            }
+        },
+    );
+}
+
 fn register_list(iii: &III, kv: &StateKV) {
     let kv = kv.clone();
     iii.register_function_with(
@@ -161,3 +905,201 @@ fn register_history(iii: &III, kv: &StateKV) {
         },
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn guard(command: &str, started_at: &str) -> GuardRecord {
+        GuardRecord {
+            id: Uuid::new_v4().to_string(),
+            command: command.into(),
+            limits: GuardLimits {
+                limit: Some(5.0),
+                ..Default::default()
+            },
+            action: "warn".into(),
+            started_at: started_at.into(),
+            usage: GuardUsage::default(),
+            pid: 4242,
+            intervention: None,
+            cwd: None,
+            agent_type: agent_for_command(command),
+            session_ids: Vec::new(),
+            process_group: false,
+            start_snapshot: None,
+        }
+    }
+
+    #[test]
+    fn matches_only_the_guard_bound_to_the_session() {
+        let mut session = Session::new(Uuid::new_v4(), AgentType::Codex);
+        session.started_at = "2026-05-01T10:05:00Z".parse().unwrap();
+        let mut guards = vec![
+            guard("npx codex exec fix", "2026-05-01T09:00:00Z"),
+            guard("/usr/local/bin/codex", "2026-05-01T10:00:00Z"),
+        ];
+        // Same agent, started just before: still not this session's guard.
+        assert!(guard_for_session(&guards, &session).is_none());
+
+        guards[0].session_ids.push(session.id.to_string());
+        let found = guard_for_session(&guards, &session).unwrap();
+        assert_eq!(found.command, "npx codex exec fix");
+    }
+
+    #[test]
+    fn parses_ps_elapsed_times() {
+        assert_eq!(parse_etime("  00:07\n"), Some(7));
+        assert_eq!(parse_etime("12:34"), Some(754));
+        assert_eq!(parse_etime("01:02:03"), Some(3723));
+        assert_eq!(parse_etime("2-00:00:01"), Some(172_801));
+        assert_eq!(parse_etime(""), None);
+    }
+
+    #[test]
+    fn binds_only_sessions_the_guarded_process_created() {
+        let mut g = guard("codex exec fix", "2026-05-01T10:00:00Z");
+        g.cwd = Some("/home/dev/app".into());
+        let session = |agent, project: Option<&str>, started: &str| {
+            let mut s = Session::new(Uuid::new_v4(), agent);
+            s.project_path = project.map(String::from);
+            s.started_at = started.parse().unwrap();
+            s
+        };
+        let sessions = vec![
+            session(
+                AgentType::Codex,
+                Some("/home/dev/app"),
+                "2026-05-01T10:00:05Z",
+            ),
+            // Another agent working in the same repository.
+            session(
+                AgentType::ClaudeCode,
+                Some("-home-dev-app"),
+                "2026-05-01T10:01:00Z",
+            ),
+            // Codex in another repository, and one from before the guard.
+            session(
+                AgentType::Codex,
+                Some("/home/dev/other"),
+                "2026-05-01T10:02:00Z",
+            ),
+            session(
+                AgentType::Codex,
+                Some("/home/dev/app"),
+                "2026-05-01T09:59:00Z",
+            ),
+            // Already claimed by a second guard in the same repository.
+            session(
+                AgentType::Codex,
+                Some("/home/dev/app"),
+                "2026-05-01T10:03:00Z",
+            ),
+        ];
+        let claimed = HashSet::from([sessions[4].id.to_string()]);
+
+        let bound = g.bind(&sessions, &claimed);
+        assert_eq!(bound, vec![sessions[0].id.to_string()]);
+        assert!(g.bind(&sessions, &claimed).is_empty());
+
+        // With the agent left open, Claude's encoded project dir still matches.
+        g.agent_type = None;
+        assert_eq!(
+            g.bind(&sessions, &claimed),
+            vec![sessions[1].id.to_string()]
+        );
+    }
+
+    #[test]
+    fn limits_parse_combine_and_report_the_one_that_fired() {
+        let limits = GuardLimits::from_input(&json!({
+            "max_tokens": 200_000,
+            "max_duration": "30m",
+            "max_turns": 40
+        }))
+        .unwrap();
+        assert_eq!(limits.limit, None);
+        assert_eq!(limits.max_duration_secs, Some(1800));
+
+        let mut usage = GuardUsage {
+            cost: 50.0,
+            tokens: 10_000,
+            turns: 12,
+            elapsed_secs: 60,
+        };
+        assert_eq!(limits.reached(&usage), None);
+        usage.elapsed_secs = 1800;
+        assert_eq!(limits.reached(&usage), Some("max_duration"));
+        usage.turns = 40;
+        assert_eq!(limits.reached(&usage), Some("max_turns"));
+
+        assert!(GuardLimits::from_input(&json!({})).is_err());
+        assert!(GuardLimits::from_input(&json!({"limit": -1.0})).is_err());
+        assert!(GuardLimits::from_input(&json!({"max_turns": 0})).is_err());
+        assert!(GuardLimits::from_input(&json!({"max_duration": "99999999999999d"})).is_err());
+        assert!(GuardLimits::from_input(&json!({"max_duration": u64::MAX})).is_err());
+    }
+
+    #[test]
+    fn records_without_new_limits_still_load() {
+        let record: GuardRecord = serde_json::from_value(json!({
+            "id": "g1",
+            "command": "claude",
+            "limit": 5.0,
+            "action": "kill",
+            "started_at": "2026-05-01T10:00:00Z",
+            "current_cost": 1.25
+        }))
+        .unwrap();
+        assert_eq!(record.limits.limit, Some(5.0));
+        assert_eq!(record.limits.max_tokens, None);
+        assert_eq!(record.usage.cost, 1.25);
+    }
+
+    #[test]
+    fn extend_only_raises_limits() {
+        let limits = GuardLimits {
+            limit: Some(5.0),
+            max_turns: Some(40),
+            ..Default::default()
+        };
+        let raised = limits
+            .extended(&GuardLimits {
+                limit: Some(8.0),
+                max_tokens: Some(500_000),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(raised.limit, Some(8.0));
+        assert_eq!(raised.max_tokens, Some(500_000));
+        assert_eq!(raised.max_turns, Some(40));
+
+        let lowered = limits.extended(&GuardLimits {
+            max_turns: Some(20),
+            ..Default::default()
+        });
+        assert!(lowered.is_err());
+    }
+
+    #[test]
+    fn snapshot_keeps_its_ref_name() {
+        let input = json!({
+            "snapshot": {
+                "head": "fbdf98d",
+                "commit": "3c0780f",
+                "ref": "refs/rimuru/guards/g1/start",
+                "taken_at": "2026-05-01T10:00:00Z"
+            },
+            "stop_snapshot": null
+        });
+        let snap = snapshot_input(&input, "snapshot").unwrap().unwrap();
+        assert_eq!(snap.git_ref, "refs/rimuru/guards/g1/start");
+        assert_eq!(
+            serde_json::to_value(&snap).unwrap()["ref"],
+            "refs/rimuru/guards/g1/start"
+        );
+        assert!(snapshot_input(&input, "stop_snapshot").unwrap().is_none());
+        assert!(snapshot_input(&json!({"snapshot": {"head": "x"}}), "snapshot").is_err());
+    }
+}
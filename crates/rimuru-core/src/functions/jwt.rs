@@ -35,6 +35,9 @@ pub struct Claims {
     pub user_id: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub team_id: Option<String>,
+    /// `admin` and `owner` may grant and revoke budget overrides.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub exp: Option<i64>,
     #[serde(flatten)]
@@ -45,6 +48,10 @@ impl Claims {
     pub fn user(&self) -> Option<String> {
         self.user_id.clone().or_else(|| self.sub.clone())
     }
+
+    pub fn is_admin(&self) -> bool {
+        matches!(self.role.as_deref(), Some("admin" | "owner"))
+    }
 }
 
 pub fn verify_hs256(token: &str, secret: &[u8]) -> Result<Claims, JwtError> {
@@ -125,6 +132,30 @@ pub fn extract_bearer(headers: &Value) -> Option<String> {
     None
 }
 
+/// Headers for a call the worker makes to its own functions on nobody's
+/// behalf. With a secret configured they carry a short-lived token with no
+/// user or team; without one, the callee applies its no-JWT rules.
+pub fn internal_headers() -> Value {
+    let Some(secret) = jwt_secret() else {
+        return Value::Null;
+    };
+    let claims = Claims {
+        sub: None,
+        user_id: None,
+        team_id: None,
+        role: None,
+        exp: Some(chrono::Utc::now().timestamp() + 60),
+        extra: serde_json::Map::new(),
+    };
+    match encode_hs256(&claims, &secret) {
+        Ok(token) => serde_json::json!({ "authorization": format!("Bearer {}", token) }),
+        Err(e) => {
+            tracing::warn!("failed to sign internal token: {}", e);
+            Value::Null
+        }
+    }
+}
+
 pub fn allow_without_jwt() -> bool {
     std::env::var("RIMURU_ALLOW_TEAM_WITHOUT_JWT")
         .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
@@ -181,6 +212,7 @@ mod tests {
             sub: Some("alice".into()),
             user_id: None,
             team_id: None,
+            role: None,
             exp: None,
             extra: Default::default(),
         };
@@ -195,6 +227,7 @@ mod tests {
             sub: None,
             user_id: Some("alice".into()),
             team_id: None,
+            role: None,
             exp: Some(123),
             extra: Default::default(),
         };
@@ -1,11 +1,17 @@
 pub mod agents;
+pub mod backtest;
 pub mod budget;
 pub mod config;
 pub mod context;
 pub mod costs;
+pub mod counters;
+pub mod currency;
+pub mod detectors;
+pub mod downgrade;
 #[cfg(feature = "email")]
 pub mod email;
 pub mod export;
+pub mod forecast;
 pub mod guard;
 pub mod hardware;
 pub mod health;
@@ -17,7 +23,10 @@ pub mod mcp_proxy;
 pub mod metrics;
 pub mod models;
 pub mod optimize;
+pub mod overrides;
+pub mod period;
 pub mod plugins;
+pub mod reservation;
 pub mod runaway;
 pub mod sessions;
 pub mod skillkit;
@@ -39,9 +48,13 @@ pub fn register_all(iii: &III, kv: &StateKV) {
 
     agents::register(iii, kv);
     budget::register(iii, kv);
+    counters::register(iii, kv);
+    reservation::register(iii, kv);
+    overrides::register(iii, kv);
     context::register(iii, kv);
     sessions::register(iii, kv);
     costs::register(iii, kv);
+    currency::register(iii, kv);
     export::register(iii, kv);
     #[cfg(feature = "email")]
     email::register(iii, kv);
@@ -54,6 +67,8 @@ pub fn register_all(iii: &III, kv: &StateKV) {
     mcp_proxy::register(iii, kv, proxy);
     indexer::register(iii, kv);
     runaway::register(iii, kv);
+    detectors::register(iii, kv);
+    backtest::register(iii, kv);
     optimize::register(iii, kv);
     skillkit::register(iii, kv);
     sync::register(iii, kv);
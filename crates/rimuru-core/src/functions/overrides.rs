@@ -0,0 +1,319 @@
+//! Time-boxed budget overrides (`rimuru.budget.override.*`).
+//!
+//! An override adds temporary headroom to every cap `rimuru.budget.check`
+//! enforces, for when `budget_action = block` trips mid-incident. Unlike
+//! raising `budget_monthly`, it lapses on its own. Only an `admin` or `owner`
+//! token may grant or revoke one, and each override records that user and
+//! why. Overrides
+//! are kept after they expire or are revoked, so `rimuru.budget.alerts` can
+//! show the full history.
+
+use chrono::{DateTime, Utc};
+use iii_sdk::{III, IIIError, RegisterFunctionMessage};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use super::currency::{self, BUDGET_CURRENCY_KEY};
+use super::jwt::{Claims, authorize};
+use super::period::parse_duration;
+use super::sysutil::{api_response, extract_input, kv_err, require_str};
+use crate::state::StateKV;
+
+pub(crate) const OVERRIDE_SCOPE: &str = "budget_overrides";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetOverride {
+    pub id: String,
+    /// Extra allowance on top of each configured cap, in `budget_currency`.
+    pub amount: f64,
+    pub reason: String,
+    pub granted_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    #[serde(default)]
+    pub revoked_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub revoked_by: Option<String>,
+}
+
+impl BudgetOverride {
+    pub fn status(&self, now: DateTime<Utc>) -> &'static str {
+        if self.revoked_at.is_some() {
+            "revoked"
+        } else if self.expires_at <= now {
+            "expired"
+        } else {
+            "active"
+        }
+    }
+
+    fn to_json(&self, now: DateTime<Utc>) -> Value {
+        let mut v = serde_json::to_value(self).unwrap_or(Value::Null);
+        v["status"] = json!(self.status(now));
+        v
+    }
+}
+
+/// Headroom granted by the overrides in effect right now.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ActiveOverrides {
+    pub headroom: f64,
+    pub ids: Vec<String>,
+    /// When the last active override lapses.
+    pub until: Option<DateTime<Utc>>,
+}
+
+pub(crate) fn summarize(overrides: &[BudgetOverride], now: DateTime<Utc>) -> ActiveOverrides {
+    let mut active = ActiveOverrides::default();
+    for o in overrides.iter().filter(|o| o.status(now) == "active") {
+        active.headroom += o.amount;
+        active.ids.push(o.id.clone());
+        active.until = active.until.max(Some(o.expires_at));
+    }
+    active
+}
+
+pub(crate) async fn load_active(kv: &StateKV) -> Result<ActiveOverrides, IIIError> {
+    let overrides: Vec<BudgetOverride> = kv.list(OVERRIDE_SCOPE).await.map_err(kv_err)?;
+    Ok(summarize(&overrides, Utc::now()))
+}
+
+/// Every override, newest first, with its current status.
+pub(crate) async fn history(kv: &StateKV, limit: usize) -> Result<Vec<Value>, IIIError> {
+    let mut overrides: Vec<BudgetOverride> = kv.list(OVERRIDE_SCOPE).await.map_err(kv_err)?;
+    overrides.sort_by_key(|o| std::cmp::Reverse(o.created_at));
+    let now = Utc::now();
+    Ok(overrides
+        .iter()
+        .take(limit)
+        .map(|o| o.to_json(now))
+        .collect())
+}
+
+/// Overrides lift every cap, so granting or revoking one needs a verified
+/// token with an `admin` or `owner` role, and the acting user comes from its
+/// claims, never the body. Under `RIMURU_ALLOW_TEAM_WITHOUT_JWT` there are
+/// no claims and the user is unknown.
+fn acting_admin(raw_input: &Value) -> Result<Option<String>, IIIError> {
+    admin_user(authorize(raw_input)?)
+}
+
+fn admin_user(claims: Option<Claims>) -> Result<Option<String>, IIIError> {
+    match claims {
+        Some(claims) if claims.is_admin() => Ok(claims.user()),
+        Some(_) => Err(IIIError::Handler(
+            "unauthorized: budget overrides need an admin or owner role".into(),
+        )),
+        None => Ok(None),
+    }
+}
+
+pub fn register(iii: &III, kv: &StateKV) {
+    register_create(iii, kv);
+    register_list(iii, kv);
+    register_revoke(iii, kv);
+}
+
+fn register_create(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.budget.override.create".to_string()),
+        move |input: Value| {
+            let kv = kv.clone();
+            async move {
+                let raw_input = input;
+                let input = extract_input(raw_input.clone());
+                let granted_by = acting_admin(&raw_input)?;
+
+                let amount = input
+                    .get("amount")
+                    .and_then(|v| v.as_f64())
+                    .ok_or_else(|| IIIError::Handler("amount is required".into()))?;
+                if !amount.is_finite() || amount <= 0.0 {
+                    return Err(IIIError::Handler("amount must be > 0".into()));
+                }
+                let duration = match input.get("duration") {
+                    Some(Value::String(s)) => parse_duration(s)?,
+                    Some(Value::Number(n)) => parse_duration(&n.to_string())?,
+                    _ => return Err(IIIError::Handler("duration is required".into())),
+                };
+                let reason = require_str(&input, "reason")?;
+                if reason.trim().is_empty() {
+                    return Err(IIIError::Handler("reason must not be empty".into()));
+                }
+
+                let now = Utc::now();
+                let expires_at = now
+                    .checked_add_signed(duration)
+                    .ok_or_else(|| IIIError::Handler("duration is out of range".into()))?;
+                let o = BudgetOverride {
+                    id: Uuid::new_v4().to_string(),
+                    amount,
+                    reason: reason.trim().to_string(),
+                    granted_by,
+                    created_at: now,
+                    expires_at,
+                    revoked_at: None,
+                    revoked_by: None,
+                };
+                kv.set(OVERRIDE_SCOPE, &o.id, &o).await.map_err(kv_err)?;
+                let code = currency::load_code(&kv, BUDGET_CURRENCY_KEY).await?;
+                tracing::info!(
+                    "budget override {} granted: +{} until {} by {} ({})",
+                    o.id,
+                    currency::format_amount(o.amount, &code),
+                    o.expires_at.to_rfc3339(),
+                    o.granted_by.as_deref().unwrap_or("unknown"),
+                    o.reason
+                );
+
+                Ok(api_response(json!({
+                    "override": o.to_json(now),
+                    "currency": code,
+                    "created": true
+                })))
+            }
+        },
+    );
+}
+
+fn register_list(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.budget.override.list".to_string()),
+        move |input: Value| {
+            let kv = kv.clone();
+            async move {
+                let input = extract_input(input);
+                let include_inactive = input
+                    .get("all")
+                    .and_then(|v| v.as_bool().or_else(|| Some(v.as_str()? == "true")))
+                    .unwrap_or(false);
+
+                let mut overrides = history(&kv, usize::MAX).await?;
+                if !include_inactive {
+                    overrides.retain(|o| o["status"] == "active");
+                }
+                let active = load_active(&kv).await?;
+
+                Ok(api_response(json!({
+                    "overrides": overrides,
+                    "count": overrides.len(),
+                    "headroom": active.headroom,
+                    "currency": currency::load_code(&kv, BUDGET_CURRENCY_KEY).await?,
+                    "active_until": active.until.map(|t| t.to_rfc3339()),
+                })))
+            }
+        },
+    );
+}
+
+fn register_revoke(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.budget.override.revoke".to_string()),
+        move |input: Value| {
+            let kv = kv.clone();
+            async move {
+                let raw_input = input;
+                let input = extract_input(raw_input.clone());
+                let revoked_by = acting_admin(&raw_input)?;
+                let id = require_str(&input, "id")?;
+
+                let mut o: BudgetOverride = kv
+                    .get(OVERRIDE_SCOPE, &id)
+                    .await
+                    .map_err(kv_err)?
+                    .ok_or_else(|| IIIError::Handler(format!("override {} not found", id)))?;
+                let now = Utc::now();
+                let status = o.status(now);
+                if status != "active" {
+                    return Err(IIIError::Handler(format!(
+                        "override {} is already {}",
+                        id, status
+                    )));
+                }
+                o.revoked_at = Some(now);
+                o.revoked_by = revoked_by;
+                kv.set(OVERRIDE_SCOPE, &id, &o).await.map_err(kv_err)?;
+                tracing::info!(
+                    "budget override {} revoked by {}",
+                    id,
+                    o.revoked_by.as_deref().unwrap_or("unknown")
+                );
+
+                Ok(api_response(json!({
+                    "override": o.to_json(now),
+                    "currency": currency::load_code(&kv, BUDGET_CURRENCY_KEY).await?,
+                    "revoked": true
+                })))
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn grant(amount: f64, expires_in_mins: i64) -> BudgetOverride {
+        let now = Utc::now();
+        BudgetOverride {
+            id: Uuid::new_v4().to_string(),
+            amount,
+            reason: "incident".into(),
+            granted_by: Some("alice".into()),
+            created_at: now - Duration::minutes(5),
+            expires_at: now + Duration::minutes(expires_in_mins),
+            revoked_at: None,
+            revoked_by: None,
+        }
+    }
+
+    #[test]
+    fn status_follows_expiry_and_revocation() {
+        let now = Utc::now();
+        assert_eq!(grant(5.0, 10).status(now), "active");
+        assert_eq!(grant(5.0, -1).status(now), "expired");
+        let mut revoked = grant(5.0, 10);
+        revoked.revoked_at = Some(now);
+        assert_eq!(revoked.status(now), "revoked");
+    }
+
+    #[test]
+    fn summarize_sums_only_active_overrides() {
+        let now = Utc::now();
+        let mut revoked = grant(100.0, 60);
+        revoked.revoked_at = Some(now);
+        let overrides = vec![grant(20.0, 120), grant(5.0, 30), grant(50.0, -10), revoked];
+        let active = summarize(&overrides, now);
+        assert_eq!(active.headroom, 25.0);
+        assert_eq!(active.ids.len(), 2);
+        assert_eq!(active.until, Some(overrides[0].expires_at));
+    }
+
+    #[test]
+    fn only_admins_and_owners_act_on_overrides() {
+        let claims = |role: Option<&str>| Claims {
+            sub: None,
+            user_id: Some("alice".into()),
+            team_id: None,
+            role: role.map(String::from),
+            exp: None,
+            extra: Default::default(),
+        };
+        assert_eq!(
+            admin_user(Some(claims(Some("owner")))).unwrap(),
+            Some("alice".into())
+        );
+        assert_eq!(
+            admin_user(Some(claims(Some("admin")))).unwrap(),
+            Some("alice".into())
+        );
+        assert!(admin_user(Some(claims(Some("member")))).is_err());
+        assert!(admin_user(Some(claims(None))).is_err());
+        assert_eq!(admin_user(None).unwrap(), None);
+    }
+}
@@ -0,0 +1,265 @@
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use iii_sdk::IIIError;
+use serde_json::Value;
+
+use super::sysutil::kv_err;
+use crate::state::StateKV;
+
+/// Config key holding the IANA timezone that defines day/week/month
+/// boundaries for budgets, rollups and exports. Defaults to UTC, which keeps
+/// date keys identical to data written before the setting existed.
+pub const TIMEZONE_CONFIG_KEY: &str = "timezone";
+
+pub fn parse_timezone(name: &str) -> Result<Tz, IIIError> {
+    name.trim()
+        .parse::<Tz>()
+        .map_err(|_| IIIError::Handler(format!("unknown IANA timezone: {}", name)))
+}
+
+/// Configured timezone, falling back to UTC when unset or unparseable.
+pub async fn load_timezone(kv: &StateKV) -> Result<Tz, IIIError> {
+    let value: Option<Value> = kv
+        .get("config", TIMEZONE_CONFIG_KEY)
+        .await
+        .map_err(kv_err)?;
+    Ok(match value.as_ref().and_then(|v| v.as_str()) {
+        Some(name) => parse_timezone(name).unwrap_or_else(|e| {
+            tracing::warn!("ignoring configured timezone: {}", e);
+            Tz::UTC
+        }),
+        None => Tz::UTC,
+    })
+}
+
+/// A `timezone` field on the request wins over the configured default.
+pub async fn resolve_timezone(kv: &StateKV, input: &Value) -> Result<Tz, IIIError> {
+    match input.get("timezone").and_then(|v| v.as_str()) {
+        Some(name) if !name.trim().is_empty() => parse_timezone(name),
+        _ => load_timezone(kv).await,
+    }
+}
+
+pub fn local_date(at: DateTime<Utc>, tz: Tz) -> NaiveDate {
+    at.with_timezone(&tz).date_naive()
+}
+
+/// `YYYY-MM-DD` key of the local day containing `at`, as used by
+/// `cost_daily`, `cost_agent` and `cost_project`.
+pub fn day_key(at: DateTime<Utc>, tz: Tz) -> String {
+    local_date(at, tz).format("%Y-%m-%d").to_string()
+}
+
+/// UTC instant at which the local day `date` begins. When a DST transition
+/// skips midnight the day starts at the first valid local time after it;
+/// when midnight occurs twice the earlier instant wins.
+pub fn day_start(date: NaiveDate, tz: Tz) -> DateTime<Utc> {
+    let mut naive = date.and_time(NaiveTime::MIN);
+    for _ in 0..96 {
+        match tz.from_local_datetime(&naive) {
+            LocalResult::Single(t) => return t.with_timezone(&Utc),
+            LocalResult::Ambiguous(earliest, _) => return earliest.with_timezone(&Utc),
+            LocalResult::None => naive += Duration::minutes(15),
+        }
+    }
+    Utc.from_utc_datetime(&date.and_time(NaiveTime::MIN))
+}
+
+pub fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+pub fn month_start(now: DateTime<Utc>, tz: Tz) -> DateTime<Utc> {
+    day_start(first_of_month(local_date(now, tz)), tz)
+}
+
+/// Start of the local ISO week (Monday) containing `now`.
+pub fn week_start(now: DateTime<Utc>, tz: Tz) -> DateTime<Utc> {
+    let today = local_date(now, tz);
+    let back = today.weekday().num_days_from_monday() as i64;
+    day_start(today - Duration::days(back), tz)
+}
+
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .and_then(|d| d.pred_opt())
+        .map(|d| d.day())
+        .unwrap_or(31)
+}
+
+/// Every local date in the month containing `now`.
+pub fn month_dates(now: DateTime<Utc>, tz: Tz) -> Vec<NaiveDate> {
+    let first = first_of_month(local_date(now, tz));
+    (0..days_in_month(first.year(), first.month()))
+        .filter_map(|offset| first.checked_add_signed(Duration::days(offset as i64)))
+        .collect()
+}
+
+/// The UTC dates the local day `date` overlaps: one when `tz` is UTC, two
+/// otherwise, three for a long DST day that straddles both UTC midnights.
+pub fn utc_dates(date: NaiveDate, tz: Tz) -> Vec<NaiveDate> {
+    let first = day_start(date, tz).date_naive();
+    let last = date
+        .succ_opt()
+        .map(|next| (day_start(next, tz) - Duration::nanoseconds(1)).date_naive())
+        .unwrap_or(first);
+    first.iter_days().take_while(|d| *d <= last).collect()
+}
+
+/// Longest duration `parse_duration` accepts: a year, which is more than
+/// any override or guard needs and keeps `now + duration` far from
+/// chrono's range.
+pub const MAX_DURATION_SECS: i64 = 366 * 24 * 3600;
+
+/// Parse a compact duration such as `90s`, `30m`, `2h`, `1d` or `1h30m`. A
+/// bare number is taken as seconds.
+pub fn parse_duration(text: &str) -> Result<Duration, IIIError> {
+    let text = text.trim();
+    let invalid = || IIIError::Handler(format!("invalid duration: {:?} (try 30m, 2h, 1d)", text));
+    let too_long = || {
+        IIIError::Handler(format!(
+            "duration {:?} is longer than the {}-day maximum",
+            text,
+            MAX_DURATION_SECS / 86_400
+        ))
+    };
+    let bounded = |d: Duration| {
+        if d > Duration::seconds(MAX_DURATION_SECS) {
+            Err(too_long())
+        } else {
+            Ok(d)
+        }
+    };
+    if let Ok(secs) = text.parse::<i64>() {
+        return match Duration::try_seconds(secs) {
+            Some(d) if secs > 0 => bounded(d),
+            Some(_) => Err(invalid()),
+            None => Err(too_long()),
+        };
+    }
+    let mut total = Duration::zero();
+    let mut digits = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let n: i64 = digits.parse().map_err(|_| invalid())?;
+        digits.clear();
+        let part = match c.to_ascii_lowercase() {
+            's' => Duration::try_seconds(n),
+            'm' => Duration::try_minutes(n),
+            'h' => Duration::try_hours(n),
+            'd' => Duration::try_days(n),
+            'w' => Duration::try_weeks(n),
+            _ => return Err(invalid()),
+        };
+        total = part
+            .and_then(|part| total.checked_add(&part))
+            .ok_or_else(too_long)?;
+    }
+    if !digits.is_empty() || total <= Duration::zero() {
+        return Err(invalid());
+    }
+    bounded(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utc_keys_match_legacy_format() {
+        let at = Utc.with_ymd_and_hms(2026, 5, 31, 23, 59, 0).unwrap();
+        assert_eq!(day_key(at, Tz::UTC), at.format("%Y-%m-%d").to_string());
+    }
+
+    #[test]
+    fn local_day_key_crosses_utc_midnight() {
+        let tz = parse_timezone("America/Los_Angeles").unwrap();
+        let at = Utc.with_ymd_and_hms(2026, 6, 1, 3, 0, 0).unwrap();
+        assert_eq!(day_key(at, tz), "2026-05-31");
+    }
+
+    #[test]
+    fn dst_spring_forward_day_is_23_hours() {
+        let tz = parse_timezone("America/New_York").unwrap();
+        let date = NaiveDate::from_ymd_opt(2026, 3, 8).unwrap();
+        let len = day_start(date.succ_opt().unwrap(), tz) - day_start(date, tz);
+        assert_eq!(len, Duration::hours(23));
+    }
+
+    #[test]
+    fn skipped_midnight_starts_at_first_valid_time() {
+        // Chile springs forward at local midnight, so 00:00 does not exist.
+        let tz = parse_timezone("America/Santiago").unwrap();
+        let date = NaiveDate::from_ymd_opt(2026, 9, 6).unwrap();
+        let start = day_start(date, tz);
+        assert_eq!(local_date(start, tz), date);
+        assert_eq!(
+            start.with_timezone(&tz).format("%H:%M").to_string(),
+            "01:00"
+        );
+    }
+
+    #[test]
+    fn local_day_maps_to_the_utc_dates_it_overlaps() {
+        let date = NaiveDate::from_ymd_opt(2026, 5, 31).unwrap();
+        assert_eq!(utc_dates(date, Tz::UTC), vec![date]);
+        let tz = parse_timezone("America/Los_Angeles").unwrap();
+        assert_eq!(utc_dates(date, tz), vec![date, date.succ_opt().unwrap()]);
+        let tz = parse_timezone("Asia/Tokyo").unwrap();
+        assert_eq!(utc_dates(date, tz), vec![date.pred_opt().unwrap(), date]);
+    }
+
+    #[test]
+    fn week_starts_on_local_monday() {
+        let tz = parse_timezone("Asia/Kolkata").unwrap();
+        // Sunday 2026-03-15 20:00 UTC is Monday 01:30 in Kolkata.
+        let now = Utc.with_ymd_and_hms(2026, 3, 15, 20, 0, 0).unwrap();
+        let start = week_start(now, tz);
+        assert_eq!(
+            local_date(start, tz),
+            NaiveDate::from_ymd_opt(2026, 3, 16).unwrap()
+        );
+    }
+
+    #[test]
+    fn month_dates_follow_local_calendar() {
+        let tz = parse_timezone("Pacific/Auckland").unwrap();
+        let now = Utc.with_ymd_and_hms(2026, 1, 31, 12, 0, 0).unwrap();
+        let dates = month_dates(now, tz);
+        assert_eq!(dates.len(), 28);
+        assert_eq!(dates[0], NaiveDate::from_ymd_opt(2026, 2, 1).unwrap());
+    }
+
+    #[test]
+    fn parses_compact_durations() {
+        assert_eq!(parse_duration("2h").unwrap(), Duration::hours(2));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::minutes(90));
+        assert_eq!(parse_duration("45").unwrap(), Duration::seconds(45));
+        assert_eq!(parse_duration(" 1d ").unwrap(), Duration::days(1));
+        for bad in ["", "h", "2x", "10m5", "0", "-5", "0h"] {
+            assert!(parse_duration(bad).is_err(), "{bad:?} should not parse");
+        }
+        assert_eq!(parse_duration("366d").unwrap(), Duration::days(366));
+        for huge in [
+            "367d",
+            "99999999999999d",
+            "9223372036854775807",
+            "9999999999999w1s",
+        ] {
+            assert!(parse_duration(huge).is_err(), "{huge:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_timezone() {
+        assert!(parse_timezone("Mars/Olympus_Mons").is_err());
+    }
+}
@@ -2,6 +2,7 @@ use chrono::Utc;
 use iii_sdk::{III, RegisterFunctionMessage};
 use serde_json::{Value, json};
 
+use super::detectors;
 use super::sysutil::{api_response, extract_input, kv_err, require_str};
 use crate::models::{PluginLanguage, PluginManifest, PluginState, PluginStatus};
 use crate::state::StateKV;
@@ -167,6 +168,7 @@ fn register_uninstall(iii: &III, kv: &StateKV) {
                 }
 
                 kv.delete("plugins", &plugin_id).await.map_err(kv_err)?;
+                detectors::remove_for_plugin(&kv, &plugin_id).await?;
 
                 kv.delete("plugin_state", &plugin_id)
                     .await
@@ -0,0 +1,578 @@
+//! Two-phase budget reservations (`rimuru.budget.reserve` / `commit` /
+//! `release`).
+//!
+//! `pending_cost` on `rimuru.budget.check` is advisory: several agents can
+//! check against the same remaining headroom and all proceed. A reservation
+//! holds its amount in atomic `budget_reserved` counters (global, plus the
+//! agent, session, team and project it was taken for) until it is committed
+//! into a real `CostRecord`, released, or its TTL lapses. `budget.check`
+//! adds those counters to spend, so concurrent reservers see each other.
+//!
+//! Every holder increments first and checks second. Two agents racing for the
+//! last dollar may therefore both be refused, but never both admitted. A
+//! commit records the cost before it lets go of the hold, so for a moment
+//! both count rather than neither.
+
+use chrono::{DateTime, Duration, Utc};
+use iii_sdk::{III, IIIError, RegisterFunctionMessage, TriggerRequest};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use super::budget::{project_matches, resolve_project_path, resolve_team_id};
+use super::costs::RECORD_CLAIM_SCOPE;
+use super::sysutil::{api_response, extract_input, kv_err, require_str};
+use crate::state::StateKV;
+
+const RESERVATION_SCOPE: &str = "budget_reservations";
+const RESERVED_SCOPE: &str = "budget_reserved";
+const RESERVED_FIELD: &str = "amount_cents";
+
+const TTL_CONFIG_KEY: &str = "budget_reservation_ttl_secs";
+const DEFAULT_TTL_SECS: i64 = 300;
+const MAX_TTL_SECS: i64 = 24 * 60 * 60;
+
+/// Settled reservations are kept this long before the sweep deletes them, so
+/// a late settler still finds the record (and its claim count) instead of
+/// recreating it.
+const SETTLED_RETENTION_SECS: i64 = 60 * 60;
+
+const STATUS_HELD: &str = "held";
+const STATUS_COMMITTED: &str = "committed";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reservation {
+    pub id: String,
+    pub amount: f64,
+    pub amount_cents: i64,
+    pub agent_id: Option<String>,
+    pub session_id: Option<String>,
+    pub team_id: Option<String>,
+    pub project_path: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// `held`, then one of `committed`, `released` or `expired`.
+    pub status: String,
+    #[serde(default)]
+    pub settled_at: Option<DateTime<Utc>>,
+    /// Bumped atomically by every settler; only the one that sees 1 returns
+    /// the held amount to the counters.
+    #[serde(default)]
+    pub settle_claims: i64,
+}
+
+impl Reservation {
+    /// `budget_reserved` keys this reservation holds its amount under.
+    fn counter_keys(&self) -> Vec<String> {
+        let mut keys = vec!["global".to_string()];
+        if let Some(id) = &self.agent_id {
+            keys.push(format!("agent::{}", id));
+        }
+        if let Some(id) = &self.session_id {
+            keys.push(format!("session::{}", id));
+        }
+        if let Some(id) = &self.team_id {
+            keys.push(format!("team::{}", id));
+        }
+        if let Some(path) = &self.project_path {
+            keys.push(format!("project::{}", path));
+        }
+        keys
+    }
+
+    fn is_held(&self) -> bool {
+        self.status == STATUS_HELD
+    }
+}
+
+/// Reserved-but-uncommitted dollars that `budget.check` adds to spend.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Outstanding {
+    pub global: f64,
+    pub agent: f64,
+    pub session: f64,
+    pub team: f64,
+    /// `(project_path, dollars)` for every project holding a reservation.
+    pub projects: Vec<(String, f64)>,
+}
+
+impl Outstanding {
+    pub fn project(&self, pattern: &str) -> f64 {
+        self.projects
+            .iter()
+            .filter(|(path, _)| project_matches(pattern, path))
+            .map(|(_, amount)| amount)
+            .sum()
+    }
+
+    /// Drop one reservation's contribution, e.g. the one being committed so
+    /// its amount isn't counted twice alongside the real record.
+    fn exclude(
+        &mut self,
+        r: &Reservation,
+        agent_id: Option<&str>,
+        session_id: Option<&str>,
+        team_id: Option<&str>,
+    ) {
+        let amount = r.amount_cents as f64 / 100.0;
+        self.global = (self.global - amount).max(0.0);
+        if r.agent_id.is_some() && r.agent_id.as_deref() == agent_id {
+            self.agent = (self.agent - amount).max(0.0);
+        }
+        if r.session_id.is_some() && r.session_id.as_deref() == session_id {
+            self.session = (self.session - amount).max(0.0);
+        }
+        if r.team_id.is_some() && r.team_id.as_deref() == team_id {
+            self.team = (self.team - amount).max(0.0);
+        }
+        if let Some(path) = &r.project_path
+            && let Some(entry) = self.projects.iter_mut().find(|(p, _)| p == path)
+        {
+            entry.1 = (entry.1 - amount).max(0.0);
+        }
+    }
+}
+
+async fn read_reserved(kv: &StateKV, key: &str) -> Result<f64, IIIError> {
+    let value: Option<Value> = kv.get(RESERVED_SCOPE, key).await.map_err(kv_err)?;
+    let cents = value
+        .and_then(|v| v.get(RESERVED_FIELD).and_then(|c| c.as_i64()))
+        .unwrap_or(0);
+    Ok(cents.max(0) as f64 / 100.0)
+}
+
+/// Outstanding reservations for the dimensions a check covers. Pass the
+/// reservation being committed as `exclude`.
+pub(crate) async fn outstanding(
+    kv: &StateKV,
+    agent_id: Option<&str>,
+    session_id: Option<&str>,
+    team_id: Option<&str>,
+    exclude: Option<&Reservation>,
+) -> Result<Outstanding, IIIError> {
+    let mut out = Outstanding {
+        global: read_reserved(kv, "global").await?,
+        ..Default::default()
+    };
+    if let Some(id) = agent_id {
+        out.agent = read_reserved(kv, &format!("agent::{}", id)).await?;
+    }
+    if let Some(id) = session_id {
+        out.session = read_reserved(kv, &format!("session::{}", id)).await?;
+    }
+    if let Some(id) = team_id {
+        out.team = read_reserved(kv, &format!("team::{}", id)).await?;
+    }
+    for key in kv.list_keys(RESERVED_SCOPE).await.map_err(kv_err)? {
+        if let Some(path) = key.strip_prefix("project::") {
+            let amount = read_reserved(kv, &key).await?;
+            if amount > 0.0 {
+                out.projects.push((path.to_string(), amount));
+            }
+        }
+    }
+    if let Some(r) = exclude.filter(|r| r.is_held()) {
+        out.exclude(r, agent_id, session_id, team_id);
+    }
+    Ok(out)
+}
+
+pub(crate) async fn load(kv: &StateKV, id: &str) -> Result<Option<Reservation>, IIIError> {
+    kv.get(RESERVATION_SCOPE, id).await.map_err(kv_err)
+}
+
+/// Return a held reservation's amount to the counters exactly once. Commit,
+/// release and the expiry sweep can race; `increment` on `settle_claims` is
+/// atomic, so only the first caller gets 1 back and undoes the hold.
+async fn settle(kv: &StateKV, r: &Reservation, status: &str) -> Result<bool, IIIError> {
+    let claims = kv
+        .increment(RESERVATION_SCOPE, &r.id, "settle_claims", 1)
+        .await
+        .map_err(kv_err)?;
+    if claims != 1 {
+        return Ok(false);
+    }
+    for key in r.counter_keys() {
+        kv.increment(RESERVED_SCOPE, &key, RESERVED_FIELD, -r.amount_cents)
+            .await
+            .map_err(kv_err)?;
+    }
+    kv.update_field(RESERVATION_SCOPE, &r.id, "status", &status)
+        .await
+        .map_err(kv_err)?;
+    kv.update_field(RESERVATION_SCOPE, &r.id, "settled_at", &Utc::now())
+        .await
+        .map_err(kv_err)?;
+    Ok(true)
+}
+
+/// Expire lapsed holds and purge long-settled records.
+pub(crate) async fn sweep(kv: &StateKV) -> Result<(), IIIError> {
+    let now = Utc::now();
+    let reservations: Vec<Reservation> = kv.list(RESERVATION_SCOPE).await.map_err(kv_err)?;
+    for r in reservations {
+        if r.is_held() {
+            if r.expires_at <= now {
+                settle(kv, &r, "expired").await?;
+            }
+        } else if r
+            .settled_at
+            .is_some_and(|at| now - at > Duration::seconds(SETTLED_RETENTION_SECS))
+        {
+            kv.delete(RESERVATION_SCOPE, &r.id).await.map_err(kv_err)?;
+            kv.delete(RECORD_CLAIM_SCOPE, &r.id).await.map_err(kv_err)?;
+        }
+    }
+    Ok(())
+}
+
+fn parse_uuid_field(input: &Value, key: &str) -> Result<Option<String>, IIIError> {
+    input
+        .get(key)
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            s.parse::<Uuid>()
+                .map(|id| id.to_string())
+                .map_err(|e| IIIError::Handler(format!("invalid {}: {}", key, e)))
+        })
+        .transpose()
+}
+
+pub fn register(iii: &III, kv: &StateKV) {
+    register_reserve(iii, kv);
+    register_commit(iii, kv);
+    register_release(iii, kv);
+    register_list(iii, kv);
+}
+
+fn register_reserve(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.budget.reserve".to_string()),
+        move |input: Value| {
+            let kv = kv.clone();
+            async move {
+                let raw_input = input;
+                let input = extract_input(raw_input.clone());
+                let team_id = resolve_team_id(&raw_input, &input)?;
+
+                let amount = input
+                    .get("amount")
+                    .and_then(|v| v.as_f64())
+                    .ok_or_else(|| IIIError::Handler("amount is required".into()))?;
+                if !amount.is_finite() || amount <= 0.0 {
+                    return Err(IIIError::Handler("amount must be > 0".into()));
+                }
+                let default_ttl: Option<Value> =
+                    kv.get("config", TTL_CONFIG_KEY).await.map_err(kv_err)?;
+                let ttl_secs = input
+                    .get("ttl_secs")
+                    .and_then(|v| v.as_i64())
+                    .or_else(|| default_ttl.and_then(|v| v.as_f64()).map(|v| v as i64))
+                    .unwrap_or(DEFAULT_TTL_SECS)
+                    .clamp(1, MAX_TTL_SECS);
+
+                let agent_id = parse_uuid_field(&input, "agent_id")?;
+                let session_id = parse_uuid_field(&input, "session_id")?;
+                let session_uuid = session_id.as_deref().and_then(|s| s.parse().ok());
+                let project_path = resolve_project_path(&kv, &input, session_uuid).await?;
+
+                sweep(&kv).await?;
+
+                let now = Utc::now();
+                let amount_cents = (amount * 100.0).round() as i64;
+                let reservation = Reservation {
+                    id: Uuid::new_v4().to_string(),
+                    amount: amount_cents as f64 / 100.0,
+                    amount_cents,
+                    agent_id,
+                    session_id,
+                    team_id,
+                    project_path,
+                    created_at: now,
+                    expires_at: now + Duration::seconds(ttl_secs),
+                    status: STATUS_HELD.to_string(),
+                    settled_at: None,
+                    settle_claims: 0,
+                };
+                kv.set(RESERVATION_SCOPE, &reservation.id, &reservation)
+                    .await
+                    .map_err(kv_err)?;
+                for key in reservation.counter_keys() {
+                    kv.increment(RESERVED_SCOPE, &key, RESERVED_FIELD, amount_cents)
+                        .await
+                        .map_err(kv_err)?;
+                }
+
+                // The hold is already in the counters, so the check sees it
+                // alongside every other outstanding reservation.
+                let check = kv
+                    .iii()
+                    .trigger(TriggerRequest {
+                        function_id: "rimuru.budget.check".to_string(),
+                        payload: json!({
+                            "session_id": reservation.session_id,
+                            "agent_id": reservation.agent_id,
+                            "team_id": reservation.team_id,
+                            "project_path": reservation.project_path,
+                        }),
+                        action: None,
+                        timeout_ms: Some(5000),
+                    })
+                    .await;
+
+                let body = match check {
+                    Ok(result) => result.get("body").cloned().unwrap_or(result),
+                    Err(e) => {
+                        tracing::warn!("budget check unavailable, reserving fail-open: {}", e);
+                        json!({"status": "ok", "exceeded": false, "warning": false})
+                    }
+                };
+                let exceeded = body
+                    .get("exceeded")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                let action = body
+                    .get("action")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("alert");
+                if exceeded && action == "block" {
+                    settle(&kv, &reservation, "released").await?;
+                    return Err(IIIError::Handler(
+                        "Budget exceeded. Reservation denied.".into(),
+                    ));
+                }
+
+                Ok(api_response(json!({
+                    "reservation_id": reservation.id,
+                    "amount": reservation.amount,
+                    "expires_at": reservation.expires_at.to_rfc3339(),
+                    "ttl_secs": ttl_secs,
+                    "status": body.get("status").cloned().unwrap_or(json!("ok")),
+                    "warning": body.get("warning").cloned().unwrap_or(json!(false)),
+                    "warnings": body.get("warnings").cloned().unwrap_or(json!([])),
+                })))
+            }
+        },
+    );
+}
+
+fn register_commit(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.budget.commit".to_string()),
+        move |input: Value| {
+            let kv = kv.clone();
+            async move {
+                let raw_input = input;
+                let input = extract_input(raw_input.clone());
+                let id = require_str(&input, "reservation_id")?;
+                let reservation = load(&kv, &id)
+                    .await?
+                    .ok_or_else(|| IIIError::Handler(format!("reservation {} not found", id)))?;
+                let committed = reservation.status == STATUS_COMMITTED;
+                if !reservation.is_held() && !committed {
+                    return Err(IIIError::Handler(format!(
+                        "reservation {} is already {}",
+                        id, reservation.status
+                    )));
+                }
+                if !committed && reservation.expires_at <= Utc::now() {
+                    settle(&kv, &reservation, "expired").await?;
+                    return Err(IIIError::Handler(format!("reservation {} expired", id)));
+                }
+
+                // The record inherits whatever the hold was taken for; an
+                // explicit field on the commit wins. Without a cost the
+                // reserved amount is recorded as-is.
+                let mut payload = match input {
+                    Value::Object(map) => map,
+                    _ => serde_json::Map::new(),
+                };
+                for (key, value) in [
+                    ("agent_id", &reservation.agent_id),
+                    ("session_id", &reservation.session_id),
+                    ("team_id", &reservation.team_id),
+                    ("project_path", &reservation.project_path),
+                ] {
+                    if let Some(v) = value {
+                        payload.entry(key).or_insert_with(|| json!(v));
+                    }
+                }
+                if !payload.contains_key("input_cost") && !payload.contains_key("output_cost") {
+                    payload.insert("input_cost".into(), json!(reservation.amount));
+                }
+                if let Some(headers) = raw_input.get("headers") {
+                    payload.insert("headers".into(), headers.clone());
+                }
+                // The record is keyed by the reservation, so a retried or
+                // concurrent commit gets the first one's record back instead
+                // of charging again. Its budget check leaves this hold out.
+                payload.insert("record_id".into(), json!(reservation.id));
+                payload.insert("reservation_id".into(), json!(reservation.id));
+
+                // Record first and let go of the hold after, so the amount is
+                // never out of both. If recording fails the hold stays until
+                // a retry commits it or its TTL lapses.
+                let result = kv
+                    .iii()
+                    .trigger(TriggerRequest {
+                        function_id: "rimuru.costs.record".to_string(),
+                        payload: Value::Object(payload),
+                        action: None,
+                        timeout_ms: Some(10_000),
+                    })
+                    .await
+                    .map_err(|e| IIIError::Handler(format!("commit failed: {}", e)))?;
+                if !settle(&kv, &reservation, STATUS_COMMITTED).await? {
+                    // A release or the sweep got the hold back first; the
+                    // cost is recorded all the same.
+                    kv.update_field(RESERVATION_SCOPE, &id, "status", &STATUS_COMMITTED)
+                        .await
+                        .map_err(kv_err)?;
+                }
+
+                let body = result.get("body").cloned().unwrap_or(result);
+                let record = body.get("record").cloned().unwrap_or(Value::Null);
+                Ok(api_response(json!({
+                    "reservation_id": id,
+                    "committed": true,
+                    "reserved": reservation.amount,
+                    "actual": record.get("total_cost").cloned().unwrap_or(json!(0.0)),
+                    "record": record,
+                    "duplicate": body.get("duplicate").cloned().unwrap_or(json!(false)),
+                    "budget_warning": body.get("budget_warning").cloned().unwrap_or(json!(false)),
+                })))
+            }
+        },
+    );
+}
+
+fn register_release(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.budget.release".to_string()),
+        move |input: Value| {
+            let kv = kv.clone();
+            async move {
+                let input = extract_input(input);
+                let id = require_str(&input, "reservation_id")?;
+                let reservation = load(&kv, &id)
+                    .await?
+                    .ok_or_else(|| IIIError::Handler(format!("reservation {} not found", id)))?;
+                let released =
+                    reservation.is_held() && settle(&kv, &reservation, "released").await?;
+                let status = if released {
+                    "released".to_string()
+                } else {
+                    load(&kv, &id)
+                        .await?
+                        .map(|r| r.status)
+                        .unwrap_or(reservation.status)
+                };
+
+                Ok(api_response(json!({
+                    "reservation_id": id,
+                    "released": released,
+                    "status": status,
+                })))
+            }
+        },
+    );
+}
+
+fn register_list(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
+    iii.register_function_with(
+        RegisterFunctionMessage::with_id("rimuru.budget.reservations".to_string()),
+        move |_input: Value| {
+            let kv = kv.clone();
+            async move {
+                sweep(&kv).await?;
+                let mut held: Vec<Reservation> = kv
+                    .list::<Reservation>(RESERVATION_SCOPE)
+                    .await
+                    .map_err(kv_err)?
+                    .into_iter()
+                    .filter(Reservation::is_held)
+                    .collect();
+                held.sort_by_key(|r| r.expires_at);
+                let reserved = read_reserved(&kv, "global").await?;
+
+                Ok(api_response(json!({
+                    "reservations": held,
+                    "count": held.len(),
+                    "reserved": reserved,
+                })))
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reservation(cents: i64) -> Reservation {
+        let now = Utc::now();
+        Reservation {
+            id: "r1".into(),
+            amount: cents as f64 / 100.0,
+            amount_cents: cents,
+            agent_id: Some("a1".into()),
+            session_id: None,
+            team_id: Some("t1".into()),
+            project_path: Some("/src/api".into()),
+            created_at: now,
+            expires_at: now + Duration::seconds(60),
+            status: STATUS_HELD.into(),
+            settled_at: None,
+            settle_claims: 0,
+        }
+    }
+
+    #[test]
+    fn counter_keys_cover_every_dimension_held() {
+        assert_eq!(
+            reservation(100).counter_keys(),
+            vec!["global", "agent::a1", "team::t1", "project::/src/api"]
+        );
+    }
+
+    #[test]
+    fn project_sums_every_matching_path() {
+        let out = Outstanding {
+            projects: vec![("/src/api".into(), 1.5), ("/src/web".into(), 2.0)],
+            ..Default::default()
+        };
+        assert_eq!(out.project("/src/api"), 1.5);
+        assert_eq!(out.project("/src/**"), 3.5);
+        assert_eq!(out.project("/docs"), 0.0);
+    }
+
+    #[test]
+    fn exclude_only_touches_matching_dimensions() {
+        let mut out = Outstanding {
+            global: 5.0,
+            agent: 3.0,
+            session: 2.0,
+            team: 4.0,
+            projects: vec![("/src/api".into(), 1.5), ("/src/web".into(), 2.0)],
+        };
+        out.exclude(&reservation(150), Some("a1"), Some("s1"), Some("t2"));
+        assert_eq!(out.global, 3.5);
+        assert_eq!(out.agent, 1.5);
+        assert_eq!(out.session, 2.0);
+        assert_eq!(out.team, 4.0);
+        assert_eq!(out.project("/src/api"), 0.0);
+        assert_eq!(out.project("/src/**"), 2.0);
+    }
+
+    #[test]
+    fn exclude_never_goes_negative() {
+        let mut out = Outstanding::default();
+        out.exclude(&reservation(500), Some("a1"), None, Some("t1"));
+        assert_eq!(out, Outstanding::default());
+    }
+}
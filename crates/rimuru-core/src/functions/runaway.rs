@@ -1,31 +1,47 @@
+use std::collections::HashMap;
+
 use iii_sdk::{III, RegisterFunctionMessage, TriggerRequest};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use uuid::Uuid;
 
+use super::detectors::{
+    self, DetectorInput, DetectorRegistry, DetectorSettings, Finding, PluginDetector, Scoring,
+};
+use super::downgrade;
+use super::guard::{self, Containment, GuardRecord};
 use super::sysutil::{api_response, extract_input, kv_err, require_str};
 use super::webhook::{load_webhook_url, post_webhook};
-use crate::models::{ContextBreakdown, Session, SessionStatus, TurnRecord};
+use crate::models::{
+    ContextBreakdown, ModelInfo, Session, SessionStatus, ToolCallRecord, TurnRecord,
+};
 use crate::state::StateKV;
-use chrono::Utc;
+use crate::triggers::schedules;
+use chrono::{DateTime, Duration, Utc};
 
 #[derive(Debug, Serialize, Deserialize)]
-struct RunawayPattern {
-    pattern_type: String,
-    description: String,
-    severity: f64,
-    metadata: Value,
+pub struct RunawayPattern {
+    pub pattern_type: String,
+    pub description: String,
+    pub severity: f64,
+    pub metadata: Value,
+    /// `turn_index` of the first and last turn the pattern covers, when the
+    /// detector can place it.
+    #[serde(default)]
+    pub start_turn: Option<u32>,
+    #[serde(default)]
+    pub end_turn: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct RunawayAnalysis {
-    session_id: Uuid,
-    is_runaway: bool,
-    severity: f64,
-    patterns: Vec<RunawayPattern>,
-    tokens_burned: u64,
-    estimated_cost_wasted: f64,
-    recommendation: String,
+pub struct RunawayAnalysis {
+    pub session_id: Uuid,
+    pub is_runaway: bool,
+    pub severity: f64,
+    pub patterns: Vec<RunawayPattern>,
+    pub tokens_burned: u64,
+    pub estimated_cost_wasted: f64,
+    pub recommendation: String,
 }
 
 pub fn register(iii: &III, kv: &StateKV) {
@@ -34,47 +50,177 @@ pub fn register(iii: &III, kv: &StateKV) {
     register_configure(iii, kv);
 }
 
-struct RunawayConfig {
-    repeat_threshold: u32,
-    token_explosion_ratio: f64,
+/// What `rimuru.runaway.scan` does beyond alerting when a flagged session
+/// runs under `rimuru guard`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RunawayAction {
+    Alert,
+    KillGuarded,
+    PauseGuarded,
+}
+
+impl RunawayAction {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "alert" => Some(Self::Alert),
+            "kill_guarded" => Some(Self::KillGuarded),
+            "pause_guarded" => Some(Self::PauseGuarded),
+            _ => None,
+        }
+    }
+
+    fn containment(self) -> Option<Containment> {
+        match self {
+            Self::Alert => None,
+            Self::KillGuarded => Some(Containment::Kill),
+            Self::PauseGuarded => Some(Containment::Pause),
+        }
+    }
+}
+
+pub struct RunawayConfig {
+    /// Turns the structural detectors look at, counted from the end.
+    pub window: usize,
+    pub repeat_threshold: u32,
+    pub token_explosion_ratio: f64,
+    /// Spend rate in dollars per minute that counts as runaway on its own;
+    /// 0 disables the absolute check.
+    pub velocity_limit: f64,
+    /// Multiple of the session's earlier spend rate that counts as a spike;
+    /// 0 disables the baseline check.
+    pub velocity_multiple: f64,
+    /// Length of the sliding window the spend rate is measured over.
+    pub velocity_window_secs: i64,
+    /// Pricing for per-turn costs, from the `model_info` catalog.
+    pub catalog: Vec<ModelInfo>,
+    /// Per-detector overrides by detector id; missing ids use defaults.
+    pub detectors: HashMap<String, DetectorSettings>,
+    pub scoring: Scoring,
+    /// Combined severity at or above which a session counts as runaway.
+    pub severity_cutoff: f64,
+    action: RunawayAction,
+    /// Minimum severity before `action` touches a guarded process.
+    action_severity: f64,
 }
 
 impl Default for RunawayConfig {
     fn default() -> Self {
         Self {
+            window: 10,
             repeat_threshold: 3,
             token_explosion_ratio: 2.0,
+            velocity_limit: 1.0,
+            velocity_multiple: 4.0,
+            velocity_window_secs: 300,
+            catalog: super::models::hardcoded_models(),
+            detectors: HashMap::new(),
+            scoring: Scoring::Max,
+            severity_cutoff: 0.5,
+            action: RunawayAction::Alert,
+            action_severity: 0.8,
         }
     }
 }
 
-async fn load_runaway_config(kv: &StateKV) -> RunawayConfig {
+impl RunawayConfig {
+    pub fn detector(&self, id: &str) -> DetectorSettings {
+        self.detectors.get(id).copied().unwrap_or_default()
+    }
+}
+
+/// `rimuru.runaway.configure` input keys and the config keys they set.
+/// Longest accepted `velocity_window_secs`: a week.
+const MAX_VELOCITY_WINDOW_SECS: i64 = 7 * 24 * 3600;
+
+pub(crate) const CONFIG_KEYS: &[(&str, &str)] = &[
+    ("window", "runaway_window"),
+    ("repeat_threshold", "runaway_repeat_threshold"),
+    ("token_explosion_ratio", "runaway_token_explosion_ratio"),
+    ("auto_scan_enabled", "runaway_auto_scan_enabled"),
+    ("scan_cron", "runaway_scan_cron"),
+    ("action", "runaway_action"),
+    ("action_severity", "runaway_action_severity"),
+    ("velocity_limit", "runaway_velocity_limit"),
+    ("velocity_multiple", "runaway_velocity_multiple"),
+    ("velocity_window_secs", "runaway_velocity_window_secs"),
+    ("scoring", "runaway_scoring"),
+    ("severity_cutoff", "runaway_severity_cutoff"),
+    ("detectors", "runaway_detectors"),
+];
+
+pub(crate) async fn load_runaway_config(kv: &StateKV) -> RunawayConfig {
     let mut cfg = RunawayConfig::default();
 
-    if let Ok(Some(v)) = kv.get::<Value>("config", "runaway_repeat_threshold").await
-        && let Some(n) = v.as_u64()
-    {
-        cfg.repeat_threshold = n as u32;
+    if let Ok(catalog) = downgrade::load_catalog(kv).await {
+        cfg.catalog = catalog;
     }
 
-    if let Ok(Some(v)) = kv
-        .get::<Value>("config", "runaway_token_explosion_ratio")
-        .await
-        && let Some(n) = v.as_f64()
-    {
-        cfg.token_explosion_ratio = n;
+    for (_, config_key) in CONFIG_KEYS {
+        if let Ok(Some(v)) = kv.get::<Value>("config", config_key).await {
+            apply_setting(&mut cfg, config_key, &v);
+        }
     }
 
     cfg
 }
 
-async fn load_runaway_window(kv: &StateKV) -> usize {
-    if let Ok(Some(v)) = kv.get::<Value>("config", "runaway_window").await
-        && let Some(n) = v.as_u64()
-    {
-        return n as usize;
+/// Apply one stored config value. Keys that only steer scheduling are
+/// ignored here, as are values of the wrong type.
+pub(crate) fn apply_setting(cfg: &mut RunawayConfig, config_key: &str, v: &Value) {
+    match config_key {
+        "runaway_window" => {
+            if let Some(n) = v.as_u64() {
+                cfg.window = n as usize;
+            }
+        }
+        "runaway_repeat_threshold" => {
+            if let Some(n) = v.as_u64() {
+                cfg.repeat_threshold = n as u32;
+            }
+        }
+        "runaway_token_explosion_ratio" => {
+            if let Some(n) = v.as_f64() {
+                cfg.token_explosion_ratio = n;
+            }
+        }
+        "runaway_action" => {
+            if let Some(action) = v.as_str().and_then(RunawayAction::parse) {
+                cfg.action = action;
+            }
+        }
+        "runaway_action_severity" => {
+            if let Some(n) = v.as_f64() {
+                cfg.action_severity = n;
+            }
+        }
+        "runaway_velocity_limit" => {
+            if let Some(n) = v.as_f64() {
+                cfg.velocity_limit = n;
+            }
+        }
+        "runaway_velocity_multiple" => {
+            if let Some(n) = v.as_f64() {
+                cfg.velocity_multiple = n;
+            }
+        }
+        "runaway_velocity_window_secs" => {
+            if let Some(n) = v.as_i64().filter(|n| *n <= MAX_VELOCITY_WINDOW_SECS) {
+                cfg.velocity_window_secs = n;
+            }
+        }
+        "runaway_detectors" => cfg.detectors = detectors::parse_settings(v),
+        "runaway_scoring" => {
+            if let Some(scoring) = v.as_str().and_then(Scoring::parse) {
+                cfg.scoring = scoring;
+            }
+        }
+        "runaway_severity_cutoff" => {
+            if let Some(n) = v.as_f64() {
+                cfg.severity_cutoff = n;
+            }
+        }
+        _ => {}
     }
-    10
 }
 
 async fn load_runaway_cooldown_secs(kv: &StateKV) -> i64 {
@@ -111,24 +257,62 @@ async fn cooldown_mark_fired(kv: &StateKV, session_id: &str) {
     }
 }
 
-fn analyze_turns(session_id: Uuid, turns: &[TurnRecord], cfg: &RunawayConfig) -> RunawayAnalysis {
-    let mut patterns: Vec<RunawayPattern> = Vec::new();
+/// Run the built-in detectors over a session's turns. Structural detectors
+/// see the last `cfg.window` turns; cost velocity takes its baseline from up
+/// to `detectors::HISTORY_TURNS` of them.
+pub fn analyze_turns(
+    session_id: Uuid,
+    history: &[TurnRecord],
+    cfg: &RunawayConfig,
+) -> RunawayAnalysis {
+    analyze_with(&DetectorRegistry::builtin(), session_id, history, cfg)
+}
 
-    detect_repeated_calls(turns, cfg.repeat_threshold, &mut patterns);
-    detect_repeated_errors(turns, cfg.repeat_threshold, &mut patterns);
-    detect_token_explosion(turns, cfg.token_explosion_ratio, &mut patterns);
-    detect_oscillation(turns, &mut patterns);
+/// Like `analyze_turns`, with a caller-supplied set of in-process detectors.
+pub fn analyze_with(
+    registry: &DetectorRegistry,
+    session_id: Uuid,
+    history: &[TurnRecord],
+    cfg: &RunawayConfig,
+) -> RunawayAnalysis {
+    let input = DetectorInput::new(session_id, history, cfg.window);
+    let findings = registry.run(&input, cfg);
+    score(&input, findings, cfg)
+}
 
-    let severity = patterns.iter().map(|p| p.severity).fold(0.0_f64, f64::max);
-    let is_runaway = severity >= 0.5;
+/// Built-ins plus the detectors plugins registered as iii functions.
+async fn analyze_session(
+    kv: &StateKV,
+    plugins: &[PluginDetector],
+    session_id: Uuid,
+    history: &[TurnRecord],
+    cfg: &RunawayConfig,
+) -> RunawayAnalysis {
+    let input = DetectorInput::new(session_id, history, cfg.window);
+    let mut findings = DetectorRegistry::builtin().run(&input, cfg);
+    detectors::run_plugins(kv, plugins, &input, cfg, &mut findings).await;
+    score(&input, findings, cfg)
+}
 
-    let tokens_burned: u64 = if is_runaway {
-        turns.iter().map(|t| t.input_tokens + t.output_tokens).sum()
+fn score(
+    input: &DetectorInput<'_>,
+    findings: Vec<Finding>,
+    cfg: &RunawayConfig,
+) -> RunawayAnalysis {
+    let severity = cfg
+        .scoring
+        .combine(findings.iter().map(|f| f.weight * f.pattern.severity));
+    let is_runaway = severity >= cfg.severity_cutoff;
+
+    let wasted = if is_runaway {
+        wasted_turns(input, &decisive(&findings, cfg))
     } else {
-        0
+        &[]
     };
-
-    let estimated_cost_wasted = tokens_burned as f64 / 1_000_000.0 * 9.0;
+    // Same tokens `estimated_cost_wasted` prices, cache traffic included.
+    let tokens_burned: u64 = wasted.iter().map(turn_tokens).sum();
+    let estimated_cost_wasted: f64 = wasted.iter().map(|t| turn_cost(t, &cfg.catalog)).sum();
+    let patterns: Vec<RunawayPattern> = findings.into_iter().map(|f| f.pattern).collect();
 
     let recommendation = if is_runaway {
         if severity >= 0.8 {
@@ -142,7 +326,7 @@ fn analyze_turns(session_id: Uuid, turns: &[TurnRecord], cfg: &RunawayConfig) ->
     };
 
     RunawayAnalysis {
-        session_id,
+        session_id: input.session_id,
         is_runaway,
         severity,
         patterns,
@@ -152,7 +336,45 @@ fn analyze_turns(session_id: Uuid, turns: &[TurnRecord], cfg: &RunawayConfig) ->
     }
 }
 
-fn detect_repeated_calls(turns: &[TurnRecord], threshold: u32, patterns: &mut Vec<RunawayPattern>) {
+/// The findings that put the session over `severity_cutoff`: the strongest
+/// ones, taken in order until they reach it on their own. Weaker patterns
+/// that happen to be present don't move where the loop started.
+fn decisive<'f>(findings: &'f [Finding], cfg: &RunawayConfig) -> Vec<&'f RunawayPattern> {
+    let weighted = |f: &Finding| f.weight * f.pattern.severity;
+    let mut ranked: Vec<&Finding> = findings.iter().collect();
+    ranked.sort_by(|a, b| weighted(b).total_cmp(&weighted(a)));
+    let mut taken = Vec::new();
+    for finding in ranked {
+        taken.push(finding);
+        if cfg.scoring.combine(taken.iter().map(|f| weighted(f))) >= cfg.severity_cutoff {
+            break;
+        }
+    }
+    taken.into_iter().map(|f| &f.pattern).collect()
+}
+
+/// Turns spent inside the loop: everything from the earliest start among
+/// `patterns` onwards. Patterns that cannot place themselves, such as
+/// plugin findings without a span, leave the whole window counted.
+fn wasted_turns<'a>(input: &DetectorInput<'a>, patterns: &[&RunawayPattern]) -> &'a [TurnRecord] {
+    match patterns.iter().filter_map(|p| p.start_turn).min() {
+        Some(start) => {
+            let from = input
+                .history
+                .iter()
+                .position(|t| t.turn_index >= start)
+                .unwrap_or(input.history.len());
+            &input.history[from..]
+        }
+        None => input.window,
+    }
+}
+
+pub(super) fn detect_repeated_calls(
+    turns: &[TurnRecord],
+    threshold: u32,
+    patterns: &mut Vec<RunawayPattern>,
+) {
     if turns.len() < 3 {
         return;
     }
@@ -160,24 +382,24 @@ fn detect_repeated_calls(turns: &[TurnRecord], threshold: u32, patterns: &mut Ve
     let mut max_streak = 1u32;
     let mut streak = 1u32;
     let mut streak_tool = String::new();
+    let mut start = 0usize;
+    let mut span = (0usize, 0usize);
 
-    for window in turns.windows(2) {
-        let prev_tools: Vec<&str> = window[0]
-            .tool_calls
-            .iter()
-            .map(|t| t.tool_name.as_str())
-            .collect();
-        let curr_tools: Vec<&str> = window[1]
-            .tool_calls
-            .iter()
-            .map(|t| t.tool_name.as_str())
-            .collect();
-
-        if !prev_tools.is_empty() && prev_tools == curr_tools {
+    for (pos, window) in turns.windows(2).enumerate() {
+        if same_calls(&window[0].tool_calls, &window[1].tool_calls) {
+            if streak == 1 {
+                start = pos;
+            }
             streak += 1;
             if streak > max_streak {
                 max_streak = streak;
-                streak_tool = prev_tools.join(", ");
+                span = (start, pos + 1);
+                streak_tool = window[0]
+                    .tool_calls
+                    .iter()
+                    .map(|t| t.tool_name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
             }
         } else {
             streak = 1;
@@ -194,11 +416,124 @@ fn detect_repeated_calls(turns: &[TurnRecord], threshold: u32, patterns: &mut Ve
             ),
             severity,
             metadata: json!({"tool": streak_tool, "count": max_streak}),
+            start_turn: Some(turns[span.0].turn_index),
+            end_turn: Some(turns[span.1].turn_index),
         });
     }
 }
 
-fn detect_repeated_errors(
+/// Whether two turns made the same calls. Calls only match on arguments
+/// when both carry a fingerprint, so turns from adapters that do not record
+/// one still compare by tool name.
+fn same_calls(a: &[ToolCallRecord], b: &[ToolCallRecord]) -> bool {
+    !a.is_empty()
+        && a.len() == b.len()
+        && a.iter().zip(b).all(|(x, y)| {
+            x.tool_name == y.tool_name
+                && match (&x.arg_fingerprint, &y.arg_fingerprint) {
+                    (Some(fx), Some(fy)) => fx == fy,
+                    _ => true,
+                }
+        })
+}
+
+/// Whether an edit to `path` can change what a call on `target` sees: the
+/// file itself, or anything under it when the target is a directory.
+fn edit_touches(target: &str, path: &str) -> bool {
+    let target = target.trim_end_matches('/');
+    path == target
+        || path
+            .strip_prefix(target)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// The same tool called with the same arguments more than `threshold`
+/// times in the window, whether or not the calls are back to back. Exact
+/// repeats rarely learn anything new, so severity climbs faster than for
+/// `repeated_calls` and crosses the runaway line on the first excess call.
+///
+/// A repeat after an edit it could observe is not a loop: edit, then
+/// `cargo test`, is how work gets done. An edit to a call's `target_path`
+/// restarts its count, and any edit restarts calls without one (shell and
+/// test commands, which may read anything).
+pub(super) fn detect_identical_calls(
+    turns: &[TurnRecord],
+    threshold: u32,
+    patterns: &mut Vec<RunawayPattern>,
+) {
+    // Each distinct call with its count since the last edit it could see,
+    // and the turns that run started and last repeated in.
+    let mut counts: Vec<(&ToolCallRecord, u32, u32, u32)> = Vec::new();
+    let mut best: Option<(&ToolCallRecord, u32, u32, u32)> = None;
+    for turn in turns {
+        for call in &turn.tool_calls {
+            if let Some(fp) = call.arg_fingerprint.as_deref() {
+                let idx = match counts.iter().position(|(c, ..)| {
+                    c.tool_name == call.tool_name && c.arg_fingerprint.as_deref() == Some(fp)
+                }) {
+                    Some(idx) => idx,
+                    None => {
+                        counts.push((call, 0, turn.turn_index, turn.turn_index));
+                        counts.len() - 1
+                    }
+                };
+                let entry = &mut counts[idx];
+                if entry.1 == 0 {
+                    entry.2 = turn.turn_index;
+                }
+                entry.1 += 1;
+                entry.3 = turn.turn_index;
+                if best.is_none_or(|(_, n, ..)| entry.1 > n) {
+                    best = Some(*entry);
+                }
+            }
+            for edit in &call.edits {
+                for entry in counts.iter_mut() {
+                    let seen = match entry.0.target_path.as_deref() {
+                        Some(target) => edit_touches(target, &edit.path),
+                        None => true,
+                    };
+                    if seen {
+                        entry.1 = 0;
+                    }
+                }
+            }
+        }
+    }
+
+    let Some((call, count, first, last)) = best else {
+        return;
+    };
+    if count <= threshold {
+        return;
+    }
+
+    let excess = (count - threshold) as f64;
+    let severity = (0.4 + 0.15 * excess).min(1.0);
+    let target = call
+        .target_path
+        .as_deref()
+        .map(|p| format!(" on {}", p))
+        .unwrap_or_default();
+    patterns.push(RunawayPattern {
+        pattern_type: "identical_calls".to_string(),
+        description: format!(
+            "Tool '{}' called {} times with identical arguments{}",
+            call.tool_name, count, target
+        ),
+        severity,
+        metadata: json!({
+            "tool": call.tool_name,
+            "fingerprint": call.arg_fingerprint,
+            "target_path": call.target_path,
+            "count": count,
+        }),
+        start_turn: Some(first),
+        end_turn: Some(last),
+    });
+}
+
+pub(super) fn detect_repeated_errors(
     turns: &[TurnRecord],
     threshold: u32,
     patterns: &mut Vec<RunawayPattern>,
@@ -207,6 +542,8 @@ fn detect_repeated_errors(
     let mut streak = 0u32;
     let mut last_signature: Option<String> = None;
     let mut max_signature: Option<String> = None;
+    let mut start = 0u32;
+    let mut span = (0u32, 0u32);
 
     for turn in turns {
         if turn.content_type.contains("error") {
@@ -215,11 +552,13 @@ fn detect_repeated_errors(
                 streak += 1;
             } else {
                 streak = 1;
+                start = turn.turn_index;
                 last_signature = Some(signature.clone());
             }
             if streak > max_streak {
                 max_streak = streak;
                 max_signature = Some(signature);
+                span = (start, turn.turn_index);
             }
         } else {
             streak = 0;
@@ -237,11 +576,13 @@ fn detect_repeated_errors(
                 "count": max_streak,
                 "signature": max_signature
             }),
+            start_turn: Some(span.0),
+            end_turn: Some(span.1),
         });
     }
 }
 
-fn detect_token_explosion(
+pub(super) fn detect_token_explosion(
     turns: &[TurnRecord],
     explosion_ratio: f64,
     patterns: &mut Vec<RunawayPattern>,
@@ -285,18 +626,24 @@ fn detect_token_explosion(
                 "last_3_avg": last_3_avg,
                 "baseline_avg": baseline_avg
             }),
+            start_turn: last_3.first().map(|t| t.turn_index),
+            end_turn: last_3.last().map(|t| t.turn_index),
         });
     }
 }
 
-fn detect_oscillation(turns: &[TurnRecord], patterns: &mut Vec<RunawayPattern>) {
+pub(super) fn detect_oscillation(turns: &[TurnRecord], patterns: &mut Vec<RunawayPattern>) {
     if turns.len() < 4 {
         return;
     }
 
-    let tool_sequence: Vec<String> = turns
+    let tool_sequence: Vec<(u32, &str)> = turns
         .iter()
-        .filter_map(|t| t.tool_calls.first().map(|tc| tc.tool_name.clone()))
+        .filter_map(|t| {
+            t.tool_calls
+                .first()
+                .map(|tc| (t.turn_index, tc.tool_name.as_str()))
+        })
         .collect();
 
     if tool_sequence.len() < 4 {
@@ -304,18 +651,19 @@ fn detect_oscillation(turns: &[TurnRecord], patterns: &mut Vec<RunawayPattern>)
     }
 
     let mut max_oscillation = 0u32;
+    let mut span = (0u32, 0u32);
 
     for i in 0..tool_sequence.len().saturating_sub(3) {
-        let a = &tool_sequence[i];
-        let b = &tool_sequence[i + 1];
+        let a = tool_sequence[i].1;
+        let b = tool_sequence[i + 1].1;
         if a == b {
             continue;
         }
 
         let mut count = 2u32;
-        for (offset, tool) in tool_sequence.iter().enumerate().skip(i + 2) {
+        for (offset, (_, tool)) in tool_sequence.iter().enumerate().skip(i + 2) {
             let expected = if (offset - i) % 2 == 0 { a } else { b };
-            if tool == expected {
+            if *tool == expected {
                 count += 1;
             } else {
                 break;
@@ -324,6 +672,7 @@ fn detect_oscillation(turns: &[TurnRecord], patterns: &mut Vec<RunawayPattern>)
 
         if count > max_oscillation {
             max_oscillation = count;
+            span = (tool_sequence[i].0, tool_sequence[i + count as usize - 1].0);
         }
     }
 
@@ -334,6 +683,260 @@ fn detect_oscillation(turns: &[TurnRecord], patterns: &mut Vec<RunawayPattern>)
             description: format!("Tools oscillating back and forth {} times", max_oscillation),
             severity,
             metadata: json!({"count": max_oscillation}),
+            start_turn: Some(span.0),
+            end_turn: Some(span.1),
+        });
+    }
+}
+
+/// Every token a turn moved, cache reads and writes included.
+fn turn_tokens(turn: &TurnRecord) -> u64 {
+    turn.input_tokens + turn.output_tokens + turn.cache_read + turn.cache_write
+}
+
+/// Dollar cost of one turn at catalog prices. Cache reads and writes use
+/// their own rates when the catalog has them. Models missing from the
+/// catalog fall back to a blended $9 per million tokens.
+pub(crate) fn turn_cost(turn: &TurnRecord, catalog: &[ModelInfo]) -> f64 {
+    let per_million = |tokens: u64, price: f64| tokens as f64 / 1_000_000.0 * price;
+    match turn
+        .model
+        .as_deref()
+        .and_then(|m| downgrade::resolve(catalog, m))
+    {
+        Some(m) => {
+            per_million(turn.input_tokens, m.input_price_per_million)
+                + per_million(turn.output_tokens, m.output_price_per_million)
+                + per_million(
+                    turn.cache_read,
+                    m.cache_read_price_per_million
+                        .unwrap_or(m.input_price_per_million),
+                )
+                + per_million(
+                    turn.cache_write,
+                    m.cache_write_price_per_million
+                        .unwrap_or(m.input_price_per_million),
+                )
+        }
+        None => per_million(turn_tokens(turn), 9.0),
+    }
+}
+
+/// Spend rate over the last `velocity_window_secs` of the session, in
+/// dollars per minute, against the absolute limit and against the rate the
+/// session ran at before that window. The baseline is measured over active
+/// time and only counts once the session has at least one full window of it.
+pub(super) fn detect_cost_velocity(
+    turns: &[TurnRecord],
+    cfg: &RunawayConfig,
+    patterns: &mut Vec<RunawayPattern>,
+) {
+    if cfg.velocity_window_secs <= 0 {
+        return;
+    }
+    let timed: Vec<(DateTime<Utc>, f64, u32)> = turns
+        .iter()
+        .filter_map(|t| {
+            let at = DateTime::parse_from_rfc3339(t.timestamp.as_deref()?).ok()?;
+            Some((
+                at.with_timezone(&Utc),
+                turn_cost(t, &cfg.catalog),
+                t.turn_index,
+            ))
+        })
+        .collect();
+    let Some(&(now, _, last_turn)) = timed.last() else {
+        return;
+    };
+
+    let Some(window_start) = Duration::try_seconds(cfg.velocity_window_secs)
+        .and_then(|window| now.checked_sub_signed(window))
+    else {
+        return;
+    };
+    let window_minutes = cfg.velocity_window_secs as f64 / 60.0;
+    let window_cost: f64 = timed
+        .iter()
+        .filter(|(at, ..)| *at > window_start)
+        .map(|(_, c, _)| c)
+        .sum();
+    let first_in_window = timed
+        .iter()
+        .find(|(at, ..)| *at > window_start)
+        .map(|(_, _, turn)| *turn);
+    let rate = window_cost / window_minutes;
+
+    // Idle stretches (lunch, an overnight pause) are not spend time: each
+    // gap between turns counts for at most one window.
+    let baseline_secs: i64 = timed
+        .windows(2)
+        .filter(|pair| pair[0].0 < window_start)
+        .map(|pair| {
+            let gap = pair[1].0.min(window_start) - pair[0].0;
+            gap.num_seconds().clamp(0, cfg.velocity_window_secs)
+        })
+        .sum();
+    let baseline_minutes = baseline_secs as f64 / 60.0;
+    let baseline = (baseline_minutes >= window_minutes).then(|| {
+        let cost: f64 = timed
+            .iter()
+            .filter(|(at, ..)| *at <= window_start)
+            .map(|(_, c, _)| c)
+            .sum();
+        cost / baseline_minutes
+    });
+
+    let mut tripped: Vec<(&str, f64)> = Vec::new();
+    if cfg.velocity_limit > 0.0 && rate >= cfg.velocity_limit {
+        tripped.push(("absolute", cfg.velocity_limit));
+    }
+    if let Some(base) = baseline
+        && base > 0.0
+        && cfg.velocity_multiple > 0.0
+        && rate >= base * cfg.velocity_multiple
+    {
+        tripped.push(("baseline", base * cfg.velocity_multiple));
+    }
+    let Some(&(trigger, threshold)) = tripped.iter().min_by(|a, b| a.1.total_cmp(&b.1)) else {
+        return;
+    };
+
+    let ratio = rate / threshold;
+    let severity = (0.5 + 0.25 * (ratio - 1.0)).min(1.0);
+    patterns.push(RunawayPattern {
+        pattern_type: "cost_velocity".to_string(),
+        description: format!(
+            "Spending ${:.2}/min over the last {} min (threshold ${:.2}/min)",
+            rate,
+            window_minutes.round(),
+            threshold
+        ),
+        severity,
+        metadata: json!({
+            "dollars_per_minute": rate,
+            "baseline_per_minute": baseline,
+            "threshold_per_minute": threshold,
+            "trigger": trigger,
+            "window_secs": cfg.velocity_window_secs,
+            "window_cost": window_cost,
+        }),
+        start_turn: first_in_window,
+        end_turn: Some(last_turn),
+    });
+}
+
+/// Tell `threshold_exceeded` hooks about a cost-velocity finding, at most
+/// once per cooldown for each session.
+async fn dispatch_velocity_hook(kv: &StateKV, analysis: &RunawayAnalysis, cooldown_secs: i64) {
+    let Some(pattern) = analysis
+        .patterns
+        .iter()
+        .find(|p| p.pattern_type == "cost_velocity")
+    else {
+        return;
+    };
+    let cooldown_key = format!("velocity.{}", analysis.session_id);
+    if !cooldown_should_fire(kv, &cooldown_key, cooldown_secs).await {
+        return;
+    }
+    let result = kv
+        .iii()
+        .trigger(TriggerRequest {
+            function_id: "rimuru.hooks.dispatch".to_string(),
+            payload: json!({
+                "event_type": "threshold_exceeded",
+                "payload": {
+                    "metric": "runaway.cost_velocity",
+                    "value": pattern.metadata["dollars_per_minute"],
+                    "threshold": pattern.metadata["threshold_per_minute"],
+                    "session_id": analysis.session_id.to_string(),
+                    "severity": pattern.severity,
+                }
+            }),
+            action: None,
+            timeout_ms: Some(5000),
+        })
+        .await;
+    match result {
+        Ok(_) => cooldown_mark_fired(kv, &cooldown_key).await,
+        Err(e) => tracing::warn!("failed to dispatch threshold_exceeded event: {}", e),
+    }
+}
+
+/// Edit history of one file within the window.
+#[derive(Default)]
+struct FileVersions {
+    /// Content hashes in the order they appeared, with the position of the
+    /// turn that produced them.
+    seen: Vec<(String, usize)>,
+    cycles: u32,
+    /// First and last turn position of the reverted work.
+    span: Option<(usize, usize)>,
+}
+
+/// A file whose content keeps coming back to an earlier version: edit, run
+/// the tests, revert, re-apply. Every edit moves the file from its `before`
+/// text to its `after` text, and landing on a version already seen counts
+/// as one cycle. Tokens spent from first leaving that version until coming
+/// back to it are reported as wasted.
+pub(super) fn detect_edit_ping_pong(turns: &[TurnRecord], patterns: &mut Vec<RunawayPattern>) {
+    let mut files: Vec<(&str, FileVersions)> = Vec::new();
+
+    for (pos, turn) in turns.iter().enumerate() {
+        for edit in turn.tool_calls.iter().flat_map(|c| &c.edits) {
+            let idx = match files.iter().position(|(path, _)| *path == edit.path) {
+                Some(idx) => idx,
+                None => {
+                    files.push((&edit.path, FileVersions::default()));
+                    files.len() - 1
+                }
+            };
+            let file = &mut files[idx].1;
+
+            if let Some(before) = &edit.before
+                && file.seen.last().map(|(v, _)| v) != Some(before)
+            {
+                file.seen.push((before.clone(), pos));
+            }
+            if file.seen.last().map(|(v, _)| v) == Some(&edit.after) {
+                continue;
+            }
+            if let Some(&(_, since)) = file.seen.iter().find(|(v, _)| *v == edit.after) {
+                file.cycles += 1;
+                file.span = Some(match file.span {
+                    Some((start, end)) => (start.min(since), end.max(pos)),
+                    None => (since, pos),
+                });
+            }
+            file.seen.push((edit.after.clone(), pos));
+        }
+    }
+
+    files.sort_by_key(|(_, f)| std::cmp::Reverse(f.cycles));
+    for (path, file) in files {
+        let Some((start, end)) = file.span else {
+            continue;
+        };
+        let wasted_tokens: u64 = turns[start..=end].iter().map(turn_tokens).sum();
+        let severity = (0.2 + 0.25 * file.cycles as f64).min(1.0);
+        patterns.push(RunawayPattern {
+            pattern_type: "edit_ping_pong".to_string(),
+            description: format!(
+                "{} returned to an earlier version {} time(s) across {} turns",
+                path,
+                file.cycles,
+                end - start + 1
+            ),
+            severity,
+            metadata: json!({
+                "file": path,
+                "cycles": file.cycles,
+                "wasted_tokens": wasted_tokens,
+                "first_turn": turns[start].turn_index,
+                "last_turn": turns[end].turn_index,
+            }),
+            start_turn: Some(turns[start].turn_index),
+            end_turn: Some(turns[end].turn_index),
         });
     }
 }
@@ -348,14 +951,11 @@ fn register_analyze(iii: &III, kv: &StateKV) {
                 let input = extract_input(input);
                 let session_id_str = require_str(&input, "session_id")?;
 
-                let configured_window = load_runaway_window(&kv).await;
-                let window = input
-                    .get("window")
-                    .and_then(|v| v.as_u64())
-                    .map(|n| n as usize)
-                    .unwrap_or(configured_window);
-
-                let cfg = load_runaway_config(&kv).await;
+                let mut cfg = load_runaway_config(&kv).await;
+                if let Some(n) = input.get("window").and_then(|v| v.as_u64()) {
+                    cfg.window = n as usize;
+                }
+                let window = cfg.window;
 
                 let session_id = session_id_str.parse::<Uuid>().map_err(|e| {
                     iii_sdk::IIIError::Handler(format!("invalid session_id: {}", e))
@@ -387,7 +987,9 @@ fn register_analyze(iii: &III, kv: &StateKV) {
                     &turns
                 };
 
-                let analysis = analyze_turns(session_id, windowed, &cfg);
+                let plugins = detectors::load_plugin_detectors(&kv).await;
+                let analysis = analyze_session(&kv, &plugins, session_id, &turns, &cfg).await;
+                dispatch_velocity_hook(&kv, &analysis, load_runaway_cooldown_secs(&kv).await).await;
 
                 if analysis.is_runaway
                     && let Some(url) = load_webhook_url(&kv, "webhooks.runaway_url").await
@@ -422,26 +1024,35 @@ fn register_scan(iii: &III, kv: &StateKV) {
             async move {
                 let input = extract_input(input);
 
-                let configured_window = load_runaway_window(&kv).await;
-                let window = input
-                    .get("window")
-                    .and_then(|v| v.as_u64())
-                    .map(|n| n as usize)
-                    .unwrap_or(configured_window);
-
-                let cfg = load_runaway_config(&kv).await;
+                let mut cfg = load_runaway_config(&kv).await;
+                if let Some(n) = input.get("window").and_then(|v| v.as_u64()) {
+                    cfg.window = n as usize;
+                }
+                let window = cfg.window;
                 let cooldown_secs = load_runaway_cooldown_secs(&kv).await;
                 let webhook_url = load_webhook_url(&kv, "webhooks.runaway_url").await;
 
+                let plugins = detectors::load_plugin_detectors(&kv).await;
+                // The live tail rescans just the session it saw change.
+                let only = input.get("session_id").and_then(|v| v.as_str());
                 let sessions: Vec<Session> = kv.list("sessions").await.map_err(kv_err)?;
                 let active: Vec<&Session> = sessions
                     .iter()
                     .filter(|s| matches!(s.status, SessionStatus::Active))
+                    .filter(|s| only.is_none_or(|id| s.id.to_string() == id))
                     .collect();
 
                 let mut flagged: Vec<RunawayAnalysis> = Vec::new();
+                let mut contained: Vec<Value> = Vec::new();
                 let mut dispatch_tasks: tokio::task::JoinSet<()> = tokio::task::JoinSet::new();
 
+                let containment = cfg.action.containment();
+                let guards: Vec<GuardRecord> = if containment.is_some() {
+                    kv.list("guards").await.map_err(kv_err)?
+                } else {
+                    Vec::new()
+                };
+
                 for session in &active {
                     let sid = session.id.to_string();
                     let breakdown: Option<ContextBreakdown> =
@@ -457,31 +1068,68 @@ fn register_scan(iii: &III, kv: &StateKV) {
                         &b.turns
                     };
 
-                    let analysis = analyze_turns(session.id, turns, &cfg);
+                    let analysis = analyze_session(&kv, &plugins, session.id, &b.turns, &cfg).await;
                     if !analysis.is_runaway {
                         continue;
                     }
+                    dispatch_velocity_hook(&kv, &analysis, cooldown_secs).await;
 
-                    if let Some(url) = webhook_url.as_ref()
+                    let target = containment
+                        .filter(|_| analysis.severity >= cfg.action_severity)
+                        .and_then(|how| Some((how, guard::guard_for_session(&guards, session)?)))
+                        .filter(|(how, g)| g.intervention.as_deref() != Some(how.intervention()));
+
+                    if (webhook_url.is_some() || target.is_some())
                         && cooldown_should_fire(&kv, &sid, cooldown_secs).await
                     {
                         cooldown_mark_fired(&kv, &sid).await;
-                        let tool_count: usize = turns.iter().map(|t| t.tool_calls.len()).sum();
-                        let payload = json!({
-                            "event": "runaway_detected",
-                            "agent": "",
-                            "session_id": sid,
-                            "tool_count": tool_count,
-                            "window_ms": 0,
-                            "severity": analysis.severity,
-                            "timestamp": Utc::now().to_rfc3339(),
-                        });
-                        let url = url.clone();
-                        let sid_for_log = sid.clone();
-                        dispatch_tasks.spawn(async move {
-                            post_webhook(&url, &payload).await;
-                            tracing::debug!("runaway webhook dispatched for {}", sid_for_log);
-                        });
+
+                        if let Some(url) = webhook_url.as_ref() {
+                            let tool_count: usize = turns.iter().map(|t| t.tool_calls.len()).sum();
+                            let payload = json!({
+                                "event": "runaway_detected",
+                                "agent": "",
+                                "session_id": sid,
+                                "tool_count": tool_count,
+                                "window_ms": 0,
+                                "severity": analysis.severity,
+                                "timestamp": Utc::now().to_rfc3339(),
+                            });
+                            let url = url.clone();
+                            let sid_for_log = sid.clone();
+                            dispatch_tasks.spawn(async move {
+                                post_webhook(&url, &payload).await;
+                                tracing::debug!("runaway webhook dispatched for {}", sid_for_log);
+                            });
+                        }
+
+                        if let Some((how, g)) = target {
+                            match guard::contain(&kv, g, how).await {
+                                Ok(()) => {
+                                    tracing::warn!(
+                                        "runaway session {} (severity {:.2}): {} guarded pid {}",
+                                        sid,
+                                        analysis.severity,
+                                        how.intervention(),
+                                        g.pid
+                                    );
+                                    contained.push(json!({
+                                        "session_id": sid,
+                                        "guard_id": g.id,
+                                        "pid": g.pid,
+                                        "action": how.intervention(),
+                                    }));
+                                }
+                                Err(e) => {
+                                    tracing::warn!(
+                                        "failed to contain runaway session {} (pid {}): {}",
+                                        sid,
+                                        g.pid,
+                                        e
+                                    );
+                                }
+                            }
+                        }
                     }
 
                     flagged.push(analysis);
@@ -549,6 +1197,7 @@ fn register_scan(iii: &III, kv: &StateKV) {
 
                 Ok(api_response(json!({
                     "flagged": flagged,
+                    "contained": contained,
                     "total_active_sessions": active.len(),
                     "total_flagged": flagged.len()
                 })))
@@ -557,6 +1206,111 @@ fn register_scan(iii: &III, kv: &StateKV) {
     );
 }
 
+/// Check a `configure` value before it is stored or tried out, returning
+/// what to store. `detectors` updates come back merged into the stored map.
+pub(crate) async fn validate_setting(
+    kv: &StateKV,
+    input_key: &str,
+    config_key: &str,
+    val: &Value,
+) -> Result<Value, iii_sdk::IIIError> {
+    let mut val = val.clone();
+    match input_key {
+        "window" | "repeat_threshold" => {
+            let n = val.as_u64().ok_or_else(|| {
+                iii_sdk::IIIError::Handler(format!("{} must be a positive integer", input_key))
+            })?;
+            if n == 0 {
+                return Err(iii_sdk::IIIError::Handler(format!(
+                    "{} must be > 0",
+                    input_key
+                )));
+            }
+        }
+        "token_explosion_ratio" => {
+            let n = val.as_f64().ok_or_else(|| {
+                iii_sdk::IIIError::Handler("token_explosion_ratio must be a number".into())
+            })?;
+            if !n.is_finite() || n <= 1.0 {
+                return Err(iii_sdk::IIIError::Handler(
+                    "token_explosion_ratio must be > 1.0".into(),
+                ));
+            }
+        }
+        "auto_scan_enabled" if !val.is_boolean() => {
+            return Err(iii_sdk::IIIError::Handler(
+                "auto_scan_enabled must be a boolean".into(),
+            ));
+        }
+        "scan_cron" => {
+            let expr = val
+                .as_str()
+                .ok_or_else(|| iii_sdk::IIIError::Handler("scan_cron must be a string".into()))?;
+            schedules::validate_cron(expr).map_err(iii_sdk::IIIError::Handler)?;
+        }
+        "action" if val.as_str().and_then(RunawayAction::parse).is_none() => {
+            return Err(iii_sdk::IIIError::Handler(
+                "action must be one of: alert, kill_guarded, pause_guarded".into(),
+            ));
+        }
+        "velocity_limit" | "velocity_multiple" => {
+            let n = val.as_f64().ok_or_else(|| {
+                iii_sdk::IIIError::Handler(format!("{} must be a number", input_key))
+            })?;
+            if !n.is_finite() || n < 0.0 {
+                return Err(iii_sdk::IIIError::Handler(format!(
+                    "{} must be >= 0 (0 disables it)",
+                    input_key
+                )));
+            }
+        }
+        "velocity_window_secs" => {
+            let n = val.as_u64().ok_or_else(|| {
+                iii_sdk::IIIError::Handler("velocity_window_secs must be a positive integer".into())
+            })?;
+            if n < 60 || n > MAX_VELOCITY_WINDOW_SECS as u64 {
+                return Err(iii_sdk::IIIError::Handler(format!(
+                    "velocity_window_secs must be between 60 and {}",
+                    MAX_VELOCITY_WINDOW_SECS
+                )));
+            }
+        }
+        "scoring" if val.as_str().and_then(Scoring::parse).is_none() => {
+            return Err(iii_sdk::IIIError::Handler(
+                "scoring must be one of: max, weighted_sum".into(),
+            ));
+        }
+        "severity_cutoff" => {
+            let n = val.as_f64().ok_or_else(|| {
+                iii_sdk::IIIError::Handler("severity_cutoff must be a number".into())
+            })?;
+            if !(n > 0.0 && n <= 1.0) {
+                return Err(iii_sdk::IIIError::Handler(
+                    "severity_cutoff must be in (0, 1]".into(),
+                ));
+            }
+        }
+        "detectors" => {
+            let stored: Option<Value> = kv.get("config", config_key).await.map_err(kv_err)?;
+            let known = detectors::known_ids(kv).await;
+            val = detectors::merge_settings(stored, &val, &known)
+                .map_err(iii_sdk::IIIError::Handler)?;
+        }
+        "action_severity" => {
+            let n = val.as_f64().ok_or_else(|| {
+                iii_sdk::IIIError::Handler("action_severity must be a number".into())
+            })?;
+            if !(0.0..=1.0).contains(&n) {
+                return Err(iii_sdk::IIIError::Handler(
+                    "action_severity must be between 0 and 1".into(),
+                ));
+            }
+        }
+        _ => {}
+    }
+    Ok(val)
+}
+
 fn register_configure(iii: &III, kv: &StateKV) {
     let kv = kv.clone();
     iii.register_function_with(
@@ -566,60 +1320,24 @@ fn register_configure(iii: &III, kv: &StateKV) {
             async move {
                 let input = extract_input(input);
 
-                let config_keys = [
-                    ("window", "runaway_window"),
-                    ("repeat_threshold", "runaway_repeat_threshold"),
-                    ("token_explosion_ratio", "runaway_token_explosion_ratio"),
-                    ("auto_scan_enabled", "runaway_auto_scan_enabled"),
-                ];
-
-                let has_updates = config_keys
+                let has_updates = CONFIG_KEYS
                     .iter()
                     .any(|(input_key, _)| input.get(*input_key).is_some());
 
                 if has_updates {
                     let mut updated: Vec<String> = Vec::new();
-                    for (input_key, config_key) in &config_keys {
+                    for (input_key, config_key) in CONFIG_KEYS {
                         let Some(val) = input.get(*input_key) else {
                             continue;
                         };
-                        match *input_key {
-                            "window" | "repeat_threshold" => {
-                                let n = val.as_u64().ok_or_else(|| {
-                                    iii_sdk::IIIError::Handler(format!(
-                                        "{} must be a positive integer",
-                                        input_key
-                                    ))
-                                })?;
-                                if n == 0 {
-                                    return Err(iii_sdk::IIIError::Handler(format!(
-                                        "{} must be > 0",
-                                        input_key
-                                    )));
-                                }
-                            }
-                            "token_explosion_ratio" => {
-                                let n = val.as_f64().ok_or_else(|| {
-                                    iii_sdk::IIIError::Handler(
-                                        "token_explosion_ratio must be a number".into(),
-                                    )
-                                })?;
-                                if !n.is_finite() || n <= 1.0 {
-                                    return Err(iii_sdk::IIIError::Handler(
-                                        "token_explosion_ratio must be > 1.0".into(),
-                                    ));
-                                }
-                            }
-                            "auto_scan_enabled" if !val.is_boolean() => {
-                                return Err(iii_sdk::IIIError::Handler(
-                                    "auto_scan_enabled must be a boolean".into(),
-                                ));
-                            }
-                            _ => {}
-                        }
-                        kv.set("config", config_key, val).await.map_err(kv_err)?;
+                        let val = validate_setting(&kv, input_key, config_key, val).await?;
+                        kv.set("config", config_key, &val).await.map_err(kv_err)?;
                         updated.push(config_key.to_string());
                     }
+                    if input.get("auto_scan_enabled").is_some() || input.get("scan_cron").is_some()
+                    {
+                        schedules::apply_config(kv.iii(), &kv).await;
+                    }
                     Ok(api_response(json!({
                         "updated": updated,
                         "count": updated.len()
@@ -629,7 +1347,16 @@ fn register_configure(iii: &III, kv: &StateKV) {
                         "runaway_window": 10,
                         "runaway_repeat_threshold": 3,
                         "runaway_token_explosion_ratio": 2.0,
-                        "runaway_auto_scan_enabled": false
+                        "runaway_auto_scan_enabled": false,
+                        "runaway_scan_cron": schedules::default_cron("rimuru.runaway.scan"),
+                        "runaway_action": "alert",
+                        "runaway_action_severity": 0.8,
+                        "runaway_velocity_limit": 1.0,
+                        "runaway_velocity_multiple": 4.0,
+                        "runaway_velocity_window_secs": 300,
+                        "runaway_scoring": "max",
+                        "runaway_severity_cutoff": 0.5,
+                        "runaway_detectors": {}
                     });
 
                     let mut config = serde_json::Map::new();
@@ -644,3 +1371,253 @@ fn register_configure(iii: &III, kv: &StateKV) {
         },
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::FileEdit;
+
+    fn read(turn_index: u32, file: &str) -> TurnRecord {
+        let input = json!({ "file_path": file });
+        TurnRecord {
+            turn_index,
+            role: "assistant".into(),
+            model: None,
+            input_tokens: 1000,
+            output_tokens: 50,
+            cache_read: 0,
+            cache_write: 0,
+            tool_calls: vec![ToolCallRecord {
+                tool_name: "Read".into(),
+                tool_id: None,
+                input_tokens_estimate: 10,
+                output_tokens_estimate: 0,
+                arg_fingerprint: Some(ToolCallRecord::fingerprint_args(&input)),
+                target_path: ToolCallRecord::target_path(&input),
+                edits: Vec::new(),
+            }],
+            timestamp: None,
+            content_type: "tool_use".into(),
+        }
+    }
+
+    fn pattern<'a>(a: &'a RunawayAnalysis, kind: &str) -> Option<&'a RunawayPattern> {
+        a.patterns.iter().find(|p| p.pattern_type == kind)
+    }
+
+    #[test]
+    fn exploratory_reads_are_not_a_loop() {
+        let turns: Vec<TurnRecord> = (0..10)
+            .map(|i| read(i, &format!("/src/mod_{}.rs", i)))
+            .collect();
+        let a = analyze_turns(Uuid::new_v4(), &turns, &RunawayConfig::default());
+        assert!(!a.is_runaway, "{:?}", a.patterns);
+    }
+
+    #[test]
+    fn rereading_one_file_is_flagged() {
+        // lib.rs keeps coming back between other reads, never back to back.
+        let files = ["/src/a.rs", "/src/lib.rs", "/src/b.rs", "/src/lib.rs"];
+        let turns: Vec<TurnRecord> = (0..10).map(|i| read(i, files[i as usize % 4])).collect();
+        let a = analyze_turns(Uuid::new_v4(), &turns, &RunawayConfig::default());
+        let p = pattern(&a, "identical_calls").expect("identical_calls");
+        assert_eq!(p.metadata["count"], 5);
+        assert_eq!(p.metadata["target_path"], "/src/lib.rs");
+        assert!(a.is_runaway);
+    }
+
+    #[test]
+    fn rereading_after_an_edit_to_the_file_starts_over() {
+        let edit = |turn_index: u32, file: &str| {
+            let input = json!({
+                "file_path": file,
+                "old_string": format!("v{}", turn_index),
+                "new_string": format!("v{}", turn_index + 1),
+            });
+            let mut t = read(turn_index, file);
+            t.tool_calls[0].tool_name = "Edit".into();
+            t.tool_calls[0].arg_fingerprint = Some(ToolCallRecord::fingerprint_args(&input));
+            t.tool_calls[0].edits = FileEdit::from_tool_call("Edit", &input);
+            t
+        };
+        // Read, fix, read again: each read sees a different file.
+        let turns: Vec<TurnRecord> = (0..12)
+            .map(|i| match i % 3 {
+                0 => read(i, "/src/lib.rs"),
+                1 => edit(i, "/src/lib.rs"),
+                _ => read(i, "/src/main.rs"),
+            })
+            .collect();
+        let a = analyze_turns(Uuid::new_v4(), &turns, &RunawayConfig::default());
+        // main.rs is never edited, so its rereads still count.
+        let p = pattern(&a, "identical_calls").expect("identical_calls");
+        assert_eq!(p.metadata["target_path"], "/src/main.rs");
+        assert_eq!(p.metadata["count"], 4);
+
+        assert!(edit_touches("/src", "/src/lib.rs"));
+        assert!(edit_touches("/src/", "/src/lib.rs"));
+        assert!(!edit_touches("/src", "/srcs/lib.rs"));
+    }
+
+    #[test]
+    fn waste_counts_loop_turns_at_their_model_price() {
+        // Four exploratory reads, then six Haiku turns re-reading lib.rs.
+        let turns: Vec<TurnRecord> = (0..10)
+            .map(|i| {
+                let mut t = read(i, &format!("/src/mod_{}.rs", i));
+                if i >= 4 {
+                    t = read(i, "/src/lib.rs");
+                    t.model = Some("claude-haiku-3-5".into());
+                }
+                t
+            })
+            .collect();
+        let a = analyze_turns(Uuid::new_v4(), &turns, &RunawayConfig::default());
+        assert!(a.is_runaway);
+        let p = pattern(&a, "repeated_calls").expect("repeated_calls");
+        assert_eq!((p.start_turn, p.end_turn), (Some(4), Some(9)));
+        assert_eq!(a.tokens_burned, 6 * 1050);
+        // 1000 input at $0.80/M plus 50 output at $4/M per turn.
+        assert!((a.estimated_cost_wasted - 0.006).abs() < 1e-9);
+    }
+
+    #[test]
+    fn waste_starts_where_the_decisive_pattern_does() {
+        let mut turns: Vec<TurnRecord> = (0..10).map(|i| read(i, "/src/lib.rs")).collect();
+        for t in &mut turns {
+            t.cache_read = 500;
+        }
+        let finding = |kind: &str, severity: f64, start: u32| Finding {
+            weight: 1.0,
+            pattern: RunawayPattern {
+                pattern_type: kind.into(),
+                description: String::new(),
+                severity,
+                metadata: json!({}),
+                start_turn: Some(start),
+                end_turn: Some(9),
+            },
+        };
+        let findings = vec![
+            finding("oscillation", 0.3, 1),
+            finding("repeated_calls", 0.7, 6),
+        ];
+        let input = DetectorInput::new(Uuid::nil(), &turns, 10);
+        let a = score(&input, findings, &RunawayConfig::default());
+
+        // The weak early pattern is reported but doesn't move the start.
+        assert_eq!(a.patterns.len(), 2);
+        assert_eq!(a.tokens_burned, 4 * 1550);
+    }
+
+    #[test]
+    fn calls_without_fingerprints_compare_by_name() {
+        let turns: Vec<TurnRecord> = (0..5)
+            .map(|i| {
+                let mut t = read(i, &format!("/src/{}.rs", i));
+                t.tool_calls[0].arg_fingerprint = None;
+                t
+            })
+            .collect();
+        let a = analyze_turns(Uuid::new_v4(), &turns, &RunawayConfig::default());
+        assert!(pattern(&a, "repeated_calls").is_some());
+        assert!(pattern(&a, "identical_calls").is_none());
+    }
+
+    /// One turn a minute for `steady` minutes at `steady_tokens`, then
+    /// `burst` more minutes at `burst_tokens`, all on an unpriced model.
+    fn timed(steady: u32, steady_tokens: u64, burst: u32, burst_tokens: u64) -> Vec<TurnRecord> {
+        let start = Utc::now() - Duration::hours(1);
+        (0..steady + burst)
+            .map(|i| {
+                let mut t = read(i, &format!("/src/mod_{}.rs", i));
+                t.input_tokens = if i < steady {
+                    steady_tokens
+                } else {
+                    burst_tokens
+                };
+                t.output_tokens = 0;
+                t.timestamp = Some((start + Duration::minutes(i as i64)).to_rfc3339());
+                t
+            })
+            .collect()
+    }
+
+    #[test]
+    fn turn_cost_prices_cache_traffic_separately() {
+        let catalog = super::super::models::hardcoded_models();
+        let mut t = read(0, "/src/lib.rs");
+        t.model = Some("claude-sonnet-4-6".into());
+        t.input_tokens = 0;
+        t.output_tokens = 0;
+        t.cache_read = 1_000_000;
+        assert!((turn_cost(&t, &catalog) - 0.3).abs() < 1e-9);
+        t.model = Some("unknown-model".into());
+        assert!((turn_cost(&t, &catalog) - 9.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn steady_spend_is_not_a_velocity_spike() {
+        // $0.09/min throughout.
+        let turns = timed(20, 10_000, 5, 10_000);
+        let a = analyze_turns(Uuid::new_v4(), &turns, &RunawayConfig::default());
+        assert!(pattern(&a, "cost_velocity").is_none());
+    }
+
+    #[test]
+    fn velocity_fires_on_absolute_rate() {
+        // Five $1.80 turns in the last five minutes, baseline check off.
+        let turns = timed(20, 10_000, 5, 200_000);
+        let cfg = RunawayConfig {
+            velocity_multiple: 0.0,
+            ..RunawayConfig::default()
+        };
+        let a = analyze_turns(Uuid::new_v4(), &turns, &cfg);
+        let p = pattern(&a, "cost_velocity").expect("cost_velocity");
+        assert_eq!(p.metadata["trigger"], "absolute");
+        assert!((p.metadata["dollars_per_minute"].as_f64().unwrap() - 1.8).abs() < 1e-9);
+        assert!(a.is_runaway);
+    }
+
+    #[test]
+    fn velocity_fires_on_baseline_multiple() {
+        // $0.45/min against a ~$0.09/min baseline, well under the absolute limit.
+        let turns = timed(20, 10_000, 5, 50_000);
+        let cfg = RunawayConfig::default();
+        let a = analyze_turns(Uuid::new_v4(), &turns, &cfg);
+        let p = pattern(&a, "cost_velocity").expect("cost_velocity");
+        assert_eq!(p.metadata["trigger"], "baseline");
+        assert!(p.metadata["baseline_per_minute"].as_f64().unwrap() < 0.1);
+
+        // An idle afternoon between the two halves does not dilute the
+        // baseline: the same rate after the break is no spike.
+        let mut resumed = timed(20, 10_000, 5, 10_000);
+        for t in &mut resumed[20..] {
+            let at = DateTime::parse_from_rfc3339(t.timestamp.as_deref().unwrap()).unwrap();
+            t.timestamp = Some((at + Duration::hours(4)).to_rfc3339());
+        }
+        let a = analyze_turns(Uuid::new_v4(), &resumed, &cfg);
+        assert!(pattern(&a, "cost_velocity").is_none(), "{:?}", a.patterns);
+
+        // Too little history for a baseline: only the absolute limit applies.
+        let short = timed(3, 10_000, 5, 50_000);
+        let a = analyze_turns(Uuid::new_v4(), &short, &cfg);
+        assert!(pattern(&a, "cost_velocity").is_none());
+
+        // A window too wide to subtract from a timestamp skips the detector
+        // instead of panicking, and is never loaded from config.
+        let wide = RunawayConfig {
+            velocity_window_secs: i64::MAX,
+            ..RunawayConfig::default()
+        };
+        let a = analyze_turns(Uuid::new_v4(), &turns, &wide);
+        assert!(pattern(&a, "cost_velocity").is_none());
+        let mut loaded = RunawayConfig::default();
+        apply_setting(
+            &mut loaded,
+            "runaway_velocity_window_secs",
+            &json!(i64::MAX),
+        );
+        assert_eq!(loaded.velocity_window_secs, 300);
+    }
+}
@@ -8,7 +8,9 @@
 //! Three iii functions are exposed:
 //!
 //! - `rimuru.sync.export` — read all installed agents, return canonical JSON
-//! - `rimuru.sync.import` — apply canonical JSON to every agent (dry-run by default)
+//! - `rimuru.sync.import` — apply canonical JSON to every agent (dry-run by default).
+//!   With `downgrade=true` each agent's default model is replaced by the
+//!   substitute `budget_action = downgrade` recommends for current spend.
 //! - `rimuru.sync.diff`   — show what would change between current state and a target
 //!
 //! Safety:
@@ -24,11 +26,12 @@ use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 use chrono::Utc;
-use iii_sdk::{III, RegisterFunctionMessage};
+use iii_sdk::{III, IIIError, RegisterFunctionMessage, TriggerRequest};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use tracing::warn;
 
+use super::downgrade::{self, Level};
 use super::sysutil::{api_response, extract_input};
 use crate::state::StateKV;
 
@@ -310,6 +313,10 @@ fn read_claude_code(content: &Value) -> SyncConfig {
     if let Some(instructions) = content.get("customInstructions").and_then(|v| v.as_str()) {
         cfg.custom_instructions = Some(instructions.to_string());
     }
+    if let Some(model) = content.get("model").and_then(|v| v.as_str()) {
+        cfg.model_preferences
+            .insert("default".into(), model.to_string());
+    }
     cfg
 }
 
@@ -340,6 +347,14 @@ fn write_claude_code(cfg: SyncConfig, existing: &Value) -> Value {
             out.remove("customInstructions");
         }
     }
+    match cfg.model_preferences.get("default") {
+        Some(default_model) => {
+            out.insert("model".into(), Value::String(default_model.clone()));
+        }
+        None => {
+            out.remove("model");
+        }
+    }
     Value::Object(out)
 }
 
@@ -635,115 +650,178 @@ fn register_diff(iii: &III, _kv: &StateKV) {
     );
 }
 
-fn register_import(iii: &III, _kv: &StateKV) {
+/// Ask `rimuru.budget.check` how far down the model ladder the current
+/// spend calls for. Only meaningful under `budget_action = downgrade`.
+async fn current_downgrade_level(kv: &StateKV) -> Result<Level, IIIError> {
+    let result = kv
+        .iii()
+        .trigger(TriggerRequest {
+            function_id: "rimuru.budget.check".to_string(),
+            payload: json!({}),
+            action: None,
+            timeout_ms: Some(5000),
+        })
+        .await
+        .map_err(|e| IIIError::Handler(format!("budget check failed: {}", e)))?;
+    let body = result.get("body").unwrap_or(&result);
+    body.get("downgrade")
+        .and_then(|d| d.get("level"))
+        .and_then(|v| v.as_str())
+        .and_then(Level::parse)
+        .ok_or_else(|| IIIError::Handler("downgrade requires budget_action = downgrade".into()))
+}
+
+fn register_import(iii: &III, kv: &StateKV) {
+    let kv = kv.clone();
     iii.register_function_with(
         RegisterFunctionMessage::with_id("rimuru.sync.import".to_string()),
-        move |input: Value| async move {
-            let input = extract_input(input);
-            let apply = input
-                .get("apply")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(false);
-            let canonical_value = input.get("canonical").cloned().ok_or_else(|| {
-                iii_sdk::IIIError::Handler("missing required field: canonical".into())
-            })?;
-            let canonical: SyncConfig = serde_json::from_value(canonical_value)
-                .map_err(|e| iii_sdk::IIIError::Handler(format!("invalid canonical: {}", e)))?;
-
-            let mut results = serde_json::Map::new();
-
-            // Tri-state load result that feeds the write gate below.
-            enum LoadState {
-                /// File existed and parsed — safe to overwrite (with backup).
-                Present(Value),
-                /// Agent not installed (file absent, dir may or may not exist).
-                NotInstalled,
-                /// Read or parse failure — refuse to write to avoid
-                /// clobbering a malformed-but-existing config.
-                Failed(String),
-            }
-
-            for agent in agent_table() {
-                let state = match load_config_file(&agent.config_file) {
-                    Ok(Some(v)) => LoadState::Present(v),
-                    Ok(None) => LoadState::NotInstalled,
-                    Err(e) => {
-                        warn!(
-                            "import: read failed for {} at {}: {}",
-                            agent.name,
-                            agent.config_file.display(),
-                            e
-                        );
-                        LoadState::Failed(e)
-                    }
+        move |input: Value| {
+            let kv = kv.clone();
+            async move {
+                let input = extract_input(input);
+                let apply = input
+                    .get("apply")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                let canonical_value = input.get("canonical").cloned().ok_or_else(|| {
+                    iii_sdk::IIIError::Handler("missing required field: canonical".into())
+                })?;
+                let canonical: SyncConfig = serde_json::from_value(canonical_value)
+                    .map_err(|e| iii_sdk::IIIError::Handler(format!("invalid canonical: {}", e)))?;
+
+                // With `downgrade`, each agent's default model is swapped for
+                // the substitute `rimuru.budget.check` would recommend right now.
+                let downgrade_level = if input
+                    .get("downgrade")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false)
+                {
+                    Some(current_downgrade_level(&kv).await?)
+                } else {
+                    None
                 };
-
-                // For dry-run and installed agents we still compute a
-                // diff (from an empty config when the file is absent,
-                // from the real content when it parsed). On a hard
-                // read failure we skip the diff entirely — the user
-                // can rerun after fixing the malformed file.
-                let (existing, installed, read_error) = match &state {
-                    LoadState::Present(v) => (v.clone(), true, None),
-                    LoadState::NotInstalled => (Value::Object(serde_json::Map::new()), false, None),
-                    LoadState::Failed(e) => (
-                        Value::Object(serde_json::Map::new()),
-                        false,
-                        Some(e.clone()),
-                    ),
+                let catalog = match downgrade_level {
+                    Some(level) if level != Level::None => downgrade::load_catalog(&kv).await?,
+                    _ => Vec::new(),
                 };
 
-                let current_cfg = (agent.read)(&existing);
-                let diff = diff_configs(&current_cfg, &canonical);
-                let new_value = (agent.write)(canonical.clone(), &existing);
-
-                let mut entry = json!({
-                    "config_file": agent.config_file.to_string_lossy(),
-                    "diff": diff,
-                });
-                if let Some(err) = read_error {
-                    entry["read_error"] = Value::String(err.clone());
+                let mut results = serde_json::Map::new();
+
+                // Tri-state load result that feeds the write gate below.
+                enum LoadState {
+                    /// File existed and parsed — safe to overwrite (with backup).
+                    Present(Value),
+                    /// Agent not installed (file absent, dir may or may not exist).
+                    NotInstalled,
+                    /// Read or parse failure — refuse to write to avoid
+                    /// clobbering a malformed-but-existing config.
+                    Failed(String),
                 }
 
-                // Write gate: apply mode + installed agent only.
-                // Refusing to write on failed reads prevents the
-                // synthetic-empty-config from clobbering a malformed
-                // real file. NotInstalled still writes in apply mode
-                // because bootstrapping a fresh config from an empty
-                // base is the documented behavior.
-                if !apply {
-                    entry["applied"] = Value::Bool(false);
-                    entry["reason"] = Value::String("dry_run".into());
-                } else if matches!(state, LoadState::Failed(_)) {
-                    entry["applied"] = Value::Bool(false);
-                    entry["reason"] = Value::String("read_error".into());
-                } else if !installed && agent.config_file.parent().is_none_or(|p| !p.exists()) {
-                    // Agent directory doesn't exist at all: not installed.
-                    entry["applied"] = Value::Bool(false);
-                    entry["reason"] = Value::String("agent_not_installed".into());
-                } else {
-                    match write_config_file(&agent.config_file, &new_value) {
-                        Ok(backup) => {
-                            entry["applied"] = Value::Bool(true);
-                            entry["backup_file"] = backup
-                                .map(|p| Value::String(p.to_string_lossy().into_owned()))
-                                .unwrap_or(Value::Null);
-                        }
+                for agent in agent_table() {
+                    let state = match load_config_file(&agent.config_file) {
+                        Ok(Some(v)) => LoadState::Present(v),
+                        Ok(None) => LoadState::NotInstalled,
                         Err(e) => {
-                            entry["applied"] = Value::Bool(false);
-                            entry["error"] = Value::String(e);
+                            warn!(
+                                "import: read failed for {} at {}: {}",
+                                agent.name,
+                                agent.config_file.display(),
+                                e
+                            );
+                            LoadState::Failed(e)
+                        }
+                    };
+
+                    // For dry-run and installed agents we still compute a
+                    // diff (from an empty config when the file is absent,
+                    // from the real content when it parsed). On a hard
+                    // read failure we skip the diff entirely — the user
+                    // can rerun after fixing the malformed file.
+                    let (existing, installed, read_error) = match &state {
+                        LoadState::Present(v) => (v.clone(), true, None),
+                        LoadState::NotInstalled => {
+                            (Value::Object(serde_json::Map::new()), false, None)
+                        }
+                        LoadState::Failed(e) => (
+                            Value::Object(serde_json::Map::new()),
+                            false,
+                            Some(e.clone()),
+                        ),
+                    };
+
+                    let current_cfg = (agent.read)(&existing);
+                    let mut target = canonical.clone();
+                    let mut substituted = None;
+                    if let Some(level) = downgrade_level {
+                        let from = canonical
+                            .model_preferences
+                            .get("default")
+                            .or_else(|| current_cfg.model_preferences.get("default"));
+                        if let Some(from) = from
+                            && let Some(to) = downgrade::substitute(&catalog, from, level)
+                        {
+                            substituted = Some(json!({ "from": from, "to": to.id }));
+                            target
+                                .model_preferences
+                                .insert("default".into(), to.id.clone());
                         }
                     }
+                    let diff = diff_configs(&current_cfg, &target);
+                    let new_value = (agent.write)(target, &existing);
+
+                    let mut entry = json!({
+                        "config_file": agent.config_file.to_string_lossy(),
+                        "diff": diff,
+                    });
+                    if let Some(sub) = substituted {
+                        entry["downgrade"] = sub;
+                    }
+                    if let Some(err) = read_error {
+                        entry["read_error"] = Value::String(err.clone());
+                    }
+
+                    // Write gate: apply mode + installed agent only.
+                    // Refusing to write on failed reads prevents the
+                    // synthetic-empty-config from clobbering a malformed
+                    // real file. NotInstalled still writes in apply mode
+                    // because bootstrapping a fresh config from an empty
+                    // base is the documented behavior.
+                    if !apply {
+                        entry["applied"] = Value::Bool(false);
+                        entry["reason"] = Value::String("dry_run".into());
+                    } else if matches!(state, LoadState::Failed(_)) {
+                        entry["applied"] = Value::Bool(false);
+                        entry["reason"] = Value::String("read_error".into());
+                    } else if !installed && agent.config_file.parent().is_none_or(|p| !p.exists()) {
+                        // Agent directory doesn't exist at all: not installed.
+                        entry["applied"] = Value::Bool(false);
+                        entry["reason"] = Value::String("agent_not_installed".into());
+                    } else {
+                        match write_config_file(&agent.config_file, &new_value) {
+                            Ok(backup) => {
+                                entry["applied"] = Value::Bool(true);
+                                entry["backup_file"] = backup
+                                    .map(|p| Value::String(p.to_string_lossy().into_owned()))
+                                    .unwrap_or(Value::Null);
+                            }
+                            Err(e) => {
+                                entry["applied"] = Value::Bool(false);
+                                entry["error"] = Value::String(e);
+                            }
+                        }
+                    }
+
+                    results.insert(agent.name.into(), entry);
                 }
 
-                results.insert(agent.name.into(), entry);
+                Ok(api_response(json!({
+                    "results": Value::Object(results),
+                    "applied": apply,
+                    "downgrade_level": downgrade_level.map(Level::as_str),
+                    "imported_at": Utc::now().to_rfc3339(),
+                })))
             }
-
-            Ok(api_response(json!({
-                "results": Value::Object(results),
-                "applied": apply,
-                "imported_at": Utc::now().to_rfc3339(),
-            })))
         },
     );
 }
@@ -6,7 +6,10 @@ use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use uuid::Uuid;
 
+use super::counters;
+use super::currency::{self, Converter, require_rate};
 use super::jwt::authorize;
+use super::period::{self, parse_timezone};
 use super::sysutil::{api_response, extract_input, kv_err, require_str};
 use crate::models::CostRecord;
 use crate::state::StateKV;
@@ -21,6 +24,14 @@ pub struct Team {
     pub created_at: DateTime<Utc>,
     #[serde(default)]
     pub budget_limit: Option<f64>,
+    /// IANA timezone overriding the global `timezone` for this team's
+    /// budget month.
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Currency `budget_limit` and the team reports are in, overriding
+    /// `budget_currency`.
+    #[serde(default)]
+    pub currency: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -186,11 +197,25 @@ fn register_create(iii: &III, kv: &StateKV) {
                     }
                 };
 
+                let timezone = match body.get("timezone").and_then(|v| v.as_str()) {
+                    Some(tz) if !tz.trim().is_empty() => {
+                        Some(parse_timezone(tz)?.name().to_string())
+                    }
+                    _ => None,
+                };
+
+                let currency = match body.get("currency").and_then(|v| v.as_str()) {
+                    Some(code) if !code.trim().is_empty() => Some(require_rate(&kv, code).await?),
+                    _ => None,
+                };
+
                 let team = Team {
                     id: Uuid::new_v4().to_string(),
                     name,
                     created_at: Utc::now(),
                     budget_limit,
+                    timezone,
+                    currency,
                 };
 
                 kv.set(TEAM_SCOPE, &team.id, &team).await.map_err(kv_err)?;
@@ -239,11 +264,25 @@ fn register_add_user(iii: &III, kv: &StateKV) {
     );
 }
 
-async fn load_members(kv: &StateKV, team_id: &str) -> Result<Vec<TeamMember>, IIIError> {
+pub(crate) async fn load_members(kv: &StateKV, team_id: &str) -> Result<Vec<TeamMember>, IIIError> {
     let all: Vec<TeamMember> = kv.list(TEAM_MEMBER_SCOPE).await.map_err(kv_err)?;
     Ok(all.into_iter().filter(|m| m.team_id == team_id).collect())
 }
 
+/// Team reports default to the team's own currency, then the display one.
+async fn team_converter(kv: &StateKV, body: &Value, team_id: &str) -> Result<Converter, IIIError> {
+    let tz = period::load_timezone(kv).await?;
+    let team: Option<Team> = kv.get(TEAM_SCOPE, team_id).await.map_err(kv_err)?;
+    if body.get("currency").is_none()
+        && let Some(team) = team
+        && let Some(code) = team.currency.as_deref()
+    {
+        let team_tz = counters::team_timezone(&team, tz);
+        return Converter::new(code, currency::load_table(kv).await?, team_tz);
+    }
+    currency::display_converter(kv, body, tz).await
+}
+
 fn register_costs(iii: &III, kv: &StateKV) {
     let kv = kv.clone();
     iii.register_function_with(
@@ -258,10 +297,14 @@ fn register_costs(iii: &III, kv: &StateKV) {
                 let until = parse_rfc3339(&body, "to")?;
 
                 let members = load_members(&kv, &team_id).await?;
-                let records: Vec<CostRecord> = kv.list("cost_records").await.map_err(kv_err)?;
+                let converter = team_converter(&kv, &body, &team_id).await?;
+                let mut records: Vec<CostRecord> = kv.list("cost_records").await.map_err(kv_err)?;
+                converter.apply(&mut records)?;
 
                 let agg = aggregate(&members, &records, &team_id, since, until);
-                Ok(api_response(json!(agg)))
+                let mut out = json!(agg);
+                out["currency"] = json!(converter.code);
+                Ok(api_response(out))
             }
         },
     );
@@ -281,7 +324,9 @@ fn register_leaderboard(iii: &III, kv: &StateKV) {
                 let until = parse_rfc3339(&body, "to")?;
 
                 let members = load_members(&kv, &team_id).await?;
-                let records: Vec<CostRecord> = kv.list("cost_records").await.map_err(kv_err)?;
+                let converter = team_converter(&kv, &body, &team_id).await?;
+                let mut records: Vec<CostRecord> = kv.list("cost_records").await.map_err(kv_err)?;
+                converter.apply(&mut records)?;
 
                 let agg = aggregate(&members, &records, &team_id, since, until);
                 let top = agg.per_user.first().cloned();
@@ -294,6 +339,7 @@ fn register_leaderboard(iii: &III, kv: &StateKV) {
                     "leaderboard": agg.per_user,
                     "period_start": agg.period_start,
                     "period_end": agg.period_end,
+                    "currency": converter.code,
                 })))
             }
         },
@@ -371,6 +417,7 @@ mod tests {
             sub: Some("alice".into()),
             user_id: Some("alice".into()),
             team_id: Some("t1".into()),
+            role: None,
             exp: Some(Utc::now().timestamp() + 3600),
             extra: Default::default(),
         };
@@ -390,6 +437,7 @@ mod tests {
             sub: Some("x".into()),
             user_id: None,
             team_id: None,
+            role: None,
             exp: Some(Utc::now().timestamp() - 10),
             extra: Default::default(),
         };
@@ -6,6 +6,8 @@ pub mod hooks;
 pub mod mcp;
 pub mod models;
 pub mod state;
+pub mod tail;
+pub mod tokenizer;
 pub mod triggers;
 pub mod worker;
 
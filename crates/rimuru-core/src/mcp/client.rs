@@ -11,6 +11,7 @@ use tracing::{debug, info, warn};
 
 use super::types::*;
 use crate::error::RimuruError;
+use crate::tokenizer;
 
 type Result<T> = std::result::Result<T, RimuruError>;
 
@@ -300,7 +301,6 @@ impl McpClient {
     }
 
     pub fn estimate_tokens(value: &Value) -> u64 {
-        let s = value.to_string();
-        (s.len() as u64) / 4
+        tokenizer::count_value_tokens(value)
     }
 }
@@ -1,5 +1,9 @@
+use std::collections::{HashMap, HashSet};
+
 use serde_json::Value;
 
+use crate::tokenizer::Encoding;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CompressionStrategy {
     Auto,
@@ -8,6 +12,7 @@ pub enum CompressionStrategy {
     JsonPaths,
     ErrorsOnly,
     TreeView,
+    Dedup,
 }
 
 #[derive(Debug, Clone)]
@@ -26,22 +31,22 @@ fn safe_truncate_chars(s: &str, max_chars: usize) -> &str {
     }
 }
 
-fn estimate_tokens(value: &Value) -> u64 {
-    serde_json::to_string(value)
-        .map(|s| s.len() as u64 / 4)
-        .unwrap_or(0)
-}
-
-fn estimate_tokens_str(s: &str) -> u64 {
-    s.len() as u64 / 4
+pub fn compress(
+    input: &Value,
+    strategy: CompressionStrategy,
+    max_tokens: u64,
+) -> CompressionResult {
+    compress_with(input, strategy, max_tokens, Encoding::default())
 }
 
-pub fn compress(
+/// [`compress`], counting tokens with a specific model family's encoding.
+pub fn compress_with(
     input: &Value,
     strategy: CompressionStrategy,
     max_tokens: u64,
+    encoding: Encoding,
 ) -> CompressionResult {
-    let original_tokens = estimate_tokens(input);
+    let original_tokens = encoding.count_value(input);
 
     if original_tokens <= max_tokens {
         return CompressionResult {
@@ -53,25 +58,29 @@ pub fn compress(
         };
     }
 
+    // Dedup rewrites lines of text, which would break a JSON value apart,
+    // so structured input gets the JSON strategy instead.
     let strategy = match strategy {
         CompressionStrategy::Auto => pick_strategy(input, original_tokens),
+        CompressionStrategy::Dedup if !input.is_string() => CompressionStrategy::JsonPaths,
         other => other,
     };
 
     let (compressed, strategy_name) = match strategy {
-        CompressionStrategy::Truncate => (truncate(input, max_tokens), "truncate"),
+        CompressionStrategy::Truncate => (truncate(input, max_tokens, encoding), "truncate"),
         CompressionStrategy::Summarize => (summarize(input, max_tokens), "summarize"),
         CompressionStrategy::JsonPaths => (json_paths(input, max_tokens), "json_paths"),
         CompressionStrategy::ErrorsOnly => (errors_only(input, max_tokens), "errors_only"),
         CompressionStrategy::TreeView => (tree_view(input, max_tokens), "tree_view"),
+        CompressionStrategy::Dedup => (dedup(input.as_str().unwrap_or_default()), "dedup"),
         CompressionStrategy::Auto => unreachable!(),
     };
 
-    let compressed_tokens = estimate_tokens(&compressed);
+    let compressed_tokens = encoding.count_value(&compressed);
 
     if compressed_tokens > max_tokens && !matches!(strategy, CompressionStrategy::Truncate) {
-        let fallback = truncate(input, max_tokens);
-        let fallback_tokens = estimate_tokens(&fallback);
+        let fallback = truncate(input, max_tokens, encoding);
+        let fallback_tokens = encoding.count_value(&fallback);
         let savings = if original_tokens > 0 {
             (1.0 - fallback_tokens as f64 / original_tokens as f64) * 100.0
         } else {
@@ -118,9 +127,13 @@ fn pick_strategy(input: &Value, tokens: u64) -> CompressionStrategy {
     }
 }
 
+/// Error output goes to `ErrorsOnly` even when it repeats: collapsing it
+/// would fold compiler `--> file:line` locations into one template.
 fn pick_string_strategy(s: &str) -> CompressionStrategy {
     if has_error_lines(s) {
         CompressionStrategy::ErrorsOnly
+    } else if is_repetitive(s) {
+        CompressionStrategy::Dedup
     } else if looks_like_file_listing(s) {
         CompressionStrategy::TreeView
     } else {
@@ -144,6 +157,18 @@ fn has_error_lines(s: &str) -> bool {
     error_count >= 2
 }
 
+/// At least 20 non-blank lines, half of which repeat an earlier line once
+/// timestamps, counters and ids are masked.
+fn is_repetitive(s: &str) -> bool {
+    let mut templates = HashSet::new();
+    let mut total = 0usize;
+    for line in s.lines().filter(|l| !l.trim().is_empty()) {
+        templates.insert(normalize_line(line));
+        total += 1;
+    }
+    total >= 20 && (total - templates.len()) * 2 >= total
+}
+
 fn looks_like_file_listing(s: &str) -> bool {
     let path_lines = s
         .lines()
@@ -158,20 +183,23 @@ fn looks_like_file_listing(s: &str) -> bool {
     path_lines as f64 / total_lines as f64 > 0.5
 }
 
-fn truncate(input: &Value, max_tokens: u64) -> Value {
+fn truncate(input: &Value, max_tokens: u64, encoding: Encoding) -> Value {
     let s = match input {
         Value::String(s) => s.clone(),
         _ => serde_json::to_string_pretty(input).unwrap_or_default(),
     };
 
-    let max_chars = max_tokens.saturating_mul(3).min(usize::MAX as u64) as usize;
-    if s.len() <= max_chars {
+    let total_tokens = encoding.count(&s);
+    if total_tokens <= max_tokens {
         return Value::String(s);
     }
 
-    let total_tokens = estimate_tokens_str(&s);
-    let truncated = safe_truncate_chars(&s, max_chars);
-    let kept_tokens = estimate_tokens_str(truncated);
+    // Chars per token swings from ~4 in English to ~1 in CJK, so search for
+    // the longest char cut that fits rather than guessing one.
+    let ends: Vec<usize> = s.char_indices().map(|(i, _)| i).chain([s.len()]).collect();
+    let fits = ends.partition_point(|&end| encoding.count(&s[..end]) <= max_tokens);
+    let truncated = &s[..ends[fits.saturating_sub(1)]];
+    let kept_tokens = encoding.count(truncated);
 
     Value::String(format!(
         "{}\n... [truncated, showing ~{}/{} tokens]",
@@ -316,6 +344,98 @@ fn errors_only(input: &Value, _max_tokens: u64) -> Value {
     Value::String(result)
 }
 
+/// Masks the volatile parts of a log line so repeats compare equal: digit
+/// runs become `#`, and so do words that are all hex (ids, hashes, `0x..`).
+fn normalize_line(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        if !c.is_ascii_alphanumeric() {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        let end = rest
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len());
+        let word = &rest[..end];
+        rest = &rest[end..];
+
+        let hex = word
+            .strip_prefix("0x")
+            .filter(|h| !h.is_empty())
+            .unwrap_or(word);
+        if word.bytes().any(|b| b.is_ascii_digit()) && hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            out.push('#');
+            continue;
+        }
+        let mut in_digits = false;
+        for c in word.chars() {
+            if c.is_ascii_digit() {
+                if !in_digits {
+                    out.push('#');
+                }
+                in_digits = true;
+            } else {
+                out.push(c);
+                in_digits = false;
+            }
+        }
+    }
+    out
+}
+
+fn dedup(s: &str) -> Value {
+    let lines: Vec<&str> = s.lines().collect();
+    let templates: Vec<String> = lines.iter().map(|l| normalize_line(l)).collect();
+
+    // template -> (occurrences, index of the last one)
+    let mut seen: HashMap<&str, (usize, usize)> = HashMap::new();
+    for (i, (line, template)) in lines.iter().zip(&templates).enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = seen.entry(template).or_insert((0, i));
+        entry.0 += 1;
+        entry.1 = i;
+    }
+
+    let mut result = String::new();
+    let mut emitted: HashMap<&str, usize> = HashMap::new();
+    let mut kept_count = 0;
+
+    for (i, (line, template)) in lines.iter().zip(&templates).enumerate() {
+        let Some(&(count, last)) = seen.get(template.as_str()) else {
+            result.push_str(line);
+            result.push('\n');
+            kept_count += 1;
+            continue;
+        };
+        let n = emitted.entry(template).or_insert(0);
+        *n += 1;
+        if count > 2 && i == last {
+            result.push_str(&format!(
+                "... (x{}) [{} similar lines collapsed] ...\n",
+                count,
+                count - 2
+            ));
+        }
+        if *n == 1 || i == last {
+            result.push_str(line);
+            result.push('\n');
+            kept_count += 1;
+        }
+    }
+
+    result.push_str(&format!(
+        "\n[dedup: kept {} of {} lines]",
+        kept_count,
+        lines.len()
+    ));
+
+    Value::String(result)
+}
+
 fn tree_view(input: &Value, _max_tokens: u64) -> Value {
     let s = match input {
         Value::String(s) => s.clone(),
@@ -422,11 +542,21 @@ mod tests {
         assert!(result.savings_percent > 0.0);
     }
 
+    #[test]
+    fn truncate_cuts_cjk_by_tokens() {
+        let cjk = "東京は日本の首都であり、世界最大の都市圏の一つです。".repeat(40);
+        let result = compress(&Value::String(cjk), CompressionStrategy::Truncate, 100);
+        let text = result.compressed.as_str().unwrap();
+        let body = text.split("\n... [truncated").next().unwrap();
+        let kept = crate::tokenizer::count_tokens(body);
+        assert!(kept <= 100 && kept > 90, "kept {kept} tokens");
+    }
+
     #[test]
     fn summarize_keeps_head_and_tail() {
         let lines: Vec<String> = (0..100).map(|i| format!("line {i}")).collect();
         let input = Value::String(lines.join("\n"));
-        let result = compress(&input, CompressionStrategy::Summarize, 50);
+        let result = compress(&input, CompressionStrategy::Summarize, 80);
         let text = result.compressed.as_str().unwrap_or("");
         assert!(text.contains("line 0"), "head missing: {text}");
         assert!(text.contains("line 99"), "tail missing: {text}");
@@ -449,7 +579,7 @@ mod tests {
             lines.push(format!("tail line {i}"));
         }
         let input = Value::String(lines.join("\n"));
-        let result = compress(&input, CompressionStrategy::ErrorsOnly, 50);
+        let result = compress(&input, CompressionStrategy::ErrorsOnly, 100);
         let text = result.compressed.as_str().unwrap_or("");
         assert!(text.to_lowercase().contains("error"));
         assert!(text.contains("traceback"));
@@ -477,11 +607,115 @@ mod tests {
     #[test]
     fn auto_picks_errors_only_when_many_errors() {
         let lines: Vec<String> = (0..10).map(|i| format!("error {i}: failed")).collect();
-        let tokens = estimate_tokens(&Value::String(lines.join("\n")));
+        let tokens = crate::tokenizer::count_tokens(&lines.join("\n"));
         let chosen = pick_strategy(&Value::String(lines.join("\n")), tokens);
         assert_eq!(chosen, CompressionStrategy::ErrorsOnly);
     }
 
+    #[test]
+    fn normalize_masks_timestamps_counters_and_ids() {
+        assert_eq!(
+            normalize_line("2026-01-01T00:00:12Z worker[3] req 0x1f3a id=deadbeef42 in 12ms"),
+            "#-#-#T#:#:#Z worker[#] req # id=# in #ms"
+        );
+        assert_eq!(
+            normalize_line("Compiling serde v1.0.228"),
+            "Compiling serde v#.#.#"
+        );
+        assert_eq!(normalize_line("cafe added"), "cafe added");
+    }
+
+    #[test]
+    fn dedup_collapses_runs_keeping_first_and_last() {
+        let mut lines = vec!["build started".to_string()];
+        lines.extend((0..50).map(|i| format!("2026-01-01T00:00:{i:02}Z fetched chunk {i} of 50")));
+        lines.push("build finished".to_string());
+        let input = Value::String(lines.join("\n"));
+        let result = compress(&input, CompressionStrategy::Dedup, 100);
+        assert_eq!(result.strategy_used, "dedup");
+        let text = result.compressed.as_str().unwrap();
+        assert_eq!(
+            text.lines().take(5).collect::<Vec<_>>(),
+            vec![
+                "build started",
+                "2026-01-01T00:00:00Z fetched chunk 0 of 50",
+                "... (x50) [48 similar lines collapsed] ...",
+                "2026-01-01T00:00:49Z fetched chunk 49 of 50",
+                "build finished",
+            ]
+        );
+        assert!(text.contains("[dedup: kept 4 of 52 lines]"));
+    }
+
+    #[test]
+    fn dedup_collapses_interleaved_near_duplicates() {
+        let lines: Vec<String> = (0..30)
+            .flat_map(|i| {
+                [
+                    format!("GET /api/items/{i} 200 {}ms", i * 3),
+                    format!("cache miss key=item:{i}"),
+                ]
+            })
+            .collect();
+        let text = dedup(&lines.join("\n"));
+        let text = text.as_str().unwrap();
+        assert!(text.starts_with("GET /api/items/0 200 0ms\ncache miss key=item:0\n"));
+        assert!(
+            text.contains("... (x30) [28 similar lines collapsed] ...\nGET /api/items/29 200 87ms")
+        );
+        assert!(
+            text.contains("... (x30) [28 similar lines collapsed] ...\ncache miss key=item:29")
+        );
+        assert!(text.contains("[dedup: kept 4 of 60 lines]"));
+    }
+
+    #[test]
+    fn dedup_leaves_json_to_the_json_strategy() {
+        let input = json!({
+            "events": (0..60)
+                .map(|i| json!({"id": i, "status": "ok"}))
+                .collect::<Vec<_>>()
+        });
+        let result = compress(&input, CompressionStrategy::Dedup, 200);
+        assert_eq!(result.strategy_used, "json_paths");
+        assert!(result.compressed_tokens < result.original_tokens);
+        assert!(result.compressed.is_object());
+    }
+
+    #[test]
+    fn auto_picks_dedup_for_repetitive_logs() {
+        let lines: Vec<String> = (0..40)
+            .map(|i| format!("[{i:04}] GET /api/items/{i} 200 in {}ms", i % 7))
+            .collect();
+        let input = Value::String(lines.join("\n"));
+        let tokens = crate::tokenizer::count_tokens(input.as_str().unwrap());
+        assert_eq!(pick_strategy(&input, tokens), CompressionStrategy::Dedup);
+
+        // Repeated compiler diagnostics keep their locations.
+        let mut lines: Vec<String> = (0..60)
+            .map(|i| format!("   Compiling dep{i} v0.1.{i}"))
+            .collect();
+        for i in 0..5 {
+            lines.push(format!("warning: unused variable `x{i}`"));
+            lines.push(format!("  --> src/lib.rs:{}:9", i + 16));
+        }
+        lines.push("error: aborting due to previous error".to_string());
+        let input = Value::String(lines.join("\n"));
+        let tokens = crate::tokenizer::count_tokens(input.as_str().unwrap());
+        assert_eq!(
+            pick_strategy(&input, tokens),
+            CompressionStrategy::ErrorsOnly
+        );
+        let text = compress(&input, CompressionStrategy::Auto, tokens / 2).compressed;
+        assert!(text.as_str().unwrap().contains("src/lib.rs:20:9"), "{text}");
+
+        let varied: Vec<String> = (0..40)
+            .map(|i| format!("line {i} says {}", "ab".repeat(i)))
+            .collect();
+        let varied = Value::String(varied.join("\n"));
+        assert_eq!(pick_strategy(&varied, 0), CompressionStrategy::Summarize);
+    }
+
     #[test]
     fn safe_truncate_handles_multibyte() {
         let s = "日本語テスト";
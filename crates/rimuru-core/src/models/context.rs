@@ -1,5 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +43,207 @@ pub struct ToolCallRecord {
     pub tool_id: Option<String>,
     pub input_tokens_estimate: u64,
     pub output_tokens_estimate: u64,
+    /// Hash of the normalized arguments (`fingerprint_args`). Two calls
+    /// with the same tool and fingerprint did the same thing.
+    #[serde(default)]
+    pub arg_fingerprint: Option<String>,
+    /// File or directory the call works on, for tools that take one.
+    #[serde(default)]
+    pub target_path: Option<String>,
+    /// Content changes made by edit tools (`FileEdit::from_tool_call`).
+    #[serde(default)]
+    pub edits: Vec<FileEdit>,
+}
+
+/// One change to one file, as hashes of the text replaced and the text
+/// written. `before` is `None` when the whole file was written.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileEdit {
+    pub path: String,
+    pub before: Option<String>,
+    pub after: String,
+}
+
+impl FileEdit {
+    fn new(path: &str, before: Option<&str>, after: &str) -> Self {
+        Self {
+            path: path.trim().to_string(),
+            before: before.map(content_hash),
+            after: content_hash(after),
+        }
+    }
+
+    /// Edits made by a call to `Edit`, `MultiEdit`, `Write` or
+    /// `apply_patch`; empty for any other tool. `apply_patch` input is the
+    /// patch text, either bare or under `input`/`patch`.
+    pub fn from_tool_call(tool_name: &str, input: &Value) -> Vec<FileEdit> {
+        let path = input
+            .get("file_path")
+            .or_else(|| input.get("path"))
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        let text = |key: &str| input.get(key).and_then(|v| v.as_str());
+        match tool_name {
+            "Edit" | "str_replace" if !path.is_empty() => {
+                match (text("old_string"), text("new_string")) {
+                    (Some(old), Some(new)) => vec![FileEdit::new(path, Some(old), new)],
+                    _ => Vec::new(),
+                }
+            }
+            "MultiEdit" if !path.is_empty() => input
+                .get("edits")
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|e| {
+                    let old = e.get("old_string")?.as_str()?;
+                    let new = e.get("new_string")?.as_str()?;
+                    Some(FileEdit::new(path, Some(old), new))
+                })
+                .collect(),
+            "Write" | "write_file" if !path.is_empty() => text("content")
+                .map(|content| vec![FileEdit::new(path, None, content)])
+                .unwrap_or_default(),
+            "apply_patch" => {
+                let patch = input
+                    .as_str()
+                    .or_else(|| text("input"))
+                    .or_else(|| text("patch"))
+                    .unwrap_or_default();
+                parse_patch(patch)
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Split an `apply_patch` envelope into per-file edits. Context lines count
+/// on both sides so two hunks that change the same line differently still
+/// hash differently; deleted files are not edits anyone can revert to.
+fn parse_patch(patch: &str) -> Vec<FileEdit> {
+    let mut edits = Vec::new();
+    let mut current: Option<(String, bool, String, String)> = None;
+    let mut flush = |current: &mut Option<(String, bool, String, String)>| {
+        if let Some((path, added, before, after)) = current.take() {
+            let before = (!added).then_some(before.as_str());
+            edits.push(FileEdit::new(&path, before, &after));
+        }
+    };
+    for line in patch.lines() {
+        if let Some(path) = line.strip_prefix("*** Update File: ") {
+            flush(&mut current);
+            current = Some((path.to_string(), false, String::new(), String::new()));
+        } else if let Some(path) = line.strip_prefix("*** Add File: ") {
+            flush(&mut current);
+            current = Some((path.to_string(), true, String::new(), String::new()));
+        } else if line.starts_with("*** ") {
+            flush(&mut current);
+        } else if let Some((_, _, before, after)) = current.as_mut() {
+            if let Some(rest) = line.strip_prefix('-') {
+                before.push_str(rest);
+                before.push('\n');
+            } else if let Some(rest) = line.strip_prefix('+') {
+                after.push_str(rest);
+                after.push('\n');
+            } else if let Some(rest) = line.strip_prefix(' ') {
+                before.push_str(rest);
+                before.push('\n');
+                after.push_str(rest);
+                after.push('\n');
+            }
+        }
+    }
+    flush(&mut current);
+    edits
+}
+
+/// First 16 hex characters of the SHA-256 of `text`.
+fn short_hash(text: &str) -> String {
+    let digest = Sha256::digest(text.as_bytes());
+    digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// `text` without trailing whitespace on any line or at the end, which
+/// editors and agents add and strip without changing what the text says.
+fn trim_trailing_whitespace(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+    lines.join("\n").trim_end().to_string()
+}
+
+/// Hash of edited text, ignoring trailing whitespace so an editor's
+/// whitespace cleanup does not hide a revert.
+fn content_hash(text: &str) -> String {
+    short_hash(&trim_trailing_whitespace(text))
+}
+
+/// Arguments that describe a call rather than change what it does.
+const DESCRIPTIVE_ARGS: &[&str] = &["description"];
+
+/// Argument names agents use for the file or directory a tool acts on.
+const PATH_ARGS: &[&str] = &[
+    "file_path",
+    "notebook_path",
+    "filePath",
+    "target_file",
+    "path",
+];
+
+fn normalize_args(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map
+                .keys()
+                .filter(|k| !DESCRIPTIVE_ARGS.contains(&k.as_str()) && !map[*k].is_null())
+                .collect();
+            keys.sort();
+            out.push('{');
+            for key in keys {
+                out.push_str(&serde_json::to_string(key).unwrap_or_default());
+                out.push(':');
+                normalize_args(&map[key], out);
+                out.push(',');
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for item in items {
+                normalize_args(item, out);
+                out.push(',');
+            }
+            out.push(']');
+        }
+        // Only trailing whitespace goes: inside a string it can be the
+        // whole difference between two calls (`grep "a  b"`, a heredoc).
+        Value::String(s) => {
+            out.push_str(&serde_json::to_string(&trim_trailing_whitespace(s)).unwrap_or_default());
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+impl ToolCallRecord {
+    /// Stable fingerprint of a tool call's arguments: keys sorted, nulls and
+    /// descriptive fields dropped, trailing whitespace trimmed from strings,
+    /// then hashed to 16 hex characters.
+    pub fn fingerprint_args(input: &Value) -> String {
+        let mut normalized = String::new();
+        normalize_args(input, &mut normalized);
+        short_hash(&normalized)
+    }
+
+    /// The path a tool call targets, taken from the first path-like argument.
+    pub fn target_path(input: &Value) -> Option<String> {
+        PATH_ARGS
+            .iter()
+            .filter_map(|key| input.get(*key)?.as_str())
+            .map(str::trim)
+            .find(|p| !p.is_empty())
+            .map(|p| {
+                let trimmed = p.trim_end_matches('/');
+                if trimmed.is_empty() { "/" } else { trimmed }.to_string()
+            })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -82,3 +285,47 @@ impl ContextBreakdown {
         (wasted as f64 / self.total_tokens as f64) * 100.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn patch_edits_hash_each_file() {
+        let patch = "*** Begin Patch\n*** Update File: src/a.rs\n@@\n fn a() {\n-    1\n+    2\n }\n*** Add File: src/b.rs\n+pub fn b() {}\n*** Delete File: src/c.rs\n*** End Patch";
+        let edits = FileEdit::from_tool_call("apply_patch", &json!({ "input": patch }));
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits[0].path, "src/a.rs");
+        assert_eq!(edits[0].before, Some(content_hash("fn a() {\n    1\n}")));
+        assert_eq!(edits[0].after, content_hash("fn a() {\n    2\n}"));
+        assert_eq!(edits[1].before, None);
+    }
+
+    #[test]
+    fn edit_revert_swaps_hashes() {
+        let forward = FileEdit::from_tool_call(
+            "Edit",
+            &json!({"file_path": "/x.rs", "old_string": "a + b", "new_string": "a - b"}),
+        );
+        let back = FileEdit::from_tool_call(
+            "Edit",
+            &json!({"file_path": "/x.rs", "old_string": "a - b  ", "new_string": "a + b"}),
+        );
+        assert_eq!(forward[0].after, back[0].before.clone().unwrap());
+        assert_eq!(back[0].after, forward[0].before.clone().unwrap());
+        assert!(FileEdit::from_tool_call("Read", &json!({"file_path": "/x.rs"})).is_empty());
+    }
+
+    #[test]
+    fn fingerprint_keeps_whitespace_inside_strings() {
+        let fp = |command: &str| ToolCallRecord::fingerprint_args(&json!({ "command": command }));
+        assert_ne!(fp("grep 'a  b' x"), fp("grep 'a b' x"));
+        assert_ne!(fp("cat <<EOF\n  x\nEOF"), fp("cat <<EOF\nx\nEOF"));
+        assert_eq!(fp("ls -la  \n"), fp("ls -la"));
+        assert_eq!(
+            ToolCallRecord::fingerprint_args(&json!({"b": 1, "a": "x", "description": "why"})),
+            ToolCallRecord::fingerprint_args(&json!({"a": "x ", "b": 1})),
+        );
+    }
+}
@@ -25,6 +25,8 @@ pub struct CostRecord {
     pub user_id: Option<String>,
     #[serde(default)]
     pub team_id: Option<String>,
+    #[serde(default)]
+    pub project_path: Option<String>,
 }
 
 impl CostRecord {
@@ -57,6 +59,7 @@ impl CostRecord {
             metadata: serde_json::json!({}),
             user_id: None,
             team_id: None,
+            project_path: None,
         }
     }
 }
@@ -0,0 +1,681 @@
+//! Live tailing of append-only session logs.
+//!
+//! `rimuru.agents.sync` re-parses every session file, so spend and runaway
+//! state only move when it runs. The tail watches the session directories of
+//! the agents that append to their logs (Claude Code and Codex JSONL, Gemini
+//! CLI `.jsonl` sessions), parses only the bytes written since the last read,
+//! and hands each new turn to `rimuru.costs.record`. The session's context
+//! breakdown is then rewritten and `rimuru.runaway.scan` runs for that
+//! session alone, so budgets and runaway alerts follow a live session within
+//! a second or two.
+//!
+//! Sessions the tail has recorded costs for are listed in `tail_sessions`
+//! with how many of their turns are accounted for, so a restarted tail
+//! resumes where it stopped and records what was written while it was down.
+//! `rimuru.agents.sync` leaves those sessions to the tail, except for the
+//! turns of a session first tailed mid-run that predate the tail, which it
+//! records once.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use iii_sdk::TriggerRequest;
+use notify::{RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::adapters::{ClaudeCodeAdapter, CodexAdapter, GeminiCliAdapter};
+use crate::adapters::{TailParser, claude_code, codex, gemini_cli};
+use crate::functions::{costs, downgrade, jwt, runaway};
+use crate::models::{
+    Agent, AgentType, ContextBreakdown, CostRecord, ModelInfo, Session, SessionStatus, TurnRecord,
+};
+use crate::state::StateKV;
+
+pub(crate) const TAIL_SCOPE: &str = "tail_sessions";
+
+/// What the tail has done for one session, kept in `tail_sessions` under
+/// the session id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct TailMarker {
+    pub path: String,
+    pub agent_type: AgentType,
+    /// Turns of the session's log already accounted for, counted from the
+    /// start: recorded by the tail, or before `first_turn`. A restarted tail
+    /// resumes after them.
+    pub turns: usize,
+    /// How many turns predated the tail when it first saw the session.
+    /// They are left to `rimuru.agents.sync`, which sets `backfilled` once
+    /// it has recorded them.
+    #[serde(default)]
+    pub first_turn: Option<usize>,
+    #[serde(default)]
+    pub backfilled: bool,
+    pub updated_at: String,
+}
+
+/// How long to wait after a change for the rest of a burst of writes.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Files unwritten for this long have their parse state dropped; the
+/// session watermark survives, so a file that wakes up again is re-read
+/// without recording anything twice.
+const IDLE_EVICT: Duration = Duration::from_secs(30 * 60);
+
+/// A directory of session logs for one agent.
+pub struct Source {
+    pub agent_type: AgentType,
+    pub dir: PathBuf,
+    parser: fn(&Path) -> Box<dyn TailParser>,
+}
+
+impl Source {
+    fn matches(&self, path: &Path) -> bool {
+        path.starts_with(&self.dir) && path.extension().and_then(|e| e.to_str()) == Some("jsonl")
+    }
+}
+
+/// The installed agents whose logs can be tailed.
+pub fn sources() -> Vec<Source> {
+    vec![
+        Source {
+            agent_type: AgentType::ClaudeCode,
+            dir: ClaudeCodeAdapter::new().projects_dir(),
+            parser: |p| Box::new(claude_code::JsonlParser::for_file(p)),
+        },
+        Source {
+            agent_type: AgentType::Codex,
+            dir: CodexAdapter::new().sessions_dir(),
+            parser: |_| Box::new(codex::RolloutParser::new(Uuid::nil())),
+        },
+        Source {
+            agent_type: AgentType::GeminiCli,
+            dir: GeminiCliAdapter::new().sessions_dir(),
+            parser: |p| Box::new(gemini_cli::JsonlParser::for_file(p)),
+        },
+    ]
+}
+
+struct TailedFile {
+    parser: Box<dyn TailParser>,
+    /// Bytes consumed, up to the end of the last complete line.
+    offset: u64,
+    /// Turns already handed on, or skipped as predating the tail.
+    emitted: usize,
+    primed: bool,
+    last_read: Instant,
+}
+
+/// Read positions and parse state for every file seen so far.
+pub struct Tailer {
+    files: HashMap<PathBuf, TailedFile>,
+    /// Turns of sessions the tail has not seen before that are stamped
+    /// before this are left to `rimuru.agents.sync`.
+    since: DateTime<Utc>,
+    /// Turns accounted for per session: by an earlier run, or handed on
+    /// by this one. A file read again from the start resumes after them.
+    resume: HashMap<Uuid, usize>,
+}
+
+impl Tailer {
+    pub fn new(since: DateTime<Utc>, resume: HashMap<Uuid, usize>) -> Self {
+        Self {
+            files: HashMap::new(),
+            since,
+            resume,
+        }
+    }
+
+    /// Parse the lines appended to `path` since the last call and return the
+    /// turns they completed. A file seen for the first time is read from the
+    /// start for its history; turns a previous run already accounted for are
+    /// skipped, and for a session never tailed so are turns stamped before
+    /// `since`. A file that shrank is read again from the start, and only
+    /// turns beyond those already handed on are returned.
+    pub fn poll(&mut self, source: &Source, path: &Path) -> std::io::Result<Vec<TurnRecord>> {
+        let mut file = std::fs::File::open(path)?;
+        let len = file.metadata()?.len();
+
+        let fresh = || TailedFile {
+            parser: (source.parser)(path),
+            offset: 0,
+            emitted: 0,
+            primed: false,
+            last_read: Instant::now(),
+        };
+        let tailed = self.files.entry(path.to_path_buf()).or_insert_with(fresh);
+        if len < tailed.offset {
+            debug!("{} was truncated; reading it again", path.display());
+            *tailed = fresh();
+        }
+        tailed.last_read = Instant::now();
+
+        let mut appended = Vec::new();
+        file.seek(SeekFrom::Start(tailed.offset))?;
+        file.read_to_end(&mut appended)?;
+        // A line still being written is left for the next read.
+        let complete = appended
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map_or(0, |i| i + 1);
+        for line in String::from_utf8_lossy(&appended[..complete]).lines() {
+            tailed.parser.push_line(line);
+        }
+        tailed.offset += complete as u64;
+
+        let breakdown = tailed.parser.breakdown();
+        // Without a session id the turns cannot be attributed yet; hold
+        // them until a later line names the session.
+        if breakdown.session_id.is_nil() {
+            return Ok(Vec::new());
+        }
+        let turns = &breakdown.turns;
+        if !tailed.primed {
+            tailed.primed = true;
+            tailed.emitted = match self.resume.get(&breakdown.session_id) {
+                Some(&done) => done,
+                None => turns
+                    .iter()
+                    .take_while(|t| !stamped_after(t, self.since))
+                    .count(),
+            };
+        }
+        let new = turns.get(tailed.emitted..).unwrap_or_default().to_vec();
+        tailed.emitted = tailed.emitted.max(turns.len());
+        self.resume.insert(breakdown.session_id, tailed.emitted);
+        Ok(new)
+    }
+
+    /// Drop the parse state of files not read for `idle`.
+    pub fn evict_idle(&mut self, idle: Duration) {
+        self.files.retain(|_, f| f.last_read.elapsed() < idle);
+    }
+
+    fn parser(&self, path: &Path) -> Option<&dyn TailParser> {
+        self.files.get(path).map(|f| f.parser.as_ref())
+    }
+}
+
+fn stamped_after(turn: &TurnRecord, since: DateTime<Utc>) -> bool {
+    turn.timestamp
+        .as_deref()
+        .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+        .is_some_and(|at| at.with_timezone(&Utc) >= since)
+}
+
+/// Start watching the session directories, unless `tail_enabled` is false.
+pub async fn spawn(kv: &StateKV) {
+    if let Ok(Some(Value::Bool(false))) = kv.get::<Value>("config", "tail_enabled").await {
+        info!("Live session tail disabled");
+        return;
+    }
+
+    let sources: Vec<Source> = sources().into_iter().filter(|s| s.dir.is_dir()).collect();
+    if sources.is_empty() {
+        return;
+    }
+
+    let (tx, rx) = mpsc::unbounded_channel::<PathBuf>();
+    let mut watcher =
+        match notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+            Ok(event) if event.kind.is_create() || event.kind.is_modify() => {
+                for path in event.paths {
+                    let _ = tx.send(path);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => warn!("session watcher error: {}", e),
+        }) {
+            Ok(w) => w,
+            Err(e) => {
+                warn!("Failed to start session watcher: {}", e);
+                return;
+            }
+        };
+    for source in &sources {
+        if let Err(e) = watcher.watch(&source.dir, RecursiveMode::Recursive) {
+            warn!("Failed to watch {}: {}", source.dir.display(), e);
+        }
+    }
+    info!("Tailing session logs in {} directories", sources.len());
+
+    let kv = kv.clone();
+    tokio::spawn(async move {
+        // Dropping the watcher stops it, so it lives as long as the loop.
+        let _watcher = watcher;
+        run(kv, sources, rx).await;
+    });
+}
+
+/// How far each previously tailed session got, from `tail_sessions`.
+async fn load_resume(kv: &StateKV) -> HashMap<Uuid, usize> {
+    let mut resume = HashMap::new();
+    for key in kv.list_keys(TAIL_SCOPE).await.unwrap_or_default() {
+        let Ok(id) = key.parse::<Uuid>() else {
+            continue;
+        };
+        if let Ok(Some(marker)) = kv.get::<TailMarker>(TAIL_SCOPE, &key).await {
+            resume.insert(id, marker.turns);
+        }
+    }
+    resume
+}
+
+async fn run(kv: StateKV, sources: Vec<Source>, mut rx: mpsc::UnboundedReceiver<PathBuf>) {
+    let mut tailer = Tailer::new(Utc::now(), load_resume(&kv).await);
+    loop {
+        tailer.evict_idle(IDLE_EVICT);
+        let first = match tokio::time::timeout(IDLE_EVICT, rx.recv()).await {
+            Ok(Some(path)) => path,
+            Ok(None) => break,
+            Err(_) => continue,
+        };
+        tokio::time::sleep(DEBOUNCE).await;
+        let mut changed = HashSet::from([first]);
+        while let Ok(path) = rx.try_recv() {
+            changed.insert(path);
+        }
+
+        let catalog = downgrade::load_catalog(&kv).await.unwrap_or_default();
+        for path in changed {
+            let Some(source) = sources.iter().find(|s| s.matches(&path)) else {
+                continue;
+            };
+            let turns = match tailer.poll(source, &path) {
+                Ok(turns) => turns,
+                Err(e) => {
+                    debug!("failed to read {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            if turns.is_empty() {
+                continue;
+            }
+            if let Some(parser) = tailer.parser(&path) {
+                publish(&kv, source, &path, parser, &turns, &catalog).await;
+            }
+        }
+    }
+}
+
+/// Record the new turns' costs, refresh the stored session and breakdown,
+/// and rescan the session for runaway patterns.
+async fn publish(
+    kv: &StateKV,
+    source: &Source,
+    path: &Path,
+    parser: &dyn TailParser,
+    turns: &[TurnRecord],
+    catalog: &[ModelInfo],
+) {
+    let breakdown = parser.breakdown();
+    let sid = breakdown.session_id.to_string();
+
+    let agents: Vec<Agent> = kv.list("agents").await.unwrap_or_default();
+    let agent_id = agents
+        .iter()
+        .find(|a| a.agent_type == source.agent_type)
+        .map(|a| a.id);
+
+    let mut session: Session = match kv.get("sessions", &sid).await {
+        Ok(Some(s)) => s,
+        _ => {
+            let mut s = Session::new(agent_id.unwrap_or_default(), source.agent_type);
+            s.id = breakdown.session_id;
+            if let Some(at) = breakdown
+                .turns
+                .iter()
+                .find_map(|t| DateTime::parse_from_rfc3339(t.timestamp.as_deref()?).ok())
+            {
+                s.started_at = at.with_timezone(&Utc);
+            }
+            s.project_path = parser.project_path().map(String::from);
+            s.metadata = json!({ "source_file": path.to_string_lossy() });
+            s
+        }
+    };
+
+    // Mark the session before recording, so a sync running meanwhile
+    // doesn't record it too. The watermark only moves once the turns are in.
+    let done = breakdown.turns.len() - turns.len();
+    if !matches!(kv.get::<Value>(TAIL_SCOPE, &sid).await, Ok(Some(_))) {
+        let marker = TailMarker {
+            path: path.to_string_lossy().into_owned(),
+            agent_type: source.agent_type,
+            turns: done,
+            first_turn: Some(done),
+            backfilled: false,
+            updated_at: Utc::now().to_rfc3339(),
+        };
+        if let Err(e) = kv.set(TAIL_SCOPE, &sid, &marker).await {
+            warn!("failed to mark session {} as tailed: {}", sid, e);
+        }
+    }
+
+    for turn in turns {
+        let cost = runaway::turn_cost(turn, catalog);
+        session.input_tokens += turn.input_tokens;
+        session.output_tokens += turn.output_tokens;
+        session.total_tokens +=
+            turn.input_tokens + turn.output_tokens + turn.cache_read + turn.cache_write;
+        session.total_cost += cost;
+        session.messages += 1;
+        if turn.model.is_some() {
+            session.model = turn.model.clone();
+        }
+
+        let Some(agent_id) = agent_id else {
+            continue;
+        };
+        record_cost(
+            kv,
+            agent_id,
+            source.agent_type,
+            &session,
+            turn,
+            cost,
+            catalog,
+        )
+        .await;
+    }
+    if agent_id.is_none() {
+        debug!(
+            "no registered {} agent; tailed turns for {} were not recorded as costs",
+            source.agent_type, sid
+        );
+    }
+    // Field by field: sync may be setting `backfilled` at the same time.
+    for (field, value) in [
+        ("turns", json!(breakdown.turns.len())),
+        ("path", json!(path.to_string_lossy())),
+        ("updated_at", json!(Utc::now().to_rfc3339())),
+    ] {
+        if let Err(e) = kv.update_field(TAIL_SCOPE, &sid, field, &value).await {
+            warn!("failed to update tail marker for {}: {}", sid, e);
+        }
+    }
+
+    session.status = SessionStatus::Active;
+    session.ended_at = None;
+    if let Err(e) = kv.set("sessions", &sid, &session).await {
+        warn!("failed to store tailed session {}: {}", sid, e);
+    }
+    if let Err(e) = kv.set("context_breakdowns", &sid, breakdown).await {
+        warn!(
+            "failed to store breakdown for tailed session {}: {}",
+            sid, e
+        );
+    }
+
+    if let Err(e) = kv
+        .iii()
+        .trigger(TriggerRequest {
+            function_id: "rimuru.runaway.scan".to_string(),
+            payload: json!({ "session_id": sid }),
+            action: None,
+            timeout_ms: Some(10_000),
+        })
+        .await
+    {
+        warn!("runaway scan for tailed session {} failed: {}", sid, e);
+    }
+}
+
+/// The part of `cost` (the turn's `turn_cost`) spent on output tokens.
+fn output_cost(turn: &TurnRecord, cost: f64, catalog: &[ModelInfo]) -> f64 {
+    turn.model
+        .as_deref()
+        .and_then(|m| downgrade::resolve(catalog, m))
+        .map(|m| turn.output_tokens as f64 / 1_000_000.0 * m.output_price_per_million)
+        .unwrap_or(0.0)
+        .min(cost)
+}
+
+/// Record the turns of a tailed session that predate the tail, which sync
+/// would otherwise never record because it leaves tailed sessions to the
+/// tail. Runs once per session; returns whether a record was stored.
+pub(crate) async fn backfill_before_tail(
+    kv: &StateKV,
+    agent: &Agent,
+    session: &Session,
+    marker: &TailMarker,
+) -> bool {
+    let Some(first_turn) = marker.first_turn.filter(|n| *n > 0 && !marker.backfilled) else {
+        return false;
+    };
+    let sid = session.id.to_string();
+    // The tail stores the breakdown right after marking the session; until
+    // then there is nothing to price, and the next sync tries again.
+    let Ok(Some(breakdown)) = kv.get::<ContextBreakdown>("context_breakdowns", &sid).await else {
+        return false;
+    };
+    let catalog = downgrade::load_catalog(kv).await.unwrap_or_default();
+    let before = &breakdown.turns[..first_turn.min(breakdown.turns.len())];
+
+    let (mut input_cost, mut output) = (0.0, 0.0);
+    for turn in before {
+        let cost = runaway::turn_cost(turn, &catalog);
+        let out = output_cost(turn, cost, &catalog);
+        input_cost += cost - out;
+        output += out;
+    }
+    let model = before
+        .iter()
+        .find_map(|t| t.model.clone())
+        .or_else(|| session.model.clone())
+        .unwrap_or_else(|| "unknown".to_string());
+    let provider = downgrade::resolve(&catalog, &model)
+        .map(|m| m.provider_key())
+        .unwrap_or("unknown");
+    let mut record = CostRecord::new(
+        agent.id,
+        agent.agent_type,
+        model.clone(),
+        provider.to_string(),
+        before.iter().map(|t| t.input_tokens).sum(),
+        before.iter().map(|t| t.output_tokens).sum(),
+        input_cost,
+        output,
+    );
+    record.cache_read_tokens = before.iter().map(|t| t.cache_read).sum();
+    record.cache_write_tokens = before.iter().map(|t| t.cache_write).sum();
+    record.session_id = Some(session.id);
+    record.project_path = session.project_path.clone();
+    record.recorded_at = session.started_at;
+
+    let stored = record.total_cost > 0.0;
+    if stored && let Err(e) = costs::store_record(kv, &record).await {
+        warn!("failed to record pre-tail turns of {}: {}", sid, e);
+        return false;
+    }
+    if let Err(e) = kv.update_field(TAIL_SCOPE, &sid, "backfilled", &true).await {
+        warn!("failed to mark {} as backfilled: {}", sid, e);
+    }
+    stored
+}
+
+async fn record_cost(
+    kv: &StateKV,
+    agent_id: Uuid,
+    agent_type: AgentType,
+    session: &Session,
+    turn: &TurnRecord,
+    cost: f64,
+    catalog: &[ModelInfo],
+) {
+    let model = turn.model.as_deref().unwrap_or("unknown");
+    let priced = downgrade::resolve(catalog, model);
+    let output_cost = output_cost(turn, cost, catalog);
+
+    let result = kv
+        .iii()
+        .trigger(TriggerRequest {
+            function_id: "rimuru.costs.record".to_string(),
+            payload: json!({
+                "headers": jwt::internal_headers(),
+                "agent_id": agent_id.to_string(),
+                "agent_type": agent_type,
+                "session_id": session.id.to_string(),
+                "project_path": session.project_path,
+                "model": model,
+                "provider": priced.map(|m| m.provider_key()).unwrap_or("unknown"),
+                "input_tokens": turn.input_tokens,
+                "output_tokens": turn.output_tokens,
+                "cache_read_tokens": turn.cache_read,
+                "cache_write_tokens": turn.cache_write,
+                "input_cost": cost - output_cost,
+                "output_cost": output_cost,
+            }),
+            action: None,
+            timeout_ms: Some(10_000),
+        })
+        .await;
+    if let Err(e) = result {
+        warn!(
+            "failed to record tailed turn {} of {}: {}",
+            turn.turn_index, session.id, e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn source(dir: &Path) -> Source {
+        Source {
+            agent_type: AgentType::ClaudeCode,
+            dir: dir.to_path_buf(),
+            parser: |p| Box::new(claude_code::JsonlParser::for_file(p)),
+        }
+    }
+
+    fn line(ts: &str, input_tokens: u64) -> String {
+        json!({
+            "timestamp": ts,
+            "message": {
+                "role": "assistant",
+                "model": "claude-sonnet-4-6",
+                "usage": { "input_tokens": input_tokens, "output_tokens": 10 },
+                "content": [{ "type": "text", "text": "ok" }],
+            }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn reads_only_complete_appended_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir
+            .path()
+            .join("proj/3f6c2a1e-0000-4000-8000-000000000001.jsonl");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut file = std::fs::File::create(&path).unwrap();
+        let src = source(dir.path());
+        let mut tailer = Tailer::new("2026-01-01T00:00:00Z".parse().unwrap(), HashMap::new());
+
+        writeln!(file, "{}", line("2025-12-31T23:59:00Z", 100)).unwrap();
+        writeln!(file, "{}", line("2026-01-01T00:01:00Z", 200)).unwrap();
+        let turns = tailer.poll(&src, &path).unwrap();
+        // The first turn predates the tail and was left to sync.
+        assert_eq!(turns.len(), 1);
+        assert_eq!(turns[0].input_tokens, 200);
+
+        let next = line("2026-01-01T00:02:00Z", 300);
+        let (head, rest) = next.split_at(20);
+        write!(file, "{}", head).unwrap();
+        assert!(tailer.poll(&src, &path).unwrap().is_empty());
+        writeln!(file, "{}", rest).unwrap();
+        let turns = tailer.poll(&src, &path).unwrap();
+        assert_eq!(turns.len(), 1);
+        assert_eq!(turns[0].turn_index, 2);
+
+        let parser = tailer.parser(&path).unwrap();
+        assert_eq!(parser.breakdown().turns.len(), 3);
+        assert_eq!(parser.project_path(), Some("proj"));
+        assert_eq!(
+            parser.breakdown().session_id.to_string(),
+            "3f6c2a1e-0000-4000-8000-000000000001"
+        );
+    }
+
+    #[test]
+    fn truncated_file_is_not_recorded_twice() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir
+            .path()
+            .join("proj/3f6c2a1e-0000-4000-8000-000000000002.jsonl");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let src = source(dir.path());
+        let mut tailer = Tailer::new("2026-01-01T00:00:00Z".parse().unwrap(), HashMap::new());
+
+        let two = format!(
+            "{}\n{}\n",
+            line("2026-01-01T00:01:00Z", 100),
+            line("2026-01-01T00:02:00Z", 100)
+        );
+        std::fs::write(&path, &two).unwrap();
+        assert_eq!(tailer.poll(&src, &path).unwrap().len(), 2);
+
+        // Rewritten shorter: the turns it still holds were handed on.
+        std::fs::write(&path, format!("{}\n", line("2026-01-01T00:01:00Z", 100))).unwrap();
+        assert!(tailer.poll(&src, &path).unwrap().is_empty());
+
+        let three = format!("{}{}\n", two, line("2026-01-01T00:03:00Z", 50));
+        std::fs::write(&path, &three).unwrap();
+        let turns = tailer.poll(&src, &path).unwrap();
+        assert_eq!(turns.len(), 1);
+        assert_eq!(turns[0].input_tokens, 50);
+    }
+
+    #[test]
+    fn evicted_file_resumes_after_what_it_handed_on() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir
+            .path()
+            .join("proj/3f6c2a1e-0000-4000-8000-000000000004.jsonl");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let src = source(dir.path());
+        let mut tailer = Tailer::new("2026-01-01T00:00:00Z".parse().unwrap(), HashMap::new());
+
+        let mut file = std::fs::File::create(&path).unwrap();
+        writeln!(file, "{}", line("2026-01-01T00:01:00Z", 100)).unwrap();
+        assert_eq!(tailer.poll(&src, &path).unwrap().len(), 1);
+
+        tailer.evict_idle(Duration::ZERO);
+        assert!(tailer.parser(&path).is_none());
+        writeln!(file, "{}", line("2026-01-01T00:02:00Z", 200)).unwrap();
+        let turns = tailer.poll(&src, &path).unwrap();
+        assert_eq!(turns.len(), 1);
+        assert_eq!(turns[0].input_tokens, 200);
+    }
+
+    #[test]
+    fn resumes_a_session_an_earlier_run_tailed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir
+            .path()
+            .join("proj/3f6c2a1e-0000-4000-8000-000000000003.jsonl");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let src = source(dir.path());
+        // The previous run recorded one turn; two more were written while
+        // the worker was down, all before this run started.
+        let resume = HashMap::from([("3f6c2a1e-0000-4000-8000-000000000003".parse().unwrap(), 1)]);
+        let mut tailer = Tailer::new("2026-01-02T00:00:00Z".parse().unwrap(), resume);
+
+        let lines: Vec<String> = (1..=3)
+            .map(|i| line(&format!("2026-01-01T00:0{}:00Z", i), i * 100))
+            .collect();
+        std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+        let turns = tailer.poll(&src, &path).unwrap();
+        assert_eq!(turns.len(), 2);
+        assert_eq!(turns[0].input_tokens, 200);
+    }
+}
@@ -0,0 +1,303 @@
+//! Pre-tokenizers. These reproduce the tiktoken split regexes by hand because
+//! the `regex` crate has no look-ahead, which both patterns rely on.
+
+use std::cmp::Ordering;
+use std::sync::LazyLock;
+
+use regex_syntax::hir::{self, HirKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Split {
+    /// `cl100k_base`: letter runs with one leading non-letter, standalone
+    /// contractions, `\r\n` glued to punctuation.
+    Cl100k,
+    /// `o200k_base`: letter runs split at case changes (`CamelCase` ->
+    /// `Camel`, `Case`), contractions glued to the preceding word.
+    O200k,
+}
+
+const CONTRACTIONS: [&str; 7] = ["s", "t", "re", "ve", "m", "ll", "d"];
+
+/// A Unicode class as sorted ranges, parsed from the same `\p{..}` syntax the
+/// tiktoken patterns use. The `char` methods follow derived properties
+/// instead: `is_alphabetic` takes in `Nl` and some marks but not others.
+struct Class(Vec<(char, char)>);
+
+impl Class {
+    fn new(pattern: &str) -> Self {
+        let hir = regex_syntax::parse(pattern).expect("valid class");
+        let HirKind::Class(hir::Class::Unicode(class)) = hir.kind() else {
+            unreachable!("{pattern} is a Unicode class");
+        };
+        Self(
+            class
+                .ranges()
+                .iter()
+                .map(|r| (r.start(), r.end()))
+                .collect(),
+        )
+    }
+
+    fn contains(&self, c: char) -> bool {
+        self.0
+            .binary_search_by(|&(lo, hi)| {
+                if hi < c {
+                    Ordering::Less
+                } else if lo > c {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
+                }
+            })
+            .is_ok()
+    }
+}
+
+static LETTER: LazyLock<Class> = LazyLock::new(|| Class::new(r"\p{L}"));
+static UPPER: LazyLock<Class> = LazyLock::new(|| Class::new(r"[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]"));
+static LOWER: LazyLock<Class> = LazyLock::new(|| Class::new(r"[\p{Ll}\p{Lm}\p{Lo}\p{M}]"));
+
+fn at(text: &str, pos: usize) -> Option<char> {
+    text.get(pos..).and_then(|s| s.chars().next())
+}
+
+fn is_newline(c: char) -> bool {
+    c == '\r' || c == '\n'
+}
+
+/// `\p{L}`.
+fn is_letter(c: char) -> bool {
+    if c.is_ascii() {
+        c.is_ascii_alphabetic()
+    } else {
+        LETTER.contains(c)
+    }
+}
+
+/// `\p{N}`, which is exactly what `is_numeric` covers.
+fn is_number(c: char) -> bool {
+    c.is_numeric()
+}
+
+fn is_punct(c: char) -> bool {
+    !c.is_whitespace() && !is_letter(c) && !is_number(c)
+}
+
+/// `[^\r\n\p{L}\p{N}]`: what may lead a letter run.
+fn is_prefix(c: char) -> bool {
+    !is_newline(c) && !is_letter(c) && !is_number(c)
+}
+
+/// o200k's `[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]`.
+fn is_upperish(c: char) -> bool {
+    if c.is_ascii() {
+        c.is_ascii_uppercase()
+    } else {
+        UPPER.contains(c)
+    }
+}
+
+/// o200k's `[\p{Ll}\p{Lm}\p{Lo}\p{M}]`.
+fn is_lowerish(c: char) -> bool {
+    if c.is_ascii() {
+        c.is_ascii_lowercase()
+    } else {
+        LOWER.contains(c)
+    }
+}
+
+fn run(text: &str, mut pos: usize, pred: impl Fn(char) -> bool) -> usize {
+    while let Some(c) = at(text, pos).filter(|&c| pred(c)) {
+        pos += c.len_utf8();
+    }
+    pos
+}
+
+/// Length of a `'s`/`'ll`/... suffix at `pos`, matched case-insensitively.
+fn contraction(text: &str, pos: usize) -> usize {
+    let Some(rest) = text.get(pos..).and_then(|s| s.strip_prefix('\'')) else {
+        return 0;
+    };
+    CONTRACTIONS
+        .iter()
+        .find(|c| {
+            rest.get(..c.len())
+                .is_some_and(|head| head.eq_ignore_ascii_case(c))
+        })
+        .map_or(0, |c| 1 + c.len())
+}
+
+/// o200k's `[upper]*[lower]+` at `pos`, backing the upper run off until a
+/// lower char follows (the classes overlap on caseless letters and marks).
+fn mixed_case(text: &str, pos: usize) -> Option<usize> {
+    let mut split = run(text, pos, is_upperish);
+    while !at(text, split).is_some_and(is_lowerish) {
+        split -= text[pos..split].chars().next_back()?.len_utf8();
+    }
+    Some(run(text, split, is_lowerish))
+}
+
+/// o200k's `[upper]+[lower]*` at `pos`.
+fn upper_case(text: &str, pos: usize) -> Option<usize> {
+    let end = run(text, pos, is_upperish);
+    (end > pos).then(|| run(text, end, is_lowerish))
+}
+
+impl Split {
+    pub fn pieces(self, text: &str) -> Pieces<'_> {
+        Pieces {
+            split: self,
+            text,
+            pos: 0,
+        }
+    }
+
+    /// End of the word starting at `pos`, if one does: a letter run with an
+    /// optional leading `[^\r\n\p{L}\p{N}]`, tried prefix-first like the
+    /// regex alternatives.
+    fn word(self, text: &str, pos: usize) -> Option<usize> {
+        let c = at(text, pos)?;
+        let next = pos + c.len_utf8();
+        let prefixed = is_prefix(c);
+        match self {
+            Split::Cl100k => {
+                // `[^\r\n\p{L}\p{N}]?+\p{L}+`: the prefix is possessive.
+                let start = if prefixed { next } else { pos };
+                let end = run(text, start, is_letter);
+                (end > start).then_some(end)
+            }
+            Split::O200k => {
+                let end = [mixed_case, upper_case].into_iter().find_map(|pattern| {
+                    prefixed
+                        .then(|| pattern(text, next))
+                        .flatten()
+                        .or_else(|| pattern(text, pos))
+                })?;
+                Some(end + contraction(text, end))
+            }
+        }
+    }
+
+    fn next_end(self, text: &str, pos: usize) -> usize {
+        let c = at(text, pos).expect("pos is inside text");
+        let next = pos + c.len_utf8();
+
+        if self == Split::Cl100k {
+            let n = contraction(text, pos);
+            if n > 0 {
+                return pos + n;
+            }
+        }
+        if let Some(end) = self.word(text, pos) {
+            return end;
+        }
+        if is_number(c) {
+            let mut end = pos;
+            for _ in 0..3 {
+                match at(text, end).filter(|&c| is_number(c)) {
+                    Some(d) => end += d.len_utf8(),
+                    None => break,
+                }
+            }
+            return end;
+        }
+        let punct_start = if c == ' ' && at(text, next).is_some_and(is_punct) {
+            next
+        } else {
+            pos
+        };
+        if at(text, punct_start).is_some_and(is_punct) {
+            let end = run(text, punct_start, is_punct);
+            return match self {
+                Split::Cl100k => run(text, end, is_newline),
+                Split::O200k => run(text, end, |c| is_newline(c) || c == '/'),
+            };
+        }
+
+        // Whitespace: up to the last newline of the run; otherwise the run
+        // minus its last char when a word follows, so that char can prefix it.
+        let end = run(text, pos, char::is_whitespace);
+        let ws = &text[pos..end];
+        if let Some(nl) = ws.rfind(is_newline) {
+            return pos + nl + 1;
+        }
+        if end == text.len() {
+            return end;
+        }
+        match ws.chars().next_back() {
+            Some(last) if ws.len() > last.len_utf8() => end - last.len_utf8(),
+            _ => next,
+        }
+    }
+}
+
+pub struct Pieces<'a> {
+    split: Split,
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Iterator for Pieces<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        if self.pos >= self.text.len() {
+            return None;
+        }
+        let start = self.pos;
+        self.pos = self.split.next_end(self.text, start);
+        Some(&self.text[start..self.pos])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(s: Split, text: &str) -> Vec<&str> {
+        s.pieces(text).collect()
+    }
+
+    #[test]
+    fn cl100k_matches_tiktoken_pieces() {
+        assert_eq!(
+            split(Split::Cl100k, "Hello world, it's 12345!\n\n  fn main() {}"),
+            vec![
+                "Hello", " world", ",", " it", "'s", " ", "123", "45", "!\n\n", " ", " fn",
+                " main", "()", " {}"
+            ]
+        );
+        assert_eq!(
+            split(Split::Cl100k, "a  \n b   "),
+            vec!["a", "  \n", " b", "   "]
+        );
+    }
+
+    #[test]
+    fn o200k_splits_case_and_glues_contractions() {
+        assert_eq!(
+            split(Split::O200k, "parseJSONValue don't path/to"),
+            vec!["parse", "JSONValue", " don't", " path", "/to"]
+        );
+        assert_eq!(split(Split::O200k, "HTTPServer"), vec!["HTTPServer"]);
+        assert_eq!(split(Split::O200k, "東京タワー"), vec!["東京タワー"]);
+    }
+
+    #[test]
+    fn letters_follow_general_categories() {
+        // A virama is a mark: punctuation to cl100k, part of the word to o200k.
+        assert_eq!(
+            split(Split::Cl100k, "\u{915}\u{94d}\u{937}"),
+            vec!["\u{915}", "\u{94d}\u{937}"]
+        );
+        assert_eq!(
+            split(Split::O200k, " \u{915}\u{94d}\u{937}"),
+            vec![" \u{915}\u{94d}\u{937}"]
+        );
+        // Roman numerals are numbers, not letters.
+        assert_eq!(
+            split(Split::Cl100k, " \u{216b} "),
+            vec![" ", "\u{216b}", " "]
+        );
+    }
+}
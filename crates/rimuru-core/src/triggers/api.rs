@@ -257,6 +257,62 @@ const ROUTES: &[Route] = &[
         path: "api/budget/alerts",
         function_id: "rimuru.budget.alerts",
     },
+    Route {
+        method: "GET",
+        path: "api/budget/forecast",
+        function_id: "rimuru.budget.forecast",
+    },
+    Route {
+        method: "POST",
+        path: "api/budget/reserve",
+        function_id: "rimuru.budget.reserve",
+    },
+    Route {
+        method: "POST",
+        path: "api/budget/commit",
+        function_id: "rimuru.budget.commit",
+    },
+    Route {
+        method: "POST",
+        path: "api/budget/release",
+        function_id: "rimuru.budget.release",
+    },
+    Route {
+        method: "GET",
+        path: "api/budget/reservations",
+        function_id: "rimuru.budget.reservations",
+    },
+    Route {
+        method: "POST",
+        path: "api/budget/overrides",
+        function_id: "rimuru.budget.override.create",
+    },
+    Route {
+        method: "GET",
+        path: "api/budget/overrides",
+        function_id: "rimuru.budget.override.list",
+    },
+    Route {
+        method: "POST",
+        path: "api/budget/overrides/:id/revoke",
+        function_id: "rimuru.budget.override.revoke",
+    },
+    // Currencies
+    Route {
+        method: "GET",
+        path: "api/currency/rates",
+        function_id: "rimuru.currency.rates",
+    },
+    Route {
+        method: "POST",
+        path: "api/currency/rates",
+        function_id: "rimuru.currency.set_rate",
+    },
+    Route {
+        method: "GET",
+        path: "api/currency/convert",
+        function_id: "rimuru.currency.convert",
+    },
     // Runaway detection
     Route {
         method: "POST",
@@ -278,6 +334,31 @@ const ROUTES: &[Route] = &[
         path: "api/runaway/configure",
         function_id: "rimuru.runaway.configure",
     },
+    Route {
+        method: "GET",
+        path: "api/runaway/detectors",
+        function_id: "rimuru.runaway.detector.list",
+    },
+    Route {
+        method: "POST",
+        path: "api/runaway/detectors",
+        function_id: "rimuru.runaway.detector.register",
+    },
+    Route {
+        method: "DELETE",
+        path: "api/runaway/detectors/:id",
+        function_id: "rimuru.runaway.detector.unregister",
+    },
+    Route {
+        method: "POST",
+        path: "api/runaway/backtest",
+        function_id: "rimuru.runaway.backtest",
+    },
+    Route {
+        method: "POST",
+        path: "api/runaway/labels",
+        function_id: "rimuru.runaway.label",
+    },
     // Tree-sitter signature indexer
     Route {
         method: "POST",
@@ -373,6 +454,31 @@ const ROUTES: &[Route] = &[
         path: "api/guard/history",
         function_id: "rimuru.guard.history",
     },
+    Route {
+        method: "GET",
+        path: "api/guard/:id/usage",
+        function_id: "rimuru.guard.usage",
+    },
+    Route {
+        method: "POST",
+        path: "api/guard/:id/kill",
+        function_id: "rimuru.guard.kill",
+    },
+    Route {
+        method: "POST",
+        path: "api/guard/:id/pause",
+        function_id: "rimuru.guard.pause",
+    },
+    Route {
+        method: "POST",
+        path: "api/guard/:id/resume",
+        function_id: "rimuru.guard.resume",
+    },
+    Route {
+        method: "POST",
+        path: "api/guard/:id/extend",
+        function_id: "rimuru.guard.extend",
+    },
 ];
 
 /// HTTP API version. Bumped when we make a breaking change to any
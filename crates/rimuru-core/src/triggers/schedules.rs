@@ -1,48 +1,170 @@
-use iii_sdk::{III, RegisterTriggerInput};
-use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use iii_sdk::{III, RegisterTriggerInput, Trigger};
+use serde_json::{Value, json};
 use tracing::info;
 
+use crate::state::StateKV;
+
 struct Schedule {
     cron: &'static str,
     function_id: &'static str,
+    /// Config key holding a cron expression that replaces `cron`.
+    cron_key: Option<&'static str>,
+    /// Config flag that must be `true` for the schedule to run at all.
+    enabled_key: Option<&'static str>,
 }
 
 const SCHEDULES: &[Schedule] = &[
     Schedule {
         cron: "0 */5 * * * *",
         function_id: "rimuru.metrics.collect",
+        cron_key: None,
+        enabled_key: None,
     },
     Schedule {
         cron: "0 0 */6 * * *",
         function_id: "rimuru.models.sync",
+        cron_key: None,
+        enabled_key: None,
     },
+    // Hourly so the rollup follows local midnight for whatever timezone is
+    // configured; each run re-finalizes the previous local day.
     Schedule {
-        cron: "0 0 0 * * *",
+        cron: "0 5 * * * *",
         function_id: "rimuru.costs.daily_rollup",
+        cron_key: None,
+        enabled_key: None,
     },
     Schedule {
         cron: "0 0 1 * * *",
         function_id: "rimuru.sessions.cleanup",
+        cron_key: None,
+        enabled_key: None,
+    },
+    // Corrects drift in the budget spend counters from backfilled or
+    // cleaned-up cost records.
+    Schedule {
+        cron: "0 35 * * * *",
+        function_id: "rimuru.budget.reconcile",
+        cron_key: None,
+        enabled_key: None,
+    },
+    // Off until `runaway.configure` sets `auto_scan_enabled`, since the scan
+    // can act on guarded processes (`runaway_action`).
+    Schedule {
+        cron: "0 * * * * *",
+        function_id: "rimuru.runaway.scan",
+        cron_key: Some("runaway_scan_cron"),
+        enabled_key: Some("runaway_auto_scan_enabled"),
     },
 ];
 
+/// Live cron triggers by function id, with the expression each runs on, so
+/// configurable schedules can be swapped without restarting the worker.
+static ACTIVE: LazyLock<Mutex<HashMap<&'static str, (String, Trigger)>>> =
+    LazyLock::new(Mutex::default);
+
+/// Default cron for a scheduled function, if it has one.
+pub fn default_cron(function_id: &str) -> Option<&'static str> {
+    SCHEDULES
+        .iter()
+        .find(|s| s.function_id == function_id)
+        .map(|s| s.cron)
+}
+
+/// iii cron expressions carry a seconds field: `sec min hour dom mon dow`,
+/// with an optional trailing year.
+pub fn validate_cron(expr: &str) -> Result<(), String> {
+    let fields = expr.split_whitespace().count();
+    if !(6..=7).contains(&fields) {
+        return Err(format!(
+            "cron expression '{}' must have 6 or 7 fields (sec min hour dom mon dow [year])",
+            expr
+        ));
+    }
+    Ok(())
+}
+
+/// Run `function_id` on `cron`, or stop running it when `cron` is `None`.
+fn set(iii: &III, function_id: &'static str, cron: Option<&str>) {
+    let mut active = ACTIVE.lock().unwrap_or_else(|e| e.into_inner());
+    if active.get(function_id).map(|(c, _)| c.as_str()) == cron {
+        return;
+    }
+    if let Some((_, trigger)) = active.remove(function_id) {
+        trigger.unregister();
+    }
+    let Some(cron) = cron else {
+        info!("Stopped cron trigger {}", function_id);
+        return;
+    };
+    match iii.register_trigger(RegisterTriggerInput {
+        trigger_type: "cron".to_string(),
+        function_id: function_id.to_string(),
+        config: json!({"expression": cron}),
+    }) {
+        Ok(trigger) => {
+            active.insert(function_id, (cron.to_string(), trigger));
+        }
+        Err(e) => {
+            tracing::error!("Failed to register cron trigger {}: {}", function_id, e);
+        }
+    }
+}
+
 pub fn register(iii: &III) {
+    let fixed: Vec<&Schedule> = SCHEDULES
+        .iter()
+        .filter(|s| s.enabled_key.is_none())
+        .collect();
+    for schedule in &fixed {
+        set(iii, schedule.function_id, Some(schedule.cron));
+    }
+
+    info!("Registered {} cron triggers", fixed.len());
+}
+
+/// Bring configurable schedules in line with the `config` scope. Called
+/// once the worker is connected and again whenever their keys change.
+pub async fn apply_config(iii: &III, kv: &StateKV) {
     for schedule in SCHEDULES {
-        match iii.register_trigger(RegisterTriggerInput {
-            trigger_type: "cron".to_string(),
-            function_id: schedule.function_id.to_string(),
-            config: json!({"expression": schedule.cron}),
-        }) {
-            Ok(_) => {}
-            Err(e) => {
-                tracing::error!(
-                    "Failed to register cron trigger {}: {}",
-                    schedule.function_id,
-                    e
-                );
-            }
+        if schedule.cron_key.is_none() && schedule.enabled_key.is_none() {
+            continue;
         }
+        let enabled = match schedule.enabled_key {
+            Some(key) => matches!(
+                kv.get::<Value>("config", key).await,
+                Ok(Some(Value::Bool(true)))
+            ),
+            None => true,
+        };
+        let cron = match schedule.cron_key {
+            Some(key) => kv
+                .get::<Value>("config", key)
+                .await
+                .ok()
+                .flatten()
+                .and_then(|v| v.as_str().map(String::from))
+                .filter(|c| validate_cron(c).is_ok())
+                .unwrap_or_else(|| schedule.cron.to_string()),
+            None => schedule.cron.to_string(),
+        };
+        set(iii, schedule.function_id, enabled.then_some(cron.as_str()));
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    info!("Registered {} cron triggers", SCHEDULES.len());
+    #[test]
+    fn cron_needs_a_seconds_field() {
+        assert!(validate_cron("0 */2 * * * *").is_ok());
+        assert!(validate_cron("0 0 9 * * Mon-Fri 2026").is_ok());
+        assert!(validate_cron("*/2 * * * *").is_err());
+        assert!(validate_cron("").is_err());
+        assert_eq!(default_cron("rimuru.runaway.scan"), Some("0 * * * * *"));
+    }
 }
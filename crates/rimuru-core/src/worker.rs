@@ -29,6 +29,8 @@ impl RimuruWorker {
 
         tokio::time::sleep(std::time::Duration::from_millis(500)).await;
 
+        triggers::schedules::apply_config(&self.iii, &kv).await;
+
         match self
             .iii
             .trigger(TriggerRequest {
@@ -77,6 +79,31 @@ impl RimuruWorker {
             }
         }
 
+        // Counters may predate records synced from disk or written by an
+        // older worker; bring them in line before the first budget check.
+        match self
+            .iii
+            .trigger(TriggerRequest {
+                function_id: "rimuru.budget.reconcile".to_string(),
+                payload: json!({}),
+                action: None,
+                timeout_ms: None,
+            })
+            .await
+        {
+            Ok(result) => {
+                let corrected = result
+                    .get("body")
+                    .and_then(|b| b.get("corrected"))
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(0);
+                info!("Reconciled spend counters ({} corrected)", corrected);
+            }
+            Err(e) => tracing::warn!("Failed to reconcile spend counters: {}", e),
+        }
+
+        crate::tail::spawn(&kv).await;
+
         match self
             .iii
             .trigger(TriggerRequest {
@@ -118,6 +118,49 @@ fn claude_code_adapter_handles_tool_use_blocks() {
     );
 }
 
+#[test]
+fn claude_code_adapter_fingerprints_tool_arguments() {
+    let dir = tempfile::tempdir().unwrap();
+    let session_id = "55555555-5555-5555-5555-555555555555";
+    let projects = dir.path().join(".claude").join("projects").join("p");
+    let path = projects.join(format!("{session_id}.jsonl"));
+    let read = |id: &str, file: &str| {
+        serde_json::json!({
+            "timestamp": "2026-01-01T00:00:00Z",
+            "sessionId": session_id,
+            "message": {
+                "role": "assistant",
+                "model": "claude-sonnet-4-5",
+                "usage": {"input_tokens": 50, "output_tokens": 30},
+                "content": [
+                    {"type": "tool_use", "id": id, "name": "Read", "input": {"file_path": file}},
+                ],
+            }
+        })
+    };
+    write_jsonl(
+        &path,
+        &[
+            read("tu1", "/src/lib.rs"),
+            read("tu2", "/src/main.rs"),
+            read("tu3", "/src/lib.rs"),
+        ],
+    );
+
+    let mut adapter = ClaudeCodeAdapter::new();
+    adapter.set_config_path_for_bench(dir.path().join(".claude"));
+    let (_session, breakdown) = adapter.parse_session_jsonl_full(&path).unwrap();
+    let calls: Vec<_> = breakdown
+        .turns
+        .iter()
+        .flat_map(|t| t.tool_calls.iter())
+        .collect();
+    assert_eq!(calls.len(), 3);
+    assert_eq!(calls[0].target_path.as_deref(), Some("/src/lib.rs"));
+    assert_eq!(calls[0].arg_fingerprint, calls[2].arg_fingerprint);
+    assert_ne!(calls[0].arg_fingerprint, calls[1].arg_fingerprint);
+}
+
 #[test]
 fn claude_code_adapter_is_installed_true_when_dir_exists() {
     let dir = tempfile::tempdir().unwrap();
@@ -0,0 +1,75 @@
+//! Runaway detection against recorded agent transcripts.
+//!
+//! The fixtures under `tests/fixtures/` are trimmed real-world shapes of
+//! each agent's session log, replayed through the adapter that would parse
+//! them in production and then through `analyze_turns`.
+
+use std::path::PathBuf;
+
+use rimuru_core::adapters::{ClaudeCodeAdapter, CodexAdapter};
+use rimuru_core::functions::runaway::{RunawayAnalysis, RunawayConfig, analyze_turns};
+use rimuru_core::models::ContextBreakdown;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+fn analyze(breakdown: &ContextBreakdown) -> RunawayAnalysis {
+    analyze_turns(
+        breakdown.session_id,
+        &breakdown.turns,
+        &RunawayConfig::default(),
+    )
+}
+
+#[test]
+fn claude_code_edit_revert_loop_is_flagged() {
+    let adapter = ClaudeCodeAdapter::new();
+    let (_session, breakdown) = adapter
+        .parse_session_jsonl_full(&fixture("claude_code_edit_ping_pong.jsonl"))
+        .unwrap();
+
+    let analysis = analyze(&breakdown);
+    let pattern = analysis
+        .patterns
+        .iter()
+        .find(|p| p.pattern_type == "edit_ping_pong")
+        .expect("edit_ping_pong pattern");
+    assert_eq!(pattern.metadata["file"], "/home/dev/parser/src/lexer.rs");
+    assert_eq!(pattern.metadata["cycles"], 2);
+    // From the first edit through the re-applied edit.
+    assert_eq!(pattern.metadata["first_turn"], 1);
+    assert_eq!(pattern.metadata["last_turn"], 5);
+    // 18k cache reads a turn count alongside input and output.
+    assert_eq!(pattern.metadata["wasted_tokens"], 110_850);
+    assert!(analysis.is_runaway);
+}
+
+#[test]
+fn codex_patch_revert_loop_is_flagged() {
+    let adapter = CodexAdapter::new();
+    let breakdown = adapter
+        .parse_session_breakdown(&fixture("codex_edit_ping_pong.jsonl"))
+        .unwrap();
+    assert_eq!(
+        breakdown.session_id.to_string(),
+        "0199f3a2-7c41-7e60-b2d8-5a9c3e1f4d72"
+    );
+    assert_eq!(breakdown.turns.len(), 8);
+
+    let analysis = analyze(&breakdown);
+    let pattern = analysis
+        .patterns
+        .iter()
+        .find(|p| p.pattern_type == "edit_ping_pong")
+        .expect("edit_ping_pong pattern");
+    // Patches run as a custom tool and through `shell` count alike.
+    assert_eq!(pattern.metadata["file"], "src/lru.rs");
+    assert_eq!(pattern.metadata["cycles"], 3);
+    assert_eq!(pattern.metadata["wasted_tokens"], 82_170);
+    assert!(pattern.severity > 0.9);
+    assert!(analysis.is_runaway);
+}
@@ -33,4 +33,13 @@ impl ApiClient {
             .await
             .ok()
     }
+
+    pub async fn post(&self, path: &str, body: &Value) -> Option<Value> {
+        let url = format!("{}/api{}", self.base, path);
+        let resp = self.client.post(&url).json(body).send().await.ok()?;
+        if !resp.status().is_success() {
+            return None;
+        }
+        resp.json::<Value>().await.ok()
+    }
 }
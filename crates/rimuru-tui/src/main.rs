@@ -70,6 +70,15 @@ mod smoke_tests {
             "action_on_exceed": "alert",
             "alert_threshold": 0.8,
         });
+        app.budget_forecast = serde_json::json!({
+            "status": "at_risk",
+            "week_end": {"date": "2026-04-26", "spent": 40.0, "projected": {"expected": 70.0, "low": 62.0, "high": 78.0}},
+            "month_end": {"date": "2026-04-30", "spent": 180.2, "projected": {"expected": 330.0, "low": 300.0, "high": 520.0}},
+            "caps": [
+                {"label": "Monthly", "limit": 500.0, "spent": 180.2, "expected_hit_date": null, "earliest_hit_date": "2026-04-29"},
+                {"label": "Daily", "limit": 50.0, "spent": 12.5, "expected_hit_date": null, "earliest_hit_date": null}
+            ]
+        });
         app.context_utilization = serde_json::json!({
             "utilizations": [
                 {
@@ -81,6 +90,15 @@ mod smoke_tests {
                 }
             ]
         });
+        app.guards = vec![serde_json::json!({
+            "id": "7f3a9c10-2b4d-4e8f-9a61-0c5d3e2f1b7a",
+            "pid": 48211,
+            "command": "codex exec fix the flaky test",
+            "limit": 5.0,
+            "current_cost": 1.42,
+            "action": "kill",
+            "session_ids": ["49316303-4e10-4465-bad9-47d2b414f6ab"]
+        })];
         for _ in 0..30 {
             app.push_token_rate_for_test(rand_rate());
         }
@@ -110,6 +128,7 @@ mod smoke_tests {
         for view in [
             View::Home, View::Costs, View::Budget, View::Models, View::Advisor,
             View::Context, View::McpProxy, View::Hooks, View::Plugins, View::Mcp, View::Metrics,
+            View::Guards,
         ] {
             app.set_view(view);
             terminal.draw(|f| ui::draw(f, &mut app)).unwrap();
@@ -166,6 +185,19 @@ mod smoke_tests {
         });
     }
 
+    #[test]
+    fn budget_renders_in_reported_currency() {
+        let backend = TestBackend::new(160, 48);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut app = App::new();
+        populate(&mut app);
+        app.budget_status["currency"] = serde_json::json!("EUR");
+        app.set_view(View::Budget);
+        terminal.draw(|f| ui::draw(f, &mut app)).unwrap();
+        let text = buffer_text(&terminal);
+        assert!(text.contains("€180.20 / €500.00"), "budget not in EUR\n{}", text);
+    }
+
     #[test]
     fn home_contains_expected_labels() {
         let backend = TestBackend::new(160, 48);
@@ -224,6 +256,16 @@ async fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Resul
             if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
                 return Ok(());
             }
+            // A pending kill takes `y` to confirm; any other key cancels it.
+            if app.pending_kill.is_some() {
+                if key.code == KeyCode::Char('y') {
+                    app.confirm_kill(&client).await;
+                } else {
+                    app.pending_kill = None;
+                    app.set_status("kill cancelled");
+                }
+                continue;
+            }
             match key.code {
                 KeyCode::Char('q') => return Ok(()),
                 KeyCode::Esc | KeyCode::Char('h') => {
@@ -232,6 +274,12 @@ async fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Resul
                     }
                 }
                 KeyCode::Char('t') => app.next_theme(),
+                KeyCode::Char('g') => {
+                    app.set_view(View::Guards);
+                    app.fetch(&client).await;
+                    last_fetch = Instant::now();
+                }
+                KeyCode::Char('x') if app.view == View::Guards => app.request_kill(),
                 KeyCode::Char('r') => {
                     app.set_status("refreshing…");
                     app.fetch(&client).await;
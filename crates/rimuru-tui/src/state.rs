@@ -6,6 +6,7 @@ use serde_json::Value;
 
 use crate::client::ApiClient;
 use crate::theme::{self, Theme};
+use crate::widgets::currency_of;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum View {
@@ -20,6 +21,7 @@ pub enum View {
     Plugins,
     Mcp,
     Metrics,
+    Guards,
 }
 
 impl View {
@@ -36,6 +38,7 @@ impl View {
             View::Plugins => "plugins",
             View::Mcp => "mcp",
             View::Metrics => "metrics",
+            View::Guards => "guards",
         }
     }
 
@@ -106,6 +109,8 @@ pub struct App {
     pub hardware: Value,
     pub cost_summary: Value,
     pub daily_costs: Vec<Value>,
+    /// Currency `/costs/*` reported in on the last fetch.
+    pub display_currency: String,
     pub models: Vec<Value>,
     pub advisories: Vec<Value>,
     pub catalog: Vec<Value>,
@@ -113,11 +118,13 @@ pub struct App {
     pub hooks: Vec<Value>,
     pub plugins: Vec<Value>,
     pub mcp_servers: Vec<Value>,
+    pub guards: Vec<Value>,
     pub context_utilization: Value,
     pub context_waste: Value,
     pub mcp_proxy_stats: Value,
     pub budget_status: Value,
     pub budget_alerts: Value,
+    pub budget_forecast: Value,
     pub total_savings: f64,
     pub catalog_summary_cache: (usize, usize, usize, usize),
 
@@ -125,6 +132,8 @@ pub struct App {
     pub prev_total_tokens: Option<u64>,
     pub prev_fetch_time: Option<Instant>,
     pub status_msg: Option<(String, Instant)>,
+    /// Guard a kill was asked for, waiting on `y` to confirm it.
+    pub pending_kill: Option<String>,
 }
 
 impl App {
@@ -145,6 +154,7 @@ impl App {
             hardware: Value::Null,
             cost_summary: Value::Null,
             daily_costs: Vec::new(),
+            display_currency: "USD".to_string(),
             models: Vec::new(),
             advisories: Vec::new(),
             catalog: Vec::new(),
@@ -152,17 +162,20 @@ impl App {
             hooks: Vec::new(),
             plugins: Vec::new(),
             mcp_servers: Vec::new(),
+            guards: Vec::new(),
             context_utilization: Value::Null,
             context_waste: Value::Null,
             mcp_proxy_stats: Value::Null,
             budget_status: Value::Null,
             budget_alerts: Value::Null,
+            budget_forecast: Value::Null,
             total_savings: 0.0,
             catalog_summary_cache: (0, 0, 0, 0),
             token_rates: VecDeque::with_capacity(120),
             prev_total_tokens: None,
             prev_fetch_time: None,
             status_msg: None,
+            pending_kill: None,
         }
     }
 
@@ -235,6 +248,7 @@ impl App {
             View::Hooks => self.hooks.len(),
             View::Plugins => self.plugins.len(),
             View::Mcp => self.mcp_servers.len(),
+            View::Guards => self.guards.len(),
             _ => 0,
         }
     }
@@ -248,6 +262,34 @@ impl App {
         self.sessions.get(self.selected)
     }
 
+    pub fn selected_guard(&self) -> Option<&Value> {
+        self.guards.get(self.scroll)
+    }
+
+    /// Arm a kill of the selected guard; nothing is sent until
+    /// `confirm_kill`.
+    pub fn request_kill(&mut self) {
+        self.pending_kill = self
+            .selected_guard()
+            .and_then(|g| g.get("id"))
+            .and_then(|v| v.as_str())
+            .map(String::from);
+    }
+
+    /// Ask the guard armed by `request_kill` to stop its process. The
+    /// wrapper acts on the request at its next poll, so the row lingers
+    /// for a few seconds.
+    pub async fn confirm_kill(&mut self, client: &ApiClient) {
+        let Some(id) = self.pending_kill.take() else {
+            return;
+        };
+        let short = &id[..8.min(id.len())];
+        match client.post(&format!("/guard/{}/kill", id), &Value::Null).await {
+            Some(_) => self.set_status(format!("kill sent to guard {}", short)),
+            None => self.set_status(format!("kill failed for guard {}", short)),
+        }
+    }
+
     pub async fn fetch(&mut self, client: &ApiClient) {
         let health = client.get("/health").await;
         self.connected = health
@@ -315,6 +357,7 @@ impl App {
                     client.get("/costs/daily"),
                 );
                 if let Some(v) = summary {
+                    self.display_currency = currency_of(&v).to_string();
                     self.cost_summary = v.get("summary").cloned().unwrap_or(v);
                 }
                 if let Some(v) = daily {
@@ -322,9 +365,16 @@ impl App {
                 }
             }
             View::Budget => {
-                if let Some(v) = client.get("/budget/alerts?limit=20").await {
+                let (alerts, forecast) = tokio::join!(
+                    client.get("/budget/alerts?limit=20"),
+                    client.get("/budget/forecast"),
+                );
+                if let Some(v) = alerts {
                     self.budget_alerts = v;
                 }
+                if let Some(v) = forecast {
+                    self.budget_forecast = v;
+                }
             }
             View::Models => {
                 if let Some(v) = client.get("/models").await {
@@ -366,6 +416,12 @@ impl App {
                 }
             }
             View::Mcp => {}
+            View::Guards => {
+                if let Some(v) = client.get("/guard").await {
+                    self.guards = unwrap_array(&v, "guards");
+                    self.scroll = self.scroll.min(self.guards.len().saturating_sub(1));
+                }
+            }
             View::Metrics => {
                 if let Some(v) = client.get("/metrics").await {
                     let inner = v.get("metrics").cloned().unwrap_or(v);
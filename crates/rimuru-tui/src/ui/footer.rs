@@ -10,13 +10,27 @@ pub(super) fn draw(f: &mut Frame, app: &App, area: Rect) {
     let theme = app.theme();
 
     let mut spans: Vec<Span> = Vec::new();
+    if let Some(id) = &app.pending_kill {
+        spans.push(Span::styled(
+            format!(" kill guard {}? ", &id[..8.min(id.len())]),
+            Style::default().fg(theme.warning_fg),
+        ));
+        push_key(&mut spans, "y", " confirm ", theme);
+        push_key(&mut spans, "any key", " cancel ", theme);
+        f.render_widget(Paragraph::new(Line::from(spans)), area);
+        return;
+    }
     if app.view == View::Home {
         push_key(&mut spans, "↑↓", " select ", theme);
         push_key(&mut spans, "1-9", " drill ", theme);
+        push_key(&mut spans, "g", " guards ", theme);
     } else {
         push_key(&mut spans, "esc", " home ", theme);
         push_key(&mut spans, "j/k", " scroll ", theme);
     }
+    if app.view == View::Guards {
+        push_key(&mut spans, "x", " kill ", theme);
+    }
     push_key(&mut spans, "t", " theme ", theme);
     push_key(&mut spans, "r", " refresh ", theme);
     push_key(&mut spans, "q", " quit ", theme);
@@ -66,6 +66,7 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         View::Plugins => views::draw_plugins(f, app, chunks[1]),
         View::Mcp => views::draw_mcp(f, app, chunks[1]),
         View::Metrics => views::draw_metrics(f, app, chunks[1]),
+        View::Guards => views::draw_guards(f, app, chunks[1]),
     }
 
     footer::draw(f, app, chunks[2]);
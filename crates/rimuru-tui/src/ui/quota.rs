@@ -5,7 +5,10 @@ use ratatui::text::{Line, Span};
 use ratatui::widgets::Paragraph;
 
 use crate::state::App;
-use crate::widgets::{btop_block, fmt_dollars, grad_at, make_gradient, remaining_bar, styled_label};
+use crate::widgets::{
+    btop_block, currency_of, fmt_dollars, fmt_money, grad_at, make_gradient, remaining_bar,
+    styled_label,
+};
 
 pub(super) fn draw(f: &mut Frame, app: &App, area: Rect) {
     let theme = app.theme();
@@ -27,6 +30,7 @@ pub(super) fn draw(f: &mut Frame, app: &App, area: Rect) {
 
     let bar_w = (inner.width as usize).saturating_sub(12).clamp(3, 20);
     let bs = &app.budget_status;
+    let code = currency_of(bs);
 
     let daily_limit = bs.get("daily_limit").and_then(|v| v.as_f64()).unwrap_or(0.0);
     let daily_used = bs.get("daily_spent").and_then(|v| v.as_f64()).unwrap_or(0.0);
@@ -61,7 +65,7 @@ pub(super) fn draw(f: &mut Frame, app: &App, area: Rect) {
             ));
             lines.push(Line::from(s));
             lines.push(Line::from(Span::styled(
-                format!("  {} / {}", fmt_dollars(daily_used), fmt_dollars(daily_limit)),
+                format!("  {} / {}", fmt_money(daily_used, code), fmt_money(daily_limit, code)),
                 Style::default().fg(theme.graph_text),
             )));
         }
@@ -77,7 +81,7 @@ pub(super) fn draw(f: &mut Frame, app: &App, area: Rect) {
             ));
             lines.push(Line::from(s));
             lines.push(Line::from(Span::styled(
-                format!("  {} / {}", fmt_dollars(monthly_used), fmt_dollars(monthly_limit)),
+                format!("  {} / {}", fmt_money(monthly_used, code), fmt_money(monthly_limit, code)),
                 Style::default().fg(theme.graph_text),
             )));
         }
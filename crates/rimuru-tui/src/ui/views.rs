@@ -6,7 +6,7 @@ use ratatui::widgets::{Cell, Paragraph, Row, Table};
 use serde_json::Value;
 
 use crate::state::App;
-use crate::widgets::{btop_block, fmt_dollars, fmt_tokens, grad_at, make_gradient, meter_bar, truncate_str};
+use crate::widgets::{btop_block, currency_of, fmt_dollars, fmt_money, fmt_tokens, grad_at, make_gradient, meter_bar, truncate_str};
 
 fn val_str(v: &Value, k: &str) -> String {
     v.get(k).and_then(|x| x.as_str()).unwrap_or("").to_string()
@@ -49,6 +49,7 @@ pub(super) fn draw_costs(f: &mut Frame, app: &mut App, area: Rect) {
         .split(area);
 
     let summary = &app.cost_summary;
+    let code = app.display_currency.as_str();
     let today_str = chrono::Utc::now().format("%Y-%m-%d").to_string();
     let today_cost = app
         .daily_costs
@@ -58,8 +59,8 @@ pub(super) fn draw_costs(f: &mut Frame, app: &mut App, area: Rect) {
         .unwrap_or(0.0);
 
     stat_cards(f, app, chunks[0], &[
-        ("Total", fmt_dollars(val_f64(summary, "total_cost")), theme.hi_fg),
-        ("Today", fmt_dollars(today_cost), theme.warning_fg),
+        ("Total", fmt_money(val_f64(summary, "total_cost"), code), theme.hi_fg),
+        ("Today", fmt_money(today_cost, code), theme.warning_fg),
         ("Input Tokens", fmt_tokens(val_u64(summary, "total_input_tokens")), theme.proc_misc),
         ("Output Tokens", fmt_tokens(val_u64(summary, "total_output_tokens")), theme.main_fg),
     ]);
@@ -74,7 +75,7 @@ pub(super) fn draw_costs(f: &mut Frame, app: &mut App, area: Rect) {
         .map(|(i, d)| {
             let row = Row::new(vec![
                 Cell::from(val_str(d, "date")),
-                Cell::from(fmt_dollars(val_f64(d, "cost").max(val_f64(d, "total_cost")))),
+                Cell::from(fmt_money(val_f64(d, "cost").max(val_f64(d, "total_cost")), code)),
                 Cell::from(fmt_tokens(val_u64(d, "input_tokens") + val_u64(d, "output_tokens"))),
                 Cell::from(format!("{}", val_u64(d, "sessions"))),
             ]);
@@ -102,10 +103,11 @@ pub(super) fn draw_budget(f: &mut Frame, app: &mut App, area: Rect) {
     let theme = app.theme();
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(5), Constraint::Length(2), Constraint::Min(0)])
+        .constraints([Constraint::Length(5), Constraint::Length(2), Constraint::Length(3), Constraint::Min(0)])
         .split(area);
 
     let status = &app.budget_status;
+    let code = currency_of(status);
     let monthly_limit = val_f64(status, "monthly_limit");
     let monthly_spent = val_f64(status, "monthly_spent");
     let daily_limit = val_f64(status, "daily_limit");
@@ -150,8 +152,8 @@ pub(super) fn draw_budget(f: &mut Frame, app: &mut App, area: Rect) {
                 theme.proc_misc
             };
             lines.push(Line::from(vec![
-                Span::styled(fmt_dollars(sp), Style::default().fg(theme.title).add_modifier(Modifier::BOLD)),
-                Span::styled(format!(" / {}", fmt_dollars(limit)), Style::default().fg(theme.graph_text)),
+                Span::styled(fmt_money(sp, code), Style::default().fg(theme.title).add_modifier(Modifier::BOLD)),
+                Span::styled(format!(" / {}", fmt_money(limit, code)), Style::default().fg(theme.graph_text)),
             ]));
             let bar_w = (area_i.width as usize).saturating_sub(4).clamp(3, 20);
             let mut bar_line = vec![Span::raw(" ")];
@@ -161,7 +163,7 @@ pub(super) fn draw_budget(f: &mut Frame, app: &mut App, area: Rect) {
         } else {
             lines.push(Line::from(vec![
                 Span::styled("— ", Style::default().fg(theme.inactive_fg)),
-                Span::styled(format!("/ {}", fmt_dollars(limit)), Style::default().fg(theme.graph_text)),
+                Span::styled(format!("/ {}", fmt_money(limit, code)), Style::default().fg(theme.graph_text)),
             ]));
             lines.push(Line::from(Span::styled("not aggregated", Style::default().fg(theme.inactive_fg))));
         }
@@ -179,10 +181,10 @@ pub(super) fn draw_budget(f: &mut Frame, app: &mut App, area: Rect) {
         Span::styled(" status ", Style::default().fg(theme.graph_text)),
         Span::styled(status_label.to_uppercase(), Style::default().fg(status_color).add_modifier(Modifier::BOLD)),
         Span::styled("   burn ", Style::default().fg(theme.graph_text)),
-        Span::styled(format!("{}/day", fmt_dollars(burn_rate)), Style::default().fg(theme.main_fg)),
+        Span::styled(format!("{}/day", fmt_money(burn_rate, code)), Style::default().fg(theme.main_fg)),
         Span::styled("   projected ", Style::default().fg(theme.graph_text)),
         Span::styled(
-            fmt_dollars(projected),
+            fmt_money(projected, code),
             Style::default().fg(if monthly_limit > 0.0 && projected > monthly_limit {
                 theme.status_fg
             } else {
@@ -194,6 +196,68 @@ pub(super) fn draw_budget(f: &mut Frame, app: &mut App, area: Rect) {
     ]);
     f.render_widget(Paragraph::new(status_line), chunks[1]);
 
+    let forecast = &app.budget_forecast;
+    let forecast_code = currency_of(forecast);
+    let band_spans = |label: &str, period: &Value| -> Vec<Span<'static>> {
+        let projected = period.get("projected").cloned().unwrap_or(Value::Null);
+        let expected = val_f64(&projected, "expected");
+        vec![
+            Span::styled(format!(" {} ", label), Style::default().fg(theme.graph_text)),
+            Span::styled(
+                fmt_money(expected, forecast_code),
+                Style::default().fg(theme.title).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(
+                format!(
+                    " ({}–{})  ",
+                    fmt_money(val_f64(&projected, "low"), forecast_code),
+                    fmt_money(val_f64(&projected, "high"), forecast_code)
+                ),
+                Style::default().fg(theme.graph_text),
+            ),
+        ]
+    };
+    let mut forecast_lines: Vec<Line> = Vec::new();
+    if forecast.is_null() {
+        forecast_lines.push(Line::from(Span::styled(" no forecast yet", Style::default().fg(theme.inactive_fg))));
+    } else {
+        let outlook = val_str(forecast, "status");
+        let outlook_color = match outlook.as_str() {
+            "projected_exceed" => theme.status_fg,
+            "at_risk" => theme.warning_fg,
+            _ => theme.proc_misc,
+        };
+        let mut spans = band_spans("week-end", forecast.get("week_end").unwrap_or(&Value::Null));
+        spans.extend(band_spans("month-end", forecast.get("month_end").unwrap_or(&Value::Null)));
+        spans.push(Span::styled(
+            outlook.replace('_', " ").to_uppercase(),
+            Style::default().fg(outlook_color).add_modifier(Modifier::BOLD),
+        ));
+        forecast_lines.push(Line::from(spans));
+
+        let mut hits: Vec<Span> = vec![Span::styled(" cap hit ", Style::default().fg(theme.graph_text))];
+        let caps = forecast.get("caps").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+        if caps.is_empty() {
+            hits.push(Span::styled("no caps configured", Style::default().fg(theme.inactive_fg)));
+        }
+        for cap in &caps {
+            let expected = val_str(cap, "expected_hit_date");
+            let earliest = val_str(cap, "earliest_hit_date");
+            let (text, color) = if !expected.is_empty() {
+                (expected, theme.status_fg)
+            } else if !earliest.is_empty() {
+                (format!("≥{}", earliest), theme.warning_fg)
+            } else {
+                ("—".to_string(), theme.proc_misc)
+            };
+            hits.push(Span::styled(format!("{} ", val_str(cap, "label")), Style::default().fg(theme.main_fg)));
+            hits.push(Span::styled(format!("{}   ", text), Style::default().fg(color)));
+        }
+        forecast_lines.push(Line::from(hits));
+    }
+    let forecast_block = btop_block("forecast", "", theme.div_line, theme);
+    f.render_widget(Paragraph::new(forecast_lines).block(forecast_block), chunks[2]);
+
     let alerts = app
         .budget_alerts
         .get("alerts")
@@ -218,8 +282,8 @@ pub(super) fn draw_budget(f: &mut Frame, app: &mut App, area: Rect) {
                 Cell::from(val_str(a, "timestamp")),
                 Cell::from(Span::styled(at.to_uppercase(), Style::default().fg(color))),
                 Cell::from(val_str(a, "limit_hit")),
-                Cell::from(fmt_dollars(val_f64(a, "monthly_spent"))),
-                Cell::from(fmt_dollars(val_f64(a, "daily_spent"))),
+                Cell::from(fmt_money(val_f64(a, "monthly_spent"), currency_of(a))),
+                Cell::from(fmt_money(val_f64(a, "daily_spent"), currency_of(a))),
                 Cell::from(val_str(a, "message")),
             ]);
             if i == app.scroll {
@@ -241,7 +305,7 @@ pub(super) fn draw_budget(f: &mut Frame, app: &mut App, area: Rect) {
     ])
     .header(header)
     .block(block);
-    f.render_widget(table, chunks[2]);
+    f.render_widget(table, chunks[3]);
 }
 
 // ── models ───────────────────────────────────────────────────────────────────
@@ -576,6 +640,89 @@ pub(super) fn draw_hooks(f: &mut Frame, app: &mut App, area: Rect) {
     f.render_widget(table, area);
 }
 
+// ── guards ───────────────────────────────────────────────────────────────────
+pub(super) fn draw_guards(f: &mut Frame, app: &mut App, area: Rect) {
+    let theme = app.theme();
+    let header = Row::new(vec!["ID", "PID", "Command", "Limits", "Spent", "Action", "Sessions"])
+        .style(Style::default().fg(theme.hi_fg).add_modifier(Modifier::BOLD));
+
+    let rows: Vec<Row> = app
+        .guards
+        .iter()
+        .enumerate()
+        .map(|(i, g)| {
+            let id = val_str(g, "id");
+            let limit = guard_limits(g);
+            let sessions = g.get("session_ids").and_then(|v| v.as_array()).map_or(0, |a| a.len());
+            let row = Row::new(vec![
+                Cell::from(id.chars().take(8).collect::<String>()),
+                Cell::from(format!("{}", val_u64(g, "pid"))),
+                Cell::from(val_str(g, "command")),
+                Cell::from(limit),
+                Cell::from(fmt_dollars(val_f64(g, "current_cost"))),
+                Cell::from(val_str(g, "action")),
+                Cell::from(format!("{}", sessions)),
+            ]);
+            if i == app.scroll {
+                row.style(Style::default().bg(theme.selected_bg).fg(theme.selected_fg))
+            } else {
+                row
+            }
+        })
+        .collect();
+
+    let block = btop_block(
+        &format!("guards ({})", app.guards.len()),
+        "",
+        theme.mem_box,
+        theme,
+    );
+    let table = Table::new(rows, [
+        Constraint::Length(10),
+        Constraint::Length(8),
+        Constraint::Percentage(30),
+        Constraint::Length(30),
+        Constraint::Length(10),
+        Constraint::Length(8),
+        Constraint::Length(9),
+    ])
+    .header(header)
+    .block(block);
+    f.render_widget(table, area);
+}
+
+/// Every limit a guard enforces, e.g. `$5.00 · 200k tok · 50 turns · 30m`.
+fn guard_limits(g: &Value) -> String {
+    let mut parts = Vec::new();
+    if let Some(limit) = g.get("limit").and_then(|v| v.as_f64()) {
+        parts.push(fmt_dollars(limit));
+    }
+    if let Some(tokens) = g.get("max_tokens").and_then(|v| v.as_u64()) {
+        parts.push(format!("{} tok", fmt_tokens(tokens)));
+    }
+    if let Some(turns) = g.get("max_turns").and_then(|v| v.as_u64()) {
+        parts.push(format!("{} turns", turns));
+    }
+    if let Some(secs) = g.get("max_duration_secs").and_then(|v| v.as_u64()) {
+        parts.push(fmt_secs(secs));
+    }
+    if parts.is_empty() {
+        "—".to_string()
+    } else {
+        parts.join(" · ")
+    }
+}
+
+fn fmt_secs(secs: u64) -> String {
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, 0) => format!("{}m", m),
+        (0, m, s) => format!("{}m{}s", m, s),
+        (h, 0, _) => format!("{}h", h),
+        (h, m, _) => format!("{}h{}m", h, m),
+    }
+}
+
 // ── plugins ──────────────────────────────────────────────────────────────────
 pub(super) fn draw_plugins(f: &mut Frame, app: &mut App, area: Rect) {
     let theme = app.theme();
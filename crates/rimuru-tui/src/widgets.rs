@@ -197,15 +197,42 @@ pub fn fmt_tokens(n: u64) -> String {
 }
 
 pub fn fmt_dollars(v: f64) -> String {
-    if v >= 1000.0 {
-        format!("${:.0}", v)
+    fmt_money(v, "USD")
+}
+
+/// Same symbols the server renders budget warnings with; other codes
+/// follow the amount.
+fn currency_symbol(code: &str) -> Option<&'static str> {
+    match code {
+        "USD" => Some("$"),
+        "EUR" => Some("€"),
+        "GBP" => Some("£"),
+        "INR" => Some("₹"),
+        "JPY" => Some("¥"),
+        _ => None,
+    }
+}
+
+/// `v` in the ISO currency `code`, with fewer decimals as it grows.
+pub fn fmt_money(v: f64, code: &str) -> String {
+    let digits = if v >= 1000.0 || matches!(code, "JPY" | "KRW") {
+        0
     } else if v >= 1.0 {
-        format!("${:.2}", v)
+        2
     } else {
-        format!("${:.3}", v)
+        3
+    };
+    match currency_symbol(code) {
+        Some(sym) => format!("{sym}{v:.digits$}"),
+        None => format!("{v:.digits$} {code}"),
     }
 }
 
+/// Currency a response reports its amounts in; USD when it doesn't say.
+pub fn currency_of(v: &serde_json::Value) -> &str {
+    v.get("currency").and_then(|c| c.as_str()).unwrap_or("USD")
+}
+
 pub fn truncate_str(s: &str, max: usize) -> String {
     if max == 0 {
         return String::new();
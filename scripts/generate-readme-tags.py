@@ -595,7 +595,7 @@ SECTIONS: list[tuple[str, str, str, str, str, str | None]] = [
     ("budget", "Budget Engine", "Hard caps - four enforcement levels", "BENIMARU", "RANK SS", "budget"),
     ("runaway", "Runaway Detection", "Four patterns - severity scoring", "GREAT SAGE", "RANK S", "runaway"),
     ("guard", "Guard Wrapper", "Kill agents at the cost limit", "SHION", "RANK S", "guard"),
-    ("compression", "Output Compression", "Six strategies - auto routing", "SHUNA", "RANK A", "compression"),
+    ("compression", "Output Compression", "Seven strategies - auto routing", "SHUNA", "RANK A", "compression"),
     ("interfaces", "Four Interfaces", "CLI - Web UI - TUI - Desktop", "NOTICE 007", "RANK B", None),
     ("advisor", "Hardware Advisor", "Run models locally - see savings", "VELDORA", "RANK B", "advisor"),
     ("architecture", "Architecture", "Worker - function - trigger", "NOTICE 009", "RANK S", None),
@@ -609,7 +609,7 @@ STATS = [
     ("functions", "60+", "iii functions", "glow"),
     ("interfaces", "4", "interfaces", "purple"),
     ("caps", "4", "budget cap levels", "glow"),
-    ("strategies", "6", "compression modes", "purple"),
+    ("strategies", "7", "compression modes", "purple"),
     ("endpoints", "46", "http endpoints", "glow"),
 ]
 